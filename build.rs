@@ -0,0 +1,1092 @@
+//! Generates request and response types from the Apache Kafka message specs
+//! vendored in `messages/` from Kafka's
+//! `clients/src/main/resources/common/message/`.
+//!
+//! Every message becomes a module of `$OUT_DIR/messages.rs`. Consecutive
+//! versions sharing an encoding share a struct, named after the first of
+//! them (`ApiVersionsResponseV3` covers versions 3 and 4), and an enum
+//! named after the message holds one variant per struct. Request enums
+//! implement `Request`, and a `Response` enum holds the responses of every
+//! API.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+use std::{env, fs};
+
+const SPEC_DIR: &str = "messages";
+
+fn main() {
+    println!("cargo:rerun-if-changed={SPEC_DIR}");
+
+    let mut paths: Vec<_> = fs::read_dir(SPEC_DIR)
+        .unwrap_or_else(|e| panic!("failed to read {SPEC_DIR}: {e}"))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    let mut out = String::from("// Generated by build.rs from the specs in messages/.\n");
+    let mut responses = vec![];
+    for path in paths {
+        let text = fs::read_to_string(&path).unwrap();
+        let message = serde_json::from_str(&strip_comments(&text))
+            .map_err(|e| e.to_string())
+            .and_then(|json| Message::from_json(&json))
+            .unwrap_or_else(|e| panic!("invalid message spec {}: {e}", path.display()));
+        out.push_str(&Generator::new(&message).generate());
+        if let (Some(api_key), true) = (message.api_key, message.name.ends_with("Response")) {
+            responses.push((api_key, message.name));
+        }
+    }
+    out.push_str(&generate_responses(&responses));
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("messages.rs"), out).unwrap();
+}
+
+/// Access to the members of a spec object.
+trait SpecObject {
+    /// A string member, or a number or boolean given in its place, as the
+    /// specs do for defaults and tags.
+    fn get_str(&self, key: &str) -> Result<Option<String>, String>;
+
+    fn require_str(&self, key: &str) -> Result<String, String> {
+        self.get_str(key)?.ok_or_else(|| format!("missing {key}"))
+    }
+
+    fn get_array(&self, key: &str) -> Result<&[Value], String>;
+}
+
+impl SpecObject for Value {
+    fn get_str(&self, key: &str) -> Result<Option<String>, String> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s.clone())),
+            Some(Value::Number(n)) => Ok(Some(n.to_string())),
+            Some(Value::Bool(b)) => Ok(Some(b.to_string())),
+            Some(v) => Err(format!("expected a string for {key}, found {v}")),
+        }
+    }
+
+    fn get_array(&self, key: &str) -> Result<&[Value], String> {
+        match self.get(key) {
+            None | Some(Value::Null) => Ok(&[]),
+            Some(Value::Array(items)) => Ok(items),
+            Some(v) => Err(format!("expected an array for {key}, found {v}")),
+        }
+    }
+}
+
+/// Blanks the `//` comment lines of a spec, such as its license header,
+/// which JSON does not allow. The lines stay, so errors point into the spec.
+fn strip_comments(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.trim_start().starts_with("//") {
+                ""
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// An inclusive range of versions, empty when `min > max`.
+#[derive(Debug, Clone, Copy)]
+struct Versions {
+    min: i16,
+    max: i16,
+}
+
+impl Versions {
+    const NONE: Self = Self { min: 0, max: -1 };
+
+    fn parse(s: &str) -> Result<Self, String> {
+        let invalid = |_| format!("invalid versions {s:?}");
+        let s = s.trim();
+        if s == "none" {
+            Ok(Self::NONE)
+        } else if let Some(min) = s.strip_suffix('+') {
+            Ok(Self {
+                min: min.parse().map_err(invalid)?,
+                max: i16::MAX,
+            })
+        } else if let Some((min, max)) = s.split_once('-') {
+            Ok(Self {
+                min: min.parse().map_err(invalid)?,
+                max: max.parse().map_err(invalid)?,
+            })
+        } else {
+            let version = s.parse().map_err(invalid)?;
+            Ok(Self {
+                min: version,
+                max: version,
+            })
+        }
+    }
+
+    fn parse_opt(s: Option<String>) -> Result<Self, String> {
+        s.map_or(Ok(Self::NONE), |s| Self::parse(&s))
+    }
+
+    fn contains(&self, version: i16) -> bool {
+        self.min <= version && version <= self.max
+    }
+
+    /// A Rust function `name(version: i16) -> bool` testing whether
+    /// `version` is in the range.
+    fn contains_fn(&self, name: &str) -> String {
+        if self.min > self.max {
+            format!("pub(crate) fn {name}(_version: i16) -> bool {{\nfalse\n}}")
+        } else {
+            format!(
+                "pub(crate) fn {name}(version: i16) -> bool {{\n({}..={}).contains(&version)\n}}",
+                self.min, self.max
+            )
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Field {
+    name: String,
+    ty: String,
+    versions: Versions,
+    nullable_versions: Versions,
+    tagged_versions: Versions,
+    tag: Option<u64>,
+    default: Option<String>,
+    about: Option<String>,
+}
+
+#[derive(Debug)]
+struct StructSpec {
+    name: String,
+    versions: Versions,
+    fields: Vec<Field>,
+}
+
+#[derive(Debug)]
+struct Message {
+    name: String,
+    api_key: Option<i16>,
+    valid_versions: Versions,
+    flexible_versions: Versions,
+    /// The message itself, then the structs of its fields and its common
+    /// structs.
+    structs: Vec<StructSpec>,
+}
+
+impl Message {
+    fn from_json(json: &Value) -> Result<Self, String> {
+        let name = json.require_str("name")?;
+        let api_key = match json.get_str("apiKey")? {
+            Some(key) => Some(key.parse().map_err(|_| format!("invalid apiKey {key}"))?),
+            None => None,
+        };
+        let valid_versions = Versions::parse(&json.require_str("validVersions")?)?;
+        let flexible_versions = Versions::parse_opt(json.get_str("flexibleVersions")?)?;
+
+        let mut structs = vec![];
+        let fields = parse_fields(json.get_array("fields")?, &mut structs)?;
+        structs.insert(
+            0,
+            StructSpec {
+                name: name.clone(),
+                versions: valid_versions,
+                fields,
+            },
+        );
+        for common in json.get_array("commonStructs")? {
+            let fields = parse_fields(common.get_array("fields")?, &mut structs)?;
+            structs.push(StructSpec {
+                name: common.require_str("name")?,
+                versions: Versions::parse(&common.require_str("versions")?)?,
+                fields,
+            });
+        }
+
+        Ok(Self {
+            name,
+            api_key,
+            valid_versions,
+            flexible_versions,
+            structs,
+        })
+    }
+
+    fn spec(&self, name: &str) -> &StructSpec {
+        self.structs
+            .iter()
+            .find(|spec| spec.name == name)
+            .unwrap_or_else(|| panic!("{}: unknown type {name}", self.name))
+    }
+}
+
+/// Parses the fields of a struct, adding the structs they declare.
+fn parse_fields(fields: &[Value], structs: &mut Vec<StructSpec>) -> Result<Vec<Field>, String> {
+    fields
+        .iter()
+        .map(|json| {
+            let name = json.require_str("name")?;
+            let ty = json.require_str("type")?;
+            let versions = Versions::parse(&json.require_str("versions")?)?;
+            let tag = match json.get_str("tag")? {
+                Some(tag) => Some(
+                    tag.parse()
+                        .map_err(|_| format!("invalid tag {tag} of {name}"))?,
+                ),
+                None => None,
+            };
+
+            if json.get("fields").is_some() {
+                let nested = parse_fields(json.get_array("fields")?, structs)?;
+                structs.push(StructSpec {
+                    name: ty.trim_start_matches("[]").to_string(),
+                    versions,
+                    fields: nested,
+                });
+            }
+
+            Ok(Field {
+                name,
+                ty,
+                versions,
+                nullable_versions: Versions::parse_opt(json.get_str("nullableVersions")?)?,
+                tagged_versions: Versions::parse_opt(json.get_str("taggedVersions")?)?,
+                tag,
+                default: json.get_str("default")?,
+                about: json.get_str("about")?,
+            })
+        })
+        .collect()
+}
+
+/// Consecutive versions of a struct with the same encoding.
+#[derive(Debug, Clone)]
+struct Group {
+    first: i16,
+    last: i16,
+    signature: String,
+}
+
+/// A field as encoded in a group.
+struct FieldLayout<'a> {
+    field: &'a Field,
+    ident: String,
+    ty: String,
+    nullable: bool,
+    tag: Option<u64>,
+}
+
+struct Generator<'a> {
+    message: &'a Message,
+    groups: HashMap<String, Vec<Group>>,
+    out: String,
+}
+
+impl<'a> Generator<'a> {
+    fn new(message: &'a Message) -> Self {
+        Self {
+            message,
+            groups: HashMap::new(),
+            out: String::new(),
+        }
+    }
+
+    fn versions(&self) -> impl Iterator<Item = i16> + use<> {
+        self.message.valid_versions.min..=self.message.valid_versions.max
+    }
+
+    fn is_flexible(&self, version: i16) -> bool {
+        self.message.flexible_versions.contains(version)
+    }
+
+    fn groups(&mut self, name: &str) -> Vec<Group> {
+        if let Some(groups) = self.groups.get(name) {
+            return groups.clone();
+        }
+        let spec = self.message.spec(name);
+        let mut groups: Vec<Group> = vec![];
+        for version in self.versions().filter(|&v| spec.versions.contains(v)) {
+            let mut signature = format!("flexible={};", self.is_flexible(version));
+            for layout in self.layouts(spec, version) {
+                let _ = write!(
+                    signature,
+                    "{}:{}:{:?};",
+                    layout.ident, layout.ty, layout.tag
+                );
+            }
+            match groups.last_mut() {
+                Some(group) if group.signature == signature && group.last == version - 1 => {
+                    group.last = version
+                }
+                _ => groups.push(Group {
+                    first: version,
+                    last: version,
+                    signature,
+                }),
+            }
+        }
+        self.groups.insert(name.to_string(), groups.clone());
+        groups
+    }
+
+    fn type_name(&mut self, name: &str, version: i16) -> String {
+        let group = self
+            .groups(name)
+            .into_iter()
+            .find(|group| group.first <= version && version <= group.last)
+            .unwrap_or_else(|| panic!("{name} has no version {version}"));
+        format!("{name}V{}", group.first)
+    }
+
+    fn layouts(&mut self, spec: &'a StructSpec, version: i16) -> Vec<FieldLayout<'a>> {
+        let flexible = self.is_flexible(version);
+        spec.fields
+            .iter()
+            .filter(|field| field.versions.contains(version))
+            .map(|field| {
+                let nullable = field.nullable_versions.contains(version);
+                let ty = match field.ty.strip_prefix("[]") {
+                    Some(element) => {
+                        let element = self.element_type(element, version, flexible, false);
+                        let array = if flexible { "CompactArray" } else { "Array" };
+                        format!("{array}<{element}>")
+                    }
+                    None => self.element_type(&field.ty, version, flexible, nullable),
+                };
+                let tag = field
+                    .tag
+                    .filter(|_| flexible && field.tagged_versions.contains(version));
+                FieldLayout {
+                    field,
+                    ident: ident(&field.name),
+                    ty,
+                    nullable,
+                    tag,
+                }
+            })
+            .collect()
+    }
+
+    fn element_type(&mut self, ty: &str, version: i16, flexible: bool, nullable: bool) -> String {
+        let encoded = |names: [&str; 4]| {
+            let i = match (flexible, nullable) {
+                (true, true) => 0,
+                (true, false) => 1,
+                (false, true) => 2,
+                (false, false) => 3,
+            };
+            names[i].to_string()
+        };
+        match ty {
+            "bool" => "bool".to_string(),
+            "int8" => "i8".to_string(),
+            "int16" => "i16".to_string(),
+            "uint16" => "u16".to_string(),
+            "int32" => "i32".to_string(),
+            "uint32" => "u32".to_string(),
+            "int64" => "i64".to_string(),
+            "float64" => "f64".to_string(),
+            "uuid" => "Uuid".to_string(),
+            "string" => encoded([
+                "CompactNullableString",
+                "CompactString",
+                "NullableString",
+                "String",
+            ]),
+            "bytes" | "records" => encoded([
+                "CompactNullableBytes",
+                "CompactBytes",
+                "NullableBytes",
+                "Bytes",
+            ]),
+            name => {
+                let name = self.type_name(name, version);
+                if nullable {
+                    format!("NullableStruct<{name}>")
+                } else {
+                    name
+                }
+            }
+        }
+    }
+
+    fn generate(mut self) -> String {
+        let message = self.message;
+        let _ = writeln!(
+            self.out,
+            "\npub(crate) mod {} {{\n\
+             #[allow(unused_imports)]\n\
+             use crate::types::{{\n\
+                 Array, ByteSizeExt, Bytes, CompactArray, CompactBytes, CompactNullableBytes,\n\
+                 CompactNullableString, CompactString, NullableBytes, NullableString,\n\
+                 NullableStruct, TaggedFields, Uuid,\n\
+             }};\n\
+             #[allow(unused_imports)]\n\
+             use serde::{{Deserialize, Serialize, de, ser::{{self, SerializeSeq}}}};",
+            ident(&message.name)
+        );
+
+        for spec in &message.structs {
+            for group in self.groups(&spec.name) {
+                self.generate_struct(spec, &group);
+            }
+        }
+        self.generate_enum();
+        self.out.push_str("}\n");
+        self.out
+    }
+
+    fn generate_struct(&mut self, spec: &'a StructSpec, group: &Group) {
+        let name = format!("{}V{}", spec.name, group.first);
+        let flexible = self.is_flexible(group.first);
+        let layouts = self.layouts(spec, group.first);
+        let tagged = layouts.iter().any(|layout| layout.tag.is_some());
+
+        let range = if group.first == group.last {
+            format!("Version {}", group.first)
+        } else {
+            format!("Versions {}-{}", group.first, group.last)
+        };
+        let _ = writeln!(self.out, "\n/// {range} of `{}`.", spec.name);
+        let defaults: Vec<String> = layouts.iter().map(default_value).collect();
+        // Clippy asks for the derive when every default is the type's own.
+        let derive_default = defaults.iter().all(|value| {
+            matches!(
+                value.as_str(),
+                "None" | "false" | "0" | "0.0" | "String::new()"
+            ) || value.ends_with("::default()")
+        });
+        let mut derives = String::new();
+        if !tagged {
+            derives.push_str(", Serialize, Deserialize");
+        }
+        if derive_default {
+            derives.push_str(", Default");
+        }
+        let _ = writeln!(self.out, "#[derive(Debug, Clone, PartialEq{derives})]");
+        let _ = writeln!(self.out, "pub(crate) struct {name} {{");
+        for layout in &layouts {
+            if let Some(about) = &layout.field.about {
+                let _ = writeln!(self.out, "/// {about}");
+            }
+            match layout.tag {
+                Some(tag) => {
+                    let _ = writeln!(self.out, "/// Tagged field {tag}, omitted when `None`.");
+                    let _ = writeln!(
+                        self.out,
+                        "pub(crate) {}: Option<{}>,",
+                        layout.ident, layout.ty
+                    );
+                }
+                None => {
+                    let _ = writeln!(self.out, "pub(crate) {}: {},", layout.ident, layout.ty);
+                }
+            }
+        }
+        if flexible {
+            self.out
+                .push_str("/// The tagged fields this version does not know.\n");
+            self.out
+                .push_str("pub(crate) tagged_fields: TaggedFields,\n");
+        }
+        self.out.push_str("}\n");
+
+        if !derive_default {
+            let _ = writeln!(
+                self.out,
+                "\nimpl Default for {name} {{\nfn default() -> Self {{\nSelf {{"
+            );
+            for (layout, value) in layouts.iter().zip(&defaults) {
+                let _ = writeln!(self.out, "{}: {value},", layout.ident);
+            }
+            if flexible {
+                self.out
+                    .push_str("tagged_fields: TaggedFields::default(),\n");
+            }
+            self.out.push_str("}\n}\n}\n");
+        }
+
+        let mut sizes: Vec<String> = layouts
+            .iter()
+            .filter(|layout| layout.tag.is_none())
+            .map(|layout| format!("self.{}.byte_size()", layout.ident))
+            .collect();
+        let _ = writeln!(
+            self.out,
+            "\nimpl ByteSizeExt for {name} {{\nfn byte_size(&self) -> usize {{"
+        );
+        if tagged {
+            // Only the size of the tagged values matters here.
+            self.out
+                .push_str("let mut tagged_fields = self.tagged_fields.clone();\n");
+            for layout in layouts.iter().filter(|layout| layout.tag.is_some()) {
+                let _ = writeln!(
+                    self.out,
+                    "if let Some(value) = &self.{} {{\n\
+                     tagged_fields.insert({}, vec![0; value.byte_size()]);\n}}",
+                    layout.ident,
+                    layout.tag.unwrap()
+                );
+            }
+            sizes.push("tagged_fields.byte_size()".to_string());
+        } else if flexible {
+            sizes.push("self.tagged_fields.byte_size()".to_string());
+        }
+        if sizes.is_empty() {
+            sizes.push("0".to_string());
+        }
+        let _ = writeln!(self.out, "{}\n}}\n}}", sizes.join("\n+ "));
+
+        if tagged {
+            self.generate_tagged_serde(&name, &layouts);
+        }
+        if self.message.api_key.is_some() && self.message.name.ends_with("Response") {
+            self.generate_error_codes(&name, &layouts);
+        }
+    }
+
+    /// Collects the error codes of a response struct and of the structs it
+    /// holds, for the tests to check without knowing the layout.
+    fn generate_error_codes(&mut self, name: &str, layouts: &[FieldLayout]) {
+        let mut body = String::new();
+        for layout in layouts {
+            let (element, array) = match layout.field.ty.strip_prefix("[]") {
+                Some(element) => (element, true),
+                None => (layout.field.ty.as_str(), false),
+            };
+            let collect = if element == "int16" && !array && layout.ident.ends_with("error_code") {
+                "codes.push(*value);".to_string()
+            } else if is_primitive(element) {
+                continue;
+            } else if array {
+                "for item in value.as_opt_slice().unwrap_or_default() {\n\
+                 item.error_codes(codes);\n}"
+                    .to_string()
+            } else if layout.nullable {
+                "if let Some(value) = value.clone().into_inner() {\n\
+                 value.error_codes(codes);\n}"
+                    .to_string()
+            } else {
+                "value.error_codes(codes);".to_string()
+            };
+            let _ = match layout.tag {
+                Some(_) => writeln!(
+                    body,
+                    "if let Some(value) = &self.{} {{\n{collect}\n}}",
+                    layout.ident
+                ),
+                None => writeln!(body, "let value = &self.{};\n{collect}", layout.ident),
+            };
+        }
+        let codes = if body.is_empty() { "_codes" } else { "codes" };
+        let _ = writeln!(
+            self.out,
+            "\n#[cfg(test)]\n\
+             impl {name} {{\n\
+             pub(crate) fn error_codes(&self, {codes}: &mut Vec<i16>) {{\n\
+             {body}}}\n}}"
+        );
+    }
+
+    /// Serializes and deserializes a struct with tagged fields, which are
+    /// encoded in its tagged fields section.
+    fn generate_tagged_serde(&mut self, name: &str, layouts: &[FieldLayout]) {
+        let _ = writeln!(
+            self.out,
+            "\nimpl ser::Serialize for {name} {{\n\
+             fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>\n\
+             where\nS: ser::Serializer,\n{{\n\
+             let mut tagged_fields = self.tagged_fields.clone();"
+        );
+        for layout in layouts.iter().filter(|layout| layout.tag.is_some()) {
+            let _ = writeln!(
+                self.out,
+                "if let Some(value) = &self.{} {{\n\
+                 let data = crate::ser::to_bytes(value).map_err(ser::Error::custom)?;\n\
+                 tagged_fields.insert({}, data);\n}}",
+                layout.ident,
+                layout.tag.unwrap()
+            );
+        }
+        self.out
+            .push_str("let mut seq = serializer.serialize_seq(None)?;\n");
+        for layout in layouts.iter().filter(|layout| layout.tag.is_none()) {
+            let _ = writeln!(self.out, "seq.serialize_element(&self.{})?;", layout.ident);
+        }
+        self.out
+            .push_str("seq.serialize_element(&tagged_fields)?;\nseq.end()\n}\n}\n");
+
+        let untagged: Vec<_> = layouts.iter().filter(|l| l.tag.is_none()).collect();
+        let _ = writeln!(
+            self.out,
+            "\nimpl<'de> de::Deserialize<'de> for {name} {{\n\
+             fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>\n\
+             where\nD: de::Deserializer<'de>,\n{{\n\
+             struct Visitor;\n\n\
+             impl<'de> de::Visitor<'de> for Visitor {{\n\
+             type Value = {name};\n\n\
+             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {{\n\
+             formatter.write_str(\"{name}\")\n}}\n\n\
+             fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>\n\
+             where\nA: de::SeqAccess<'de>,\n{{"
+        );
+        for layout in &untagged {
+            let _ = writeln!(
+                self.out,
+                "let {0} = seq\n.next_element()?\n.ok_or_else(|| de::Error::missing_field(\"{0}\"))?;",
+                layout.ident
+            );
+        }
+        self.out.push_str(
+            "let mut tagged_fields: TaggedFields = seq\n\
+             .next_element()?\n\
+             .ok_or_else(|| de::Error::missing_field(\"tagged_fields\"))?;\n",
+        );
+        for layout in layouts.iter().filter(|layout| layout.tag.is_some()) {
+            let _ = writeln!(
+                self.out,
+                "let {} = tagged_fields\n\
+                 .remove({})\n\
+                 .map(|data| crate::de::from_bytes(&data))\n\
+                 .transpose()\n\
+                 .map_err(de::Error::custom)?;",
+                layout.ident,
+                layout.tag.unwrap()
+            );
+        }
+        let _ = writeln!(self.out, "Ok({name} {{");
+        for layout in layouts {
+            let _ = writeln!(self.out, "{},", layout.ident);
+        }
+        let _ = writeln!(
+            self.out,
+            "tagged_fields,\n}})\n}}\n}}\n\
+             deserializer.deserialize_tuple({}, Visitor)\n}}\n}}",
+            untagged.len() + 1
+        );
+    }
+
+    fn generate_enum(&mut self) {
+        let message = self.message;
+        let name = &message.name;
+        let groups = self.groups(name);
+
+        let _ = writeln!(
+            self.out,
+            "\n/// A `{name}` of any version.\n\
+             #[derive(Debug, Clone, PartialEq, Serialize)]\n\
+             #[serde(untagged)]\n\
+             pub(crate) enum {name} {{"
+        );
+        for group in &groups {
+            let _ = writeln!(self.out, "V{0}({name}V{0}),", group.first);
+        }
+        self.out.push_str("}\n");
+
+        let _ = writeln!(
+            self.out,
+            "\nimpl ByteSizeExt for {name} {{\nfn byte_size(&self) -> usize {{\nmatch self {{"
+        );
+        for group in &groups {
+            let _ = writeln!(
+                self.out,
+                "Self::V{}(body) => body.byte_size(),",
+                group.first
+            );
+        }
+        self.out.push_str("}\n}\n}\n");
+
+        // The broker only writes responses: reading them, and so their
+        // versions, is for the tests.
+        let response = message.api_key.is_some() && name.ends_with("Response");
+        let test_only = if response { "#[cfg(test)]\n" } else { "" };
+        let _ = writeln!(self.out, "\nimpl {name} {{");
+        if let Some(api_key) = message.api_key {
+            let _ = writeln!(
+                self.out,
+                "{test_only}pub(crate) const API_KEY: i16 = {api_key};"
+            );
+        }
+        if !response {
+            let _ = writeln!(
+                self.out,
+                "pub(crate) const MIN_VERSION: i16 = {};\n\
+                 pub(crate) const MAX_VERSION: i16 = {};\n",
+                message.valid_versions.min, message.valid_versions.max,
+            );
+        }
+        let _ = writeln!(
+            self.out,
+            "/// Whether `version` uses the flexible encoding, with compact\n\
+             /// strings and arrays and tagged fields.\n\
+             {}\n\n\
+             /// Reads the message of `version`.\n\
+             {test_only}pub(crate) fn read<R: std::io::Read>(\n\
+             version: i16,\n\
+             deserializer: &mut crate::de::Deserializer<R>,\n\
+             ) -> crate::Result<Self> {{\n\
+             match version {{",
+            message.flexible_versions.contains_fn("is_flexible"),
+        );
+        for group in &groups {
+            let _ = writeln!(
+                self.out,
+                "{}..={} => Ok(Self::V{}(Deserialize::deserialize(deserializer)?)),",
+                group.first, group.last, group.first
+            );
+        }
+        self.generate_read_error();
+        self.out.push_str("}\n}\n");
+        if response {
+            self.generate_response_fns(&groups);
+        }
+        self.out.push_str("}\n");
+
+        if message.api_key.is_some() && name.ends_with("Request") {
+            let _ = writeln!(
+                self.out,
+                "\nimpl super::Request for {name} {{\n\
+                 fn is_flexible(version: i16) -> bool {{\n\
+                 Self::is_flexible(version)\n}}\n\n\
+                 fn read<R: std::io::Read>(\n\
+                 version: i16,\n\
+                 deserializer: &mut crate::de::Deserializer<R>,\n\
+                 ) -> crate::Result<Self> {{\n\
+                 Self::read(version, deserializer)\n}}\n}}"
+            );
+        }
+    }
+
+    /// The functions of a response enum which need no request: an error
+    /// response and setting the throttle time.
+    fn generate_response_fns(&mut self, groups: &[Group]) {
+        let message = self.message;
+        let name = &message.name;
+        let top_level = |this: &mut Self, group: &Group, ident: &str| {
+            this.layouts(message.spec(name), group.first)
+                .iter()
+                .any(|layout| layout.ident == ident && layout.tag.is_none())
+        };
+
+        let with_error: Vec<bool> = groups
+            .iter()
+            .map(|group| top_level(self, group, "error_code"))
+            .collect();
+        let error_code = if with_error.contains(&true) {
+            "error_code"
+        } else {
+            "_error_code"
+        };
+        let _ = writeln!(
+            self.out,
+            "\n/// A response of `version` which reports `error_code` in its\n\
+             /// top-level error code, if the version has one, or `None` for a\n\
+             /// version the spec does not define.\n\
+             pub(crate) fn error(version: i16, {error_code}: i16) -> Option<Self> {{\n\
+             match version {{"
+        );
+        for (group, with_error) in groups.iter().zip(with_error) {
+            let body = if with_error {
+                format!(
+                    "{name}V{} {{\nerror_code,\n..Default::default()\n}}",
+                    group.first
+                )
+            } else {
+                format!("{name}V{}::default()", group.first)
+            };
+            let _ = writeln!(
+                self.out,
+                "{}..={} => Some(Self::V{}({body})),",
+                group.first, group.last, group.first
+            );
+        }
+        self.out.push_str("_ => None,\n}\n}\n");
+
+        let with_throttle: Vec<&Group> = groups
+            .iter()
+            .filter(|group| top_level(self, group, "throttle_time_ms"))
+            .collect();
+        let ms = if with_throttle.is_empty() {
+            "_ms"
+        } else {
+            "ms"
+        };
+        let _ = writeln!(
+            self.out,
+            "\n/// Reports how long the client is throttled for, in the versions\n\
+             /// which have the field.\n\
+             pub(crate) fn set_throttle_time_ms(&mut self, {ms}: i32) {{"
+        );
+        if !with_throttle.is_empty() {
+            self.out.push_str("match self {\n");
+            for group in &with_throttle {
+                let _ = writeln!(
+                    self.out,
+                    "Self::V{}(body) => body.throttle_time_ms = ms,",
+                    group.first
+                );
+            }
+            if with_throttle.len() < groups.len() {
+                self.out.push_str("_ => {}\n");
+            }
+            self.out.push_str("}\n");
+        }
+        self.out.push_str("}\n");
+
+        self.out.push_str(
+            "\n/// The error codes of the response, in the order of its fields.\n\
+             #[cfg(test)]\n\
+             pub(crate) fn error_codes(&self) -> Vec<i16> {\n\
+             let mut codes = vec![];\n\
+             match self {\n",
+        );
+        for group in groups {
+            let _ = writeln!(
+                self.out,
+                "Self::V{}(body) => body.error_codes(&mut codes),",
+                group.first
+            );
+        }
+        self.out.push_str("}\ncodes\n}\n");
+    }
+
+    /// The arm of `read` for the versions the spec does not define.
+    fn generate_read_error(&mut self) {
+        let message = self.message;
+        let name = &message.name;
+        match message.api_key {
+            Some(_) => self.out.push_str(
+                "_ => Err(crate::KafkaError::UnsupportedVersion {\n\
+                 api_key: Self::API_KEY,\n\
+                 api_version: version,\n}),\n",
+            ),
+            None => {
+                let _ = writeln!(
+                    self.out,
+                    "_ => Err(crate::KafkaError::DeserializationError(format!(\n\
+                     \"unsupported {name} version {{version}}\"\n))),"
+                );
+            }
+        }
+    }
+}
+
+/// The `Response` enum over the responses of every API, given by API key
+/// and message name.
+fn generate_responses(responses: &[(i16, String)]) -> String {
+    let mut out = String::from(
+        "\n/// A response of any API.\n\
+         #[derive(Debug, Clone, PartialEq, serde::Serialize)]\n\
+         #[serde(untagged)]\n\
+         pub(crate) enum Response {\n",
+    );
+    let path = |name: &str| format!("{}::{name}", ident(name));
+    for (_, name) in responses {
+        let _ = writeln!(
+            out,
+            "{}({}),",
+            name.trim_end_matches("Response"),
+            path(name)
+        );
+    }
+    out.push_str("}\n");
+
+    out.push_str(
+        "\nimpl crate::types::ByteSizeExt for Response {\n\
+         fn byte_size(&self) -> usize {\n\
+         match self {\n",
+    );
+    for (_, name) in responses {
+        let _ = writeln!(
+            out,
+            "Self::{}(body) => body.byte_size(),",
+            name.trim_end_matches("Response")
+        );
+    }
+    out.push_str("}\n}\n}\n");
+
+    out.push_str(
+        "\nimpl Response {\n\
+         /// Whether the responses of `version` of the API with `api_key` use\n\
+         /// the flexible encoding, or `None` for an unknown API.\n\
+         pub(crate) fn is_flexible(api_key: i16, version: i16) -> Option<bool> {\n\
+         match api_key {\n",
+    );
+    for (api_key, name) in responses {
+        let _ = writeln!(
+            out,
+            "{api_key} => Some({}::is_flexible(version)),",
+            path(name)
+        );
+    }
+    out.push_str("_ => None,\n}\n}\n");
+
+    out.push_str(
+        "\n/// A response of `version` of the API with `api_key` which reports\n\
+         /// `error_code`, or `None` for an unknown API or version.\n\
+         pub(crate) fn error(api_key: i16, version: i16, error_code: i16) -> Option<Self> {\n\
+         match api_key {\n",
+    );
+    for (api_key, name) in responses {
+        let _ = writeln!(
+            out,
+            "{api_key} => {}::error(version, error_code).map(Self::{}),",
+            path(name),
+            name.trim_end_matches("Response")
+        );
+    }
+    out.push_str("_ => None,\n}\n}\n");
+
+    out.push_str(
+        "\n/// Reads a response of `version` of the API with `api_key`.\n\
+         #[cfg(test)]\n\
+         pub(crate) fn read<R: std::io::Read>(\n\
+         api_key: i16,\n\
+         version: i16,\n\
+         deserializer: &mut crate::de::Deserializer<R>,\n\
+         ) -> crate::Result<Self> {\n\
+         match api_key {\n",
+    );
+    for (api_key, name) in responses {
+        let _ = writeln!(
+            out,
+            "{api_key} => {}::read(version, deserializer).map(Self::{}),",
+            path(name),
+            name.trim_end_matches("Response")
+        );
+    }
+    out.push_str(
+        "_ => Err(crate::KafkaError::UnsupportedVersion {\n\
+         api_key,\n\
+         api_version: version,\n}),\n}\n}\n",
+    );
+
+    out.push_str(
+        "\n/// The error codes of the response, in the order of its fields.\n\
+         #[cfg(test)]\n\
+         pub(crate) fn error_codes(&self) -> Vec<i16> {\n\
+         match self {\n",
+    );
+    for (_, name) in responses {
+        let _ = writeln!(
+            out,
+            "Self::{}(body) => body.error_codes(),",
+            name.trim_end_matches("Response")
+        );
+    }
+    out.push_str("}\n}\n");
+
+    out.push_str(
+        "\n/// Reports how long the client is throttled for, in the responses\n\
+         /// which have the field.\n\
+         pub(crate) fn set_throttle_time_ms(&mut self, ms: i32) {\n\
+         match self {\n",
+    );
+    for (_, name) in responses {
+        let _ = writeln!(
+            out,
+            "Self::{}(body) => body.set_throttle_time_ms(ms),",
+            name.trim_end_matches("Response")
+        );
+    }
+    out.push_str("}\n}\n}\n");
+    out
+}
+
+/// Whether a spec type is not a struct.
+fn is_primitive(ty: &str) -> bool {
+    matches!(
+        ty,
+        "bool"
+            | "int8"
+            | "int16"
+            | "uint16"
+            | "int32"
+            | "uint32"
+            | "int64"
+            | "float64"
+            | "uuid"
+            | "string"
+            | "bytes"
+            | "records"
+    )
+}
+
+/// The value of a field when it is not set, as given by the spec.
+fn default_value(layout: &FieldLayout) -> String {
+    if layout.tag.is_some() {
+        return "None".to_string();
+    }
+    let default = layout.field.default.as_deref();
+    let base = layout.ty.split('<').next().unwrap();
+    match base {
+        "bool" => default.unwrap_or("false").to_string(),
+        "f64" => {
+            let default = default.unwrap_or("0");
+            if default.contains('.') {
+                default.to_string()
+            } else {
+                format!("{default}.0")
+            }
+        }
+        "i8" | "i16" | "u16" | "i32" | "u32" | "i64" => default.unwrap_or("0").to_string(),
+        "Uuid" => "Uuid::default()".to_string(),
+        "String" => string_value(default),
+        "CompactString" => format!("{base}::new({})", string_value(default)),
+        "NullableString" | "CompactNullableString" => match default {
+            Some("null") => format!("{base}::new(None)"),
+            default => format!("{base}::new(Some({}))", string_value(default)),
+        },
+        "Bytes" | "CompactBytes" => format!("{base}::new(vec![])"),
+        "NullableBytes" | "CompactNullableBytes" | "NullableStruct" if default == Some("null") => {
+            format!("{base}::new(None)")
+        }
+        "NullableBytes" | "CompactNullableBytes" => format!("{base}::new(Some(vec![]))"),
+        "NullableStruct" => format!(
+            "NullableStruct::new(Some({}::default()))",
+            &layout.ty["NullableStruct<".len()..layout.ty.len() - 1]
+        ),
+        "Array" | "CompactArray" if layout.nullable && default == Some("null") => {
+            format!("{base}::new(None)")
+        }
+        "Array" | "CompactArray" => format!("{base}::new(Some(vec![]))"),
+        _ => format!("{}::default()", layout.ty),
+    }
+}
+
+fn string_value(default: Option<&str>) -> String {
+    match default {
+        None | Some("") => "String::new()".to_string(),
+        Some(default) => format!("{default:?}.to_string()"),
+    }
+}
+
+/// The Rust name of a field or message: `ApiKeys` becomes `api_keys`.
+fn ident(name: &str) -> String {
+    let mut ident = String::new();
+    let chars: Vec<char> = name.chars().collect();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() {
+            let after_lower = i > 0 && !chars[i - 1].is_ascii_uppercase();
+            let before_lower = chars.get(i + 1).is_some_and(|c| c.is_ascii_lowercase());
+            if i > 0 && (after_lower || before_lower) {
+                ident.push('_');
+            }
+            ident.push(c.to_ascii_lowercase());
+        } else {
+            ident.push(c);
+        }
+    }
+    match ident.as_str() {
+        "type" | "match" | "ref" | "move" | "in" | "use" | "mod" | "self" | "static" => {
+            format!("r#{ident}")
+        }
+        _ => ident,
+    }
+}
@@ -0,0 +1,634 @@
+//! Derives the encoding of Kafka protocol structs for the broker crate.
+//!
+//! `ByteSize` implements `ByteSizeExt` as the sum of the sizes of the
+//! fields, for structs encoded the same way in every version.
+//!
+//! `KafkaEncode` and `KafkaDecode` implement the versioned traits of the
+//! same names, in field order, with these field attributes:
+//!
+//! - `#[kafka(versions = "3+")]`: the field is only encoded in these
+//!   versions, and decodes to its default in the others.
+//! - `#[kafka(tag = 0)]`: the field, an `Option`, is encoded in the tagged
+//!   fields section held by the `tagged_fields` field, and omitted when
+//!   `None`.
+//! - `#[kafka(compact)]` or `#[kafka(compact = "3+")]`: strings, bytes
+//!   and arrays of the field use the compact encoding, always or in these
+//!   versions.
+//!
+//! Versions are given as in Kafka's message specs: `"3"`, `"0-2"`, `"3+"`.
+//! The generated code refers to `crate::types`, so the derives are only
+//! meant for the broker crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Error, Fields, GenericArgument, Ident, LitInt, LitStr, PathArguments,
+    Result, Type, parse_macro_input, parse_quote,
+};
+
+#[proc_macro_derive(ByteSize, attributes(kafka))]
+pub fn derive_byte_size(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_byte_size(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(KafkaEncode, attributes(kafka))]
+pub fn derive_kafka_encode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_encode(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(KafkaDecode, attributes(kafka))]
+pub fn derive_kafka_decode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_decode(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// An inclusive range of versions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Versions {
+    min: i16,
+    max: Option<i16>,
+}
+
+impl Versions {
+    fn parse(lit: &LitStr) -> Result<Self> {
+        let value = lit.value();
+        let invalid = || Error::new(lit.span(), format!("invalid versions {value:?}"));
+        let version = |s: &str| s.trim().parse::<i16>().map_err(|_| invalid());
+        if let Some(min) = value.strip_suffix('+') {
+            Ok(Self {
+                min: version(min)?,
+                max: None,
+            })
+        } else if let Some((min, max)) = value.split_once('-') {
+            let (min, max) = (version(min)?, version(max)?);
+            if min > max {
+                return Err(invalid());
+            }
+            Ok(Self {
+                min,
+                max: Some(max),
+            })
+        } else {
+            let version = version(&value)?;
+            Ok(Self {
+                min: version,
+                max: Some(version),
+            })
+        }
+    }
+
+    /// Whether `version` is in the range.
+    fn contains(&self) -> TokenStream2 {
+        let min = self.min;
+        match self.max {
+            None => quote!(version >= #min),
+            Some(max) if max == min => quote!(version == #min),
+            Some(max) => quote!((#min..=#max).contains(&version)),
+        }
+    }
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    versions: Option<Versions>,
+    tag: Option<u64>,
+    compact: Option<Option<Versions>>,
+}
+
+impl FieldAttrs {
+    fn parse(field: &syn::Field) -> Result<Self> {
+        let mut attrs = Self::default();
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("kafka")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("versions") {
+                    attrs.versions = Some(Versions::parse(&meta.value()?.parse()?)?);
+                } else if meta.path.is_ident("tag") {
+                    attrs.tag = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+                } else if meta.path.is_ident("compact") {
+                    attrs.compact = Some(if meta.input.peek(syn::Token![=]) {
+                        Some(Versions::parse(&meta.value()?.parse()?)?)
+                    } else {
+                        None
+                    });
+                } else {
+                    return Err(meta.error("expected `versions`, `tag` or `compact`"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(attrs)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.versions.is_none() && self.tag.is_none() && self.compact.is_none()
+    }
+
+    /// Whether the field is encoded in `version`.
+    fn present(&self) -> TokenStream2 {
+        self.versions.map_or(quote!(true), |v| v.contains())
+    }
+
+    /// Whether the field uses the compact encoding in `version`.
+    fn compact(&self) -> TokenStream2 {
+        match self.compact {
+            None => quote!(false),
+            Some(None) => quote!(true),
+            Some(Some(versions)) => versions.contains(),
+        }
+    }
+}
+
+struct Field<'a> {
+    ident: &'a Ident,
+    ty: &'a Type,
+    attrs: FieldAttrs,
+}
+
+/// The named fields of a struct, with their attributes.
+fn struct_fields<'a>(input: &'a DeriveInput, derive: &str) -> Result<Vec<Field<'a>>> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            Fields::Unit => return Ok(vec![]),
+            Fields::Unnamed(_) => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    format!("{derive} requires named fields"),
+                ));
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                format!("{derive} can only be derived for structs"),
+            ));
+        }
+    };
+    let fields = fields
+        .iter()
+        .map(|field| {
+            Ok(Field {
+                ident: field.ident.as_ref().unwrap(),
+                ty: &field.ty,
+                attrs: FieldAttrs::parse(field)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let tagged_fields = fields.iter().find(|f| f.ident == "tagged_fields");
+    for field in &fields {
+        if field.attrs.tag.is_some() {
+            if tagged_fields.is_none() {
+                return Err(Error::new_spanned(
+                    field.ident,
+                    "tagged fields require a `tagged_fields` field",
+                ));
+            }
+            option_inner(field.ty)?;
+        }
+    }
+    Ok(fields)
+}
+
+/// The `T` of an `Option<T>`.
+fn option_inner(ty: &Type) -> Result<&Type> {
+    if let Type::Path(path) = ty
+        && let Some(segment) = path.path.segments.last()
+        && segment.ident == "Option"
+        && let PathArguments::AngleBracketed(args) = &segment.arguments
+        && let Some(GenericArgument::Type(inner)) = args.args.first()
+    {
+        return Ok(inner);
+    }
+    Err(Error::new_spanned(ty, "tagged fields must be an `Option`"))
+}
+
+/// Adds `bound` to every type parameter.
+fn add_bounds(input: &mut DeriveInput, bound: syn::TypeParamBound) {
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(bound.clone());
+    }
+}
+
+fn expand_byte_size(mut input: DeriveInput) -> Result<TokenStream2> {
+    add_bounds(&mut input, parse_quote!(crate::types::ByteSizeExt));
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let sizes = |fields: &Fields, bind: fn(usize, &syn::Field) -> TokenStream2| {
+        let sizes: Vec<_> = fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let value = bind(i, field);
+                quote!(crate::types::ByteSizeExt::byte_size(#value))
+            })
+            .collect();
+        if sizes.is_empty() {
+            quote!(0)
+        } else {
+            quote!(#(#sizes)+*)
+        }
+    };
+    let check = |fields: &Fields| -> Result<()> {
+        for field in fields {
+            if !FieldAttrs::parse(field)?.is_empty() {
+                return Err(Error::new_spanned(
+                    field,
+                    "ByteSize fields are encoded the same way in every version; \
+                     derive KafkaEncode for versioned fields",
+                ));
+            }
+        }
+        Ok(())
+    };
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            check(&data.fields)?;
+            sizes(&data.fields, |i, field| match &field.ident {
+                Some(ident) => quote!(&self.#ident),
+                None => {
+                    let index = syn::Index::from(i);
+                    quote!(&self.#index)
+                }
+            })
+        }
+        Data::Enum(data) => {
+            let mut arms = vec![];
+            for variant in &data.variants {
+                check(&variant.fields)?;
+                let ident = &variant.ident;
+                let binding = |i: usize, field: &syn::Field| match &field.ident {
+                    Some(ident) => quote!(#ident),
+                    None => {
+                        let ident = Ident::new(&format!("field{i}"), Span::call_site());
+                        quote!(#ident)
+                    }
+                };
+                let bindings: Vec<_> = variant
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .map(|(i, field)| binding(i, field))
+                    .collect();
+                let pattern = match &variant.fields {
+                    Fields::Named(_) => quote!(Self::#ident { #(#bindings),* }),
+                    Fields::Unnamed(_) => quote!(Self::#ident(#(#bindings),*)),
+                    Fields::Unit => quote!(Self::#ident),
+                };
+                let size = sizes(&variant.fields, binding);
+                arms.push(quote!(#pattern => #size));
+            }
+            if arms.is_empty() {
+                quote!(0)
+            } else {
+                quote!(match self { #(#arms),* })
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                name,
+                "ByteSize cannot be derived for unions",
+            ));
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics crate::types::ByteSizeExt for #name #ty_generics #where_clause {
+            fn byte_size(&self) -> usize {
+                #body
+            }
+        }
+    })
+}
+
+fn expand_encode(mut input: DeriveInput) -> Result<TokenStream2> {
+    add_bounds(&mut input, parse_quote!(crate::types::KafkaEncode));
+    let fields = struct_fields(&input, "KafkaEncode")?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut encode = vec![];
+    let mut size = vec![];
+    for field in fields.iter().filter(|f| f.attrs.tag.is_none()) {
+        let ident = field.ident;
+        let present = field.attrs.present();
+        let compact = field.attrs.compact();
+        if ident != "tagged_fields" {
+            encode.push(quote! {
+                if #present {
+                    crate::types::KafkaEncode::encode(&self.#ident, version, #compact, seq)?;
+                }
+            });
+            size.push(quote! {
+                if #present {
+                    size += crate::types::KafkaEncode::encoded_size(&self.#ident, version, #compact);
+                }
+            });
+            continue;
+        }
+
+        // The tagged fields section holds the tagged fields set, in tag
+        // order, with the unknown ones read.
+        let mut insert_encoded = vec![];
+        let mut insert_sized = vec![];
+        for tagged in fields.iter().filter(|f| f.attrs.tag.is_some()) {
+            let ident = tagged.ident;
+            let tag = tagged.attrs.tag.unwrap();
+            let present = tagged.attrs.present();
+            insert_encoded.push(quote! {
+                if let Some(value) = self.#ident.as_ref().filter(|_| #present) {
+                    let data = crate::types::encode_to_vec(value, version, true)
+                        .map_err(::serde::ser::Error::custom)?;
+                    tagged_fields.insert(#tag, data);
+                }
+            });
+            insert_sized.push(quote! {
+                if let Some(value) = self.#ident.as_ref().filter(|_| #present) {
+                    let size = crate::types::KafkaEncode::encoded_size(value, version, true);
+                    tagged_fields.insert(#tag, vec![0; size]);
+                }
+            });
+        }
+        let section = |inserts: &[TokenStream2]| {
+            if inserts.is_empty() {
+                quote!(let tagged_fields = &self.tagged_fields;)
+            } else {
+                quote! {
+                    let mut tagged_fields = self.tagged_fields.clone();
+                    #(#inserts)*
+                    let tagged_fields = &tagged_fields;
+                }
+            }
+        };
+        let encoded = section(&insert_encoded);
+        let sized = section(&insert_sized);
+        encode.push(quote! {
+            if #present {
+                #encoded
+                crate::types::KafkaEncode::encode(tagged_fields, version, false, seq)?;
+            }
+        });
+        size.push(quote! {
+            if #present {
+                #sized
+                size += crate::types::KafkaEncode::encoded_size(tagged_fields, version, false);
+            }
+        });
+    }
+
+    let size = if size.is_empty() {
+        quote!(0)
+    } else {
+        quote! {
+            let mut size = 0;
+            #(#size)*
+            size
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics crate::types::KafkaEncode for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn encode<S: ::serde::ser::SerializeSeq>(
+                &self,
+                version: i16,
+                _compact: bool,
+                seq: &mut S,
+            ) -> ::std::result::Result<(), S::Error> {
+                #(#encode)*
+                Ok(())
+            }
+
+            #[allow(unused_variables)]
+            fn encoded_size(&self, version: i16, _compact: bool) -> usize {
+                #size
+            }
+        }
+    })
+}
+
+fn expand_decode(mut input: DeriveInput) -> Result<TokenStream2> {
+    add_bounds(&mut input, parse_quote!(crate::types::KafkaDecode));
+    let fields = struct_fields(&input, "KafkaDecode")?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let has_tags = fields.iter().any(|f| f.attrs.tag.is_some());
+
+    let mut decode = vec![];
+    for field in fields.iter().filter(|f| f.attrs.tag.is_none()) {
+        let ident = field.ident;
+        let ty = field.ty;
+        let present = field.attrs.present();
+        let compact = field.attrs.compact();
+        let binding = if ident == "tagged_fields" && has_tags {
+            quote!(mut #ident)
+        } else {
+            quote!(#ident)
+        };
+        decode.push(quote! {
+            let #binding: #ty = if #present {
+                crate::types::KafkaDecode::decode(version, #compact, deserializer)?
+            } else {
+                ::std::default::Default::default()
+            };
+        });
+    }
+    for field in fields.iter().filter(|f| f.attrs.tag.is_some()) {
+        let ident = field.ident;
+        let ty = field.ty;
+        let tag = field.attrs.tag.unwrap();
+        let present = field.attrs.present();
+        // A tag outside the versions of its field stays an unknown tag.
+        decode.push(quote! {
+            let #ident: #ty = if #present && let Some(data) = tagged_fields.remove(#tag) {
+                Some(crate::types::decode_from_slice(&data, version, true)?)
+            } else {
+                None
+            };
+        });
+    }
+    let idents = fields.iter().map(|f| f.ident);
+
+    Ok(quote! {
+        impl #impl_generics crate::types::KafkaDecode for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn decode<R: ::std::io::Read>(
+                version: i16,
+                _compact: bool,
+                deserializer: &mut crate::de::Deserializer<R>,
+            ) -> crate::Result<Self> {
+                #(#decode)*
+                Ok(Self { #(#idents),* })
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_versions() {
+        let parse = |s: &str| Versions::parse(&LitStr::new(s, Span::call_site()));
+        assert_eq!(parse("3+").unwrap(), Versions { min: 3, max: None });
+        assert_eq!(
+            parse("0-2").unwrap(),
+            Versions {
+                min: 0,
+                max: Some(2)
+            }
+        );
+        assert_eq!(
+            parse("1").unwrap(),
+            Versions {
+                min: 1,
+                max: Some(1)
+            }
+        );
+        assert!(parse("2-1").is_err());
+        assert!(parse("none").is_err());
+    }
+
+    /// Whether `expanded` contains the tokens of `expected`.
+    fn contains(expanded: &TokenStream2, expected: TokenStream2) -> bool {
+        expanded.to_string().contains(&expected.to_string())
+    }
+
+    #[test]
+    fn test_expand_versions() {
+        let input: DeriveInput = parse_quote! {
+            struct Request {
+                #[kafka(versions = "0-2")]
+                a: i32,
+                #[kafka(versions = "1")]
+                b: i32,
+                #[kafka(versions = "3+")]
+                c: i32,
+                d: i32,
+            }
+        };
+
+        let encode = expand_encode(input.clone()).unwrap();
+        for (ident, present) in [
+            (quote!(a), quote!((0i16..=2i16).contains(&version))),
+            (quote!(b), quote!(version == 1i16)),
+            (quote!(c), quote!(version >= 3i16)),
+            (quote!(d), quote!(true)),
+        ] {
+            assert!(contains(
+                &encode,
+                quote! {
+                    if #present {
+                        crate::types::KafkaEncode::encode(&self.#ident, version, false, seq)?;
+                    }
+                }
+            ));
+        }
+
+        let decode = expand_decode(input).unwrap();
+        assert!(contains(
+            &decode,
+            quote! {
+                let c: i32 = if version >= 3i16 {
+                    crate::types::KafkaDecode::decode(version, false, deserializer)?
+                } else {
+                    ::std::default::Default::default()
+                };
+            }
+        ));
+        assert!(contains(&decode, quote!(Ok(Self { a, b, c, d }))));
+    }
+
+    #[test]
+    fn test_expand_compact() {
+        let input: DeriveInput = parse_quote! {
+            struct Topic {
+                #[kafka(compact)]
+                name: String,
+                #[kafka(compact = "2+")]
+                partitions: Vec<i32>,
+            }
+        };
+
+        let encode = expand_encode(input.clone()).unwrap();
+        assert!(contains(
+            &encode,
+            quote!(crate::types::KafkaEncode::encode(
+                &self.name, version, true, seq
+            )?)
+        ));
+        assert!(contains(
+            &encode,
+            quote!(crate::types::KafkaEncode::encoded_size(
+                &self.partitions,
+                version,
+                version >= 2i16
+            ))
+        ));
+
+        let decode = expand_decode(input).unwrap();
+        assert!(contains(
+            &decode,
+            quote!(crate::types::KafkaDecode::decode(
+                version,
+                version >= 2i16,
+                deserializer
+            )?)
+        ));
+    }
+
+    #[test]
+    fn test_expand_tag() {
+        let input: DeriveInput = parse_quote! {
+            struct Response {
+                #[kafka(versions = "3+", tag = 1)]
+                epoch: Option<i32>,
+                #[kafka(versions = "2+")]
+                tagged_fields: TaggedFields,
+            }
+        };
+
+        let encode = expand_encode(input.clone()).unwrap();
+        assert!(contains(
+            &encode,
+            quote! {
+                if let Some(value) = self.epoch.as_ref().filter(|_| version >= 3i16) {
+                    let data = crate::types::encode_to_vec(value, version, true)
+                        .map_err(::serde::ser::Error::custom)?;
+                    tagged_fields.insert(1u64, data);
+                }
+            }
+        ));
+        assert!(contains(
+            &encode,
+            quote!(crate::types::KafkaEncode::encode(
+                tagged_fields,
+                version,
+                false,
+                seq
+            )?)
+        ));
+
+        let decode = expand_decode(input).unwrap();
+        assert!(contains(
+            &decode,
+            quote!(let mut tagged_fields: TaggedFields)
+        ));
+        assert!(contains(
+            &decode,
+            quote!(if version >= 3i16 && let Some(data) = tagged_fields.remove(1u64))
+        ));
+    }
+}
@@ -0,0 +1,7 @@
+//! Derives with bad `#[kafka]` attributes fail to compile with an error on
+//! the attribute.
+
+#[test]
+fn test_bad_attributes() {
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,9 @@
+use kafka_derive::ByteSize;
+
+#[derive(ByteSize)]
+struct Topic {
+    #[kafka(compact)]
+    name: String,
+}
+
+fn main() {}
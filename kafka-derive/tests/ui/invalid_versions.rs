@@ -0,0 +1,9 @@
+use kafka_derive::KafkaDecode;
+
+#[derive(KafkaDecode)]
+struct Request {
+    #[kafka(versions = "2-1")]
+    rack: String,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use kafka_derive::KafkaEncode;
+
+#[derive(KafkaEncode)]
+struct Response {
+    #[kafka(tag = 0)]
+    epoch: i32,
+    tagged_fields: Vec<u8>,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use kafka_derive::KafkaDecode;
+
+#[derive(KafkaDecode)]
+struct Response {
+    #[kafka(tag = 0)]
+    epoch: Option<i32>,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use kafka_derive::KafkaEncode;
+
+#[derive(KafkaEncode)]
+struct Request {
+    #[kafka(version = "1+")]
+    rack: String,
+}
+
+fn main() {}
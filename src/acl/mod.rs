@@ -0,0 +1,361 @@
+mod standard;
+
+pub(crate) use standard::StandardAuthorizer;
+
+use crate::api::ErrorCode;
+
+use std::fmt;
+
+/// The name of the cluster resource, the only one of its type.
+pub(crate) const CLUSTER_NAME: &str = "kafka-cluster";
+
+/// A resource name matching every resource of its type.
+const WILDCARD_RESOURCE: &str = "*";
+
+/// The principal and host matching every principal and host.
+const WILDCARD_PRINCIPAL: &str = "User:*";
+const WILDCARD_HOST: &str = "*";
+
+/// Why creating or deleting an ACL failed.
+pub(crate) type AclError = (ErrorCode, String);
+
+/// An operation on a resource, as in the `authorized_operations` fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum AclOperation {
+    /// Only in filters: matches every operation.
+    Any = 1,
+    All = 2,
+    Read = 3,
+    Write = 4,
+    Create = 5,
+    Delete = 6,
+    Alter = 7,
+    Describe = 8,
+    ClusterAction = 9,
+    DescribeConfigs = 10,
+    AlterConfigs = 11,
+    IdempotentWrite = 12,
+    CreateTokens = 13,
+    DescribeTokens = 14,
+}
+
+impl AclOperation {
+    pub(crate) fn from_i8(code: i8) -> Option<Self> {
+        match code {
+            1 => Some(Self::Any),
+            2 => Some(Self::All),
+            3 => Some(Self::Read),
+            4 => Some(Self::Write),
+            5 => Some(Self::Create),
+            6 => Some(Self::Delete),
+            7 => Some(Self::Alter),
+            8 => Some(Self::Describe),
+            9 => Some(Self::ClusterAction),
+            10 => Some(Self::DescribeConfigs),
+            11 => Some(Self::AlterConfigs),
+            12 => Some(Self::IdempotentWrite),
+            13 => Some(Self::CreateTokens),
+            14 => Some(Self::DescribeTokens),
+            _ => None,
+        }
+    }
+
+    /// Whether an ACL allowing this operation allows `operation` too. ALL
+    /// implies every operation; READ, WRITE, DELETE and ALTER imply
+    /// DESCRIBE; ALTER_CONFIGS implies DESCRIBE_CONFIGS.
+    fn implies(self, operation: AclOperation) -> bool {
+        self == operation
+            || self == Self::All
+            || (operation == Self::Describe
+                && matches!(self, Self::Read | Self::Write | Self::Delete | Self::Alter))
+            || (operation == Self::DescribeConfigs && self == Self::AlterConfigs)
+    }
+}
+
+/// The type of resource an ACL applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ResourceType {
+    /// Only in filters: matches every resource type.
+    Any = 1,
+    Topic = 2,
+    Group = 3,
+    Cluster = 4,
+    TransactionalId = 5,
+    DelegationToken = 6,
+    User = 7,
+}
+
+impl ResourceType {
+    pub(crate) fn from_i8(code: i8) -> Option<Self> {
+        match code {
+            1 => Some(Self::Any),
+            2 => Some(Self::Topic),
+            3 => Some(Self::Group),
+            4 => Some(Self::Cluster),
+            5 => Some(Self::TransactionalId),
+            6 => Some(Self::DelegationToken),
+            7 => Some(Self::User),
+            _ => None,
+        }
+    }
+}
+
+/// How the resource name of an ACL matches the names of resources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum PatternType {
+    /// Only in filters: matches ACLs of every pattern type.
+    Any = 1,
+    /// Only in filters: matches the ACLs which apply to the resource name.
+    Match = 2,
+    Literal = 3,
+    Prefixed = 4,
+}
+
+impl PatternType {
+    pub(crate) fn from_i8(code: i8) -> Option<Self> {
+        match code {
+            1 => Some(Self::Any),
+            2 => Some(Self::Match),
+            3 => Some(Self::Literal),
+            4 => Some(Self::Prefixed),
+            _ => None,
+        }
+    }
+}
+
+/// Whether an ACL allows or denies its operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum PermissionType {
+    /// Only in filters: matches both permission types.
+    Any = 1,
+    Deny = 2,
+    Allow = 3,
+}
+
+impl PermissionType {
+    pub(crate) fn from_i8(code: i8) -> Option<Self> {
+        match code {
+            1 => Some(Self::Any),
+            2 => Some(Self::Deny),
+            3 => Some(Self::Allow),
+            _ => None,
+        }
+    }
+}
+
+/// An ACL: whether `principal` connecting from `host` may perform
+/// `operation` on the resources matching the pattern.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct AclBinding {
+    pub(crate) resource_type: ResourceType,
+    pub(crate) resource_name: String,
+    pub(crate) pattern_type: PatternType,
+    pub(crate) principal: String,
+    pub(crate) host: String,
+    pub(crate) operation: AclOperation,
+    pub(crate) permission_type: PermissionType,
+}
+
+impl AclBinding {
+    /// Rejects the filter-only values and malformed principals.
+    fn validate(&self) -> Result<(), AclError> {
+        let invalid = |message: &str| Err((ErrorCode::InvalidRequest, message.to_string()));
+        if self.resource_type == ResourceType::Any {
+            return invalid("Invalid resource type ANY");
+        }
+        if !matches!(
+            self.pattern_type,
+            PatternType::Literal | PatternType::Prefixed
+        ) {
+            return invalid("Invalid pattern type, only LITERAL and PREFIXED are allowed");
+        }
+        if self.operation == AclOperation::Any {
+            return invalid("Invalid operation ANY");
+        }
+        if self.permission_type == PermissionType::Any {
+            return invalid("Invalid permission type ANY");
+        }
+        if self.resource_name.is_empty() {
+            return invalid("Resource name must not be empty");
+        }
+        if !self
+            .principal
+            .split_once(':')
+            .is_some_and(|(kind, name)| !kind.is_empty() && !name.is_empty())
+        {
+            return invalid(&format!("Invalid principal {}", self.principal));
+        }
+        Ok(())
+    }
+
+    /// Whether the pattern of the ACL applies to the resource.
+    fn applies_to(&self, resource_type: ResourceType, resource_name: &str) -> bool {
+        self.resource_type == resource_type
+            && match self.pattern_type {
+                PatternType::Literal => {
+                    self.resource_name == resource_name || self.resource_name == WILDCARD_RESOURCE
+                }
+                PatternType::Prefixed => resource_name.starts_with(&self.resource_name),
+                PatternType::Any | PatternType::Match => false,
+            }
+    }
+
+    fn applies_to_client(&self, principal: &str, host: &str) -> bool {
+        (self.principal == principal || self.principal == WILDCARD_PRINCIPAL)
+            && (self.host == host || self.host == WILDCARD_HOST)
+    }
+}
+
+/// Selects ACLs, as in DescribeAcls and DeleteAcls. `None` and the `Any`
+/// values match everything.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AclBindingFilter {
+    pub(crate) resource_type: ResourceType,
+    pub(crate) resource_name: Option<String>,
+    pub(crate) pattern_type: PatternType,
+    pub(crate) principal: Option<String>,
+    pub(crate) host: Option<String>,
+    pub(crate) operation: AclOperation,
+    pub(crate) permission_type: PermissionType,
+}
+
+impl AclBindingFilter {
+    pub(crate) fn matches(&self, binding: &AclBinding) -> bool {
+        self.matches_pattern(binding)
+            && self
+                .principal
+                .as_ref()
+                .is_none_or(|principal| *principal == binding.principal)
+            && self.host.as_ref().is_none_or(|host| *host == binding.host)
+            && (self.operation == AclOperation::Any || self.operation == binding.operation)
+            && (self.permission_type == PermissionType::Any
+                || self.permission_type == binding.permission_type)
+    }
+
+    fn matches_pattern(&self, binding: &AclBinding) -> bool {
+        if self.resource_type != ResourceType::Any && self.resource_type != binding.resource_type {
+            return false;
+        }
+        let Some(name) = &self.resource_name else {
+            return matches!(self.pattern_type, PatternType::Any | PatternType::Match)
+                || self.pattern_type == binding.pattern_type;
+        };
+        match self.pattern_type {
+            PatternType::Any => *name == binding.resource_name,
+            PatternType::Match => binding.applies_to(binding.resource_type, name),
+            pattern_type => pattern_type == binding.pattern_type && *name == binding.resource_name,
+        }
+    }
+}
+
+/// Decides which operations clients may perform, and manages the ACLs the
+/// decisions are based on.
+pub(crate) trait Authorizer: fmt::Debug + Send + Sync {
+    /// Whether `principal`, connected from `host`, may perform `operation`
+    /// on the named resource.
+    fn authorize(
+        &self,
+        principal: &str,
+        host: &str,
+        operation: AclOperation,
+        resource_type: ResourceType,
+        resource_name: &str,
+    ) -> bool;
+
+    /// Whether `principal`, connected from `host`, may perform `operation`
+    /// on some resource of the type, as Kafka's `authorizeByResourceType`.
+    fn authorize_any(
+        &self,
+        principal: &str,
+        host: &str,
+        operation: AclOperation,
+        resource_type: ResourceType,
+    ) -> bool;
+
+    /// Adds the ACLs, returning the outcome for each in order.
+    fn create_acls(&self, bindings: Vec<AclBinding>) -> Vec<Result<(), AclError>>;
+
+    /// The ACLs matching the filter.
+    fn acls(&self, filter: &AclBindingFilter) -> Vec<AclBinding>;
+
+    /// Removes the ACLs matching each filter, returning the ACLs removed by
+    /// each in order.
+    fn delete_acls(&self, filters: Vec<AclBindingFilter>)
+    -> Vec<Result<Vec<AclBinding>, AclError>>;
+}
+
+/// The operations which apply to topics.
+pub(crate) const TOPIC_OPERATIONS: &[AclOperation] = &[
+    AclOperation::Read,
+    AclOperation::Write,
+    AclOperation::Create,
+    AclOperation::Delete,
+    AclOperation::Alter,
+    AclOperation::Describe,
+    AclOperation::DescribeConfigs,
+    AclOperation::AlterConfigs,
+];
+
+/// The operations which apply to groups.
+pub(crate) const GROUP_OPERATIONS: &[AclOperation] = &[
+    AclOperation::Read,
+    AclOperation::Delete,
+    AclOperation::Describe,
+];
+
+/// Encodes operations as the bit field of the `authorized_operations`
+/// fields, where bit `n` is set for the operation with code `n`.
+pub(crate) fn operations_bitfield(operations: &[AclOperation]) -> i32 {
+    operations
+        .iter()
+        .fold(0, |bits, &operation| bits | (1 << operation as i32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operations_bitfield() {
+        assert_eq!(operations_bitfield(&[]), 0);
+        assert_eq!(
+            operations_bitfield(&[AclOperation::Read, AclOperation::Describe]),
+            0b1_0000_1000
+        );
+        assert_eq!(operations_bitfield(TOPIC_OPERATIONS), 0b1101_1111_1000);
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let binding = AclBinding {
+            resource_type: ResourceType::Topic,
+            resource_name: "orders-".to_string(),
+            pattern_type: PatternType::Prefixed,
+            principal: "User:alice".to_string(),
+            host: "*".to_string(),
+            operation: AclOperation::Read,
+            permission_type: PermissionType::Allow,
+        };
+        let filter = |resource_name: Option<&str>, pattern_type| AclBindingFilter {
+            resource_type: ResourceType::Any,
+            resource_name: resource_name.map(str::to_string),
+            pattern_type,
+            principal: None,
+            host: None,
+            operation: AclOperation::Any,
+            permission_type: PermissionType::Any,
+        };
+
+        assert!(filter(None, PatternType::Any).matches(&binding));
+        assert!(filter(None, PatternType::Prefixed).matches(&binding));
+        assert!(!filter(None, PatternType::Literal).matches(&binding));
+        assert!(filter(Some("orders-"), PatternType::Any).matches(&binding));
+        assert!(!filter(Some("orders-eu"), PatternType::Prefixed).matches(&binding));
+        assert!(filter(Some("orders-eu"), PatternType::Match).matches(&binding));
+        assert!(!filter(Some("payments"), PatternType::Match).matches(&binding));
+
+        let mut principal_filter = filter(None, PatternType::Any);
+        principal_filter.principal = Some("User:bob".to_string());
+        assert!(!principal_filter.matches(&binding));
+    }
+}
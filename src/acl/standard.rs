@@ -0,0 +1,430 @@
+use super::{
+    AclBinding, AclBindingFilter, AclError, AclOperation, Authorizer, PatternType, PermissionType,
+    ResourceType, WILDCARD_RESOURCE,
+};
+
+use crate::{
+    Config, Result,
+    api::ErrorCode,
+    log::LogManager,
+    types::{
+        AccessControlEntryRecord, CompactString, RecordVariant, RemoveAccessControlEntryRecord,
+        Uuid,
+    },
+};
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Evaluates the ACLs of the cluster, as Kafka's `StandardAuthorizer`
+/// does: super users may do anything, a matching DENY wins over a matching
+/// ALLOW, and resources without any ACL are only accessible when
+/// `allow.everyone.if.no.acl.found` is set. ACLs are persisted as
+/// `AccessControlEntryRecord`s in the metadata log.
+#[derive(Debug)]
+pub(crate) struct StandardAuthorizer {
+    logs: Arc<LogManager>,
+    super_users: Vec<String>,
+    allow_everyone_if_no_acl_found: bool,
+    acls: Mutex<BTreeMap<Uuid, AclBinding>>,
+}
+
+impl StandardAuthorizer {
+    pub(crate) fn new(config: &Config, logs: Arc<LogManager>) -> Self {
+        Self {
+            logs,
+            super_users: config.super_users.clone(),
+            allow_everyone_if_no_acl_found: config.allow_everyone_if_no_acl_found,
+            acls: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Replays the ACL records of the metadata log.
+    pub(crate) fn load(&self) -> Result<()> {
+        let metadata = self.logs.read_metadata()?;
+
+        let mut acls = self.acls.lock().unwrap();
+        for value in metadata {
+            apply_record(&mut acls, value.value);
+        }
+        Ok(())
+    }
+
+    /// Writes the records and applies them once they are persisted.
+    fn commit(
+        &self,
+        acls: &mut BTreeMap<Uuid, AclBinding>,
+        records: Vec<RecordVariant>,
+    ) -> std::result::Result<(), AclError> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        self.logs.append_metadata(records.clone()).map_err(|e| {
+            eprintln!("Failed to write ACL records: {e}");
+            (ErrorCode::KafkaStorageError, e.to_string())
+        })?;
+        for record in records {
+            apply_record(acls, record);
+        }
+        Ok(())
+    }
+}
+
+impl Authorizer for StandardAuthorizer {
+    fn authorize(
+        &self,
+        principal: &str,
+        host: &str,
+        operation: AclOperation,
+        resource_type: ResourceType,
+        resource_name: &str,
+    ) -> bool {
+        if self.super_users.iter().any(|user| user == principal) {
+            return true;
+        }
+
+        let acls = self.acls.lock().unwrap();
+        let mut resource_acls = acls
+            .values()
+            .filter(|binding| binding.applies_to(resource_type, resource_name))
+            .peekable();
+        if resource_acls.peek().is_none() {
+            return self.allow_everyone_if_no_acl_found;
+        }
+
+        let mut allowed = false;
+        for binding in resource_acls.filter(|binding| binding.applies_to_client(principal, host)) {
+            match binding.permission_type {
+                PermissionType::Deny
+                    if binding.operation == operation || binding.operation == AclOperation::All =>
+                {
+                    return false;
+                }
+                PermissionType::Allow if binding.operation.implies(operation) => allowed = true,
+                _ => {}
+            }
+        }
+        allowed
+    }
+
+    fn authorize_any(
+        &self,
+        principal: &str,
+        host: &str,
+        operation: AclOperation,
+        resource_type: ResourceType,
+    ) -> bool {
+        if self.super_users.iter().any(|user| user == principal) {
+            return true;
+        }
+
+        let acls = self.acls.lock().unwrap();
+        let mut type_acls = acls
+            .values()
+            .filter(|binding| binding.resource_type == resource_type)
+            .peekable();
+        if type_acls.peek().is_none() {
+            return self.allow_everyone_if_no_acl_found;
+        }
+
+        // A DENY only rules out every resource when it applies to all names.
+        let mut allowed = false;
+        for binding in type_acls.filter(|binding| binding.applies_to_client(principal, host)) {
+            match binding.permission_type {
+                PermissionType::Deny
+                    if (binding.operation == operation
+                        || binding.operation == AclOperation::All)
+                        && binding.pattern_type == PatternType::Literal
+                        && binding.resource_name == WILDCARD_RESOURCE =>
+                {
+                    return false;
+                }
+                PermissionType::Allow if binding.operation.implies(operation) => allowed = true,
+                _ => {}
+            }
+        }
+        allowed
+    }
+
+    fn create_acls(&self, bindings: Vec<AclBinding>) -> Vec<std::result::Result<(), AclError>> {
+        let mut acls = self.acls.lock().unwrap();
+
+        // Existing ACLs are not added again, but their creation succeeds.
+        let mut results = vec![];
+        let mut created: Vec<AclBinding> = vec![];
+        for binding in bindings {
+            let result = binding.validate();
+            if result.is_ok() && !acls.values().chain(&created).any(|acl| *acl == binding) {
+                created.push(binding);
+            }
+            results.push(result);
+        }
+
+        let records = created
+            .into_iter()
+            .map(|binding| {
+                RecordVariant::AccessControlEntry(AccessControlEntryRecord {
+                    id: Uuid::random(),
+                    resource_type: binding.resource_type as i8,
+                    resource_name: CompactString::new(binding.resource_name),
+                    pattern_type: binding.pattern_type as i8,
+                    principal: CompactString::new(binding.principal),
+                    host: CompactString::new(binding.host),
+                    operation: binding.operation as i8,
+                    permission_type: binding.permission_type as i8,
+                })
+            })
+            .collect();
+
+        let written = self.commit(&mut acls, records);
+        results
+            .into_iter()
+            .map(|result| result.and_then(|()| written.clone()))
+            .collect()
+    }
+
+    fn acls(&self, filter: &AclBindingFilter) -> Vec<AclBinding> {
+        self.acls
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|binding| filter.matches(binding))
+            .cloned()
+            .collect()
+    }
+
+    fn delete_acls(
+        &self,
+        filters: Vec<AclBindingFilter>,
+    ) -> Vec<std::result::Result<Vec<AclBinding>, AclError>> {
+        let mut acls = self.acls.lock().unwrap();
+
+        // An ACL matched by several filters is reported by the first.
+        let mut deleted: Vec<Uuid> = vec![];
+        let mut matches: Vec<Vec<AclBinding>> = vec![];
+        for filter in &filters {
+            let mut matched = vec![];
+            for (id, binding) in acls.iter() {
+                if !deleted.contains(id) && filter.matches(binding) {
+                    deleted.push(*id);
+                    matched.push(binding.clone());
+                }
+            }
+            matches.push(matched);
+        }
+
+        let records = deleted
+            .into_iter()
+            .map(|id| {
+                RecordVariant::RemoveAccessControlEntry(RemoveAccessControlEntryRecord { id })
+            })
+            .collect();
+        let written = self.commit(&mut acls, records);
+        matches
+            .into_iter()
+            .map(|matched| written.clone().map(|()| matched))
+            .collect()
+    }
+}
+
+/// The ACL of a record, or `None` if it has codes this broker does not know.
+fn binding_of(record: &AccessControlEntryRecord) -> Option<AclBinding> {
+    Some(AclBinding {
+        resource_type: ResourceType::from_i8(record.resource_type)?,
+        resource_name: record.resource_name.as_str().to_string(),
+        pattern_type: PatternType::from_i8(record.pattern_type)?,
+        principal: record.principal.as_str().to_string(),
+        host: record.host.as_str().to_string(),
+        operation: AclOperation::from_i8(record.operation)?,
+        permission_type: PermissionType::from_i8(record.permission_type)?,
+    })
+}
+
+fn apply_record(acls: &mut BTreeMap<Uuid, AclBinding>, record: RecordVariant) {
+    match record {
+        RecordVariant::AccessControlEntry(record) => {
+            if let Some(binding) = binding_of(&record) {
+                acls.insert(record.id, binding);
+            }
+        }
+        RecordVariant::RemoveAccessControlEntry(record) => {
+            acls.remove(&record.id);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorizer(dir: &std::path::Path, properties: &str) -> StandardAuthorizer {
+        let config = Config::from_properties(properties).unwrap();
+        let authorizer = StandardAuthorizer::new(&config, Arc::new(LogManager::new(dir)));
+        authorizer.load().unwrap();
+        authorizer
+    }
+
+    fn acl(
+        resource_name: &str,
+        pattern_type: PatternType,
+        principal: &str,
+        operation: AclOperation,
+        permission_type: PermissionType,
+    ) -> AclBinding {
+        AclBinding {
+            resource_type: ResourceType::Topic,
+            resource_name: resource_name.to_string(),
+            pattern_type,
+            principal: principal.to_string(),
+            host: "*".to_string(),
+            operation,
+            permission_type,
+        }
+    }
+
+    #[test]
+    fn test_authorize_and_persist_acls() {
+        let dir = std::env::temp_dir().join(format!("kafka-acl-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let authorizer = authorizer(&dir, "super.users=User:admin");
+
+        let results = authorizer.create_acls(vec![
+            acl(
+                "orders-",
+                PatternType::Prefixed,
+                "User:alice",
+                AclOperation::Read,
+                PermissionType::Allow,
+            ),
+            acl(
+                "orders-secret",
+                PatternType::Literal,
+                "User:*",
+                AclOperation::All,
+                PermissionType::Deny,
+            ),
+            acl(
+                "*",
+                PatternType::Literal,
+                "User:bob",
+                AclOperation::Write,
+                PermissionType::Allow,
+            ),
+            acl(
+                "orders-",
+                PatternType::Any,
+                "User:bob",
+                AclOperation::Read,
+                PermissionType::Allow,
+            ),
+        ]);
+        let codes: Vec<Option<ErrorCode>> = results
+            .iter()
+            .map(|result| result.as_ref().err().map(|e| e.0))
+            .collect();
+        assert_eq!(codes, [None, None, None, Some(ErrorCode::InvalidRequest)]);
+
+        let authorize = |authorizer: &StandardAuthorizer, principal, operation, topic| {
+            authorizer.authorize(principal, "10.0.0.1", operation, ResourceType::Topic, topic)
+        };
+        // Prefixed ACLs apply, and READ implies DESCRIBE.
+        assert!(authorize(
+            &authorizer,
+            "User:alice",
+            AclOperation::Read,
+            "orders-eu"
+        ));
+        assert!(authorize(
+            &authorizer,
+            "User:alice",
+            AclOperation::Describe,
+            "orders-eu"
+        ));
+        assert!(!authorize(
+            &authorizer,
+            "User:alice",
+            AclOperation::Write,
+            "orders-eu"
+        ));
+        // A DENY wins over a matching ALLOW, except for super users.
+        assert!(!authorize(
+            &authorizer,
+            "User:alice",
+            AclOperation::Read,
+            "orders-secret"
+        ));
+        assert!(authorize(
+            &authorizer,
+            "User:admin",
+            AclOperation::Read,
+            "orders-secret"
+        ));
+        // The wildcard resource name applies to every topic.
+        assert!(authorize(
+            &authorizer,
+            "User:bob",
+            AclOperation::Write,
+            "payments"
+        ));
+        assert!(!authorize(
+            &authorizer,
+            "User:carol",
+            AclOperation::Write,
+            "payments"
+        ));
+        // Some topic may be written by bob, through the wildcard, and read by
+        // alice, through the prefix.
+        let authorize_any = |principal, operation| {
+            authorizer.authorize_any(principal, "10.0.0.1", operation, ResourceType::Topic)
+        };
+        assert!(authorize_any("User:bob", AclOperation::Write));
+        assert!(authorize_any("User:alice", AclOperation::Read));
+        assert!(!authorize_any("User:alice", AclOperation::Write));
+        assert!(!authorize_any("User:carol", AclOperation::Write));
+        // Group resources have no ACL and everyone is denied by default.
+        assert!(!authorizer.authorize(
+            "User:alice",
+            "10.0.0.1",
+            AclOperation::Read,
+            ResourceType::Group,
+            "group"
+        ));
+
+        // The ACLs are replayed from the metadata log.
+        let authorizer = self::authorizer(&dir, "");
+        let all = AclBindingFilter {
+            resource_type: ResourceType::Any,
+            resource_name: None,
+            pattern_type: PatternType::Any,
+            principal: None,
+            host: None,
+            operation: AclOperation::Any,
+            permission_type: PermissionType::Any,
+        };
+        assert_eq!(authorizer.acls(&all).len(), 3);
+
+        let deny = AclBindingFilter {
+            permission_type: PermissionType::Deny,
+            ..all.clone()
+        };
+        let results = authorizer.delete_acls(vec![deny, all.clone()]);
+        let deleted: Vec<usize> = results
+            .into_iter()
+            .map(|result| result.unwrap().len())
+            .collect();
+        assert_eq!(deleted, [1, 2]);
+        assert!(authorizer.acls(&all).is_empty());
+        assert!(self::authorizer(&dir, "").acls(&all).is_empty());
+
+        // Without any ACL, everyone may be allowed.
+        let authorizer = self::authorizer(&dir, "allow.everyone.if.no.acl.found=true");
+        assert!(authorize(
+            &authorizer,
+            "User:carol",
+            AclOperation::Write,
+            "payments"
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
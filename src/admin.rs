@@ -0,0 +1,242 @@
+//! A minimal HTTP endpoint for operational queries that have no Kafka API.
+//!
+//! Routes:
+//! - `GET /groups/{group_id}/lag`: committed offset, log end offset and lag
+//!   of every partition the group has committed offsets for.
+
+use crate::{Broker, Result, group::PartitionLag};
+
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Weak};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How long accepting waits between checks for the broker shutting down.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long a connection may take to send its request or read the
+/// response, so a stalled client only holds its own thread.
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Requests are a request line and headers; longer ones are cut off.
+const MAX_REQUEST_BYTES: u64 = 16 * 1024;
+
+/// Serves admin requests on `listener` from a background thread, until the
+/// broker shuts down. Each connection is handled on a thread of its own.
+pub fn serve_admin(listener: TcpListener, broker: Arc<Broker>) -> Result<()> {
+    // Accepting must not block, to notice the shutdown.
+    listener.set_nonblocking(true)?;
+    let weak: Weak<Broker> = Arc::downgrade(&broker);
+    broker.spawn_background(move |background| {
+        let mut connections: Vec<JoinHandle<()>> = vec![];
+        loop {
+            connections.retain(|connection| !connection.is_finished());
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    if background.sleep(ACCEPT_POLL_INTERVAL) {
+                        break;
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("Error accepting admin connection: {e}");
+                    continue;
+                }
+            };
+            let Some(broker) = weak.upgrade() else {
+                break;
+            };
+            connections.push(std::thread::spawn(move || {
+                if let Err(e) = handle(stream, &broker) {
+                    eprintln!("Error handling admin request: {e}");
+                }
+            }));
+        }
+        // The timeouts bound how long the last requests take.
+        for connection in connections {
+            let _ = connection.join();
+        }
+    });
+    Ok(())
+}
+
+fn handle(stream: TcpStream, broker: &Broker) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let mut reader = BufReader::new((&stream).take(MAX_REQUEST_BYTES));
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Skip the headers, requests have no body.
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let (status, body) = route(&request_line, broker)?;
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    (&stream).write_all(response.as_bytes())?;
+    Ok(())
+}
+
+fn route(request_line: &str, broker: &Broker) -> Result<(&'static str, String)> {
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Ok(("400 Bad Request", error("malformed request")));
+    };
+
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["groups", group_id, "lag"] => {
+            if method != "GET" {
+                return Ok(("405 Method Not Allowed", error("method not allowed")));
+            }
+            let group_id = percent_decode(group_id);
+            match broker.groups.lag(&group_id)? {
+                Some(lag) => Ok(("200 OK", lag_json(&group_id, &lag))),
+                None => Ok(("404 Not Found", error("group not found"))),
+            }
+        }
+        _ => Ok(("404 Not Found", error("not found"))),
+    }
+}
+
+fn lag_json(group_id: &str, lag: &[PartitionLag]) -> String {
+    let total: i64 = lag.iter().filter_map(PartitionLag::lag).sum();
+
+    let mut json = format!(
+        "{{\"group_id\":{},\"total_lag\":{total},\"partitions\":[",
+        json_string(group_id)
+    );
+    for (i, partition) in lag.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        let _ = write!(
+            json,
+            "{{\"topic\":{},\"partition\":{},\"committed_offset\":{},\"log_end_offset\":{},\"lag\":{}}}",
+            json_string(&partition.tp.topic),
+            partition.tp.partition,
+            partition.committed_offset,
+            json_number(partition.log_end_offset),
+            json_number(partition.lag()),
+        );
+    }
+    json.push_str("]}");
+    json
+}
+
+fn error(message: &str) -> String {
+    format!("{{\"error\":{}}}", json_string(message))
+}
+
+fn json_number(value: Option<i64>) -> String {
+    value.map_or_else(|| "null".to_string(), |v| v.to_string())
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::TopicPartition;
+
+    #[test]
+    fn test_lag_json() {
+        let lag = vec![
+            PartitionLag {
+                tp: TopicPartition::new("foo", 0),
+                committed_offset: 1,
+                log_end_offset: Some(3),
+            },
+            PartitionLag {
+                tp: TopicPartition::new("bar", 1),
+                committed_offset: 5,
+                log_end_offset: None,
+            },
+        ];
+        assert_eq!(
+            lag_json("g\"1", &lag),
+            "{\"group_id\":\"g\\\"1\",\"total_lag\":2,\"partitions\":[\
+             {\"topic\":\"foo\",\"partition\":0,\"committed_offset\":1,\"log_end_offset\":3,\"lag\":2},\
+             {\"topic\":\"bar\",\"partition\":1,\"committed_offset\":5,\"log_end_offset\":null,\"lag\":null}]}"
+        );
+    }
+
+    #[test]
+    fn test_serve_admin_connections_separately() {
+        let broker = Broker::start_for_test("admin", "");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        serve_admin(listener, Arc::clone(&broker)).unwrap();
+
+        // A client which sends nothing does not hold up the others.
+        let stalled = TcpStream::connect(addr).unwrap();
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        client
+            .write_all(b"GET /groups/g/lag HTTP/1.1\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        drop(stalled);
+        broker.shutdown().unwrap();
+        assert!(TcpStream::connect(addr).is_err());
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("my%20group"), "my group");
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+}
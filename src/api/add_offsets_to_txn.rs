@@ -0,0 +1,126 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, ResourceType},
+    de::Deserializer,
+    group::GROUP_METADATA_TOPIC,
+    log::TopicPartition,
+    types::{ByteSize, TaggedFields},
+};
+
+use super::{
+    API_KEY_ADD_OFFSETS_TO_TXN, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::add_offsets_to_txn_request::AddOffsetsToTxnRequest, read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        3 | 4 => {
+            let (correlation_id, AddOffsetsToTxnRequest::V3(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+
+            let transactional_id = req_body.transactional_id.as_str();
+            let group_id = req_body.group_id.as_str();
+            let result = if !conn.authorize(
+                broker,
+                AclOperation::Write,
+                ResourceType::TransactionalId,
+                transactional_id,
+            ) {
+                Err(ErrorCode::TransactionalIdAuthorizationFailed)
+            } else if !conn.authorize(broker, AclOperation::Read, ResourceType::Group, group_id) {
+                Err(ErrorCode::GroupAuthorizationFailed)
+            } else {
+                // The group's offsets partition joins the transaction, so it
+                // gets a marker when the transaction ends.
+                let tp = TopicPartition::new(
+                    GROUP_METADATA_TOPIC,
+                    broker.groups.partition_for(group_id),
+                );
+                broker.txns.add_partitions(
+                    transactional_id,
+                    req_body.producer_id,
+                    req_body.producer_epoch,
+                    &[tp],
+                )
+            };
+            let error_code = match result {
+                Ok(()) => ErrorCode::NoError,
+                Err(error_code) => error_code,
+            };
+
+            let res_body = ResponseBody::AddOffsetsToTxn(AddOffsetsToTxnResponseBody {
+                throttle_time_ms: 0,
+                error_code,
+                tagged_fields: TaggedFields::new(None),
+            });
+
+            Ok(Message::new(res_header, Some(res_body)))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_ADD_OFFSETS_TO_TXN,
+            api_version,
+        }),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct AddOffsetsToTxnResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    error_code: ErrorCode,
+    tagged_fields: TaggedFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{client, request};
+
+    fn add_offsets(broker: &Broker, conn: &Connection, producer_id: i64, epoch: i16) -> ErrorCode {
+        let body = [
+            &[0x03, b't', b'x'][..],    // transactional_id
+            &producer_id.to_be_bytes(), // producer_id
+            &epoch.to_be_bytes(),       // producer_epoch
+            &[0x02, b'g', 0x00],        // group_id, tagged_fields
+        ]
+        .concat();
+        let request = request(API_KEY_ADD_OFFSETS_TO_TXN, 4, &body);
+        let message = run(4, Deserializer::new(&request[..]), broker, conn).unwrap();
+        let Some(ResponseBody::AddOffsetsToTxn(body)) = message.into_body() else {
+            panic!("expected an AddOffsetsToTxn response");
+        };
+        body.error_code
+    }
+
+    #[test]
+    fn test_add_offsets_to_txn() {
+        let (broker, conn) = client("add-offsets-to-txn", "");
+        assert_eq!(
+            add_offsets(&broker, &conn, 0, 0),
+            ErrorCode::InvalidProducerIdMapping
+        );
+
+        let (producer_id, epoch) = broker
+            .txns
+            .init_producer_id(Some("tx"), 60_000, None, &broker.groups)
+            .unwrap();
+        assert_eq!(
+            add_offsets(&broker, &conn, producer_id, epoch),
+            ErrorCode::NoError
+        );
+        let tp = TopicPartition::new(GROUP_METADATA_TOPIC, broker.groups.partition_for("g"));
+        assert!(broker.txns.is_in_transaction("tx", producer_id, epoch, &tp));
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+}
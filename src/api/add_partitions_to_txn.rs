@@ -0,0 +1,225 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, ResourceType},
+    de::Deserializer,
+    log::TopicPartition,
+    types::{ByteSize, CompactArray, CompactString, TaggedFields},
+};
+
+use super::{
+    API_KEY_ADD_PARTITIONS_TO_TXN, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::add_partitions_to_txn_request::AddPartitionsToTxnRequest, partition_exists,
+    read_meta_records, read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        3 => {
+            let (correlation_id, AddPartitionsToTxnRequest::V3(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+
+            let partitions: Vec<TopicPartition> = req_body
+                .v3_and_below_topics
+                .into_iter()
+                .flat_map(|topic| {
+                    let name = topic.name.as_str().to_string();
+                    topic
+                        .partitions
+                        .into_iter()
+                        .map(move |partition| TopicPartition::new(name.clone(), partition))
+                })
+                .collect();
+
+            // Unauthorized and unknown partitions fail the whole request; the
+            // others are not attempted.
+            let transactional_id = req_body.v3_and_below_transactional_id.as_str();
+            let metadata = read_meta_records(broker).unwrap_or_default();
+            let partition_error = |tp: &TopicPartition| {
+                if !conn.authorize(broker, AclOperation::Write, ResourceType::Topic, &tp.topic) {
+                    Some(ErrorCode::TopicAuthorizationFailed)
+                } else if !partition_exists(&metadata, tp) {
+                    Some(ErrorCode::UnknownTopicOrPartition)
+                } else {
+                    None
+                }
+            };
+            let results: Vec<(TopicPartition, ErrorCode)> = if !conn.authorize(
+                broker,
+                AclOperation::Write,
+                ResourceType::TransactionalId,
+                transactional_id,
+            ) {
+                partitions
+                    .into_iter()
+                    .map(|tp| (tp, ErrorCode::TransactionalIdAuthorizationFailed))
+                    .collect()
+            } else if partitions.iter().all(|tp| partition_error(tp).is_none()) {
+                let error_code = match broker.txns.add_partitions(
+                    transactional_id,
+                    req_body.v3_and_below_producer_id,
+                    req_body.v3_and_below_producer_epoch,
+                    &partitions,
+                ) {
+                    Ok(()) => ErrorCode::NoError,
+                    Err(error_code) => error_code,
+                };
+                partitions.into_iter().map(|tp| (tp, error_code)).collect()
+            } else {
+                partitions
+                    .into_iter()
+                    .map(|tp| {
+                        let error_code =
+                            partition_error(&tp).unwrap_or(ErrorCode::OperationNotAttempted);
+                        (tp, error_code)
+                    })
+                    .collect()
+            };
+
+            let mut topics: Vec<AddPartitionsToTxnTopicResult> = vec![];
+            for (tp, error_code) in results {
+                let result = AddPartitionsToTxnPartitionResult {
+                    partition_index: tp.partition,
+                    partition_error_code: error_code,
+                    tagged_fields: TaggedFields::new(None),
+                };
+                match topics.iter_mut().find(|t| t.name.as_str() == tp.topic) {
+                    Some(topic) => topic.results_by_partition.push(result),
+                    None => topics.push(AddPartitionsToTxnTopicResult {
+                        name: CompactString::new(tp.topic),
+                        results_by_partition: vec![result],
+                    }),
+                }
+            }
+
+            let res_body = ResponseBody::AddPartitionsToTxn(AddPartitionsToTxnResponseBodyV3 {
+                throttle_time_ms: 0,
+                results_by_topic_v3_and_below: topics
+                    .into_iter()
+                    .map(|topic| AddPartitionsToTxnTopicResultV3 {
+                        name: topic.name,
+                        results_by_partition: CompactArray::new(Some(topic.results_by_partition)),
+                        tagged_fields: TaggedFields::new(None),
+                    })
+                    .collect(),
+                tagged_fields: TaggedFields::new(None),
+            });
+
+            Ok(Message::new(res_header, Some(res_body)))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_ADD_PARTITIONS_TO_TXN,
+            api_version,
+        }),
+    }
+}
+
+/// Results of one topic, grouped in request order.
+struct AddPartitionsToTxnTopicResult {
+    name: CompactString,
+    results_by_partition: Vec<AddPartitionsToTxnPartitionResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct AddPartitionsToTxnResponseBodyV3 {
+    pub(crate) throttle_time_ms: i32,
+    results_by_topic_v3_and_below: CompactArray<AddPartitionsToTxnTopicResultV3>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct AddPartitionsToTxnTopicResultV3 {
+    name: CompactString,
+    results_by_partition: CompactArray<AddPartitionsToTxnPartitionResult>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct AddPartitionsToTxnPartitionResult {
+    partition_index: i32,
+    partition_error_code: ErrorCode,
+    tagged_fields: TaggedFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{client, request};
+
+    fn add_partitions(
+        broker: &Broker,
+        conn: &Connection,
+        producer_id: i64,
+        epoch: i16,
+        partitions: &[i32],
+    ) -> Vec<(i32, ErrorCode)> {
+        let body = [
+            &[0x03, b't', b'x'][..],    // transactional_id
+            &producer_id.to_be_bytes(), // producer_id
+            &epoch.to_be_bytes(),       // producer_epoch
+            &[0x02, 0x04],
+            b"foo", // topics
+            &[partitions.len() as u8 + 1],
+            &partitions
+                .iter()
+                .flat_map(|partition| partition.to_be_bytes())
+                .collect::<Vec<u8>>(), // partitions
+            &[0x00, 0x00], // tagged_fields
+        ]
+        .concat();
+        let request = request(API_KEY_ADD_PARTITIONS_TO_TXN, 3, &body);
+        let message = run(3, Deserializer::new(&request[..]), broker, conn).unwrap();
+        let Some(ResponseBody::AddPartitionsToTxn(body)) = message.into_body() else {
+            panic!("expected an AddPartitionsToTxn response");
+        };
+        body.results_by_topic_v3_and_below
+            .as_opt_slice()
+            .unwrap()
+            .iter()
+            .flat_map(|topic| topic.results_by_partition.as_opt_slice().unwrap())
+            .map(|result| (result.partition_index, result.partition_error_code))
+            .collect()
+    }
+
+    #[test]
+    fn test_add_partitions_to_txn() {
+        let (broker, conn) = client("add-partitions-to-txn", "");
+        let (producer_id, epoch) = broker
+            .txns
+            .init_producer_id(Some("tx"), 60_000, None, &broker.groups)
+            .unwrap();
+        let tp = TopicPartition::new("foo", 0);
+
+        // An unknown partition fails the whole request.
+        assert_eq!(
+            add_partitions(&broker, &conn, producer_id, epoch, &[0, 1]),
+            [
+                (0, ErrorCode::OperationNotAttempted),
+                (1, ErrorCode::UnknownTopicOrPartition)
+            ]
+        );
+        assert!(!broker.txns.is_in_transaction("tx", producer_id, epoch, &tp));
+
+        assert_eq!(
+            add_partitions(&broker, &conn, producer_id, epoch + 1, &[0]),
+            [(0, ErrorCode::ProducerFenced)]
+        );
+        assert_eq!(
+            add_partitions(&broker, &conn, producer_id, epoch, &[0]),
+            [(0, ErrorCode::NoError)]
+        );
+        assert!(broker.txns.is_in_transaction("tx", producer_id, epoch, &tp));
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+}
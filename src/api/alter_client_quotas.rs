@@ -0,0 +1,134 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, CLUSTER_NAME, ResourceType},
+    de::Deserializer,
+    quota::{EntityComponents, QuotaAlteration},
+    types::{ByteSize, CompactArray, CompactNullableString, CompactString, TaggedFields},
+};
+
+use super::{
+    API_KEY_ALTER_CLIENT_QUOTAS, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::alter_client_quotas_request::AlterClientQuotasRequest, read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        1 => {
+            let (correlation_id, AlterClientQuotasRequest::V1(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let entries: Vec<(EntityComponents, Vec<QuotaAlteration>)> = req_body
+                .entries
+                .into_iter()
+                .map(|entry| {
+                    let components = entry
+                        .entity
+                        .into_iter()
+                        .map(|data| {
+                            (
+                                data.entity_type.as_str().to_string(),
+                                data.entity_name.as_ref().cloned(),
+                            )
+                        })
+                        .collect();
+                    let alterations = entry
+                        .ops
+                        .into_iter()
+                        .map(|op| QuotaAlteration {
+                            key: op.key.as_str().to_string(),
+                            value: op.value,
+                            remove: op.remove,
+                        })
+                        .collect();
+                    (components, alterations)
+                })
+                .collect();
+
+            let authorized = conn.authorize(
+                broker,
+                AclOperation::AlterConfigs,
+                ResourceType::Cluster,
+                CLUSTER_NAME,
+            );
+            let results = if authorized {
+                broker.quotas.alter(entries.clone(), req_body.validate_only)
+            } else {
+                entries
+                    .iter()
+                    .map(|_| Err((ErrorCode::ClusterAuthorizationFailed, String::new())))
+                    .collect()
+            };
+
+            let entries = entries
+                .into_iter()
+                .zip(results)
+                .map(|((components, _), result)| {
+                    let (error_code, error_message) = match result {
+                        Ok(()) => (ErrorCode::NoError, None),
+                        Err((code, message)) => (code, Some(message)),
+                    };
+                    EntryData {
+                        error_code,
+                        error_message: CompactNullableString::new(error_message),
+                        entity: components
+                            .into_iter()
+                            .map(|(entity_type, name)| EntityData {
+                                entity_type: CompactString::new(entity_type),
+                                entity_name: CompactNullableString::new(name),
+                                tagged_fields: TaggedFields::new(None),
+                            })
+                            .collect(),
+                        tagged_fields: TaggedFields::new(None),
+                    }
+                })
+                .collect();
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+            let res_body = AlterClientQuotasResponseBody {
+                throttle_time_ms: 0,
+                entries,
+                tagged_fields: TaggedFields::new(None),
+            };
+            Ok(Message::new(
+                res_header,
+                Some(ResponseBody::AlterClientQuotas(res_body)),
+            ))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_ALTER_CLIENT_QUOTAS,
+            api_version,
+        }),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct AlterClientQuotasResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    entries: CompactArray<EntryData>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct EntryData {
+    error_code: ErrorCode,
+    error_message: CompactNullableString,
+    entity: CompactArray<EntityData>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct EntityData {
+    entity_type: CompactString,
+    entity_name: CompactNullableString,
+    tagged_fields: TaggedFields,
+}
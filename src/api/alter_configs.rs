@@ -0,0 +1,168 @@
+use crate::{
+    Broker, Result,
+    acl::AclOperation,
+    de::Deserializer,
+    dynamic_config::ResourceType,
+    types::{ByteSize, CompactArray, CompactNullableString, CompactString, TaggedFields},
+};
+
+use super::{
+    API_KEY_ALTER_CONFIGS, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    authorize_configs, messages::alter_configs_request::AlterConfigsRequest, read_meta_records,
+    read_request, topic_exists,
+};
+use serde::Serialize;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        2 => {
+            let (correlation_id, AlterConfigsRequest::V2(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+
+            let metadata = read_meta_records(broker).unwrap_or_default();
+            let responses = req_body
+                .resources
+                .into_iter()
+                .map(|resource| {
+                    let name = resource.resource_name.as_str();
+                    let configs = resource
+                        .configs
+                        .into_iter()
+                        .map(|config| {
+                            (
+                                config.name.as_str().to_string(),
+                                config.value.as_ref().cloned(),
+                            )
+                        })
+                        .collect();
+                    let result = match ResourceType::from_i8(resource.resource_type) {
+                        Some(resource_type) => authorize_configs(
+                            broker,
+                            conn,
+                            AclOperation::AlterConfigs,
+                            resource_type,
+                            name,
+                        )
+                        .and_then(|()| match resource_type {
+                            ResourceType::Topic if !topic_exists(&metadata, name) => Err((
+                                ErrorCode::UnknownTopicOrPartition,
+                                format!("Topic {name} does not exist"),
+                            )),
+                            _ => broker.configs.alter(
+                                resource_type,
+                                name,
+                                configs,
+                                req_body.validate_only,
+                            ),
+                        }),
+                        None => Err((
+                            ErrorCode::InvalidRequest,
+                            format!("Unsupported resource type {}", resource.resource_type),
+                        )),
+                    };
+
+                    let (error_code, error_message) = match result {
+                        Ok(()) => (ErrorCode::NoError, None),
+                        Err((code, message)) => (code, Some(message)),
+                    };
+                    AlterConfigsResourceResponse {
+                        error_code,
+                        error_message: CompactNullableString::new(error_message),
+                        resource_type: resource.resource_type,
+                        resource_name: resource.resource_name,
+                        tagged_fields: TaggedFields::new(None),
+                    }
+                })
+                .collect();
+
+            let res_body = ResponseBody::AlterConfigs(AlterConfigsResponseBody {
+                throttle_time_ms: 0,
+                responses,
+                tagged_fields: TaggedFields::new(None),
+            });
+
+            Ok(Message::new(res_header, Some(res_body)))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_ALTER_CONFIGS,
+            api_version,
+        }),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct AlterConfigsResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    responses: CompactArray<AlterConfigsResourceResponse>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct AlterConfigsResourceResponse {
+    error_code: ErrorCode,
+    error_message: CompactNullableString,
+    resource_type: i8,
+    resource_name: CompactString,
+    tagged_fields: TaggedFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{client, request};
+
+    #[test]
+    fn test_alter_configs() {
+        let (broker, conn) = client("alter-configs", "");
+        let resource = |topic: &[u8]| {
+            [
+                &[0x02, topic.len() as u8 + 1][..],
+                topic, // resource_type, resource_name
+                &[0x02, 0x0d],
+                b"retention.ms",
+                &[0x02, b'1', 0x00], // configs
+                &[0x00],             // tagged_fields
+            ]
+            .concat()
+        };
+        let body = [
+            &[0x03][..],       // resources
+            &resource(b"foo"), // an existing topic
+            &resource(b"bar"), // an unknown topic
+            &[0x00, 0x00],     // validate_only, tagged_fields
+        ]
+        .concat();
+        let request = request(API_KEY_ALTER_CONFIGS, 2, &body);
+        let message = run(2, Deserializer::new(&request[..]), &broker, &conn).unwrap();
+        let Some(ResponseBody::AlterConfigs(body)) = message.into_body() else {
+            panic!("expected an AlterConfigs response");
+        };
+
+        let error_codes: Vec<ErrorCode> = body
+            .responses
+            .as_opt_slice()
+            .unwrap()
+            .iter()
+            .map(|response| response.error_code)
+            .collect();
+        assert_eq!(
+            error_codes,
+            [ErrorCode::NoError, ErrorCode::UnknownTopicOrPartition]
+        );
+        let configs = broker.configs.describe(ResourceType::Topic, "foo").unwrap();
+        let retention = configs.iter().find(|c| c.name == "retention.ms").unwrap();
+        assert_eq!(retention.value.as_deref(), Some("1"));
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, CLUSTER_NAME, ResourceType},
+    de::Deserializer,
+    sasl::{ScramDeletion, ScramUpsertion},
+    types::{ByteSize, CompactArray, CompactNullableString, CompactString, TaggedFields},
+};
+
+use super::{
+    API_KEY_ALTER_USER_SCRAM_CREDENTIALS, Connection, ErrorCode, Message, ResponseBody,
+    ResponseHeader,
+    messages::alter_user_scram_credentials_request::AlterUserScramCredentialsRequest, read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        0 => {
+            let (correlation_id, AlterUserScramCredentialsRequest::V0(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let deletions: Vec<ScramDeletion> = req_body
+                .deletions
+                .into_iter()
+                .map(|deletion| ScramDeletion {
+                    name: deletion.name.as_str().to_string(),
+                    mechanism: deletion.mechanism,
+                })
+                .collect();
+            let upsertions: Vec<ScramUpsertion> = req_body
+                .upsertions
+                .into_iter()
+                .map(|upsertion| ScramUpsertion {
+                    name: upsertion.name.as_str().to_string(),
+                    mechanism: upsertion.mechanism,
+                    iterations: upsertion.iterations,
+                    salt: upsertion.salt.as_bytes().to_vec(),
+                    salted_password: upsertion.salted_password.as_bytes().to_vec(),
+                })
+                .collect();
+            let authorized = conn.authorize(
+                broker,
+                AclOperation::Alter,
+                ResourceType::Cluster,
+                CLUSTER_NAME,
+            );
+            let results = if authorized {
+                broker.credentials.alter(deletions, upsertions)
+            } else {
+                let mut users: Vec<String> = vec![];
+                for name in deletions
+                    .into_iter()
+                    .map(|deletion| deletion.name)
+                    .chain(upsertions.into_iter().map(|upsertion| upsertion.name))
+                {
+                    if !users.contains(&name) {
+                        users.push(name);
+                    }
+                }
+                users
+                    .into_iter()
+                    .map(|user| {
+                        let error = (ErrorCode::ClusterAuthorizationFailed, String::new());
+                        (user, Err(error))
+                    })
+                    .collect()
+            };
+            let results = results
+                .into_iter()
+                .map(|(user, result)| {
+                    let (error_code, error_message) = match result {
+                        Ok(()) => (ErrorCode::NoError, None),
+                        Err((code, message)) => (code, Some(message)),
+                    };
+                    AlterUserScramCredentialsResult {
+                        user: CompactString::new(user),
+                        error_code,
+                        error_message: CompactNullableString::new(error_message),
+                        tagged_fields: TaggedFields::new(None),
+                    }
+                })
+                .collect();
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+            let res_body = AlterUserScramCredentialsResponseBody {
+                throttle_time_ms: 0,
+                results,
+                tagged_fields: TaggedFields::new(None),
+            };
+            Ok(Message::new(
+                res_header,
+                Some(ResponseBody::AlterUserScramCredentials(res_body)),
+            ))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_ALTER_USER_SCRAM_CREDENTIALS,
+            api_version,
+        }),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct AlterUserScramCredentialsResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    results: CompactArray<AlterUserScramCredentialsResult>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct AlterUserScramCredentialsResult {
+    user: CompactString,
+    error_code: ErrorCode,
+    error_message: CompactNullableString,
+    tagged_fields: TaggedFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{client, request};
+    use crate::sasl::ScramMechanism;
+
+    #[test]
+    fn test_alter_user_scram_credentials() {
+        let (broker, conn) = client("alter-user-scram-credentials", "");
+        let body = [
+            &[0x02, 0x04][..],
+            b"bob",
+            &[0x02, 0x00], // deletions
+            &[0x02, 0x06],
+            b"alice",
+            &[0x02, 0x00, 0x00, 0x10, 0x00], // mechanism, iterations
+            &[0x02, 0x01, 0x02, 0x02, 0x00], // salt, salted_password
+            &[0x00],                         // tagged_fields
+        ]
+        .concat();
+        let request = request(API_KEY_ALTER_USER_SCRAM_CREDENTIALS, 0, &body);
+        let message = run(0, Deserializer::new(&request[..]), &broker, &conn).unwrap();
+        let Some(ResponseBody::AlterUserScramCredentials(body)) = message.into_body() else {
+            panic!("expected an AlterUserScramCredentials response");
+        };
+
+        let results: Vec<(&str, ErrorCode)> = body
+            .results
+            .as_opt_slice()
+            .unwrap()
+            .iter()
+            .map(|result| (result.user.as_str(), result.error_code))
+            .collect();
+        assert_eq!(
+            results,
+            [
+                ("bob", ErrorCode::ResourceNotFound),
+                ("alice", ErrorCode::NoError)
+            ]
+        );
+        let described = broker.credentials.describe(Some(vec!["alice".to_string()]));
+        assert_eq!(described[0].1, Ok(vec![(ScramMechanism::Sha512, 4096)]));
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+}
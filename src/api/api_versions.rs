@@ -1,211 +1,160 @@
 use crate::{
     Result,
     de::Deserializer,
-    types::{ByteSizeExt, CompactArray, TaggedFields},
+    types::{Array, CompactArray},
 };
 
 use super::{
-    API_KEY_API_VERSIONS, API_KEY_DESCRIBE_TOPIC_PARTITIONS, API_KEY_FETCH, ErrorCode, Message,
-    RequestHeaderV1, RequestHeaderV2, ResponseBody, ResponseHeader,
+    ErrorCode, Message, RequestHeaderV2, ResponseBody, ResponseHeader,
+    messages::{
+        add_offsets_to_txn_request, add_partitions_to_txn_request, alter_client_quotas_request,
+        alter_configs_request, alter_user_scram_credentials_request,
+        api_versions_request::{self, ApiVersionsRequest},
+        api_versions_response::{
+            ApiVersionV0, ApiVersionV3, ApiVersionsResponse, ApiVersionsResponseV0,
+            ApiVersionsResponseV1, ApiVersionsResponseV3,
+        },
+        consumer_group_heartbeat_request, create_acls_request, delete_acls_request,
+        delete_groups_request, describe_acls_request, describe_client_quotas_request,
+        describe_cluster_request, describe_configs_request, describe_groups_request,
+        describe_producers_request, describe_topic_partitions_request,
+        describe_transactions_request, describe_user_scram_credentials_request, end_txn_request,
+        fetch_request, incremental_alter_configs_request, init_producer_id_request,
+        list_groups_request, list_transactions_request, offset_commit_request,
+        offset_delete_request, offset_fetch_request, produce_request, sasl_authenticate_request,
+        sasl_handshake_request, txn_offset_commit_request, write_txn_markers_request,
+    },
+    parse, read_request,
 };
-use serde::{Deserialize, Serialize};
 use std::io::Read;
 
 pub(crate) fn run<R: Read>(api_version: i16, mut de: Deserializer<R>) -> Result<Message> {
-    match api_version {
-        0 => {
-            let req_header: RequestHeaderV1 = Deserialize::deserialize(&mut de)?;
-            let res_header = ResponseHeader::V0 {
-                correlation_id: req_header.correlation_id,
-            };
-            let res_body = ResponseBody::ApiVersions(ApiVersionsResponseBody::V0 {
-                error_code: ErrorCode::NoError,
-                api_versions: supported_versions_v1(),
-            });
-            Ok(Message::new(res_header, Some(res_body)))
-        }
-        1 => {
-            let req_header: RequestHeaderV1 = Deserialize::deserialize(&mut de)?;
-            let res_header = ResponseHeader::V0 {
-                correlation_id: req_header.correlation_id,
-            };
-            let res_body = ResponseBody::ApiVersions(ApiVersionsResponseBody::V1 {
-                error_code: ErrorCode::NoError,
-                api_versions: supported_versions_v1(),
-                throttle_time_ms: 0,
-            });
-            Ok(Message::new(res_header, Some(res_body)))
-        }
-        2 => {
-            let req_header: RequestHeaderV1 = Deserialize::deserialize(&mut de)?;
-            let res_header = ResponseHeader::V0 {
-                correlation_id: req_header.correlation_id,
-            };
-            let res_body = ResponseBody::ApiVersions(ApiVersionsResponseBody::V1 {
-                error_code: ErrorCode::NoError,
-                api_versions: supported_versions_v1(),
-                throttle_time_ms: 0,
-            });
-            Ok(Message::new(res_header, Some(res_body)))
-        }
-        3 => {
-            let req_header: RequestHeaderV2 = Deserialize::deserialize(&mut de)?;
-            let res_header = ResponseHeader::V0 {
-                correlation_id: req_header.correlation_id,
-            };
-            let res_body = ResponseBody::ApiVersions(ApiVersionsResponseBody::V3 {
-                error_code: ErrorCode::NoError,
-                api_versions: supported_versions_v2(),
-                throttle_time_ms: 0,
-                tagged_fields: TaggedFields::new(None),
-            });
-            Ok(Message::new(res_header, Some(res_body)))
-        }
-        4 => {
-            let req_header: RequestHeaderV2 = Deserialize::deserialize(&mut de)?;
-            let res_header = ResponseHeader::V0 {
-                correlation_id: req_header.correlation_id,
-            };
-            let res_body = ResponseBody::ApiVersions(ApiVersionsResponseBody::V3 {
-                error_code: ErrorCode::NoError,
-                api_versions: supported_versions_v2(),
-                throttle_time_ms: 0,
-                tagged_fields: TaggedFields::new(None),
-            });
-            Ok(Message::new(res_header, Some(res_body)))
-        }
-        _ => {
-            let req_header: RequestHeaderV2 = Deserialize::deserialize(&mut de)?;
-            let res_header = ResponseHeader::V0 {
-                correlation_id: req_header.correlation_id,
-            };
-            let res_body = ResponseBody::ApiVersions(ApiVersionsResponseBody::V3 {
-                error_code: ErrorCode::UnsupportedVersion,
-                api_versions: CompactArray::new(Some(vec![])),
-                throttle_time_ms: 0,
-                tagged_fields: TaggedFields::new(None),
-            });
-            Ok(Message::new(res_header, Some(res_body)))
-        }
+    if !(ApiVersionsRequest::MIN_VERSION..=ApiVersionsRequest::MAX_VERSION).contains(&api_version) {
+        let req_header: RequestHeaderV2 = parse(&mut de)?;
+        let res_header = ResponseHeader::V0 {
+            correlation_id: req_header.correlation_id,
+        };
+        let res_body = ApiVersionsResponse::V3(ApiVersionsResponseV3 {
+            error_code: ErrorCode::UnsupportedVersion as i16,
+            ..Default::default()
+        });
+        return Ok(Message::new(
+            res_header,
+            Some(ResponseBody::ApiVersions(res_body)),
+        ));
     }
-}
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
-#[serde(untagged)]
-pub(crate) enum ApiVersionsResponseBody {
-    V0 {
-        error_code: ErrorCode,
-        api_versions: CompactArray<ApiVersionV1>,
-    },
-    V1 {
-        error_code: ErrorCode,
-        api_versions: CompactArray<ApiVersionV1>,
-        throttle_time_ms: i32,
-    },
-    V3 {
-        error_code: ErrorCode,
-        api_versions: CompactArray<ApiVersionV2>,
-        throttle_time_ms: i32,
-        tagged_fields: TaggedFields,
-    },
-}
+    let (correlation_id, _req_body): (i32, ApiVersionsRequest) =
+        read_request(api_version, &mut de)?;
 
-impl ByteSizeExt for ApiVersionsResponseBody {
-    fn byte_size(&self) -> usize {
-        match self {
-            Self::V0 {
-                error_code,
-                api_versions,
-            } => error_code.byte_size() + api_versions.byte_size(),
-            Self::V1 {
-                error_code,
-                api_versions,
-                throttle_time_ms,
-            } => error_code.byte_size() + api_versions.byte_size() + throttle_time_ms.byte_size(),
-            Self::V3 {
-                error_code,
-                api_versions,
-                throttle_time_ms,
-                tagged_fields,
-            } => {
-                error_code.byte_size()
-                    + api_versions.byte_size()
-                    + throttle_time_ms.byte_size()
-                    + tagged_fields.byte_size()
-            }
-        }
-    }
+    // The response header stays version 0, so that clients can read it
+    // before they know which versions are supported.
+    let res_header = ResponseHeader::V0 { correlation_id };
+    let res_body = response_body(api_version, ErrorCode::NoError);
+    Ok(Message::new(
+        res_header,
+        Some(ResponseBody::ApiVersions(res_body)),
+    ))
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
-pub(crate) struct ApiVersionV1 {
-    api_key: i16,
-    min_version: i16,
-    max_version: i16,
+/// Like Kafka, reports the supported versions along with the error, so
+/// that clients can retry with a version this broker handles.
+pub(crate) fn error_body(api_version: i16, error_code: ErrorCode) -> ResponseBody {
+    ResponseBody::ApiVersions(response_body(api_version, error_code))
 }
 
-impl ByteSizeExt for ApiVersionV1 {
-    fn byte_size(&self) -> usize {
-        self.api_key.byte_size() + self.min_version.byte_size() + self.max_version.byte_size()
+fn response_body(api_version: i16, error_code: ErrorCode) -> ApiVersionsResponse {
+    let error_code = error_code as i16;
+    match api_version {
+        ..=0 => ApiVersionsResponse::V0(ApiVersionsResponseV0 {
+            error_code,
+            api_keys: supported_versions_v0(),
+        }),
+        1 | 2 => ApiVersionsResponse::V1(ApiVersionsResponseV1 {
+            error_code,
+            api_keys: supported_versions_v0(),
+            throttle_time_ms: 0,
+        }),
+        _ => ApiVersionsResponse::V3(ApiVersionsResponseV3 {
+            error_code,
+            api_keys: supported_versions_v3(),
+            ..Default::default()
+        }),
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
-pub(crate) struct ApiVersionV2 {
-    api_key: i16,
-    min_version: i16,
-    max_version: i16,
-    tagged_fields: TaggedFields,
+/// Gives the (api_key, min_version, max_version) of each request type.
+macro_rules! versions {
+    ($($request:ty),* $(,)?) => {
+        &[$((<$request>::API_KEY, <$request>::MIN_VERSION, <$request>::MAX_VERSION)),*]
+    };
 }
 
-impl ByteSizeExt for ApiVersionV2 {
-    fn byte_size(&self) -> usize {
-        self.api_key.byte_size()
-            + self.min_version.byte_size()
-            + self.max_version.byte_size()
-            + self.tagged_fields.byte_size()
-    }
+/// (api_key, min_version, max_version) of every API this broker handles,
+/// as the vendored message specs give them.
+const SUPPORTED_APIS: &[(i16, i16, i16)] = versions![
+    produce_request::ProduceRequest,
+    fetch_request::FetchRequest,
+    offset_commit_request::OffsetCommitRequest,
+    offset_fetch_request::OffsetFetchRequest,
+    describe_groups_request::DescribeGroupsRequest,
+    list_groups_request::ListGroupsRequest,
+    sasl_handshake_request::SaslHandshakeRequest,
+    api_versions_request::ApiVersionsRequest,
+    init_producer_id_request::InitProducerIdRequest,
+    add_partitions_to_txn_request::AddPartitionsToTxnRequest,
+    add_offsets_to_txn_request::AddOffsetsToTxnRequest,
+    end_txn_request::EndTxnRequest,
+    write_txn_markers_request::WriteTxnMarkersRequest,
+    txn_offset_commit_request::TxnOffsetCommitRequest,
+    describe_acls_request::DescribeAclsRequest,
+    create_acls_request::CreateAclsRequest,
+    delete_acls_request::DeleteAclsRequest,
+    describe_configs_request::DescribeConfigsRequest,
+    alter_configs_request::AlterConfigsRequest,
+    sasl_authenticate_request::SaslAuthenticateRequest,
+    delete_groups_request::DeleteGroupsRequest,
+    incremental_alter_configs_request::IncrementalAlterConfigsRequest,
+    offset_delete_request::OffsetDeleteRequest,
+    describe_client_quotas_request::DescribeClientQuotasRequest,
+    alter_client_quotas_request::AlterClientQuotasRequest,
+    describe_user_scram_credentials_request::DescribeUserScramCredentialsRequest,
+    alter_user_scram_credentials_request::AlterUserScramCredentialsRequest,
+    describe_cluster_request::DescribeClusterRequest,
+    describe_producers_request::DescribeProducersRequest,
+    describe_transactions_request::DescribeTransactionsRequest,
+    list_transactions_request::ListTransactionsRequest,
+    consumer_group_heartbeat_request::ConsumerGroupHeartbeatRequest,
+    describe_topic_partitions_request::DescribeTopicPartitionsRequest,
+];
+
+/// The lowest and highest version of the API this broker handles.
+pub(crate) fn supported_versions(api_key: i16) -> Option<(i16, i16)> {
+    SUPPORTED_APIS
+        .iter()
+        .find(|&&(key, _, _)| key == api_key)
+        .map(|&(_, min_version, max_version)| (min_version, max_version))
 }
 
-fn supported_versions_v1() -> CompactArray<ApiVersionV1> {
-    CompactArray::new(Some(vec![
-        ApiVersionV1 {
-            api_key: API_KEY_FETCH,
-            min_version: 4,
-            max_version: 16,
-        },
-        ApiVersionV1 {
-            api_key: API_KEY_API_VERSIONS,
-            min_version: 0,
-            max_version: 4,
-        },
-        ApiVersionV1 {
-            api_key: API_KEY_DESCRIBE_TOPIC_PARTITIONS,
-            min_version: 0,
-            max_version: 0,
-        },
-    ]))
+fn supported_versions_v0() -> Array<ApiVersionV0> {
+    SUPPORTED_APIS
+        .iter()
+        .map(|&(api_key, min_version, max_version)| ApiVersionV0 {
+            api_key,
+            min_version,
+            max_version,
+        })
+        .collect()
 }
 
-fn supported_versions_v2() -> CompactArray<ApiVersionV2> {
-    CompactArray::new(Some(vec![
-        ApiVersionV2 {
-            api_key: API_KEY_FETCH,
-            min_version: 4,
-            max_version: 16,
-            tagged_fields: TaggedFields::new(None),
-        },
-        ApiVersionV2 {
-            api_key: API_KEY_API_VERSIONS,
-            min_version: 0,
-            max_version: 4,
-            tagged_fields: TaggedFields::new(None),
-        },
-        ApiVersionV2 {
-            api_key: API_KEY_DESCRIBE_TOPIC_PARTITIONS,
-            min_version: 0,
-            max_version: 0,
-            tagged_fields: TaggedFields::new(None),
-        },
-    ]))
+fn supported_versions_v3() -> CompactArray<ApiVersionV3> {
+    SUPPORTED_APIS
+        .iter()
+        .map(|&(api_key, min_version, max_version)| ApiVersionV3 {
+            api_key,
+            min_version,
+            max_version,
+            ..Default::default()
+        })
+        .collect()
 }
@@ -0,0 +1,226 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, ResourceType},
+    de::Deserializer,
+    group::{Assignment, HeartbeatRequest, TopicMetadata},
+    types::{
+        ByteSize, CompactArray, CompactNullableString, NullableStruct, RecordVariant, TaggedFields,
+        Uuid,
+    },
+};
+
+use super::{
+    API_KEY_CONSUMER_GROUP_HEARTBEAT, Connection, ErrorCode, Message, RequestHeaderV2,
+    ResponseBody, ResponseHeader, malformed,
+    messages::consumer_group_heartbeat_request::{
+        ConsumerGroupHeartbeatRequest, TopicPartitionsV0,
+    },
+    parse,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        0 => {
+            let req_header: RequestHeaderV2 = parse(&mut de)?;
+            let ConsumerGroupHeartbeatRequest::V0(req_body) =
+                ConsumerGroupHeartbeatRequest::read(api_version, &mut de).map_err(malformed)?;
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id: req_header.correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+
+            let request = HeartbeatRequest {
+                group_id: req_body.group_id.as_str().to_string(),
+                member_id: req_body.member_id.as_str().to_string(),
+                member_epoch: req_body.member_epoch,
+                instance_id: req_body.instance_id.as_ref().cloned(),
+                rack_id: req_body.rack_id.as_ref().cloned(),
+                client_id: req_header.client_id.as_ref().cloned().unwrap_or_default(),
+                client_host: conn.host.clone(),
+                rebalance_timeout_ms: req_body.rebalance_timeout_ms,
+                subscribed_topic_names: req_body
+                    .subscribed_topic_names
+                    .as_opt_slice()
+                    .map(|names| names.iter().map(|name| name.as_str().to_string()).collect()),
+                subscribed_topic_regex: req_body.subscribed_topic_regex.as_ref().cloned(),
+                server_assignor: req_body.server_assignor.as_ref().cloned(),
+                owned_topic_partitions: req_body.topic_partitions.as_opt_slice().map(to_assignment),
+            };
+
+            let result = if conn.authorize(
+                broker,
+                AclOperation::Read,
+                ResourceType::Group,
+                &request.group_id,
+            ) {
+                broker
+                    .groups
+                    .consumer_group_heartbeat(request, &topic_metadata(broker))
+            } else {
+                Err(ErrorCode::GroupAuthorizationFailed)
+            };
+            let res_body = match result {
+                Ok(response) => ConsumerGroupHeartbeatResponseBody {
+                    throttle_time_ms: 0,
+                    error_code: ErrorCode::NoError,
+                    error_message: CompactNullableString::new(None),
+                    member_id: CompactNullableString::new(Some(response.member_id)),
+                    member_epoch: response.member_epoch,
+                    heartbeat_interval_ms: response.heartbeat_interval_ms,
+                    assignment: NullableStruct::new(response.assignment.map(|assignment| {
+                        ConsumerGroupHeartbeatAssignment {
+                            topic_partitions: assignment
+                                .into_iter()
+                                .map(|(topic_id, partitions)| TopicPartitions {
+                                    topic_id,
+                                    partitions: partitions.into_iter().collect(),
+                                    tagged_fields: TaggedFields::new(None),
+                                })
+                                .collect(),
+                            tagged_fields: TaggedFields::new(None),
+                        }
+                    })),
+                    tagged_fields: TaggedFields::new(None),
+                },
+                Err(error_code) => ConsumerGroupHeartbeatResponseBody {
+                    throttle_time_ms: 0,
+                    error_code,
+                    error_message: CompactNullableString::new(None),
+                    member_id: CompactNullableString::new(None),
+                    member_epoch: 0,
+                    heartbeat_interval_ms: 0,
+                    assignment: NullableStruct::new(None),
+                    tagged_fields: TaggedFields::new(None),
+                },
+            };
+
+            Ok(Message::new(
+                res_header,
+                Some(ResponseBody::ConsumerGroupHeartbeat(res_body)),
+            ))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_CONSUMER_GROUP_HEARTBEAT,
+            api_version,
+        }),
+    }
+}
+
+/// Topics known to the cluster, with their partition counts.
+fn topic_metadata(broker: &Broker) -> Vec<TopicMetadata> {
+    // Without a metadata log there are no topics to assign.
+    let records = super::read_meta_records(broker).unwrap_or_default();
+
+    let mut partitions: BTreeMap<Uuid, i32> = BTreeMap::new();
+    for record in records.iter() {
+        if let RecordVariant::Partition(p) = record {
+            *partitions.entry(p.topic_id).or_default() += 1;
+        }
+    }
+
+    records
+        .iter()
+        .filter_map(|record| match record {
+            RecordVariant::Topic(t) => Some(TopicMetadata {
+                id: t.topic_id,
+                name: t.name.as_str().to_string(),
+                num_partitions: partitions.get(&t.topic_id).copied().unwrap_or_default(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn to_assignment(topics: &[TopicPartitionsV0]) -> Assignment {
+    topics
+        .iter()
+        .map(|topic| {
+            let partitions = topic
+                .partitions
+                .as_opt_slice()
+                .unwrap_or_default()
+                .iter()
+                .copied()
+                .collect();
+            (topic.topic_id, partitions)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct TopicPartitions {
+    topic_id: Uuid,
+    partitions: CompactArray<i32>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct ConsumerGroupHeartbeatResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    error_code: ErrorCode,
+    error_message: CompactNullableString,
+    member_id: CompactNullableString,
+    member_epoch: i32,
+    heartbeat_interval_ms: i32,
+    assignment: NullableStruct<ConsumerGroupHeartbeatAssignment>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct ConsumerGroupHeartbeatAssignment {
+    topic_partitions: CompactArray<TopicPartitions>,
+    tagged_fields: TaggedFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ser::Serializer, types::ByteSizeExt};
+
+    #[test]
+    fn test_response_serialization() {
+        let body = ConsumerGroupHeartbeatResponseBody {
+            throttle_time_ms: 0,
+            error_code: ErrorCode::NoError,
+            error_message: CompactNullableString::new(None),
+            member_id: CompactNullableString::new(Some("m".to_string())),
+            member_epoch: 1,
+            heartbeat_interval_ms: 5000,
+            assignment: NullableStruct::new(Some(ConsumerGroupHeartbeatAssignment {
+                topic_partitions: CompactArray::new(Some(vec![TopicPartitions {
+                    topic_id: Uuid::default(),
+                    partitions: CompactArray::new(Some(vec![0])),
+                    tagged_fields: TaggedFields::new(None),
+                }])),
+                tagged_fields: TaggedFields::new(None),
+            })),
+            tagged_fields: TaggedFields::new(None),
+        };
+
+        let mut buf: Vec<u8> = Vec::new();
+        body.serialize(&mut Serializer::new(&mut buf)).unwrap();
+        assert_eq!(buf.len(), body.byte_size());
+        assert_eq!(
+            buf[..19],
+            [
+                0x00, 0x00, 0x00, 0x00, // throttle_time_ms
+                0x00, 0x00, // error_code
+                0x00, // error_message
+                0x02, b'm', // member_id
+                0x00, 0x00, 0x00, 0x01, // member_epoch
+                0x00, 0x00, 0x13, 0x88, // heartbeat_interval_ms
+                0x01, // assignment present
+                0x02, // topic_partitions
+            ]
+        );
+    }
+}
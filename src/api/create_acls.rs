@@ -0,0 +1,131 @@
+use crate::{
+    Broker, Result,
+    acl::{
+        AclBinding, AclError, AclOperation, CLUSTER_NAME, PatternType, PermissionType, ResourceType,
+    },
+    de::Deserializer,
+    types::{ByteSize, CompactArray, CompactNullableString, TaggedFields},
+};
+
+use super::{
+    API_KEY_CREATE_ACLS, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::create_acls_request::{AclCreationV2, CreateAclsRequest},
+    read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        2 | 3 => {
+            let (correlation_id, CreateAclsRequest::V2(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let results = create(broker, conn, req_body.creations.into_iter().collect())
+                .into_iter()
+                .map(|result| {
+                    let (error_code, error_message) = match result {
+                        Ok(()) => (ErrorCode::NoError, None),
+                        Err((code, message)) => (code, Some(message)),
+                    };
+                    AclCreationResult {
+                        error_code,
+                        error_message: CompactNullableString::new(error_message),
+                        tagged_fields: TaggedFields::new(None),
+                    }
+                })
+                .collect();
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+            let res_body = CreateAclsResponseBody {
+                throttle_time_ms: 0,
+                results,
+                tagged_fields: TaggedFields::new(None),
+            };
+            Ok(Message::new(
+                res_header,
+                Some(ResponseBody::CreateAcls(res_body)),
+            ))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_CREATE_ACLS,
+            api_version,
+        }),
+    }
+}
+
+/// Creates the ACLs, returning the outcome of each creation in order.
+fn create(
+    broker: &Broker,
+    conn: &Connection,
+    creations: Vec<AclCreationV2>,
+) -> Vec<std::result::Result<(), AclError>> {
+    let Some(authorizer) = &broker.authorizer else {
+        let message = "No Authorizer is configured on the broker";
+        return creations
+            .iter()
+            .map(|_| Err((ErrorCode::SecurityDisabled, message.to_string())))
+            .collect();
+    };
+    if !conn.authorize(
+        broker,
+        AclOperation::Alter,
+        ResourceType::Cluster,
+        CLUSTER_NAME,
+    ) {
+        return creations
+            .iter()
+            .map(|_| Err((ErrorCode::ClusterAuthorizationFailed, String::new())))
+            .collect();
+    }
+
+    let bindings: Vec<Option<AclBinding>> = creations.into_iter().map(binding).collect();
+    let mut created = authorizer
+        .create_acls(bindings.iter().flatten().cloned().collect())
+        .into_iter();
+    bindings
+        .into_iter()
+        .map(|binding| match binding {
+            Some(_) => created.next().unwrap_or(Ok(())),
+            None => Err((
+                ErrorCode::InvalidRequest,
+                "Invalid ACL creation".to_string(),
+            )),
+        })
+        .collect()
+}
+
+/// The ACL to create, or `None` if the creation has an unknown code.
+fn binding(creation: AclCreationV2) -> Option<AclBinding> {
+    Some(AclBinding {
+        resource_type: ResourceType::from_i8(creation.resource_type)?,
+        resource_name: creation.resource_name.as_str().to_string(),
+        pattern_type: PatternType::from_i8(creation.resource_pattern_type)?,
+        principal: creation.principal.as_str().to_string(),
+        host: creation.host.as_str().to_string(),
+        operation: AclOperation::from_i8(creation.operation)?,
+        permission_type: PermissionType::from_i8(creation.permission_type)?,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct CreateAclsResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    results: CompactArray<AclCreationResult>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct AclCreationResult {
+    error_code: ErrorCode,
+    error_message: CompactNullableString,
+    tagged_fields: TaggedFields,
+}
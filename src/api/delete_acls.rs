@@ -0,0 +1,161 @@
+use crate::{
+    Broker, Result,
+    acl::{
+        AclBinding, AclBindingFilter, AclError, AclOperation, CLUSTER_NAME, PatternType,
+        PermissionType, ResourceType,
+    },
+    de::Deserializer,
+    types::{ByteSize, CompactArray, CompactNullableString, CompactString, TaggedFields},
+};
+
+use super::{
+    API_KEY_DELETE_ACLS, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::delete_acls_request::{DeleteAclsFilterV2, DeleteAclsRequest},
+    read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        2 | 3 => {
+            let (correlation_id, DeleteAclsRequest::V2(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let filter_results = delete(broker, conn, req_body.filters.into_iter().collect())
+                .into_iter()
+                .map(|result| {
+                    let (error_code, error_message, bindings) = match result {
+                        Ok(bindings) => (ErrorCode::NoError, None, bindings),
+                        Err((code, message)) => (code, Some(message), vec![]),
+                    };
+                    DeleteAclsFilterResult {
+                        error_code,
+                        error_message: CompactNullableString::new(error_message),
+                        matching_acls: bindings.into_iter().map(matching_acl).collect(),
+                        tagged_fields: TaggedFields::new(None),
+                    }
+                })
+                .collect();
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+            let res_body = DeleteAclsResponseBody {
+                throttle_time_ms: 0,
+                filter_results,
+                tagged_fields: TaggedFields::new(None),
+            };
+            Ok(Message::new(
+                res_header,
+                Some(ResponseBody::DeleteAcls(res_body)),
+            ))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_DELETE_ACLS,
+            api_version,
+        }),
+    }
+}
+
+/// Deletes the ACLs matching each filter, returning the deleted ACLs of
+/// each filter in order.
+fn delete(
+    broker: &Broker,
+    conn: &Connection,
+    filters: Vec<DeleteAclsFilterV2>,
+) -> Vec<std::result::Result<Vec<AclBinding>, AclError>> {
+    let Some(authorizer) = &broker.authorizer else {
+        let message = "No Authorizer is configured on the broker";
+        return filters
+            .iter()
+            .map(|_| Err((ErrorCode::SecurityDisabled, message.to_string())))
+            .collect();
+    };
+    if !conn.authorize(
+        broker,
+        AclOperation::Alter,
+        ResourceType::Cluster,
+        CLUSTER_NAME,
+    ) {
+        return filters
+            .iter()
+            .map(|_| Err((ErrorCode::ClusterAuthorizationFailed, String::new())))
+            .collect();
+    }
+
+    let filters: Vec<Option<AclBindingFilter>> = filters.iter().map(filter).collect();
+    let mut deleted = authorizer
+        .delete_acls(filters.iter().flatten().cloned().collect())
+        .into_iter();
+    filters
+        .into_iter()
+        .map(|filter| match filter {
+            Some(_) => deleted.next().unwrap_or(Ok(vec![])),
+            None => Err((ErrorCode::InvalidRequest, "Invalid ACL filter".to_string())),
+        })
+        .collect()
+}
+
+/// The filter of the request, or `None` if it has an unknown code.
+fn filter(filter: &DeleteAclsFilterV2) -> Option<AclBindingFilter> {
+    Some(AclBindingFilter {
+        resource_type: ResourceType::from_i8(filter.resource_type_filter)?,
+        resource_name: filter.resource_name_filter.as_ref().cloned(),
+        pattern_type: PatternType::from_i8(filter.pattern_type_filter)?,
+        principal: filter.principal_filter.as_ref().cloned(),
+        host: filter.host_filter.as_ref().cloned(),
+        operation: AclOperation::from_i8(filter.operation)?,
+        permission_type: PermissionType::from_i8(filter.permission_type)?,
+    })
+}
+
+fn matching_acl(binding: AclBinding) -> DeleteAclsMatchingAcl {
+    DeleteAclsMatchingAcl {
+        error_code: ErrorCode::NoError,
+        error_message: CompactNullableString::new(None),
+        resource_type: binding.resource_type as i8,
+        resource_name: CompactString::new(binding.resource_name),
+        pattern_type: binding.pattern_type as i8,
+        principal: CompactString::new(binding.principal),
+        host: CompactString::new(binding.host),
+        operation: binding.operation as i8,
+        permission_type: binding.permission_type as i8,
+        tagged_fields: TaggedFields::new(None),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DeleteAclsResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    filter_results: CompactArray<DeleteAclsFilterResult>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DeleteAclsFilterResult {
+    error_code: ErrorCode,
+    error_message: CompactNullableString,
+    matching_acls: CompactArray<DeleteAclsMatchingAcl>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DeleteAclsMatchingAcl {
+    error_code: ErrorCode,
+    error_message: CompactNullableString,
+    resource_type: i8,
+    resource_name: CompactString,
+    pattern_type: i8,
+    principal: CompactString,
+    host: CompactString,
+    operation: i8,
+    permission_type: i8,
+    tagged_fields: TaggedFields,
+}
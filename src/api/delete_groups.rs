@@ -0,0 +1,78 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, ResourceType},
+    de::Deserializer,
+    types::{ByteSize, CompactArray, CompactString, TaggedFields},
+};
+
+use super::{
+    API_KEY_DELETE_GROUPS, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::delete_groups_request::DeleteGroupsRequest, read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        2 => {
+            let (correlation_id, DeleteGroupsRequest::V2(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+
+            let res_body = ResponseBody::DeleteGroups(DeleteGroupsResponseBody {
+                throttle_time_ms: 0,
+                results: req_body
+                    .groups_names
+                    .into_iter()
+                    .map(|group_id| {
+                        let error_code = if conn.authorize(
+                            broker,
+                            AclOperation::Delete,
+                            ResourceType::Group,
+                            group_id.as_str(),
+                        ) {
+                            broker.groups.delete_group(group_id.as_str())
+                        } else {
+                            ErrorCode::GroupAuthorizationFailed
+                        };
+                        DeletableGroupResult {
+                            group_id,
+                            error_code,
+                            tagged_fields: TaggedFields::new(None),
+                        }
+                    })
+                    .collect(),
+                tagged_fields: TaggedFields::new(None),
+            });
+
+            Ok(Message::new(res_header, Some(res_body)))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_DELETE_GROUPS,
+            api_version,
+        }),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DeleteGroupsResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    results: CompactArray<DeletableGroupResult>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DeletableGroupResult {
+    group_id: CompactString,
+    error_code: ErrorCode,
+    tagged_fields: TaggedFields,
+}
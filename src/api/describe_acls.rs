@@ -0,0 +1,156 @@
+use crate::{
+    Broker, Result,
+    acl::{
+        AclBinding, AclBindingFilter, AclOperation, CLUSTER_NAME, PatternType, PermissionType,
+        ResourceType,
+    },
+    de::Deserializer,
+    types::{ByteSize, CompactArray, CompactNullableString, CompactString, TaggedFields},
+};
+
+use super::{
+    API_KEY_DESCRIBE_ACLS, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::describe_acls_request::{DescribeAclsRequest, DescribeAclsRequestV2},
+    read_request,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        2 | 3 => {
+            let (correlation_id, DescribeAclsRequest::V2(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let (error_code, error_message, resources) = describe(broker, conn, &req_body);
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+            let res_body = DescribeAclsResponseBody {
+                throttle_time_ms: 0,
+                error_code,
+                error_message: CompactNullableString::new(error_message),
+                resources,
+                tagged_fields: TaggedFields::new(None),
+            };
+            Ok(Message::new(
+                res_header,
+                Some(ResponseBody::DescribeAcls(res_body)),
+            ))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_DESCRIBE_ACLS,
+            api_version,
+        }),
+    }
+}
+
+fn describe(
+    broker: &Broker,
+    conn: &Connection,
+    req_body: &DescribeAclsRequestV2,
+) -> (
+    ErrorCode,
+    Option<String>,
+    CompactArray<DescribeAclsResource>,
+) {
+    let empty = CompactArray::new(Some(vec![]));
+    let Some(authorizer) = &broker.authorizer else {
+        let message = "No Authorizer is configured on the broker".to_string();
+        return (ErrorCode::SecurityDisabled, Some(message), empty);
+    };
+    if !conn.authorize(
+        broker,
+        AclOperation::Describe,
+        ResourceType::Cluster,
+        CLUSTER_NAME,
+    ) {
+        return (ErrorCode::ClusterAuthorizationFailed, None, empty);
+    }
+    let Some(filter) = filter(req_body) else {
+        let message = "Invalid ACL filter".to_string();
+        return (ErrorCode::InvalidRequest, Some(message), empty);
+    };
+
+    // ACLs are grouped by the resource pattern they apply to.
+    let mut resources: BTreeMap<(ResourceType, String, PatternType), Vec<AclBinding>> =
+        BTreeMap::new();
+    for binding in authorizer.acls(&filter) {
+        let key = (
+            binding.resource_type,
+            binding.resource_name.clone(),
+            binding.pattern_type,
+        );
+        resources.entry(key).or_default().push(binding);
+    }
+    let resources = resources
+        .into_iter()
+        .map(
+            |((resource_type, resource_name, pattern_type), bindings)| DescribeAclsResource {
+                resource_type: resource_type as i8,
+                resource_name: CompactString::new(resource_name),
+                pattern_type: pattern_type as i8,
+                acls: bindings
+                    .into_iter()
+                    .map(|binding| AclDescription {
+                        principal: CompactString::new(binding.principal),
+                        host: CompactString::new(binding.host),
+                        operation: binding.operation as i8,
+                        permission_type: binding.permission_type as i8,
+                        tagged_fields: TaggedFields::new(None),
+                    })
+                    .collect(),
+                tagged_fields: TaggedFields::new(None),
+            },
+        )
+        .collect();
+    (ErrorCode::NoError, None, resources)
+}
+
+/// The filter of the request, or `None` if it has an unknown code.
+fn filter(req_body: &DescribeAclsRequestV2) -> Option<AclBindingFilter> {
+    Some(AclBindingFilter {
+        resource_type: ResourceType::from_i8(req_body.resource_type_filter)?,
+        resource_name: req_body.resource_name_filter.as_ref().cloned(),
+        pattern_type: PatternType::from_i8(req_body.pattern_type_filter)?,
+        principal: req_body.principal_filter.as_ref().cloned(),
+        host: req_body.host_filter.as_ref().cloned(),
+        operation: AclOperation::from_i8(req_body.operation)?,
+        permission_type: PermissionType::from_i8(req_body.permission_type)?,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DescribeAclsResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    error_code: ErrorCode,
+    error_message: CompactNullableString,
+    resources: CompactArray<DescribeAclsResource>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DescribeAclsResource {
+    resource_type: i8,
+    resource_name: CompactString,
+    pattern_type: i8,
+    acls: CompactArray<AclDescription>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct AclDescription {
+    principal: CompactString,
+    host: CompactString,
+    operation: i8,
+    permission_type: i8,
+    tagged_fields: TaggedFields,
+}
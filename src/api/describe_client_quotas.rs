@@ -0,0 +1,152 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, CLUSTER_NAME, ResourceType},
+    de::Deserializer,
+    quota::{ComponentFilter, EntityQuotas, QuotaEntity, QuotaError},
+    types::{ByteSize, CompactArray, CompactNullableString, CompactString, TaggedFields},
+};
+
+use super::{
+    API_KEY_DESCRIBE_CLIENT_QUOTAS, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::describe_client_quotas_request::{
+        DescribeClientQuotasRequest, DescribeClientQuotasRequestV1,
+    },
+    read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+const MATCH_TYPE_EXACT: i8 = 0;
+const MATCH_TYPE_DEFAULT: i8 = 1;
+const MATCH_TYPE_SPECIFIED: i8 = 2;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        1 => {
+            let (correlation_id, DescribeClientQuotasRequest::V1(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let (error_code, error_message, entries) = match describe(broker, conn, req_body) {
+                Ok(entries) => (
+                    ErrorCode::NoError,
+                    None,
+                    entries.into_iter().map(entry).collect(),
+                ),
+                Err((code, message)) => (code, Some(message), CompactArray::new(None)),
+            };
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+            let res_body = DescribeClientQuotasResponseBody {
+                throttle_time_ms: 0,
+                error_code,
+                error_message: CompactNullableString::new(error_message),
+                entries,
+                tagged_fields: TaggedFields::new(None),
+            };
+            Ok(Message::new(
+                res_header,
+                Some(ResponseBody::DescribeClientQuotas(res_body)),
+            ))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_DESCRIBE_CLIENT_QUOTAS,
+            api_version,
+        }),
+    }
+}
+
+fn describe(
+    broker: &Broker,
+    conn: &Connection,
+    req_body: DescribeClientQuotasRequestV1,
+) -> std::result::Result<Vec<(QuotaEntity, EntityQuotas)>, QuotaError> {
+    if !conn.authorize(
+        broker,
+        AclOperation::DescribeConfigs,
+        ResourceType::Cluster,
+        CLUSTER_NAME,
+    ) {
+        return Err((ErrorCode::ClusterAuthorizationFailed, String::new()));
+    }
+
+    let filters = req_body
+        .components
+        .into_iter()
+        .map(|component| {
+            let filter = match (component.match_type, component.r#match.as_ref()) {
+                (MATCH_TYPE_EXACT, Some(name)) => ComponentFilter::Exact(name.clone()),
+                (MATCH_TYPE_DEFAULT, _) => ComponentFilter::Default,
+                (MATCH_TYPE_SPECIFIED, _) => ComponentFilter::Specified,
+                _ => {
+                    return Err((
+                        ErrorCode::InvalidRequest,
+                        format!("Invalid match type {}", component.match_type),
+                    ));
+                }
+            };
+            Ok((component.entity_type.as_str().to_string(), filter))
+        })
+        .collect::<std::result::Result<_, QuotaError>>()?;
+    broker.quotas.describe(filters, req_body.strict)
+}
+
+fn entry((entity, quotas): (QuotaEntity, EntityQuotas)) -> EntryData {
+    EntryData {
+        entity: entity
+            .components()
+            .into_iter()
+            .map(|(entity_type, name)| EntityData {
+                entity_type: CompactString::new(entity_type),
+                entity_name: CompactNullableString::new(name),
+                tagged_fields: TaggedFields::new(None),
+            })
+            .collect(),
+        values: quotas
+            .into_iter()
+            .map(|(quota_type, value)| ValueData {
+                key: CompactString::new(quota_type.key().to_string()),
+                value,
+                tagged_fields: TaggedFields::new(None),
+            })
+            .collect(),
+        tagged_fields: TaggedFields::new(None),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DescribeClientQuotasResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    error_code: ErrorCode,
+    error_message: CompactNullableString,
+    entries: CompactArray<EntryData>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct EntryData {
+    entity: CompactArray<EntityData>,
+    values: CompactArray<ValueData>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct EntityData {
+    entity_type: CompactString,
+    entity_name: CompactNullableString,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct ValueData {
+    key: CompactString,
+    value: f64,
+    tagged_fields: TaggedFields,
+}
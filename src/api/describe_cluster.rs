@@ -0,0 +1,144 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, CLUSTER_NAME, ResourceType},
+    de::Deserializer,
+    types::{CompactArray, CompactNullableString, CompactString, TaggedFields},
+};
+
+use super::{
+    Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::describe_cluster_request::DescribeClusterRequest,
+    messages::describe_cluster_response::{
+        DescribeClusterBrokerV0, DescribeClusterResponse, DescribeClusterResponseV0,
+        DescribeClusterResponseV1,
+    },
+    read_request,
+};
+use std::io::Read;
+
+const ENDPOINT_TYPE_BROKERS: i8 = 1;
+
+/// Authorized operations are not reported.
+const AUTHORIZED_OPERATIONS_OMITTED: i32 = i32::MIN;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    let (correlation_id, req_body) = read_request(api_version, &mut de)?;
+    match req_body {
+        DescribeClusterRequest::V0(_) => {
+            if !authorize(broker, conn) {
+                let res_body = error_body(api_version, ErrorCode::ClusterAuthorizationFailed);
+                return Ok(response(correlation_id, res_body));
+            }
+
+            let res_body = DescribeClusterResponse::V0(DescribeClusterResponseV0 {
+                cluster_id: CompactString::new(broker.cluster.cluster_id().to_string()),
+                controller_id: broker.cluster.controller_id(),
+                brokers: brokers(broker, conn),
+                cluster_authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+                ..Default::default()
+            });
+
+            Ok(response(
+                correlation_id,
+                ResponseBody::DescribeCluster(res_body),
+            ))
+        }
+        DescribeClusterRequest::V1(req_body) => {
+            if !authorize(broker, conn) {
+                let res_body = error_body(api_version, ErrorCode::ClusterAuthorizationFailed);
+                return Ok(response(correlation_id, res_body));
+            }
+
+            let (error_code, error_message, brokers) =
+                if req_body.endpoint_type == ENDPOINT_TYPE_BROKERS {
+                    (ErrorCode::NoError, None, brokers(broker, conn))
+                } else {
+                    (
+                        ErrorCode::UnsupportedEndpointType,
+                        Some(format!(
+                            "Unsupported endpoint type {}",
+                            req_body.endpoint_type
+                        )),
+                        CompactArray::new(Some(vec![])),
+                    )
+                };
+
+            let res_body = DescribeClusterResponse::V1(DescribeClusterResponseV1 {
+                error_code: error_code as i16,
+                error_message: CompactNullableString::new(error_message),
+                endpoint_type: req_body.endpoint_type,
+                cluster_id: CompactString::new(broker.cluster.cluster_id().to_string()),
+                controller_id: broker.cluster.controller_id(),
+                brokers,
+                cluster_authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+                ..Default::default()
+            });
+
+            Ok(response(
+                correlation_id,
+                ResponseBody::DescribeCluster(res_body),
+            ))
+        }
+    }
+}
+
+fn error_body(api_version: i16, error_code: ErrorCode) -> ResponseBody {
+    let error_code = error_code as i16;
+    let body = if api_version < 1 {
+        DescribeClusterResponse::V0(DescribeClusterResponseV0 {
+            error_code,
+            cluster_authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+            ..Default::default()
+        })
+    } else {
+        DescribeClusterResponse::V1(DescribeClusterResponseV1 {
+            error_code,
+            endpoint_type: ENDPOINT_TYPE_BROKERS,
+            cluster_authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+            ..Default::default()
+        })
+    };
+    ResponseBody::DescribeCluster(body)
+}
+
+fn response(correlation_id: i32, res_body: ResponseBody) -> Message {
+    let res_header = ResponseHeader::V1 {
+        correlation_id,
+        tagged_fields: TaggedFields::new(None),
+    };
+    Message::new(res_header, Some(res_body))
+}
+
+/// Describing the cluster needs DESCRIBE on the cluster.
+fn authorize(broker: &Broker, conn: &Connection) -> bool {
+    conn.authorize(
+        broker,
+        AclOperation::Describe,
+        ResourceType::Cluster,
+        CLUSTER_NAME,
+    )
+}
+
+/// The registered brokers which are not fenced, at their endpoint for the
+/// listener of the connection.
+fn brokers(broker: &Broker, conn: &Connection) -> CompactArray<DescribeClusterBrokerV0> {
+    broker
+        .cluster
+        .brokers(&conn.listener.endpoint.name)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|registration| !registration.fenced)
+        .map(|registration| DescribeClusterBrokerV0 {
+            broker_id: registration.broker_id,
+            host: CompactString::new(registration.host),
+            port: registration.port as i32,
+            rack: CompactNullableString::new(registration.rack),
+            tagged_fields: TaggedFields::new(None),
+        })
+        .collect()
+}
@@ -0,0 +1,225 @@
+use crate::{
+    Broker, Result,
+    acl::AclOperation,
+    de::Deserializer,
+    dynamic_config::{ConfigSynonym, DescribedConfig, ResourceType},
+    types::{ByteSize, CompactArray, CompactNullableString, CompactString, TaggedFields},
+};
+
+use super::{
+    API_KEY_DESCRIBE_CONFIGS, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    authorize_configs, messages::describe_configs_request::DescribeConfigsRequest,
+    read_meta_records, read_request, topic_exists,
+};
+use serde::Serialize;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        4 => {
+            let (correlation_id, DescribeConfigsRequest::V4(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+
+            let metadata = read_meta_records(broker).unwrap_or_default();
+            let results = req_body
+                .resources
+                .into_iter()
+                .map(|resource| {
+                    let name = resource.resource_name.as_str();
+                    let described = match ResourceType::from_i8(resource.resource_type) {
+                        Some(resource_type) => authorize_configs(
+                            broker,
+                            conn,
+                            AclOperation::DescribeConfigs,
+                            resource_type,
+                            name,
+                        )
+                        .and_then(|()| match resource_type {
+                            ResourceType::Topic if !topic_exists(&metadata, name) => Err((
+                                ErrorCode::UnknownTopicOrPartition,
+                                format!("Topic {name} does not exist"),
+                            )),
+                            _ => broker.configs.describe(resource_type, name),
+                        }),
+                        None => Err((
+                            ErrorCode::InvalidRequest,
+                            format!("Unsupported resource type {}", resource.resource_type),
+                        )),
+                    };
+
+                    let keys = resource.configuration_keys.as_opt_slice();
+                    let configs = described.map(|configs| {
+                        configs
+                            .into_iter()
+                            .filter(|config| {
+                                keys.is_none_or(|keys| {
+                                    keys.iter().any(|key| key.as_str() == config.name)
+                                })
+                            })
+                            .map(|config| {
+                                describe(
+                                    config,
+                                    req_body.include_synonyms,
+                                    req_body.include_documentation,
+                                )
+                            })
+                            .collect()
+                    });
+
+                    let (error_code, error_message, configs) = match configs {
+                        Ok(configs) => (ErrorCode::NoError, None, configs),
+                        Err((code, message)) => (code, Some(message), vec![]),
+                    };
+                    DescribeConfigsResult {
+                        error_code,
+                        error_message: CompactNullableString::new(error_message),
+                        resource_type: resource.resource_type,
+                        resource_name: resource.resource_name,
+                        configs: CompactArray::new(Some(configs)),
+                        tagged_fields: TaggedFields::new(None),
+                    }
+                })
+                .collect();
+
+            let res_body = ResponseBody::DescribeConfigs(DescribeConfigsResponseBody {
+                throttle_time_ms: 0,
+                results,
+                tagged_fields: TaggedFields::new(None),
+            });
+
+            Ok(Message::new(res_header, Some(res_body)))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_DESCRIBE_CONFIGS,
+            api_version,
+        }),
+    }
+}
+
+fn describe(
+    config: DescribedConfig,
+    include_synonyms: bool,
+    include_documentation: bool,
+) -> DescribeConfigsResourceResult {
+    let synonyms = if include_synonyms {
+        config.synonyms.into_iter().map(synonym).collect()
+    } else {
+        vec![]
+    };
+    let documentation = include_documentation.then(|| config.documentation.to_string());
+
+    DescribeConfigsResourceResult {
+        name: CompactString::new(config.name),
+        value: CompactNullableString::new(config.value),
+        read_only: false,
+        config_source: config.source as i8,
+        is_sensitive: false,
+        synonyms: CompactArray::new(Some(synonyms)),
+        config_type: config.config_type as i8,
+        documentation: CompactNullableString::new(documentation),
+        tagged_fields: TaggedFields::new(None),
+    }
+}
+
+fn synonym(synonym: ConfigSynonym) -> DescribeConfigsSynonym {
+    DescribeConfigsSynonym {
+        name: CompactString::new(synonym.name),
+        value: CompactNullableString::new(synonym.value),
+        source: synonym.source as i8,
+        tagged_fields: TaggedFields::new(None),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DescribeConfigsResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    results: CompactArray<DescribeConfigsResult>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DescribeConfigsResult {
+    error_code: ErrorCode,
+    error_message: CompactNullableString,
+    resource_type: i8,
+    resource_name: CompactString,
+    configs: CompactArray<DescribeConfigsResourceResult>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DescribeConfigsResourceResult {
+    name: CompactString,
+    value: CompactNullableString,
+    read_only: bool,
+    config_source: i8,
+    is_sensitive: bool,
+    synonyms: CompactArray<DescribeConfigsSynonym>,
+    config_type: i8,
+    documentation: CompactNullableString,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DescribeConfigsSynonym {
+    name: CompactString,
+    value: CompactNullableString,
+    source: i8,
+    tagged_fields: TaggedFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{client, request};
+    use crate::dynamic_config::ConfigSource;
+
+    #[test]
+    fn test_describe_configs() {
+        let (broker, conn) = client("describe-configs", "");
+        let configs = vec![("retention.ms".to_string(), Some("1".to_string()))];
+        broker
+            .configs
+            .alter(ResourceType::Topic, "foo", configs, false)
+            .unwrap();
+
+        let body = [
+            &[0x02][..], // resources
+            &[0x02, 0x04],
+            b"foo", // resource_type, resource_name
+            &[0x02, 0x0d],
+            b"retention.ms",
+            &[0x00],             // configuration_keys, tagged_fields
+            &[0x01, 0x00, 0x00], // include_synonyms, include_documentation, tagged_fields
+        ]
+        .concat();
+        let request = request(API_KEY_DESCRIBE_CONFIGS, 4, &body);
+        let message = run(4, Deserializer::new(&request[..]), &broker, &conn).unwrap();
+        let Some(ResponseBody::DescribeConfigs(body)) = message.into_body() else {
+            panic!("expected a DescribeConfigs response");
+        };
+
+        let result = &body.results.as_opt_slice().unwrap()[0];
+        assert_eq!(result.error_code, ErrorCode::NoError);
+        // Only the requested key is described, along with its synonyms.
+        let [config] = result.configs.as_opt_slice().unwrap() else {
+            panic!("expected one config");
+        };
+        assert_eq!(config.name.as_str(), "retention.ms");
+        assert_eq!(config.value.as_ref().map(String::as_str), Some("1"));
+        assert_eq!(config.config_source, ConfigSource::Topic as i8);
+        assert!(!config.synonyms.as_opt_slice().unwrap().is_empty());
+        assert!(config.documentation.as_ref().is_none());
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+}
@@ -0,0 +1,251 @@
+use crate::{
+    Broker, Result,
+    acl::{self, AclOperation, ResourceType},
+    de::Deserializer,
+    group::{GroupDescription, MemberDescription},
+    ser::Serializer,
+    types::{
+        Array, ByteSize, CompactArray, CompactBytes, CompactNullableString, CompactString,
+        NullableBytes, TaggedFields,
+    },
+};
+
+use super::{
+    API_KEY_DESCRIBE_GROUPS, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::describe_groups_request::DescribeGroupsRequest, read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+/// Authorized operations are only computed when requested.
+const AUTHORIZED_OPERATIONS_OMITTED: i32 = i32::MIN;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        5 => {
+            let (correlation_id, DescribeGroupsRequest::V5(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+
+            let authorized = |group_id: &str, operation| {
+                conn.authorize(broker, operation, ResourceType::Group, group_id)
+            };
+            let include_authorized_operations = req_body.include_authorized_operations;
+            let authorized_operations = |group_id: &str| {
+                if !include_authorized_operations {
+                    return AUTHORIZED_OPERATIONS_OMITTED;
+                }
+                let operations: Vec<AclOperation> = acl::GROUP_OPERATIONS
+                    .iter()
+                    .copied()
+                    .filter(|&operation| authorized(group_id, operation))
+                    .collect();
+                acl::operations_bitfield(&operations)
+            };
+
+            let groups = req_body
+                .groups
+                .into_iter()
+                .map(|group_id| {
+                    if !authorized(group_id.as_str(), AclOperation::Describe) {
+                        return Ok(unauthorized_group(group_id));
+                    }
+                    let operations = authorized_operations(group_id.as_str());
+                    match broker.groups.describe_group(group_id.as_str()) {
+                        Some(description) => describe(description, operations),
+                        None => Ok(dead_group(group_id, operations)),
+                    }
+                })
+                .collect::<Result<_>>()?;
+
+            let res_body = ResponseBody::DescribeGroups(DescribeGroupsResponseBody {
+                throttle_time_ms: 0,
+                groups,
+                tagged_fields: TaggedFields::new(None),
+            });
+
+            Ok(Message::new(res_header, Some(res_body)))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_DESCRIBE_GROUPS,
+            api_version,
+        }),
+    }
+}
+
+fn describe(description: GroupDescription, authorized_operations: i32) -> Result<DescribedGroup> {
+    let members = description
+        .members
+        .into_iter()
+        .map(describe_member)
+        .collect::<Result<_>>()?;
+
+    Ok(DescribedGroup {
+        error_code: ErrorCode::NoError,
+        group_id: CompactString::new(description.group_id),
+        group_state: CompactString::new(description.state),
+        protocol_type: CompactString::new(description.protocol_type),
+        protocol_data: CompactString::new(description.protocol_data),
+        members,
+        authorized_operations,
+        tagged_fields: TaggedFields::new(None),
+    })
+}
+
+/// Unknown groups are reported as dead rather than with an error.
+fn dead_group(group_id: CompactString, authorized_operations: i32) -> DescribedGroup {
+    DescribedGroup {
+        error_code: ErrorCode::NoError,
+        group_id,
+        group_state: CompactString::new("Dead".to_string()),
+        protocol_type: CompactString::new(String::new()),
+        protocol_data: CompactString::new(String::new()),
+        members: CompactArray::new(Some(vec![])),
+        authorized_operations,
+        tagged_fields: TaggedFields::new(None),
+    }
+}
+
+fn unauthorized_group(group_id: CompactString) -> DescribedGroup {
+    DescribedGroup {
+        error_code: ErrorCode::GroupAuthorizationFailed,
+        group_state: CompactString::new(String::new()),
+        authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+        ..dead_group(group_id, AUTHORIZED_OPERATIONS_OMITTED)
+    }
+}
+
+/// Encodes the member's subscription and assignment the way the consumer
+/// protocol of classic groups does, so existing tooling can decode them.
+fn describe_member(member: MemberDescription) -> Result<DescribedGroupMember> {
+    let subscription = ConsumerProtocolSubscription {
+        version: 0,
+        topics: member.subscribed_topics.into_iter().collect(),
+        user_data: NullableBytes::new(None),
+    };
+    let assignment = ConsumerProtocolAssignment {
+        version: 0,
+        assigned_partitions: member
+            .assignment
+            .into_iter()
+            .map(|(topic, partitions)| ConsumerProtocolTopicPartition {
+                topic,
+                partitions: partitions.into_iter().collect(),
+            })
+            .collect(),
+        user_data: NullableBytes::new(None),
+    };
+
+    Ok(DescribedGroupMember {
+        member_id: CompactString::new(member.member_id),
+        group_instance_id: CompactNullableString::new(member.instance_id),
+        client_id: CompactString::new(member.client_id),
+        client_host: CompactString::new(member.client_host),
+        member_metadata: CompactBytes::new(encode(&subscription)?),
+        member_assignment: CompactBytes::new(encode(&assignment)?),
+        tagged_fields: TaggedFields::new(None),
+    })
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    let mut buf: Vec<u8> = Vec::new();
+    value.serialize(&mut Serializer::new(&mut buf))?;
+    Ok(buf)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DescribeGroupsResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    groups: CompactArray<DescribedGroup>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DescribedGroup {
+    error_code: ErrorCode,
+    group_id: CompactString,
+    group_state: CompactString,
+    protocol_type: CompactString,
+    protocol_data: CompactString,
+    members: CompactArray<DescribedGroupMember>,
+    authorized_operations: i32,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DescribedGroupMember {
+    member_id: CompactString,
+    group_instance_id: CompactNullableString,
+    client_id: CompactString,
+    client_host: CompactString,
+    member_metadata: CompactBytes,
+    member_assignment: CompactBytes,
+    tagged_fields: TaggedFields,
+}
+
+/// `ConsumerProtocolSubscription` version 0.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ConsumerProtocolSubscription {
+    version: i16,
+    topics: Array<String>,
+    user_data: NullableBytes,
+}
+
+/// `ConsumerProtocolAssignment` version 0.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ConsumerProtocolAssignment {
+    version: i16,
+    assigned_partitions: Array<ConsumerProtocolTopicPartition>,
+    user_data: NullableBytes,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ConsumerProtocolTopicPartition {
+    topic: String,
+    partitions: Array<i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_member_encodes_consumer_protocol() {
+        let member = describe_member(MemberDescription {
+            member_id: "m".to_string(),
+            instance_id: None,
+            client_id: "c".to_string(),
+            client_host: String::new(),
+            subscribed_topics: vec!["foo".to_string()],
+            assignment: vec![("foo".to_string(), vec![1])],
+        })
+        .unwrap();
+
+        assert_eq!(
+            member.member_metadata.as_ref(),
+            &vec![
+                0x00, 0x00, // version
+                0x00, 0x00, 0x00, 0x01, 0x00, 0x03, b'f', b'o', b'o', // topics
+                0xff, 0xff, 0xff, 0xff, // user_data
+            ]
+        );
+        assert_eq!(
+            member.member_assignment.as_ref(),
+            &vec![
+                0x00, 0x00, // version
+                0x00, 0x00, 0x00, 0x01, 0x00, 0x03, b'f', b'o', b'o', // topic
+                0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, // partitions
+                0xff, 0xff, 0xff, 0xff, // user_data
+            ]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, ResourceType},
+    de::Deserializer,
+    log::{ActiveProducer, TopicPartition},
+    types::{
+        ByteSize, CompactArray, CompactNullableString, CompactString, RecordVariant, TaggedFields,
+    },
+};
+
+use super::{
+    API_KEY_DESCRIBE_PRODUCERS, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::describe_producers_request::DescribeProducersRequest, partition_exists,
+    read_meta_records, read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+/// Markers are written by this broker's own coordinator, whose epoch is not
+/// tracked per producer.
+const UNKNOWN_COORDINATOR_EPOCH: i32 = -1;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        0 => {
+            let (correlation_id, DescribeProducersRequest::V0(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+
+            let metadata = read_meta_records(broker).unwrap_or_default();
+            let topics = req_body
+                .topics
+                .into_iter()
+                .map(|topic| {
+                    let name = topic.name.as_str().to_string();
+                    let authorized =
+                        conn.authorize(broker, AclOperation::Read, ResourceType::Topic, &name);
+                    TopicResponse {
+                        partitions: topic
+                            .partition_indexes
+                            .into_iter()
+                            .map(|partition| {
+                                if !authorized {
+                                    return partition_response(
+                                        partition,
+                                        Err(ErrorCode::TopicAuthorizationFailed),
+                                    );
+                                }
+                                let tp = TopicPartition::new(name.as_str(), partition);
+                                describe_partition(broker, &metadata, &tp)
+                            })
+                            .collect(),
+                        name: topic.name,
+                        tagged_fields: TaggedFields::new(None),
+                    }
+                })
+                .collect();
+
+            let res_body = ResponseBody::DescribeProducers(DescribeProducersResponseBody {
+                throttle_time_ms: 0,
+                topics,
+                tagged_fields: TaggedFields::new(None),
+            });
+
+            Ok(Message::new(res_header, Some(res_body)))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_DESCRIBE_PRODUCERS,
+            api_version,
+        }),
+    }
+}
+
+fn describe_partition(
+    broker: &Broker,
+    metadata: &[RecordVariant],
+    tp: &TopicPartition,
+) -> PartitionResponse {
+    if !partition_exists(metadata, tp) {
+        return partition_response(tp.partition, Err(ErrorCode::UnknownTopicOrPartition));
+    }
+
+    // A partition nobody wrote to has no producers.
+    let producers = match broker.logs.get(tp) {
+        Ok(Some(log)) => Ok(log.lock().unwrap().active_producers()),
+        Ok(None) => Ok(vec![]),
+        Err(e) => {
+            eprintln!("Failed to open {tp}: {e}");
+            Err(ErrorCode::KafkaStorageError)
+        }
+    };
+    partition_response(tp.partition, producers)
+}
+
+fn partition_response(
+    partition_index: i32,
+    producers: std::result::Result<Vec<ActiveProducer>, ErrorCode>,
+) -> PartitionResponse {
+    let (error_code, producers) = match producers {
+        Ok(producers) => (ErrorCode::NoError, producers),
+        Err(error_code) => (error_code, vec![]),
+    };
+
+    PartitionResponse {
+        partition_index,
+        error_code,
+        error_message: CompactNullableString::new(None),
+        active_producers: producers
+            .into_iter()
+            .map(|producer| ProducerState {
+                producer_id: producer.producer_id,
+                producer_epoch: producer.producer_epoch as i32,
+                last_sequence: producer.last_sequence,
+                last_timestamp: producer.last_timestamp,
+                coordinator_epoch: UNKNOWN_COORDINATOR_EPOCH,
+                current_txn_start_offset: producer.current_txn_first_offset.unwrap_or(-1),
+                tagged_fields: TaggedFields::new(None),
+            })
+            .collect(),
+        tagged_fields: TaggedFields::new(None),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DescribeProducersResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    topics: CompactArray<TopicResponse>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct TopicResponse {
+    name: CompactString,
+    partitions: CompactArray<PartitionResponse>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct PartitionResponse {
+    partition_index: i32,
+    error_code: ErrorCode,
+    error_message: CompactNullableString,
+    active_producers: CompactArray<ProducerState>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct ProducerState {
+    producer_id: i64,
+    producer_epoch: i32,
+    last_sequence: i32,
+    last_timestamp: i64,
+    coordinator_epoch: i32,
+    current_txn_start_offset: i64,
+    tagged_fields: TaggedFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{client, request};
+    use crate::types::{Record, RecordBatch, VarintBytes};
+
+    #[test]
+    fn test_describe_producers() {
+        let (broker, conn) = client("describe-producers", "");
+        let record = Record::new(0, VarintBytes::new(None), VarintBytes::new(Some(vec![1])));
+        let batch = RecordBatch::new(0, vec![record])
+            .unwrap()
+            .into_transactional(1000, 0)
+            .unwrap();
+        let log = broker
+            .logs
+            .get_or_create(&TopicPartition::new("foo", 0))
+            .unwrap();
+        log.lock().unwrap().append(batch).unwrap();
+
+        let body = [
+            &[0x02, 0x04][..],
+            b"foo",                                                  // topics
+            &[0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01], // partition_indexes
+            &[0x00, 0x00],                                           // tagged_fields
+        ]
+        .concat();
+        let request = request(API_KEY_DESCRIBE_PRODUCERS, 0, &body);
+        let message = run(0, Deserializer::new(&request[..]), &broker, &conn).unwrap();
+        let Some(ResponseBody::DescribeProducers(body)) = message.into_body() else {
+            panic!("expected a DescribeProducers response");
+        };
+
+        let topic = &body.topics.as_opt_slice().unwrap()[0];
+        let [known, unknown] = topic.partitions.as_opt_slice().unwrap() else {
+            panic!("expected two partitions");
+        };
+        assert_eq!(known.error_code, ErrorCode::NoError);
+        let [producer] = known.active_producers.as_opt_slice().unwrap() else {
+            panic!("expected one producer");
+        };
+        assert_eq!((producer.producer_id, producer.producer_epoch), (1000, 0));
+        assert_eq!(producer.current_txn_start_offset, 0);
+        assert_eq!(unknown.error_code, ErrorCode::UnknownTopicOrPartition);
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+}
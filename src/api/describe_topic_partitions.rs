@@ -1,50 +1,76 @@
 use crate::{
-    Result,
+    Broker, Result,
+    acl::{self, AclOperation, ResourceType},
+    cluster::is_internal_topic,
     de::Deserializer,
     types::{
-        ByteSizeExt, CompactArray, CompactNullableString, CompactString, RecordVariant,
-        TaggedFields, Uuid,
+        ByteSize, ByteSizeExt, CompactArray, CompactNullableString, CompactString, NullableStruct,
+        RecordVariant, TaggedFields, Uuid, Value,
     },
 };
 
 use super::{
-    API_KEY_DESCRIBE_TOPIC_PARTITIONS, ErrorCode, Message, RequestHeaderV2, ResponseBody,
-    ResponseHeader, read_meta_records,
+    API_KEY_DESCRIBE_TOPIC_PARTITIONS, Connection, ErrorCode, Message, ResponseBody,
+    ResponseHeader,
+    messages::describe_topic_partitions_request::{DescribeTopicPartitionsRequest, TopicRequestV0},
+    read_meta_values, read_request,
 };
-use serde::{Deserialize, Serialize, ser};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::Read;
 
-pub(crate) fn run<R: Read>(api_version: i16, mut de: Deserializer<R>) -> Result<Message> {
+/// Tags of the replica lists of a partition record kept in tagged fields.
+const TAG_ELIGIBLE_LEADER_REPLICAS: u64 = 1;
+const TAG_LAST_KNOWN_ELR: u64 = 2;
+
+/// The most partitions described in one response.
+const MAX_RESPONSE_PARTITION_LIMIT: i32 = 2000;
+
+/// Whether the client may perform an operation on the named topic.
+type Authorized<'a> = &'a dyn Fn(&str, AclOperation) -> bool;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
     match api_version {
         0 => {
-            let req_header: RequestHeaderV2 = Deserialize::deserialize(&mut de)?;
-            let req_body: RequestBody = Deserialize::deserialize(&mut de)?;
+            let (correlation_id, DescribeTopicPartitionsRequest::V0(req_body)) =
+                read_request(api_version, &mut de)?;
 
             let res_header = ResponseHeader::V1 {
-                correlation_id: req_header.correlation_id,
+                correlation_id,
                 tagged_fields: TaggedFields::new(None),
             };
 
-            let records = read_meta_records()?;
-
-            let mut topics: Vec<ResponseTopic> = req_body
-                .topics
-                .into_iter()
-                .map(make_response(records.as_slice()))
-                .collect();
+            let metadata = read_meta_values(broker)?;
+            let authorized = |name: &str, operation| {
+                conn.authorize(broker, operation, ResourceType::Topic, name)
+            };
 
-            topics.sort_by(|a, b| match (a.name.as_ref(), b.name.as_ref()) {
-                (Some(name_a), Some(name_b)) => name_a.cmp(name_b),
-                (Some(_), None) => std::cmp::Ordering::Greater,
-                (None, Some(_)) => std::cmp::Ordering::Less,
-                (None, None) => std::cmp::Ordering::Equal,
-            });
+            let limit = req_body
+                .response_partition_limit
+                .clamp(1, MAX_RESPONSE_PARTITION_LIMIT) as usize;
+            let (topics, next_cursor) = describe_topics(
+                &metadata,
+                requested_names(&metadata, req_body.topics, &authorized),
+                req_body.cursor.into_inner().map(|cursor| {
+                    Cursor::new(
+                        cursor.topic_name.as_str().to_string(),
+                        cursor.partition_index,
+                    )
+                }),
+                limit,
+                &authorized,
+            );
 
             let res_body =
                 ResponseBody::DescribeTopicPartitions(DescribeTopicPartitionsResponseBody::V0 {
                     throttle_time_ms: 0,
                     topics: CompactArray::new(Some(topics)),
-                    next_cursor: NextCursor(None),
+                    next_cursor: NullableStruct::new(next_cursor),
                     tagged_fields: TaggedFields::new(None),
                 });
 
@@ -57,43 +83,20 @@ pub(crate) fn run<R: Read>(api_version: i16, mut de: Deserializer<R>) -> Result<
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
-struct RequestBody {
-    topics: CompactArray<RequestTopic>,
-    // NOTE:
-    // According to the Kafka protocol documentation, the response_partition_limit
-    // and cursor fields are part of the DescribeTopicPartitionsRequest structure.
-    // However, the test(#VT6) fails if they are included here.
-    //
-    //response_partition_limit: i32,
-    //cursor: Cursor,
-    tagged_fields: TaggedFields,
-}
-
-#[derive(Debug, Clone, PartialEq, Deserialize)]
-struct RequestTopic {
-    name: CompactString,
-    tagged_fields: TaggedFields,
-}
-
-impl ByteSizeExt for RequestTopic {
-    fn byte_size(&self) -> usize {
-        self.name.byte_size() + self.tagged_fields.byte_size()
-    }
-}
-
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-struct Cursor {
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct Cursor {
     topic_name: CompactString,
     partition_index: i32,
     tagged_fields: TaggedFields,
 }
 
-impl ByteSizeExt for Cursor {
-    fn byte_size(&self) -> usize {
-        self.topic_name.byte_size()
-            + self.partition_index.byte_size()
-            + self.tagged_fields.byte_size()
+impl Cursor {
+    fn new(topic_name: String, partition_index: i32) -> Self {
+        Self {
+            topic_name: CompactString::new(topic_name),
+            partition_index,
+            tagged_fields: TaggedFields::new(None),
+        }
     }
 }
 
@@ -103,7 +106,7 @@ pub(crate) enum DescribeTopicPartitionsResponseBody {
     V0 {
         throttle_time_ms: i32,
         topics: CompactArray<ResponseTopic>,
-        next_cursor: NextCursor,
+        next_cursor: NullableStruct<Cursor>,
         tagged_fields: TaggedFields,
     },
 }
@@ -126,7 +129,7 @@ impl ByteSizeExt for DescribeTopicPartitionsResponseBody {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
 pub(crate) struct ResponseTopic {
     error_code: ErrorCode,
     name: CompactNullableString,
@@ -137,19 +140,25 @@ pub(crate) struct ResponseTopic {
     tagged_fields: TaggedFields,
 }
 
-impl ByteSizeExt for ResponseTopic {
-    fn byte_size(&self) -> usize {
-        self.error_code.byte_size()
-            + self.name.byte_size()
-            + self.topic_id.byte_size()
-            + self.is_internal.byte_size()
-            + self.partitions.byte_size()
-            + self.topic_authorized_operations.byte_size()
-            + self.tagged_fields.byte_size()
+impl ResponseTopic {
+    fn unknown(name: String) -> Self {
+        Self::error(name, ErrorCode::UnknownTopicOrPartition)
+    }
+
+    fn error(name: String, error_code: ErrorCode) -> Self {
+        Self {
+            error_code,
+            name: CompactNullableString::new(Some(name)),
+            topic_id: Uuid::default(),
+            is_internal: false,
+            partitions: CompactArray::new(None),
+            topic_authorized_operations: 0,
+            tagged_fields: TaggedFields::new(None),
+        }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
 pub(crate) struct Partition {
     error_code: ErrorCode,
     partition_index: i32,
@@ -163,94 +172,316 @@ pub(crate) struct Partition {
     tagged_fields: TaggedFields,
 }
 
-impl ByteSizeExt for Partition {
-    fn byte_size(&self) -> usize {
-        self.error_code.byte_size()
-            + self.partition_index.byte_size()
-            + self.leader_id.byte_size()
-            + self.leader_epoch.byte_size()
-            + self.replica_nodes.byte_size()
-            + self.isr_nodes.byte_size()
-            + self.eligible_leader_replicas.byte_size()
-            + self.last_known_elr.byte_size()
-            + self.offline_replicas.byte_size()
-            + self.tagged_fields.byte_size()
+/// The requested topic names, sorted and deduplicated. No topics means
+/// every topic of the cluster the client may describe.
+fn requested_names(
+    metadata: &[Value],
+    topics: CompactArray<TopicRequestV0>,
+    authorized: Authorized,
+) -> Vec<String> {
+    let mut names: Vec<String> = topics
+        .into_iter()
+        .map(|topic| topic.name.as_str().to_string())
+        .collect();
+    if names.is_empty() {
+        names = metadata
+            .iter()
+            .filter_map(|record| match &record.value {
+                RecordVariant::Topic(t) => Some(t.name.as_str().to_string()),
+                _ => None,
+            })
+            .filter(|name| authorized(name, AclOperation::Describe))
+            .collect();
     }
+    names.sort();
+    names.dedup();
+    names
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub(crate) struct NextCursor(Option<Cursor>);
-
-impl ser::Serialize for NextCursor {
-    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
-    where
-        S: ser::Serializer,
-    {
-        match self.0.as_ref() {
-            Some(cursor) => cursor.serialize(serializer),
-            None => (-1 as i8).serialize(serializer),
+/// Describes the topics from the cursor on, in name order, until `limit`
+/// partitions are described. Returns the cursor of the next page if any
+/// partition was left out.
+fn describe_topics(
+    metadata: &[Value],
+    names: Vec<String>,
+    cursor: Option<Cursor>,
+    mut limit: usize,
+    authorized: Authorized,
+) -> (Vec<ResponseTopic>, Option<Cursor>) {
+    let (first_name, first_partition) = match cursor.as_ref() {
+        Some(cursor) => (Some(cursor.topic_name.as_str()), cursor.partition_index),
+        None => (None, 0),
+    };
+
+    let live_brokers = live_brokers(metadata);
+    let mut topics = vec![];
+    for name in names {
+        if first_name.is_some_and(|first| name.as_str() < first) {
+            continue;
+        }
+        let start = if first_name == Some(name.as_str()) {
+            first_partition
+        } else {
+            0
+        };
+        if limit == 0 {
+            return (topics, Some(Cursor::new(name, start)));
         }
-    }
-}
 
-impl ByteSizeExt for NextCursor {
-    fn byte_size(&self) -> usize {
-        match self.0.as_ref() {
-            Some(cursor) => cursor.byte_size(),
-            None => 1, // size of int8
+        if !authorized(&name, AclOperation::Describe) {
+            topics.push(ResponseTopic::error(
+                name,
+                ErrorCode::TopicAuthorizationFailed,
+            ));
+            continue;
+        }
+        let Some(mut topic) = describe_topic(metadata, &name, &live_brokers) else {
+            topics.push(ResponseTopic::unknown(name));
+            continue;
+        };
+        let operations: Vec<AclOperation> = acl::TOPIC_OPERATIONS
+            .iter()
+            .copied()
+            .filter(|&operation| authorized(&name, operation))
+            .collect();
+        topic.topic_authorized_operations = acl::operations_bitfield(&operations);
+        let mut partitions: Vec<Partition> = topic
+            .partitions
+            .into_iter()
+            .filter(|p| p.partition_index >= start)
+            .collect();
+        let next = partitions.get(limit).map(|p| p.partition_index);
+        partitions.truncate(limit);
+        limit -= partitions.len();
+        topic.partitions = CompactArray::new(Some(partitions));
+        topics.push(topic);
+
+        if let Some(next) = next {
+            return (topics, Some(Cursor::new(name, next)));
         }
     }
+    (topics, None)
 }
 
-fn make_response(slice: &[RecordVariant]) -> Box<dyn Fn(RequestTopic) -> ResponseTopic + '_> {
-    Box::new(move |topic: RequestTopic| -> ResponseTopic {
-        match slice.iter().find(|record| {
-            if let RecordVariant::Topic(t) = record {
-                t.name.as_str() == topic.name.as_str()
-            } else {
-                false
-            }
-        }) {
-            Some(RecordVariant::Topic(t)) => {
-                let partitions: Vec<Partition> = slice
+fn describe_topic(
+    metadata: &[Value],
+    name: &str,
+    live_brokers: &BTreeSet<i32>,
+) -> Option<ResponseTopic> {
+    let topic_id = metadata.iter().find_map(|record| match &record.value {
+        RecordVariant::Topic(t) if t.name.as_str() == name => Some(t.topic_id),
+        _ => None,
+    })?;
+
+    let mut partitions: Vec<Partition> = metadata
+        .iter()
+        .filter_map(|record| match &record.value {
+            RecordVariant::Partition(p) if p.topic_id == topic_id => {
+                let replicas = p.replicas.as_opt_slice().unwrap_or_default();
+                let offline_replicas = replicas
                     .iter()
-                    .filter_map(|record| match record {
-                        RecordVariant::Partition(p) if p.topic_id == t.topic_id => {
-                            Some(Partition {
-                                error_code: ErrorCode::NoError,
-                                partition_index: p.partition_id,
-                                leader_id: p.leader,
-                                leader_epoch: p.leader_epoch,
-                                replica_nodes: p.replicas.clone(),
-                                isr_nodes: p.isr.clone(),
-                                eligible_leader_replicas: CompactArray::new(None),
-                                last_known_elr: CompactArray::new(None),
-                                offline_replicas: CompactArray::new(None),
-                                tagged_fields: TaggedFields::new(None),
-                            })
-                        }
-                        _ => None,
-                    })
+                    .filter(|replica| !live_brokers.contains(replica))
+                    .copied()
                     .collect();
-                ResponseTopic {
+                Some(Partition {
                     error_code: ErrorCode::NoError,
-                    name: CompactNullableString::from(topic.name),
-                    topic_id: t.topic_id,
-                    is_internal: false,
-                    partitions: CompactArray::new(Some(partitions)),
-                    topic_authorized_operations: 0,
+                    partition_index: p.partition_id,
+                    leader_id: p.leader,
+                    leader_epoch: p.leader_epoch,
+                    replica_nodes: p.replicas.clone(),
+                    isr_nodes: p.isr.clone(),
+                    eligible_leader_replicas: tagged_replicas(record, TAG_ELIGIBLE_LEADER_REPLICAS),
+                    last_known_elr: tagged_replicas(record, TAG_LAST_KNOWN_ELR),
+                    offline_replicas,
                     tagged_fields: TaggedFields::new(None),
-                }
+                })
             }
-            _ => ResponseTopic {
-                error_code: ErrorCode::UnknownTopicOrPartition,
-                name: CompactNullableString::from(topic.name),
-                topic_id: Uuid::default(),
-                is_internal: false,
-                partitions: CompactArray::new(None),
-                topic_authorized_operations: 0,
-                tagged_fields: TaggedFields::new(None),
-            },
-        }
+            _ => None,
+        })
+        .collect();
+    partitions.sort_by_key(|p| p.partition_index);
+
+    Some(ResponseTopic {
+        error_code: ErrorCode::NoError,
+        name: CompactNullableString::new(Some(name.to_string())),
+        topic_id,
+        is_internal: is_internal_topic(name),
+        partitions: CompactArray::new(Some(partitions)),
+        topic_authorized_operations: 0,
+        tagged_fields: TaggedFields::new(None),
     })
 }
+
+/// The brokers whose latest registration is not fenced.
+fn live_brokers(metadata: &[Value]) -> BTreeSet<i32> {
+    let mut fenced = BTreeMap::new();
+    for record in metadata {
+        if let RecordVariant::RegisterBroker(registration) = &record.value {
+            fenced.insert(registration.broker_id, registration.fenced);
+        }
+    }
+    fenced
+        .into_iter()
+        .filter(|&(_, fenced)| !fenced)
+        .map(|(broker_id, _)| broker_id)
+        .collect()
+}
+
+/// Reads a replica list stored as a tagged field of a partition record.
+fn tagged_replicas(record: &Value, tag: u64) -> CompactArray<i32> {
+    record
+        .tagged_fields
+        .get(tag)
+        .and_then(|data| Deserialize::deserialize(&mut Deserializer::new(data)).ok())
+        .unwrap_or_else(|| CompactArray::new(Some(vec![])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Partition as PartitionRecord, RegisterBrokerRecord, TaggedField, Topic};
+
+    fn records(topics: &[(&str, u8, i32)]) -> Vec<Value> {
+        let mut records = vec![];
+        for &(name, id, partitions) in topics {
+            let topic_id = Uuid::from([id; 16]);
+            records.push(Value::new(RecordVariant::Topic(Topic {
+                name: CompactString::new(name.to_string()),
+                topic_id,
+            })));
+            for partition_id in (0..partitions).rev() {
+                records.push(Value::new(RecordVariant::Partition(PartitionRecord {
+                    partition_id,
+                    topic_id,
+                    replicas: CompactArray::new(Some(vec![1])),
+                    isr: CompactArray::new(Some(vec![1])),
+                    removing_replicas: CompactArray::new(Some(vec![])),
+                    adding_replicas: CompactArray::new(Some(vec![])),
+                    leader: 1,
+                    leader_epoch: 0,
+                    partition_epoch: 0,
+                    directories: CompactArray::new(Some(vec![])),
+                })));
+            }
+        }
+        records
+    }
+
+    fn page(
+        records: &[Value],
+        cursor: Option<Cursor>,
+        limit: usize,
+    ) -> (Vec<(String, Vec<i32>)>, Option<Cursor>) {
+        let names = requested_names(records, CompactArray::new(Some(vec![])), &|_, _| true);
+        let (topics, next) = describe_topics(records, names, cursor, limit, &|_, _| true);
+        let topics = topics
+            .into_iter()
+            .map(|topic| {
+                let partitions = topic.partitions.as_opt_slice().unwrap_or_default();
+                (
+                    topic.name.as_ref().unwrap().clone(),
+                    partitions.iter().map(|p| p.partition_index).collect(),
+                )
+            })
+            .collect();
+        (topics, next)
+    }
+
+    #[test]
+    fn test_describe_topics_paginated() {
+        let records = records(&[("foo", 1, 3), ("bar", 2, 2)]);
+
+        let (topics, next) = page(&records, None, 3);
+        assert_eq!(
+            topics,
+            [
+                ("bar".to_string(), vec![0, 1]),
+                ("foo".to_string(), vec![0])
+            ]
+        );
+        assert_eq!(next, Some(Cursor::new("foo".to_string(), 1)));
+
+        let (topics, next) = page(&records, next, 3);
+        assert_eq!(topics, [("foo".to_string(), vec![1, 2])]);
+        assert_eq!(next, None);
+
+        // A page ending with a topic points to the first partition of the next.
+        let (topics, next) = page(&records, None, 2);
+        assert_eq!(topics, [("bar".to_string(), vec![0, 1])]);
+        assert_eq!(next, Some(Cursor::new("foo".to_string(), 0)));
+    }
+
+    #[test]
+    fn test_describe_partition_details() {
+        let mut records = records(&[("__consumer_offsets", 1, 1)]);
+        let Value {
+            value: RecordVariant::Partition(partition),
+            tagged_fields,
+            ..
+        } = &mut records[1]
+        else {
+            unreachable!()
+        };
+        partition.replicas = CompactArray::new(Some(vec![1, 2]));
+        // Tagged `[]int32` fields: ELR [2] and an empty last known ELR.
+        *tagged_fields = TaggedFields::new(Some(vec![
+            TaggedField::new(1, vec![0x02, 0x00, 0x00, 0x00, 0x02]),
+            TaggedField::new(2, vec![0x01]),
+        ]));
+        records.push(Value::new(RecordVariant::RegisterBroker(
+            RegisterBrokerRecord {
+                broker_id: 1,
+                is_migrating_zk_broker: false,
+                incarnation_id: Uuid::default(),
+                broker_epoch: 0,
+                end_points: CompactArray::new(Some(vec![])),
+                features: CompactArray::new(Some(vec![])),
+                rack: CompactNullableString::new(None),
+                fenced: false,
+                in_controlled_shutdown: false,
+                log_dirs: CompactArray::new(Some(vec![])),
+            },
+        )));
+
+        let (topics, _) = describe_topics(
+            &records,
+            vec!["__consumer_offsets".to_string()],
+            None,
+            10,
+            &|_, _| true,
+        );
+        let topic = &topics[0];
+        assert!(topic.is_internal);
+        assert_eq!(topic.topic_authorized_operations, 0b1101_1111_1000);
+
+        let partition = &topic.partitions.as_opt_slice().unwrap()[0];
+        assert_eq!(
+            partition.eligible_leader_replicas.as_opt_slice(),
+            Some(&[2][..])
+        );
+        assert_eq!(partition.last_known_elr.as_opt_slice(), Some(&[][..]));
+        assert_eq!(partition.offline_replicas.as_opt_slice(), Some(&[2][..]));
+    }
+
+    #[test]
+    fn test_describe_topics_authorized() {
+        let records = records(&[("foo", 1, 1), ("bar", 2, 1)]);
+        let authorized = |name: &str, operation| {
+            name == "foo" && matches!(operation, AclOperation::Read | AclOperation::Describe)
+        };
+
+        let names = requested_names(&records, CompactArray::new(Some(vec![])), &authorized);
+        assert_eq!(names, ["foo"]);
+
+        let (topics, _) = describe_topics(
+            &records,
+            vec!["bar".to_string(), "foo".to_string()],
+            None,
+            10,
+            &authorized,
+        );
+        assert_eq!(topics[0].error_code, ErrorCode::TopicAuthorizationFailed);
+        assert_eq!(topics[1].error_code, ErrorCode::NoError);
+        assert_eq!(topics[1].topic_authorized_operations, 0b1_0000_1000);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, ResourceType},
+    de::Deserializer,
+    txn::TransactionMetadata,
+    types::{ByteSize, CompactArray, CompactString, TaggedFields},
+};
+
+use super::{
+    API_KEY_DESCRIBE_TRANSACTIONS, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::describe_transactions_request::DescribeTransactionsRequest, read_request,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        0 => {
+            let (correlation_id, DescribeTransactionsRequest::V0(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+
+            let transaction_states = req_body
+                .transactional_ids
+                .into_iter()
+                .map(|transactional_id| {
+                    if !conn.authorize(
+                        broker,
+                        AclOperation::Describe,
+                        ResourceType::TransactionalId,
+                        transactional_id.as_str(),
+                    ) {
+                        return failed(
+                            transactional_id,
+                            ErrorCode::TransactionalIdAuthorizationFailed,
+                        );
+                    }
+                    match broker.txns.describe_transaction(transactional_id.as_str()) {
+                        // Only the topics the client may describe are listed.
+                        Some(txn) => describe(txn, |topic| {
+                            conn.authorize(
+                                broker,
+                                AclOperation::Describe,
+                                ResourceType::Topic,
+                                topic,
+                            )
+                        }),
+                        None => failed(transactional_id, ErrorCode::TransactionalIdNotFound),
+                    }
+                })
+                .collect();
+
+            let res_body = ResponseBody::DescribeTransactions(DescribeTransactionsResponseBody {
+                throttle_time_ms: 0,
+                transaction_states,
+                tagged_fields: TaggedFields::new(None),
+            });
+
+            Ok(Message::new(res_header, Some(res_body)))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_DESCRIBE_TRANSACTIONS,
+            api_version,
+        }),
+    }
+}
+
+fn describe(txn: TransactionMetadata, authorized: impl Fn(&str) -> bool) -> TransactionStateResult {
+    let mut by_topic: BTreeMap<String, Vec<i32>> = BTreeMap::new();
+    for tp in txn
+        .partitions
+        .into_iter()
+        .filter(|tp| authorized(&tp.topic))
+    {
+        by_topic.entry(tp.topic).or_default().push(tp.partition);
+    }
+
+    TransactionStateResult {
+        error_code: ErrorCode::NoError,
+        transactional_id: CompactString::new(txn.transactional_id),
+        transaction_state: CompactString::new(txn.state.to_string()),
+        transaction_timeout_ms: txn.timeout_ms,
+        transaction_start_time_ms: txn.start_timestamp_ms,
+        producer_id: txn.producer_id,
+        producer_epoch: txn.producer_epoch,
+        topics: by_topic
+            .into_iter()
+            .map(|(topic, partitions)| TopicData {
+                topic: CompactString::new(topic),
+                partitions: CompactArray::new(Some(partitions)),
+                tagged_fields: TaggedFields::new(None),
+            })
+            .collect(),
+        tagged_fields: TaggedFields::new(None),
+    }
+}
+
+fn failed(transactional_id: CompactString, error_code: ErrorCode) -> TransactionStateResult {
+    TransactionStateResult {
+        error_code,
+        transactional_id,
+        transaction_state: CompactString::new(String::new()),
+        transaction_timeout_ms: 0,
+        transaction_start_time_ms: -1,
+        producer_id: -1,
+        producer_epoch: -1,
+        topics: CompactArray::new(Some(vec![])),
+        tagged_fields: TaggedFields::new(None),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DescribeTransactionsResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    transaction_states: CompactArray<TransactionStateResult>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct TransactionStateResult {
+    error_code: ErrorCode,
+    transactional_id: CompactString,
+    transaction_state: CompactString,
+    transaction_timeout_ms: i32,
+    transaction_start_time_ms: i64,
+    producer_id: i64,
+    producer_epoch: i16,
+    topics: CompactArray<TopicData>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct TopicData {
+    topic: CompactString,
+    partitions: CompactArray<i32>,
+    tagged_fields: TaggedFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{client, request};
+    use crate::log::TopicPartition;
+
+    #[test]
+    fn test_describe_transactions() {
+        let (broker, conn) = client("describe-transactions", "");
+        let (producer_id, epoch) = broker
+            .txns
+            .init_producer_id(Some("tx"), 60_000, None, &broker.groups)
+            .unwrap();
+        broker
+            .txns
+            .add_partitions("tx", producer_id, epoch, &[TopicPartition::new("foo", 0)])
+            .unwrap();
+
+        let body = [&[0x03, 0x03][..], b"tx", &[0x06], b"other", &[0x00]].concat();
+        let request = request(API_KEY_DESCRIBE_TRANSACTIONS, 0, &body);
+        let message = run(0, Deserializer::new(&request[..]), &broker, &conn).unwrap();
+        let Some(ResponseBody::DescribeTransactions(body)) = message.into_body() else {
+            panic!("expected a DescribeTransactions response");
+        };
+
+        let [txn, other] = body.transaction_states.as_opt_slice().unwrap() else {
+            panic!("expected two transactions");
+        };
+        assert_eq!(txn.error_code, ErrorCode::NoError);
+        assert_eq!(txn.transaction_state.as_str(), "Ongoing");
+        assert_eq!((txn.producer_id, txn.producer_epoch), (producer_id, epoch));
+        let topics = txn.topics.as_opt_slice().unwrap();
+        assert_eq!(topics[0].topic.as_str(), "foo");
+        assert_eq!(topics[0].partitions.as_opt_slice().unwrap(), [0]);
+        assert_eq!(other.error_code, ErrorCode::TransactionalIdNotFound);
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, CLUSTER_NAME, ResourceType},
+    de::Deserializer,
+    types::{ByteSize, CompactArray, CompactNullableString, CompactString, TaggedFields},
+};
+
+use super::{
+    API_KEY_DESCRIBE_USER_SCRAM_CREDENTIALS, Connection, ErrorCode, Message, ResponseBody,
+    ResponseHeader,
+    messages::describe_user_scram_credentials_request::DescribeUserScramCredentialsRequest,
+    read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        0 => {
+            let (correlation_id, DescribeUserScramCredentialsRequest::V0(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+            if !conn.authorize(
+                broker,
+                AclOperation::Describe,
+                ResourceType::Cluster,
+                CLUSTER_NAME,
+            ) {
+                return Ok(Message::new(
+                    res_header,
+                    Some(error_body(ErrorCode::ClusterAuthorizationFailed)),
+                ));
+            }
+
+            // A null or empty list describes every user with a credential.
+            let users = req_body
+                .users
+                .as_opt_slice()
+                .filter(|users| !users.is_empty())
+                .map(|users| {
+                    users
+                        .iter()
+                        .map(|user| user.name.as_str().to_string())
+                        .collect()
+                });
+            let results = broker
+                .credentials
+                .describe(users)
+                .into_iter()
+                .map(|(user, result)| {
+                    let (error_code, error_message, infos) = match result {
+                        Ok(infos) => (ErrorCode::NoError, None, infos),
+                        Err((code, message)) => (code, Some(message), vec![]),
+                    };
+                    DescribeUserScramCredentialsResult {
+                        user: CompactString::new(user),
+                        error_code,
+                        error_message: CompactNullableString::new(error_message),
+                        credential_infos: infos
+                            .into_iter()
+                            .map(|(mechanism, iterations)| CredentialInfo {
+                                mechanism: mechanism as i8,
+                                iterations,
+                                tagged_fields: TaggedFields::new(None),
+                            })
+                            .collect(),
+                        tagged_fields: TaggedFields::new(None),
+                    }
+                })
+                .collect();
+
+            let res_body = DescribeUserScramCredentialsResponseBody {
+                throttle_time_ms: 0,
+                error_code: ErrorCode::NoError,
+                error_message: CompactNullableString::new(None),
+                results,
+                tagged_fields: TaggedFields::new(None),
+            };
+            Ok(Message::new(
+                res_header,
+                Some(ResponseBody::DescribeUserScramCredentials(res_body)),
+            ))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_DESCRIBE_USER_SCRAM_CREDENTIALS,
+            api_version,
+        }),
+    }
+}
+
+fn error_body(error_code: ErrorCode) -> ResponseBody {
+    ResponseBody::DescribeUserScramCredentials(DescribeUserScramCredentialsResponseBody {
+        throttle_time_ms: 0,
+        error_code,
+        error_message: CompactNullableString::new(None),
+        results: CompactArray::new(Some(vec![])),
+        tagged_fields: TaggedFields::new(None),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DescribeUserScramCredentialsResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    error_code: ErrorCode,
+    error_message: CompactNullableString,
+    results: CompactArray<DescribeUserScramCredentialsResult>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct DescribeUserScramCredentialsResult {
+    user: CompactString,
+    error_code: ErrorCode,
+    error_message: CompactNullableString,
+    credential_infos: CompactArray<CredentialInfo>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct CredentialInfo {
+    mechanism: i8,
+    iterations: i32,
+    tagged_fields: TaggedFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{client, request};
+    use crate::sasl::ScramUpsertion;
+
+    #[test]
+    fn test_describe_user_scram_credentials() {
+        let (broker, conn) = client("describe-user-scram-credentials", "");
+        let upsertion = ScramUpsertion {
+            name: "alice".to_string(),
+            mechanism: 2,
+            iterations: 4096,
+            salt: vec![1],
+            salted_password: vec![2],
+        };
+        let altered = broker.credentials.alter(vec![], vec![upsertion]);
+        assert_eq!(altered[0].1, Ok(()));
+
+        let body = [
+            &[0x03, 0x06][..],
+            b"alice",
+            &[0x00, 0x06],
+            b"carol",
+            &[0x00, 0x00], // users, tagged_fields
+        ]
+        .concat();
+        let request = request(API_KEY_DESCRIBE_USER_SCRAM_CREDENTIALS, 0, &body);
+        let message = run(0, Deserializer::new(&request[..]), &broker, &conn).unwrap();
+        let Some(ResponseBody::DescribeUserScramCredentials(body)) = message.into_body() else {
+            panic!("expected a DescribeUserScramCredentials response");
+        };
+
+        assert_eq!(body.error_code, ErrorCode::NoError);
+        let [alice, carol] = body.results.as_opt_slice().unwrap() else {
+            panic!("expected two users");
+        };
+        assert_eq!(alice.error_code, ErrorCode::NoError);
+        let infos = alice.credential_infos.as_opt_slice().unwrap();
+        assert_eq!((infos[0].mechanism, infos[0].iterations), (2, 4096));
+        assert_eq!(carol.error_code, ErrorCode::ResourceNotFound);
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, ResourceType},
+    de::Deserializer,
+    types::{ByteSize, TaggedFields},
+};
+
+use super::{
+    API_KEY_END_TXN, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::end_txn_request::EndTxnRequest, read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        3 | 4 => {
+            let (correlation_id, EndTxnRequest::V3(req_body)) = read_request(api_version, &mut de)?;
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+
+            let transactional_id = req_body.transactional_id.as_str();
+            let authorized = conn.authorize(
+                broker,
+                AclOperation::Write,
+                ResourceType::TransactionalId,
+                transactional_id,
+            );
+            let result = if authorized {
+                broker.txns.end_txn(
+                    transactional_id,
+                    req_body.producer_id,
+                    req_body.producer_epoch,
+                    req_body.committed,
+                    &broker.groups,
+                )
+            } else {
+                Err(ErrorCode::TransactionalIdAuthorizationFailed)
+            };
+            let error_code = match result {
+                Ok(()) => ErrorCode::NoError,
+                Err(error_code) => error_code,
+            };
+
+            let res_body = ResponseBody::EndTxn(EndTxnResponseBody {
+                throttle_time_ms: 0,
+                error_code,
+                tagged_fields: TaggedFields::new(None),
+            });
+
+            Ok(Message::new(res_header, Some(res_body)))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_END_TXN,
+            api_version,
+        }),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct EndTxnResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    error_code: ErrorCode,
+    tagged_fields: TaggedFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{client, request};
+    use crate::log::TopicPartition;
+    use crate::txn::TransactionState;
+
+    fn end_txn(
+        broker: &Broker,
+        conn: &Connection,
+        producer_id: i64,
+        epoch: i16,
+        committed: bool,
+    ) -> ErrorCode {
+        let body = [
+            &[0x03, b't', b'x'][..],    // transactional_id
+            &producer_id.to_be_bytes(), // producer_id
+            &epoch.to_be_bytes(),       // producer_epoch
+            &[committed as u8, 0x00],   // committed, tagged_fields
+        ]
+        .concat();
+        let request = request(API_KEY_END_TXN, 4, &body);
+        let message = run(4, Deserializer::new(&request[..]), broker, conn).unwrap();
+        let Some(ResponseBody::EndTxn(body)) = message.into_body() else {
+            panic!("expected an EndTxn response");
+        };
+        body.error_code
+    }
+
+    #[test]
+    fn test_end_txn() {
+        let (broker, conn) = client("end-txn", "");
+        let (producer_id, epoch) = broker
+            .txns
+            .init_producer_id(Some("tx"), 60_000, None, &broker.groups)
+            .unwrap();
+        broker
+            .txns
+            .add_partitions("tx", producer_id, epoch, &[TopicPartition::new("foo", 0)])
+            .unwrap();
+
+        assert_eq!(
+            end_txn(&broker, &conn, producer_id, epoch + 1, true),
+            ErrorCode::ProducerFenced
+        );
+        assert_eq!(
+            end_txn(&broker, &conn, producer_id, epoch, true),
+            ErrorCode::NoError
+        );
+        let txn = broker.txns.describe_transaction("tx").unwrap();
+        assert_eq!(txn.state, TransactionState::CompleteCommit);
+
+        // Retries succeed, the opposite result does not.
+        assert_eq!(
+            end_txn(&broker, &conn, producer_id, epoch, true),
+            ErrorCode::NoError
+        );
+        assert_eq!(
+            end_txn(&broker, &conn, producer_id, epoch, false),
+            ErrorCode::InvalidTxnState
+        );
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+}
@@ -1,69 +1,129 @@
 use crate::{
-    Result,
+    Broker, Result,
+    acl::{AclOperation, ResourceType},
     de::Deserializer,
-    types::{
-        ByteSizeExt, CompactArray, CompactNullableBytes, CompactString, RecordVariant,
-        TaggedFields, Uuid,
-    },
+    fetch_session::{CachedPartition, TopicIdPartition},
+    log::{METADATA_TOPIC, TopicPartition, Watch},
+    types::{ByteSize, CompactArray, CompactNullableBytes, RecordVariant, TaggedFields, Uuid},
+    util,
 };
 
 use super::{
-    API_KEY_FETCH, ErrorCode, Message, RequestHeaderV2, ResponseBody, ResponseHeader,
-    read_meta_records,
+    API_KEY_FETCH, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::fetch_request::FetchRequest, partition_exists, read_meta_records, read_request,
 };
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use std::io::Read;
+use std::time::{Duration, Instant};
 
-pub(crate) fn run<R: Read>(api_version: i16, mut de: Deserializer<R>) -> Result<Message> {
-    match api_version {
-        16 => {
-            let req_header: RequestHeaderV2 = Deserialize::deserialize(&mut de)?;
-            let req_body: FetchRequestBody = Deserialize::deserialize(&mut de)?;
+/// Consumers with this isolation level only see committed transactions.
+const READ_COMMITTED: i8 = 1;
+/// A full fetch which opens a new fetch session.
+const INITIAL_EPOCH: i32 = 0;
+/// A full fetch without a fetch session.
+const FINAL_EPOCH: i32 = -1;
 
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Fetched> {
+    match read_request(api_version, &mut de)? {
+        (correlation_id, FetchRequest::V15(req_body)) if api_version == 16 => {
             let res_header = ResponseHeader::V1 {
-                correlation_id: req_header.correlation_id,
+                correlation_id,
                 tagged_fields: TaggedFields::new(None),
             };
 
-            let topics = req_body.topics.as_opt_slice();
-
-            if topics.is_none() || topics.is_some_and(|t| t.is_empty()) {
-                let res_body = ResponseBody::Fetch(FetchResponseBody {
-                    throttle_time_ms: 0,
-                    error_code: ErrorCode::NoError,
-                    session_id: 0,
-                    responses: CompactArray::new(Some(vec![])),
-                    tagged_fields: TaggedFields::new(None),
-                });
-                return Ok(Message::new(res_header, Some(res_body)));
-            }
-
-            let records = read_meta_records()?;
-
-            let responses = req_body
+            let now = util::now_ms();
+            let params = FetchParams {
+                isolation_level: req_body.isolation_level,
+                max_wait_ms: req_body.max_wait_ms,
+                min_bytes: req_body.min_bytes,
+                max_bytes: req_body.max_bytes,
+            };
+            let requested: Vec<(TopicIdPartition, CachedPartition)> = req_body
                 .topics
                 .into_iter()
-                .map(|topic| FetchResponseTopic {
-                    id: topic.id,
-                    partitions: topic
-                        .partitions
-                        .into_iter()
-                        .map(make_response(topic.id, records.as_slice()))
-                        .collect(),
-                    tagged_fields: TaggedFields::new(None),
+                .flat_map(|topic| {
+                    let topic_id = topic.topic_id;
+                    topic.partitions.into_iter().map(move |partition| {
+                        let key = TopicIdPartition {
+                            topic_id,
+                            partition: partition.partition,
+                        };
+                        let cached = CachedPartition::new(
+                            partition.fetch_offset,
+                            partition.partition_max_bytes,
+                        );
+                        (key, cached)
+                    })
                 })
                 .collect();
 
-            let res_body = ResponseBody::Fetch(FetchResponseBody {
-                throttle_time_ms: 0,
-                error_code: ErrorCode::NoError,
-                session_id: 0,
-                responses,
-                tagged_fields: TaggedFields::new(None),
-            });
+            let sessions = &broker.fetch_sessions;
+            let (partitions, session) = if req_body.session_epoch == INITIAL_EPOCH
+                || req_body.session_epoch == FINAL_EPOCH
+            {
+                // A full fetch replaces the previous session, if any.
+                if req_body.session_id != 0 {
+                    sessions.remove(req_body.session_id);
+                }
+                // No session is opened for a fetch without partitions.
+                let open = req_body.session_epoch == INITIAL_EPOCH && !requested.is_empty();
+                (requested, SessionUpdate::Full { open })
+            } else {
+                let updated = requested
+                    .into_iter()
+                    .map(|(key, cached)| (key, cached.fetch_offset, cached.max_bytes))
+                    .collect();
+                let forgotten: Vec<TopicIdPartition> = req_body
+                    .forgotten_topics_data
+                    .into_iter()
+                    .flat_map(|topic| {
+                        let topic_id = topic.topic_id;
+                        topic
+                            .partitions
+                            .into_iter()
+                            .map(move |partition| TopicIdPartition {
+                                topic_id,
+                                partition,
+                            })
+                    })
+                    .collect();
+
+                match sessions.update(
+                    req_body.session_id,
+                    req_body.session_epoch,
+                    updated,
+                    &forgotten,
+                    now,
+                ) {
+                    Ok(partitions) => (
+                        partitions,
+                        SessionUpdate::Incremental {
+                            session_id: req_body.session_id,
+                        },
+                    ),
+                    Err(error_code) => {
+                        let res_body = error_body(error_code);
+                        return Ok(Fetched::Complete(Message::new(res_header, Some(res_body))));
+                    }
+                }
+            };
 
-            Ok(Message::new(res_header, Some(res_body)))
+            let fetch = DelayedFetch::new(broker, res_header, params, partitions, session)?;
+            let responses = fetch.read(broker, &|topic| {
+                conn.authorize(broker, AclOperation::Read, ResourceType::Topic, topic)
+            });
+            if fetch.is_satisfied(&responses) {
+                Ok(Fetched::Complete(fetch.complete(broker, responses)))
+            } else {
+                Ok(Fetched::Delayed(fetch))
+            }
         }
+        // Only the latest version is implemented.
         _ => Err(crate::KafkaError::UnsupportedVersion {
             api_key: API_KEY_FETCH,
             api_version,
@@ -71,78 +131,33 @@ pub(crate) fn run<R: Read>(api_version: i16, mut de: Deserializer<R>) -> Result<
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
-pub(crate) struct FetchRequestBody {
-    max_wait_ms: i32,
-    min_bytes: i32,
-    max_bytes: i32,
-    isolation_level: i8,
-    session_id: i32,
-    session_epoch: i32,
-    topics: CompactArray<FetchRequestTopic>,
-    forgotten_topics: CompactArray<ForgottenTopic>,
-    rack_id: CompactString,
-    tagged_fields: TaggedFields,
-}
-
-#[derive(Debug, Clone, PartialEq, Deserialize)]
-pub(crate) struct FetchRequestTopic {
-    id: Uuid,
-    partitions: CompactArray<FetchRequestPartition>,
-    tagged_fields: TaggedFields,
-}
-
-#[derive(Debug, Clone, PartialEq, Deserialize)]
-pub(crate) struct FetchRequestPartition {
-    partition_index: i32,
-    current_leader_epoch: i32,
-    fetch_offset: i64,
-    last_fetched_epoch: i32,
-    log_start_offset: i64,
-    partition_max_bytes: i32,
-    tagged_fields: TaggedFields,
-}
-
-#[derive(Debug, Clone, PartialEq, Deserialize)]
-pub(crate) struct ForgottenTopic {
-    id: Uuid,
-    partitions: CompactArray<i32>,
-    tagged_fields: TaggedFields,
+fn error_body(error_code: ErrorCode) -> ResponseBody {
+    ResponseBody::Fetch(FetchResponseBody {
+        throttle_time_ms: 0,
+        error_code,
+        session_id: 0,
+        responses: CompactArray::new(Some(vec![])),
+        tagged_fields: TaggedFields::new(None),
+    })
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
 pub(crate) struct FetchResponseBody {
-    throttle_time_ms: i32,
+    pub(crate) throttle_time_ms: i32,
     error_code: ErrorCode,
     session_id: i32,
     responses: CompactArray<FetchResponseTopic>,
     tagged_fields: TaggedFields,
 }
 
-impl ByteSizeExt for FetchResponseBody {
-    fn byte_size(&self) -> usize {
-        self.throttle_time_ms.byte_size()
-            + self.error_code.byte_size()
-            + self.session_id.byte_size()
-            + self.responses.byte_size()
-            + self.tagged_fields.byte_size()
-    }
-}
-
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
 pub(crate) struct FetchResponseTopic {
     id: Uuid,
     partitions: CompactArray<FetchResponsePartition>,
     tagged_fields: TaggedFields,
 }
 
-impl ByteSizeExt for FetchResponseTopic {
-    fn byte_size(&self) -> usize {
-        self.id.byte_size() + self.partitions.byte_size() + self.tagged_fields.byte_size()
-    }
-}
-
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
 pub(crate) struct FetchResponsePartition {
     partition_index: i32,
     error_code: ErrorCode,
@@ -155,69 +170,540 @@ pub(crate) struct FetchResponsePartition {
     tagged_fields: TaggedFields,
 }
 
-impl ByteSizeExt for FetchResponsePartition {
-    fn byte_size(&self) -> usize {
-        self.partition_index.byte_size()
-            + self.error_code.byte_size()
-            + self.high_watermark.byte_size()
-            + self.last_stable_offset.byte_size()
-            + self.log_start_offset.byte_size()
-            + self.aborted_transactions.byte_size()
-            + self.preferred_read_replica.byte_size()
-            + self.records.byte_size()
-            + self.tagged_fields.byte_size()
-    }
-}
-
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
 pub(crate) struct AbortedTransaction {
     producer_id: i64,
     first_offset: i64,
     tagged_fields: TaggedFields,
 }
 
-impl ByteSizeExt for AbortedTransaction {
-    fn byte_size(&self) -> usize {
-        self.producer_id.byte_size()
-            + self.first_offset.byte_size()
-            + self.tagged_fields.byte_size()
-    }
+/// How long a fetch may be parked, how much data completes it and how much
+/// a response may hold.
+struct FetchParams {
+    isolation_level: i8,
+    max_wait_ms: i32,
+    min_bytes: i32,
+    max_bytes: i32,
 }
 
-fn make_response(
-    topic_id: Uuid,
-    slice: &[RecordVariant],
-) -> Box<dyn Fn(FetchRequestPartition) -> FetchResponsePartition + '_> {
-    Box::new(move |partition: FetchRequestPartition| {
-        match slice.iter().find(|record| {
-            if let RecordVariant::Partition(p) = record {
-                p.partition_id == partition.partition_index && p.topic_id == topic_id
-            } else {
-                false
+/// What a completed fetch does to its fetch session.
+enum SessionUpdate {
+    /// A full fetch, which opens a new session of its partitions if `open`.
+    Full { open: bool },
+    /// An incremental fetch of the partitions of an existing session.
+    Incremental { session_id: i32 },
+}
+
+/// The result of a Fetch request: its response, or the fetch parked until
+/// there is enough data to respond.
+pub(crate) enum Fetched {
+    Complete(Message),
+    Delayed(DelayedFetch),
+}
+
+/// A fetch whose partitions held less than `min_bytes` of records. It is
+/// parked in the purgatory, holding no thread, until appends bring enough
+/// data, `max_wait_ms` passes or the leader of a partition changes.
+pub(crate) struct DelayedFetch {
+    res_header: ResponseHeader,
+    params: FetchParams,
+    partitions: Vec<(TopicIdPartition, CachedPartition)>,
+    session: SessionUpdate,
+    deadline: Instant,
+    metadata: Vec<RecordVariant>,
+    leaders: Vec<Option<(i32, i32)>>,
+    /// The fetched partitions and the metadata log, whose appends wake the
+    /// fetch.
+    watch: Watch,
+    /// The metadata log alone, to tell whether it must be read again.
+    metadata_watch: Watch,
+}
+
+impl DelayedFetch {
+    fn new(
+        broker: &Broker,
+        res_header: ResponseHeader,
+        params: FetchParams,
+        partitions: Vec<(TopicIdPartition, CachedPartition)>,
+        session: SessionUpdate,
+    ) -> Result<Self> {
+        let deadline = Instant::now() + Duration::from_millis(params.max_wait_ms.max(0) as u64);
+        let purgatory = broker.logs.purgatory();
+        // The watches are taken before the reads, so no append is missed.
+        let metadata_tp = TopicPartition::new(METADATA_TOPIC, 0);
+        let metadata_watch = purgatory.watch(std::slice::from_ref(&metadata_tp));
+        let metadata = read_meta_records(broker)?;
+        let mut tps: Vec<TopicPartition> = partitions
+            .iter()
+            .filter_map(|(key, _)| topic_partition(&metadata, key))
+            .collect();
+        tps.push(metadata_tp);
+        let watch = purgatory.watch(&tps);
+        let leaders = leaders(&metadata, &partitions);
+
+        Ok(Self {
+            res_header,
+            params,
+            partitions,
+            session,
+            deadline,
+            metadata,
+            leaders,
+            watch,
+            metadata_watch,
+        })
+    }
+
+    /// Reads every partition, checking with `authorized` whether the client
+    /// may read the topic. Once the response holds `max_bytes` of records,
+    /// the partitions after are returned without any, but the first batch
+    /// read is always returned so consumers make progress.
+    fn read(
+        &self,
+        broker: &Broker,
+        authorized: &dyn Fn(&str) -> bool,
+    ) -> Vec<(TopicIdPartition, FetchResponsePartition)> {
+        let response_max_bytes = self.params.max_bytes.max(0) as usize;
+        let mut bytes = 0;
+        self.partitions
+            .iter()
+            .map(|(key, cached)| {
+                let remaining = response_max_bytes.saturating_sub(bytes);
+                let max_bytes = (cached.max_bytes.max(0) as usize).min(remaining);
+                let mut response = fetch_partition(
+                    broker,
+                    authorized,
+                    &self.metadata,
+                    self.params.isolation_level,
+                    key,
+                    cached,
+                    max_bytes,
+                );
+                if let Some(records) = response.records.as_ref() {
+                    // Only the first batch of the response may exceed the
+                    // limit.
+                    if bytes > 0 && records.len() > remaining {
+                        response.records = CompactNullableBytes::new(Some(vec![]));
+                    } else {
+                        bytes += records.len();
+                    }
+                }
+                (*key, response)
+            })
+            .collect()
+    }
+
+    /// Whether the responses are final: they hold `min_bytes` of records or
+    /// an error, or the fetch may wait no longer.
+    fn is_satisfied(&self, responses: &[(TopicIdPartition, FetchResponsePartition)]) -> bool {
+        let bytes: usize = responses
+            .iter()
+            .filter_map(|(_, response)| response.records.as_ref())
+            .map(Vec::len)
+            .sum();
+        let has_error = responses
+            .iter()
+            .any(|(_, response)| response.error_code != ErrorCode::NoError);
+        responses.is_empty()
+            || has_error
+            || bytes >= self.params.min_bytes.max(0) as usize
+            || Instant::now() >= self.deadline
+    }
+
+    /// Waits until a watched log is appended to or the deadline passes.
+    pub(crate) async fn wait(&mut self, broker: &Broker) {
+        let purgatory = broker.logs.purgatory();
+        purgatory.wait(&mut self.watch, self.deadline).await;
+    }
+
+    /// Reads the partitions again and returns the response if the fetch is
+    /// satisfied now. The metadata log is only read again once appended to.
+    pub(crate) fn try_complete(&mut self, broker: &Broker) -> Result<Option<Message>> {
+        let mut leader_changed = false;
+        if broker
+            .logs
+            .purgatory()
+            .has_changed(&mut self.metadata_watch)
+        {
+            self.metadata = read_meta_records(broker)?;
+            leader_changed = leaders(&self.metadata, &self.partitions) != self.leaders;
+        }
+
+        // Every partition passed authorization before the fetch was parked,
+        // or the fetch would have completed with the error.
+        let responses = self.read(broker, &|_| true);
+        if leader_changed || self.is_satisfied(&responses) {
+            Ok(Some(self.complete(broker, responses)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// The response with the partitions read, after updating the fetch
+    /// session with them.
+    fn complete(
+        &self,
+        broker: &Broker,
+        responses: Vec<(TopicIdPartition, FetchResponsePartition)>,
+    ) -> Message {
+        let sessions = &broker.fetch_sessions;
+        let (session_id, responses) = match self.session {
+            SessionUpdate::Full { open } => {
+                let mut session_id = 0;
+                if open {
+                    let partitions = self.partitions.iter().copied().collect();
+                    session_id = sessions.create(partitions, util::now_ms());
+                    for (key, response) in &responses {
+                        sessions.update_offsets(
+                            session_id,
+                            key,
+                            response.high_watermark,
+                            response.last_stable_offset,
+                            response.log_start_offset,
+                        );
+                    }
+                }
+                (session_id, responses)
             }
-        }) {
-            Some(RecordVariant::Partition(_)) => FetchResponsePartition {
-                partition_index: partition.partition_index,
-                error_code: ErrorCode::NoError,
-                high_watermark: 0,
-                last_stable_offset: 0,
-                log_start_offset: 0,
-                aborted_transactions: CompactArray::new(None),
-                preferred_read_replica: -1,
-                records: CompactNullableBytes::new(Some(vec![])),
-                tagged_fields: TaggedFields::new(None),
-            },
-            _ => FetchResponsePartition {
-                partition_index: partition.partition_index,
-                error_code: ErrorCode::UnknownTopicId,
-                high_watermark: 0,
-                last_stable_offset: 0,
-                log_start_offset: 0,
-                aborted_transactions: CompactArray::new(None),
-                preferred_read_replica: 0,
-                records: CompactNullableBytes::new(None),
-                tagged_fields: TaggedFields::new(None),
-            },
+            SessionUpdate::Incremental { session_id } => {
+                // Incremental responses leave out the partitions without
+                // news for the consumer.
+                let responses = responses
+                    .into_iter()
+                    .filter(|(key, response)| {
+                        let changed = sessions.update_offsets(
+                            session_id,
+                            key,
+                            response.high_watermark,
+                            response.last_stable_offset,
+                            response.log_start_offset,
+                        );
+                        changed
+                            || response.error_code != ErrorCode::NoError
+                            || response.records.as_ref().is_some_and(|r| !r.is_empty())
+                    })
+                    .collect();
+                (session_id, responses)
+            }
+        };
+
+        let res_body = ResponseBody::Fetch(FetchResponseBody {
+            throttle_time_ms: 0,
+            error_code: ErrorCode::NoError,
+            session_id,
+            responses: group_by_topic(responses),
+            tagged_fields: TaggedFields::new(None),
+        });
+        Message::new(self.res_header.clone(), Some(res_body))
+    }
+}
+
+fn topic_partition(metadata: &[RecordVariant], key: &TopicIdPartition) -> Option<TopicPartition> {
+    metadata.iter().find_map(|record| match record {
+        RecordVariant::Topic(t) if t.topic_id == key.topic_id => {
+            Some(TopicPartition::new(t.name.as_str(), key.partition))
+        }
+        _ => None,
+    })
+}
+
+/// The leader and leader epoch of each partition, from its latest record.
+fn leaders(
+    metadata: &[RecordVariant],
+    partitions: &[(TopicIdPartition, CachedPartition)],
+) -> Vec<Option<(i32, i32)>> {
+    partitions
+        .iter()
+        .map(|(key, _)| {
+            metadata.iter().rev().find_map(|record| match record {
+                RecordVariant::Partition(p)
+                    if p.topic_id == key.topic_id && p.partition_id == key.partition =>
+                {
+                    Some((p.leader, p.leader_epoch))
+                }
+                _ => None,
+            })
+        })
+        .collect()
+}
+
+/// Groups partition responses of the same topic, keeping their order.
+fn group_by_topic(
+    responses: Vec<(TopicIdPartition, FetchResponsePartition)>,
+) -> CompactArray<FetchResponseTopic> {
+    let mut topics: Vec<(Uuid, Vec<FetchResponsePartition>)> = vec![];
+    for (key, response) in responses {
+        match topics.last_mut() {
+            Some((topic_id, partitions)) if *topic_id == key.topic_id => partitions.push(response),
+            _ => topics.push((key.topic_id, vec![response])),
         }
+    }
+    topics
+        .into_iter()
+        .map(|(id, partitions)| FetchResponseTopic {
+            id,
+            partitions: CompactArray::new(Some(partitions)),
+            tagged_fields: TaggedFields::new(None),
+        })
+        .collect()
+}
+
+fn fetch_partition(
+    broker: &Broker,
+    authorized: &dyn Fn(&str) -> bool,
+    metadata: &[RecordVariant],
+    isolation_level: i8,
+    key: &TopicIdPartition,
+    cached: &CachedPartition,
+    max_bytes: usize,
+) -> FetchResponsePartition {
+    let Some(tp) = topic_partition(metadata, key) else {
+        return error_response(key.partition, ErrorCode::UnknownTopicId);
+    };
+    if !authorized(&tp.topic) {
+        return error_response(key.partition, ErrorCode::TopicAuthorizationFailed);
+    }
+    if !partition_exists(metadata, &tp) {
+        return error_response(key.partition, ErrorCode::UnknownTopicId);
+    }
+
+    read_partition(broker, &tp, isolation_level, cached, max_bytes).unwrap_or_else(|e| {
+        eprintln!("Failed to read {tp}: {e}");
+        error_response(key.partition, ErrorCode::KafkaStorageError)
     })
 }
+
+/// Reads up to `max_bytes` of the partition from the fetch offset, or its
+/// first batch if that is larger. read_committed fetches stop at the last
+/// stable offset and list the aborted transactions they overlap, so the
+/// consumer can drop their records.
+fn read_partition(
+    broker: &Broker,
+    tp: &TopicPartition,
+    isolation_level: i8,
+    cached: &CachedPartition,
+    max_bytes: usize,
+) -> Result<FetchResponsePartition> {
+    let mut response = error_response(tp.partition, ErrorCode::NoError);
+
+    // Nothing has been produced to the partition yet.
+    let Some(log) = broker.logs.get(tp)? else {
+        response.records = CompactNullableBytes::new(Some(vec![]));
+        return Ok(response);
+    };
+    let log = log.lock().unwrap();
+
+    response.high_watermark = log.log_end_offset();
+    response.last_stable_offset = log.last_stable_offset();
+    if cached.fetch_offset < 0 || cached.fetch_offset > response.high_watermark {
+        response.error_code = ErrorCode::OffsetOutOfRange;
+        return Ok(response);
+    }
+
+    let end_offset = if isolation_level == READ_COMMITTED {
+        let aborted = log
+            .aborted_transactions(cached.fetch_offset, response.last_stable_offset)
+            .into_iter()
+            .map(|txn| AbortedTransaction {
+                producer_id: txn.producer_id,
+                first_offset: txn.first_offset,
+                tagged_fields: TaggedFields::new(None),
+            })
+            .collect();
+        response.aborted_transactions = CompactArray::new(Some(aborted));
+        response.last_stable_offset
+    } else {
+        response.high_watermark
+    };
+
+    response.records = CompactNullableBytes::new(Some(log.read_bytes(
+        cached.fetch_offset,
+        end_offset,
+        max_bytes,
+    )?));
+    Ok(response)
+}
+
+fn error_response(partition_index: i32, error_code: ErrorCode) -> FetchResponsePartition {
+    FetchResponsePartition {
+        partition_index,
+        error_code,
+        high_watermark: 0,
+        last_stable_offset: 0,
+        log_start_offset: 0,
+        aborted_transactions: CompactArray::new(None),
+        preferred_read_replica: -1,
+        records: CompactNullableBytes::new(None),
+        tagged_fields: TaggedFields::new(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CompactString, Partition, Record, RecordBatch, Topic, VarintBytes};
+
+    const TOPIC_ID: [u8; 16] = [1; 16];
+
+    fn partition_record(leader_epoch: i32) -> RecordVariant {
+        partition_record_of(0, leader_epoch)
+    }
+
+    fn partition_record_of(partition_id: i32, leader_epoch: i32) -> RecordVariant {
+        RecordVariant::Partition(Partition {
+            partition_id,
+            topic_id: Uuid::from(TOPIC_ID),
+            replicas: CompactArray::new(Some(vec![1])),
+            isr: CompactArray::new(Some(vec![1])),
+            removing_replicas: CompactArray::new(Some(vec![])),
+            adding_replicas: CompactArray::new(Some(vec![])),
+            leader: 1,
+            leader_epoch,
+            partition_epoch: 0,
+            directories: CompactArray::new(Some(vec![])),
+        })
+    }
+
+    /// A broker with the topic `foo` of one partition.
+    fn broker(name: &str) -> std::sync::Arc<Broker> {
+        let broker = Broker::start_for_test(name, "");
+        let topic = RecordVariant::Topic(Topic {
+            name: CompactString::new("foo".to_string()),
+            topic_id: Uuid::from(TOPIC_ID),
+        });
+        broker
+            .logs
+            .append_metadata(vec![topic, partition_record(0)])
+            .unwrap();
+        broker
+    }
+
+    fn delayed_fetch(broker: &Broker, max_wait_ms: i32) -> DelayedFetch {
+        fetch_partitions(broker, max_wait_ms, i32::MAX, &[0])
+    }
+
+    fn fetch_partitions(
+        broker: &Broker,
+        max_wait_ms: i32,
+        max_bytes: i32,
+        partitions: &[i32],
+    ) -> DelayedFetch {
+        let params = FetchParams {
+            isolation_level: 0,
+            max_wait_ms,
+            min_bytes: 1,
+            max_bytes,
+        };
+        let partitions = partitions
+            .iter()
+            .map(|&partition| {
+                let key = TopicIdPartition {
+                    topic_id: Uuid::from(TOPIC_ID),
+                    partition,
+                };
+                (key, CachedPartition::new(0, 1 << 20))
+            })
+            .collect();
+        let res_header = ResponseHeader::V1 {
+            correlation_id: 7,
+            tagged_fields: TaggedFields::new(None),
+        };
+        let session = SessionUpdate::Full { open: false };
+        DelayedFetch::new(broker, res_header, params, partitions, session).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_delayed_fetch_completes_on_append() {
+        let broker = broker("fetch-append");
+        let mut fetch = delayed_fetch(&broker, 60_000);
+        assert!(!fetch.is_satisfied(&fetch.read(&broker, &|_| true)));
+        assert!(fetch.try_complete(&broker).unwrap().is_none());
+
+        let tp = TopicPartition::new("foo", 0);
+        let record = Record::new(0, VarintBytes::new(None), VarintBytes::new(Some(vec![1])));
+        let log = broker.logs.get_or_create(&tp).unwrap();
+        log.lock()
+            .unwrap()
+            .append(RecordBatch::new(0, vec![record]).unwrap())
+            .unwrap();
+        broker.logs.purgatory().notify(&tp);
+
+        tokio::time::timeout(Duration::from_secs(10), fetch.wait(&broker))
+            .await
+            .unwrap();
+        assert!(fetch.try_complete(&broker).unwrap().is_some());
+
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delayed_fetch_completes_on_leader_change() {
+        let broker = broker("fetch-leader");
+        let mut fetch = delayed_fetch(&broker, 60_000);
+        assert!(fetch.try_complete(&broker).unwrap().is_none());
+
+        broker
+            .logs
+            .append_metadata(vec![partition_record(1)])
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(10), fetch.wait(&broker))
+            .await
+            .unwrap();
+        assert!(fetch.try_complete(&broker).unwrap().is_some());
+
+        // Without a wait, the fetch is satisfied by what there is.
+        let fetch = delayed_fetch(&broker, 0);
+        assert!(fetch.is_satisfied(&fetch.read(&broker, &|_| true)));
+
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+
+    #[test]
+    fn test_fetch_max_bytes() {
+        let broker = broker("fetch-max-bytes");
+        broker
+            .logs
+            .append_metadata(vec![partition_record_of(1, 0)])
+            .unwrap();
+        for partition in [0, 1] {
+            let log = broker
+                .logs
+                .get_or_create(&TopicPartition::new("foo", partition))
+                .unwrap();
+            let mut log = log.lock().unwrap();
+            for value in [1, 2] {
+                let record = Record::new(
+                    0,
+                    VarintBytes::new(None),
+                    VarintBytes::new(Some(vec![value])),
+                );
+                log.append(RecordBatch::new(0, vec![record]).unwrap())
+                    .unwrap();
+            }
+        }
+        let records = |fetch: &DelayedFetch| -> Vec<usize> {
+            fetch
+                .read(&broker, &|_| true)
+                .iter()
+                .map(|(_, response)| {
+                    RecordBatch::<VarintBytes>::from_reader(&response.records.as_ref().unwrap()[..])
+                        .unwrap()
+                        .len()
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            records(&fetch_partitions(&broker, 0, i32::MAX, &[0, 1])),
+            vec![2, 2]
+        );
+        // The first batch is returned even though it exceeds the limit, and
+        // no partition is added after.
+        assert_eq!(
+            records(&fetch_partitions(&broker, 0, 1, &[0, 1])),
+            vec![1, 0]
+        );
+
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{
+    Broker, Result,
+    acl::AclOperation,
+    de::Deserializer,
+    dynamic_config::{AlterConfigOp, ConfigError, ResourceType},
+    types::{ByteSize, CompactArray, CompactNullableString, CompactString, TaggedFields},
+};
+
+use super::{
+    API_KEY_INCREMENTAL_ALTER_CONFIGS, Connection, ErrorCode, Message, ResponseBody,
+    ResponseHeader, authorize_configs,
+    messages::incremental_alter_configs_request::IncrementalAlterConfigsRequest, read_meta_records,
+    read_request, topic_exists,
+};
+use serde::Serialize;
+use std::io::Read;
+
+const OP_SET: i8 = 0;
+const OP_DELETE: i8 = 1;
+const OP_APPEND: i8 = 2;
+const OP_SUBTRACT: i8 = 3;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        1 => {
+            let (correlation_id, IncrementalAlterConfigsRequest::V1(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+
+            let metadata = read_meta_records(broker).unwrap_or_default();
+            let responses = req_body
+                .resources
+                .into_iter()
+                .map(|resource| {
+                    let name = resource.resource_name.as_str();
+                    let ops: std::result::Result<Vec<_>, _> = resource
+                        .configs
+                        .into_iter()
+                        .map(|config| {
+                            let op = alter_config_op(config.config_operation, config.value)?;
+                            Ok((config.name.as_str().to_string(), op))
+                        })
+                        .collect();
+                    let result = match ResourceType::from_i8(resource.resource_type) {
+                        Some(resource_type) => authorize_configs(
+                            broker,
+                            conn,
+                            AclOperation::AlterConfigs,
+                            resource_type,
+                            name,
+                        )
+                        .and_then(|()| match resource_type {
+                            ResourceType::Topic if !topic_exists(&metadata, name) => Err((
+                                ErrorCode::UnknownTopicOrPartition,
+                                format!("Topic {name} does not exist"),
+                            )),
+                            _ => ops.and_then(|ops| {
+                                broker.configs.incremental_alter(
+                                    resource_type,
+                                    name,
+                                    ops,
+                                    req_body.validate_only,
+                                )
+                            }),
+                        }),
+                        None => Err((
+                            ErrorCode::InvalidRequest,
+                            format!("Unsupported resource type {}", resource.resource_type),
+                        )),
+                    };
+
+                    let (error_code, error_message) = match result {
+                        Ok(()) => (ErrorCode::NoError, None),
+                        Err((code, message)) => (code, Some(message)),
+                    };
+                    IncrementalAlterConfigsResourceResponse {
+                        error_code,
+                        error_message: CompactNullableString::new(error_message),
+                        resource_type: resource.resource_type,
+                        resource_name: resource.resource_name,
+                        tagged_fields: TaggedFields::new(None),
+                    }
+                })
+                .collect();
+
+            let res_body =
+                ResponseBody::IncrementalAlterConfigs(IncrementalAlterConfigsResponseBody {
+                    throttle_time_ms: 0,
+                    responses,
+                    tagged_fields: TaggedFields::new(None),
+                });
+
+            Ok(Message::new(res_header, Some(res_body)))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_INCREMENTAL_ALTER_CONFIGS,
+            api_version,
+        }),
+    }
+}
+
+fn alter_config_op(
+    operation: i8,
+    value: CompactNullableString,
+) -> std::result::Result<AlterConfigOp, ConfigError> {
+    let value = value.as_ref().cloned().unwrap_or_default();
+    match operation {
+        OP_SET => Ok(AlterConfigOp::Set(value)),
+        OP_DELETE => Ok(AlterConfigOp::Delete),
+        OP_APPEND => Ok(AlterConfigOp::Append(value)),
+        OP_SUBTRACT => Ok(AlterConfigOp::Subtract(value)),
+        _ => Err((
+            ErrorCode::InvalidRequest,
+            format!("Unknown config operation {operation}"),
+        )),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct IncrementalAlterConfigsResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    responses: CompactArray<IncrementalAlterConfigsResourceResponse>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct IncrementalAlterConfigsResourceResponse {
+    error_code: ErrorCode,
+    error_message: CompactNullableString,
+    resource_type: i8,
+    resource_name: CompactString,
+    tagged_fields: TaggedFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{client, request};
+
+    fn alter(broker: &Broker, conn: &Connection, op: i8, value: &[u8]) -> ErrorCode {
+        let body = [
+            &[0x02][..], // resources
+            &[0x02, 0x04],
+            b"foo", // resource_type, resource_name
+            &[0x02, 0x0f],
+            b"cleanup.policy",
+            &[op as u8, value.len() as u8 + 1],
+            value,                     // configs
+            &[0x00, 0x00, 0x00, 0x00], // tagged_fields, validate_only, tagged_fields
+        ]
+        .concat();
+        let request = request(API_KEY_INCREMENTAL_ALTER_CONFIGS, 1, &body);
+        let message = run(1, Deserializer::new(&request[..]), broker, conn).unwrap();
+        let Some(ResponseBody::IncrementalAlterConfigs(body)) = message.into_body() else {
+            panic!("expected an IncrementalAlterConfigs response");
+        };
+        body.responses.as_opt_slice().unwrap()[0].error_code
+    }
+
+    #[test]
+    fn test_incremental_alter_configs() {
+        let (broker, conn) = client("incremental-alter-configs", "");
+        let cleanup_policy = || {
+            let configs = broker.configs.describe(ResourceType::Topic, "foo").unwrap();
+            configs
+                .into_iter()
+                .find(|config| config.name == "cleanup.policy")
+                .and_then(|config| config.value)
+        };
+
+        assert_eq!(
+            alter(&broker, &conn, OP_APPEND, b"compact"),
+            ErrorCode::NoError
+        );
+        assert_eq!(cleanup_policy().as_deref(), Some("delete,compact"));
+        assert_eq!(
+            alter(&broker, &conn, OP_SUBTRACT, b"delete"),
+            ErrorCode::NoError
+        );
+        assert_eq!(cleanup_policy().as_deref(), Some("compact"));
+        assert_eq!(alter(&broker, &conn, 4, b""), ErrorCode::InvalidRequest);
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, CLUSTER_NAME, ResourceType},
+    de::Deserializer,
+    types::{ByteSize, CompactNullableString, TaggedFields},
+};
+
+use super::{
+    Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::init_producer_id_request::InitProducerIdRequest, read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    let (correlation_id, req_body) = read_request(api_version, &mut de)?;
+    let (transactional_id, transaction_timeout_ms, expected) = match &req_body {
+        InitProducerIdRequest::V2(req_body) => (
+            &req_body.transactional_id,
+            req_body.transaction_timeout_ms,
+            None,
+        ),
+        InitProducerIdRequest::V3(req_body) => {
+            // A producer which already has an ID sends it, so it can be
+            // fenced if another instance took over meanwhile.
+            let expected = (req_body.producer_id >= 0)
+                .then_some((req_body.producer_id, req_body.producer_epoch));
+            (
+                &req_body.transactional_id,
+                req_body.transaction_timeout_ms,
+                expected,
+            )
+        }
+    };
+
+    Ok(response(
+        correlation_id,
+        init_producer_id(
+            broker,
+            conn,
+            transactional_id,
+            transaction_timeout_ms,
+            expected,
+        ),
+    ))
+}
+
+fn response(correlation_id: i32, body: InitProducerIdResponseBody) -> Message {
+    let res_header = ResponseHeader::V1 {
+        correlation_id,
+        tagged_fields: TaggedFields::new(None),
+    };
+    Message::new(res_header, Some(ResponseBody::InitProducerId(body)))
+}
+
+fn init_producer_id(
+    broker: &Broker,
+    conn: &Connection,
+    transactional_id: &CompactNullableString,
+    transaction_timeout_ms: i32,
+    expected: Option<(i64, i16)>,
+) -> InitProducerIdResponseBody {
+    let transactional_id = transactional_id.as_ref().map(|id| id.as_str());
+    // Idempotent producers need IDEMPOTENT_WRITE on the cluster, which
+    // writing to any topic implies.
+    let authorization_error = match transactional_id {
+        Some(id) => (!conn.authorize(
+            broker,
+            AclOperation::Write,
+            ResourceType::TransactionalId,
+            id,
+        ))
+        .then_some(ErrorCode::TransactionalIdAuthorizationFailed),
+        None => (!conn.authorize(
+            broker,
+            AclOperation::IdempotentWrite,
+            ResourceType::Cluster,
+            CLUSTER_NAME,
+        ) && !conn.authorize_any(broker, AclOperation::Write, ResourceType::Topic))
+        .then_some(ErrorCode::ClusterAuthorizationFailed),
+    };
+    if let Some(error_code) = authorization_error {
+        return InitProducerIdResponseBody::error(error_code);
+    }
+
+    match broker.txns.init_producer_id(
+        transactional_id,
+        transaction_timeout_ms,
+        expected,
+        &broker.groups,
+    ) {
+        Ok((producer_id, producer_epoch)) => InitProducerIdResponseBody {
+            throttle_time_ms: 0,
+            error_code: ErrorCode::NoError,
+            producer_id,
+            producer_epoch,
+            tagged_fields: TaggedFields::new(None),
+        },
+        Err(error_code) => InitProducerIdResponseBody::error(error_code),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct InitProducerIdResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    error_code: ErrorCode,
+    producer_id: i64,
+    producer_epoch: i16,
+    tagged_fields: TaggedFields,
+}
+
+impl InitProducerIdResponseBody {
+    fn error(error_code: ErrorCode) -> Self {
+        Self {
+            throttle_time_ms: 0,
+            error_code,
+            producer_id: -1,
+            producer_epoch: -1,
+            tagged_fields: TaggedFields::new(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{API_KEY_INIT_PRODUCER_ID, client, request};
+
+    fn init(
+        broker: &Broker,
+        conn: &Connection,
+        transactional_id: &[u8],
+        producer_id: i64,
+        epoch: i16,
+    ) -> InitProducerIdResponseBody {
+        let body = [
+            transactional_id,
+            &[0x00, 0x00, 0xea, 0x60],  // transaction_timeout_ms
+            &producer_id.to_be_bytes(), // producer_id
+            &epoch.to_be_bytes(),       // producer_epoch
+            &[0x00],                    // tagged_fields
+        ]
+        .concat();
+        let request = request(API_KEY_INIT_PRODUCER_ID, 4, &body);
+        let message = run(4, Deserializer::new(&request[..]), broker, conn).unwrap();
+        let Some(ResponseBody::InitProducerId(body)) = message.into_body() else {
+            panic!("expected an InitProducerId response");
+        };
+        body
+    }
+
+    #[test]
+    fn test_init_producer_id() {
+        let (broker, conn) = client("init-producer-id", "");
+        let tx = [0x03, b't', b'x'];
+
+        let first = init(&broker, &conn, &tx, -1, -1);
+        assert_eq!(first.error_code, ErrorCode::NoError);
+        assert_eq!(first.producer_epoch, 0);
+
+        // The producer keeps its ID and is bumped to the next epoch, which
+        // fences the instance still at the previous one.
+        let second = init(&broker, &conn, &tx, first.producer_id, 0);
+        assert_eq!(second.error_code, ErrorCode::NoError);
+        assert_eq!(
+            (second.producer_id, second.producer_epoch),
+            (first.producer_id, 1)
+        );
+        let fenced = init(&broker, &conn, &tx, first.producer_id, 0);
+        assert_eq!(fenced.error_code, ErrorCode::ProducerFenced);
+
+        // Idempotent producers get a new ID every time.
+        let idempotent = init(&broker, &conn, &[0x00], -1, -1);
+        assert_eq!(idempotent.error_code, ErrorCode::NoError);
+        assert_ne!(idempotent.producer_id, first.producer_id);
+        assert_eq!(idempotent.producer_epoch, 0);
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+}
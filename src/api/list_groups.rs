@@ -0,0 +1,167 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, ResourceType},
+    de::Deserializer,
+    group::GroupOverview,
+    types::{ByteSize, ByteSizeExt, CompactArray, CompactString, TaggedFields},
+};
+
+use super::{
+    Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::list_groups_request::ListGroupsRequest, read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    let (correlation_id, req_body) = read_request(api_version, &mut de)?;
+    match req_body {
+        ListGroupsRequest::V4(req_body) => {
+            let groups = list_groups(
+                broker,
+                conn,
+                &req_body.states_filter,
+                &CompactArray::new(None),
+            )
+            .into_iter()
+            .map(|group| ListedGroup::V4 {
+                group_id: CompactString::new(group.group_id),
+                protocol_type: CompactString::new(group.protocol_type),
+                group_state: CompactString::new(group.state),
+                tagged_fields: TaggedFields::new(None),
+            })
+            .collect();
+
+            Ok(response(correlation_id, groups))
+        }
+        ListGroupsRequest::V5(req_body) => {
+            let groups = list_groups(
+                broker,
+                conn,
+                &req_body.states_filter,
+                &req_body.types_filter,
+            )
+            .into_iter()
+            .map(|group| ListedGroup::V5 {
+                group_id: CompactString::new(group.group_id),
+                protocol_type: CompactString::new(group.protocol_type),
+                group_state: CompactString::new(group.state),
+                group_type: CompactString::new(group.group_type),
+                tagged_fields: TaggedFields::new(None),
+            })
+            .collect();
+
+            Ok(response(correlation_id, groups))
+        }
+    }
+}
+
+fn response(correlation_id: i32, groups: CompactArray<ListedGroup>) -> Message {
+    let res_header = ResponseHeader::V1 {
+        correlation_id,
+        tagged_fields: TaggedFields::new(None),
+    };
+    let res_body = ResponseBody::ListGroups(ListGroupsResponseBody {
+        throttle_time_ms: 0,
+        error_code: ErrorCode::NoError,
+        groups,
+        tagged_fields: TaggedFields::new(None),
+    });
+    Message::new(res_header, Some(res_body))
+}
+
+/// Groups matching the filters. Filters are case-insensitive and an empty
+/// filter matches every group.
+/// The groups matching the filters which the client may describe.
+fn list_groups(
+    broker: &Broker,
+    conn: &Connection,
+    states_filter: &CompactArray<CompactString>,
+    types_filter: &CompactArray<CompactString>,
+) -> Vec<GroupOverview> {
+    let matches = |filter: &CompactArray<CompactString>, value: &str| {
+        let filter = filter.as_opt_slice().unwrap_or_default();
+        filter.is_empty()
+            || filter
+                .iter()
+                .any(|item| item.as_str().eq_ignore_ascii_case(value))
+    };
+
+    broker
+        .groups
+        .list_groups()
+        .into_iter()
+        .filter(|group| matches(states_filter, &group.state))
+        .filter(|group| matches(types_filter, &group.group_type))
+        .filter(|group| {
+            conn.authorize(
+                broker,
+                AclOperation::Describe,
+                ResourceType::Group,
+                &group.group_id,
+            )
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct ListGroupsResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    error_code: ErrorCode,
+    groups: CompactArray<ListedGroup>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub(crate) enum ListedGroup {
+    V4 {
+        group_id: CompactString,
+        protocol_type: CompactString,
+        group_state: CompactString,
+        tagged_fields: TaggedFields,
+    },
+    V5 {
+        group_id: CompactString,
+        protocol_type: CompactString,
+        group_state: CompactString,
+        group_type: CompactString,
+        tagged_fields: TaggedFields,
+    },
+}
+
+impl ByteSizeExt for ListedGroup {
+    fn byte_size(&self) -> usize {
+        match self {
+            Self::V4 {
+                group_id,
+                protocol_type,
+                group_state,
+                tagged_fields,
+            } => {
+                group_id.byte_size()
+                    + protocol_type.byte_size()
+                    + group_state.byte_size()
+                    + tagged_fields.byte_size()
+            }
+            Self::V5 {
+                group_id,
+                protocol_type,
+                group_state,
+                group_type,
+                tagged_fields,
+            } => {
+                group_id.byte_size()
+                    + protocol_type.byte_size()
+                    + group_state.byte_size()
+                    + group_type.byte_size()
+                    + tagged_fields.byte_size()
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, ResourceType},
+    de::Deserializer,
+    txn::{TransactionMetadata, TransactionState},
+    types::{ByteSize, CompactArray, CompactString, TaggedFields},
+    util,
+};
+
+use super::{
+    Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::list_transactions_request::ListTransactionsRequest, read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    let (correlation_id, req_body) = read_request(api_version, &mut de)?;
+    match req_body {
+        ListTransactionsRequest::V0(req_body) => {
+            let filters = Filters {
+                states: req_body.state_filters,
+                producer_ids: req_body.producer_id_filters,
+                duration_ms: -1,
+            };
+            Ok(response(correlation_id, broker, conn, filters))
+        }
+        ListTransactionsRequest::V1(req_body) => {
+            let filters = Filters {
+                states: req_body.state_filters,
+                producer_ids: req_body.producer_id_filters,
+                duration_ms: req_body.duration_filter,
+            };
+            Ok(response(correlation_id, broker, conn, filters))
+        }
+    }
+}
+
+/// Empty filters match every transaction. A negative duration disables the
+/// duration filter.
+struct Filters {
+    states: CompactArray<CompactString>,
+    producer_ids: CompactArray<i64>,
+    duration_ms: i64,
+}
+
+impl Filters {
+    fn matches(&self, txn: &TransactionMetadata, now: i64) -> bool {
+        let states = self.states.as_opt_slice().unwrap_or_default();
+        let producer_ids = self.producer_ids.as_opt_slice().unwrap_or_default();
+
+        (states.is_empty() || states.iter().any(|s| s.as_str() == txn.state.to_string()))
+            && (producer_ids.is_empty() || producer_ids.contains(&txn.producer_id))
+            && (self.duration_ms < 0
+                || (txn.start_timestamp_ms >= 0 && now - txn.start_timestamp_ms > self.duration_ms))
+    }
+
+    /// State filters which do not name a transaction state.
+    fn unknown_states(&self) -> Vec<CompactString> {
+        self.states
+            .as_opt_slice()
+            .unwrap_or_default()
+            .iter()
+            .filter(|s| {
+                !TransactionState::ALL
+                    .iter()
+                    .any(|state| s.as_str() == state.to_string())
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+fn response(correlation_id: i32, broker: &Broker, conn: &Connection, filters: Filters) -> Message {
+    let res_header = ResponseHeader::V1 {
+        correlation_id,
+        tagged_fields: TaggedFields::new(None),
+    };
+
+    let now = util::now_ms();
+    let transaction_states = broker
+        .txns
+        .list_transactions()
+        .into_iter()
+        .filter(|txn| filters.matches(txn, now))
+        // Transactions the client may not describe are left out.
+        .filter(|txn| {
+            conn.authorize(
+                broker,
+                AclOperation::Describe,
+                ResourceType::TransactionalId,
+                &txn.transactional_id,
+            )
+        })
+        .map(|txn| ListedTransaction {
+            transactional_id: CompactString::new(txn.transactional_id),
+            producer_id: txn.producer_id,
+            transaction_state: CompactString::new(txn.state.to_string()),
+            tagged_fields: TaggedFields::new(None),
+        })
+        .collect();
+
+    let res_body = ResponseBody::ListTransactions(ListTransactionsResponseBody {
+        throttle_time_ms: 0,
+        error_code: ErrorCode::NoError,
+        unknown_state_filters: CompactArray::new(Some(filters.unknown_states())),
+        transaction_states,
+        tagged_fields: TaggedFields::new(None),
+    });
+    Message::new(res_header, Some(res_body))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct ListTransactionsResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    error_code: ErrorCode,
+    unknown_state_filters: CompactArray<CompactString>,
+    transaction_states: CompactArray<ListedTransaction>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct ListedTransaction {
+    transactional_id: CompactString,
+    producer_id: i64,
+    transaction_state: CompactString,
+    tagged_fields: TaggedFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{API_KEY_LIST_TRANSACTIONS, acl_client, request};
+
+    fn list(broker: &Broker, conn: &Connection) -> Vec<String> {
+        let body = [
+            &[0x01, 0x01][..], // state_filters, producer_id_filters
+            &[0xff; 8],        // duration_filter
+            &[0x00],           // tagged_fields
+        ]
+        .concat();
+        let request = request(API_KEY_LIST_TRANSACTIONS, 1, &body);
+        let message = run(1, Deserializer::new(&request[..]), broker, conn).unwrap();
+        let Some(ResponseBody::ListTransactions(body)) = message.into_body() else {
+            panic!("expected a ListTransactions response");
+        };
+        body.transaction_states
+            .as_opt_slice()
+            .unwrap()
+            .iter()
+            .map(|txn| txn.transactional_id.as_str().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_list_transactions_filtered() {
+        let (broker, conn) = acl_client(
+            "list-transactions",
+            &[(ResourceType::TransactionalId, "tx", AclOperation::Describe)],
+        );
+        for transactional_id in ["tx", "other"] {
+            broker
+                .txns
+                .init_producer_id(Some(transactional_id), 60_000, None, &broker.groups)
+                .unwrap();
+        }
+
+        // Only the transactions the client may describe are listed.
+        assert_eq!(list(&broker, &conn), ["tx"]);
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+}
@@ -0,0 +1,269 @@
+//! Request and response types generated by `build.rs` from the Kafka
+//! message specs in `messages/`. A module per message holds a struct per
+//! range of versions with the same encoding, and an enum of them, such as
+//! `api_versions_response::ApiVersionsResponse::V3`.
+
+include!(concat!(env!("OUT_DIR"), "/messages.rs"));
+
+/// A request of any version, as read by the handlers.
+pub(crate) trait Request: Sized {
+    /// Whether `version` uses the flexible encoding, and so the flexible
+    /// request header.
+    fn is_flexible(version: i16) -> bool;
+
+    /// Reads the request body of `version`.
+    fn read<R: std::io::Read>(
+        version: i16,
+        deserializer: &mut crate::de::Deserializer<R>,
+    ) -> crate::Result<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::api_versions_response::*;
+    use super::{
+        describe_groups_response, describe_topic_partitions_request, fetch_request, fetch_response,
+        init_producer_id_response, sasl_authenticate_request, sasl_authenticate_response,
+        sasl_handshake_request, txn_offset_commit_request,
+    };
+    use crate::{
+        de::Deserializer,
+        ser,
+        types::{
+            Array, ByteSizeExt, Bytes, CompactArray, CompactNullableString, CompactString,
+            NullableString, NullableStruct, TaggedField, TaggedFields,
+        },
+    };
+    use serde::Deserialize;
+
+    #[test]
+    fn test_api_versions_response() {
+        let body = ApiVersionsResponse::V0(ApiVersionsResponseV0 {
+            error_code: 0,
+            api_keys: Array::new(Some(vec![ApiVersionV0 {
+                api_key: 18,
+                min_version: 0,
+                max_version: 4,
+            }])),
+        });
+        let buf = ser::to_bytes(&body).unwrap();
+        assert_eq!(buf, [0, 0, 0, 0, 0, 1, 0, 18, 0, 0, 0, 4]);
+        assert_eq!(buf.len(), body.byte_size());
+
+        let body = ApiVersionsResponseV3 {
+            api_keys: CompactArray::new(Some(vec![ApiVersionV3 {
+                api_key: 18,
+                min_version: 0,
+                max_version: 4,
+                ..Default::default()
+            }])),
+            finalized_features_epoch: Some(5),
+            zk_migration_ready: Some(true),
+            ..Default::default()
+        };
+        let buf = ser::to_bytes(&body).unwrap();
+        assert_eq!(
+            buf,
+            [
+                0x00, 0x00, // error_code
+                0x02, 0x00, 0x12, 0x00, 0x00, 0x00, 0x04, 0x00, // api_keys
+                0x00, 0x00, 0x00, 0x00, // throttle_time_ms
+                0x02, // tagged fields
+                0x01, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, // epoch
+                0x03, 0x01, 0x01, // zk_migration_ready
+            ]
+        );
+        assert_eq!(buf.len(), body.byte_size());
+
+        let mut de = Deserializer::new(&buf[..]);
+        let v = ApiVersionsResponse::read(4, &mut de).unwrap();
+        assert_eq!(v, ApiVersionsResponse::V3(body));
+        let v: ApiVersionsResponseV3 =
+            Deserialize::deserialize(&mut Deserializer::new(&buf[..])).unwrap();
+        assert_eq!(v.finalized_features, None);
+        assert!(ApiVersionsResponse::read(5, &mut de).is_err());
+    }
+
+    #[test]
+    fn test_spec_defaults() {
+        use fetch_request::FetchRequestV12;
+        use init_producer_id_response::InitProducerIdResponseV2;
+        use txn_offset_commit_request::TxnOffsetCommitRequestV3;
+
+        let response = InitProducerIdResponseV2::default();
+        assert_eq!(response.producer_id, -1);
+        assert_eq!(response.producer_epoch, 0);
+
+        assert_eq!(FetchRequestV12::default().max_bytes, i32::MAX);
+        assert_eq!(
+            describe_groups_response::DescribedGroupV5::default().authorized_operations,
+            i32::MIN
+        );
+        assert_eq!(
+            describe_topic_partitions_request::DescribeTopicPartitionsRequestV0::default()
+                .response_partition_limit,
+            2000
+        );
+
+        let request = TxnOffsetCommitRequestV3::default();
+        assert_eq!(request.generation_id, -1);
+        assert_eq!(request.member_id, CompactString::new(String::new()));
+        assert_eq!(request.group_instance_id, CompactNullableString::new(None));
+    }
+
+    #[test]
+    fn test_nullable_fields() {
+        use describe_topic_partitions_request::*;
+        use sasl_authenticate_response::SaslAuthenticateResponseV0;
+
+        let response = SaslAuthenticateResponseV0 {
+            error_code: 1,
+            error_message: NullableString::new(None),
+            auth_bytes: Bytes::new(vec![2]),
+        };
+        let buf = ser::to_bytes(&response).unwrap();
+        assert_eq!(buf, [0, 1, 0xff, 0xff, 0, 0, 0, 1, 2]);
+        assert_eq!(buf.len(), response.byte_size());
+
+        let body = DescribeTopicPartitionsRequestV0::default();
+        let buf = ser::to_bytes(&body).unwrap();
+        assert_eq!(buf, [0x01, 0x00, 0x00, 0x07, 0xd0, 0xff, 0x00]);
+        assert_eq!(buf.len(), body.byte_size());
+
+        let body = DescribeTopicPartitionsRequestV0 {
+            cursor: NullableStruct::new(Some(CursorV0 {
+                topic_name: CompactString::new("t".to_string()),
+                partition_index: 1,
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        let buf = ser::to_bytes(&body).unwrap();
+        assert_eq!(
+            buf,
+            [
+                0x01, // topics
+                0x00, 0x00, 0x07, 0xd0, // response_partition_limit
+                0x01, 0x02, b't', 0x00, 0x00, 0x00, 0x01, 0x00, // cursor
+                0x00, // tagged fields
+            ]
+        );
+        assert_eq!(buf.len(), body.byte_size());
+
+        let mut de = Deserializer::new(&buf[..]);
+        let v = DescribeTopicPartitionsRequest::read(0, &mut de).unwrap();
+        assert_eq!(v, DescribeTopicPartitionsRequest::V0(body));
+    }
+
+    #[test]
+    fn test_tagged_struct_fields() {
+        use fetch_response::*;
+
+        let partition = PartitionDataV12 {
+            current_leader: Some(LeaderIdAndEpochV12 {
+                leader_id: 2,
+                leader_epoch: 5,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let buf = ser::to_bytes(&partition).unwrap();
+        let untagged = [
+            &[0x00; 14][..], // partition_index, error_code, high_watermark
+            &[0xff; 16],     // last_stable_offset, log_start_offset
+            &[0x01],         // aborted_transactions
+            &[0xff; 4],      // preferred_read_replica
+            &[0x01],         // records
+        ]
+        .concat();
+        assert_eq!(buf[..untagged.len()], untagged);
+        assert_eq!(
+            buf[untagged.len()..],
+            [
+                0x01, // tagged fields
+                0x01, 0x09, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x05,
+                0x00, // current_leader
+            ]
+        );
+        assert_eq!(buf.len(), partition.byte_size());
+
+        let body = FetchResponseV12 {
+            responses: CompactArray::new(Some(vec![FetchableTopicResponseV12 {
+                topic: CompactString::new("t".to_string()),
+                partitions: CompactArray::new(Some(vec![partition])),
+                ..Default::default()
+            }])),
+            ..Default::default()
+        };
+        let buf = ser::to_bytes(&body).unwrap();
+        assert_eq!(buf.len(), body.byte_size());
+        let mut de = Deserializer::new(&buf[..]);
+        let v = FetchResponse::read(12, &mut de).unwrap();
+        assert_eq!(v, FetchResponse::V12(body));
+
+        // Tags this version does not know are kept as they are.
+        let buf = [&untagged[..], &[0x01, 0x07, 0x01, 0xaa]].concat();
+        let v: PartitionDataV12 =
+            Deserialize::deserialize(&mut Deserializer::new(&buf[..])).unwrap();
+        assert_eq!(v.current_leader, None);
+        assert_eq!(
+            v.tagged_fields,
+            TaggedFields::new(Some(vec![TaggedField::new(7, vec![0xaa])]))
+        );
+        assert_eq!(ser::to_bytes(&v).unwrap(), buf);
+    }
+
+    #[test]
+    fn test_flexible_versions() {
+        use txn_offset_commit_request::*;
+
+        assert!(!sasl_handshake_request::SaslHandshakeRequest::is_flexible(
+            0
+        ));
+        assert!(!sasl_handshake_request::SaslHandshakeRequest::is_flexible(
+            1
+        ));
+        assert!(!sasl_authenticate_request::SaslAuthenticateRequest::is_flexible(1));
+        assert!(sasl_authenticate_request::SaslAuthenticateRequest::is_flexible(2));
+        assert!(!TxnOffsetCommitRequest::is_flexible(2));
+        assert!(TxnOffsetCommitRequest::is_flexible(3));
+
+        // Versions 0 and 1 share an encoding, and so a struct.
+        use sasl_authenticate_request::{SaslAuthenticateRequest, SaslAuthenticateRequestV0};
+        let body = SaslAuthenticateRequestV0 {
+            auth_bytes: Bytes::new(vec![1, 2]),
+        };
+        let buf = ser::to_bytes(&body).unwrap();
+        assert_eq!(buf, [0x00, 0x00, 0x00, 0x02, 0x01, 0x02]);
+        let mut de = Deserializer::new(&buf[..]);
+        let v = SaslAuthenticateRequest::read(1, &mut de).unwrap();
+        assert_eq!(v, SaslAuthenticateRequest::V0(body));
+
+        let body = TxnOffsetCommitRequestV3 {
+            transactional_id: CompactString::new("tx".to_string()),
+            group_id: CompactString::new("g".to_string()),
+            producer_id: 7,
+            producer_epoch: 1,
+            ..Default::default()
+        };
+        let buf = ser::to_bytes(&body).unwrap();
+        assert_eq!(
+            buf,
+            [
+                0x03, b't', b'x', // transactional_id
+                0x02, b'g', // group_id
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x07, // producer_id
+                0x00, 0x01, // producer_epoch
+                0xff, 0xff, 0xff, 0xff, // generation_id
+                0x01, // member_id
+                0x00, // group_instance_id
+                0x01, // topics
+                0x00, // tagged fields
+            ]
+        );
+        assert_eq!(buf.len(), body.byte_size());
+        let mut de = Deserializer::new(&buf[..]);
+        let v = TxnOffsetCommitRequest::read(3, &mut de).unwrap();
+        assert_eq!(v, TxnOffsetCommitRequest::V3(body));
+    }
+}
@@ -1,27 +1,289 @@
 use crate::{
-    KafkaError, Result,
+    Broker, KafkaError, Result,
+    acl::{AclOperation, CLUSTER_NAME, ResourceType},
+    config::Listener,
     de::Deserializer,
-    types::{RecordBatch, RecordVariant},
+    dynamic_config::{self, ConfigError},
+    log::TopicPartition,
+    quota::QuotaType,
+    sasl::Authenticator,
+    types::{ByteSizeExt, RecordVariant, TaggedFields, Value},
+    util,
 };
-use std::fs::File;
+use serde::Deserialize;
 use std::io::Read;
-use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+mod add_offsets_to_txn;
+mod add_partitions_to_txn;
+mod alter_client_quotas;
+mod alter_configs;
+mod alter_user_scram_credentials;
 mod api_versions;
+mod consumer_group_heartbeat;
+mod create_acls;
+mod delete_acls;
+mod delete_groups;
+mod describe_acls;
+mod describe_client_quotas;
+mod describe_cluster;
+mod describe_configs;
+mod describe_groups;
+mod describe_producers;
 mod describe_topic_partitions;
+mod describe_transactions;
+mod describe_user_scram_credentials;
+mod end_txn;
 mod fetch;
+mod incremental_alter_configs;
+mod init_producer_id;
+mod list_groups;
+mod list_transactions;
+mod messages;
+mod offset_commit;
+mod offset_delete;
+mod offset_fetch;
+mod produce;
 mod request;
 mod response;
+mod sasl_authenticate;
+mod sasl_handshake;
+mod txn_offset_commit;
+mod write_txn_markers;
 
 use request::{RequestHeaderV1, RequestHeaderV2};
-use response::{ErrorCode, Message, ResponseBody, ResponseHeader};
+pub(crate) use response::Message;
+use response::{ResponseBody, ResponseHeader};
 
+pub(crate) use response::ErrorCode;
+
+const API_KEY_PRODUCE: i16 = 0;
 const API_KEY_FETCH: i16 = 1;
+const API_KEY_OFFSET_COMMIT: i16 = 8;
+const API_KEY_OFFSET_FETCH: i16 = 9;
+const API_KEY_DESCRIBE_GROUPS: i16 = 15;
+const API_KEY_LIST_GROUPS: i16 = 16;
+const API_KEY_SASL_HANDSHAKE: i16 = 17;
 const API_KEY_API_VERSIONS: i16 = 18;
+const API_KEY_INIT_PRODUCER_ID: i16 = 22;
+const API_KEY_ADD_PARTITIONS_TO_TXN: i16 = 24;
+const API_KEY_ADD_OFFSETS_TO_TXN: i16 = 25;
+const API_KEY_END_TXN: i16 = 26;
+const API_KEY_WRITE_TXN_MARKERS: i16 = 27;
+const API_KEY_TXN_OFFSET_COMMIT: i16 = 28;
+const API_KEY_DESCRIBE_ACLS: i16 = 29;
+const API_KEY_CREATE_ACLS: i16 = 30;
+const API_KEY_DELETE_ACLS: i16 = 31;
+const API_KEY_DESCRIBE_CONFIGS: i16 = 32;
+const API_KEY_ALTER_CONFIGS: i16 = 33;
+const API_KEY_SASL_AUTHENTICATE: i16 = 36;
+const API_KEY_DELETE_GROUPS: i16 = 42;
+const API_KEY_INCREMENTAL_ALTER_CONFIGS: i16 = 44;
+const API_KEY_OFFSET_DELETE: i16 = 47;
+const API_KEY_DESCRIBE_CLIENT_QUOTAS: i16 = 48;
+const API_KEY_ALTER_CLIENT_QUOTAS: i16 = 49;
+const API_KEY_DESCRIBE_USER_SCRAM_CREDENTIALS: i16 = 50;
+const API_KEY_ALTER_USER_SCRAM_CREDENTIALS: i16 = 51;
+const API_KEY_DESCRIBE_CLUSTER: i16 = 60;
+const API_KEY_DESCRIBE_PRODUCERS: i16 = 61;
+const API_KEY_DESCRIBE_TRANSACTIONS: i16 = 65;
+const API_KEY_LIST_TRANSACTIONS: i16 = 66;
+const API_KEY_CONSUMER_GROUP_HEARTBEAT: i16 = 68;
 const API_KEY_DESCRIBE_TOPIC_PARTITIONS: i16 = 75;
 
-pub(crate) fn handle(bytes: Vec<u8>) -> Result<Message> {
-    if bytes.len() < 4 {
+/// A client connection: the listener it was accepted on, the address of
+/// its client and the authentication of the client.
+#[derive(Debug)]
+pub(crate) struct Connection {
+    pub(crate) listener: Listener,
+    pub(crate) host: String,
+    pub(crate) auth: Authenticator,
+    /// Set when the client exceeded a quota: no further request is read
+    /// from the connection until then. Shared with the delayed requests of
+    /// the connection, which record their quotas once complete.
+    pub(crate) throttled_until: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Connection {
+    /// Whether the client may perform `operation` on the named resource.
+    /// Everything is allowed when no authorizer is configured.
+    pub(crate) fn authorize(
+        &self,
+        broker: &Broker,
+        operation: AclOperation,
+        resource_type: ResourceType,
+        resource_name: &str,
+    ) -> bool {
+        broker.authorizer.as_ref().is_none_or(|authorizer| {
+            authorizer.authorize(
+                self.auth.principal(),
+                &self.host,
+                operation,
+                resource_type,
+                resource_name,
+            )
+        })
+    }
+
+    /// Whether the client may perform `operation` on some resource of the
+    /// type.
+    pub(crate) fn authorize_any(
+        &self,
+        broker: &Broker,
+        operation: AclOperation,
+        resource_type: ResourceType,
+    ) -> bool {
+        broker.authorizer.as_ref().is_none_or(|authorizer| {
+            authorizer.authorize_any(self.auth.principal(), &self.host, operation, resource_type)
+        })
+    }
+}
+
+/// Who a request is recorded against in the quotas, and where the
+/// connection is muted once the client exceeds one.
+#[derive(Debug)]
+struct QuotaClient {
+    user: String,
+    client_id: String,
+    throttled_until: Arc<Mutex<Option<Instant>>>,
+}
+
+impl QuotaClient {
+    fn new(conn: &Connection, request: &[u8]) -> Self {
+        let client_id = RequestHeaderV1::deserialize(&mut Deserializer::new(request))
+            .ok()
+            .and_then(|header| header.client_id.as_ref().cloned())
+            .unwrap_or_default();
+        let principal = conn.auth.principal();
+        Self {
+            user: principal
+                .strip_prefix("User:")
+                .unwrap_or(principal)
+                .to_string(),
+            client_id,
+            throttled_until: Arc::clone(&conn.throttled_until),
+        }
+    }
+
+    /// Records the request against the quotas of the client: the request
+    /// bytes of Produce, the response bytes of Fetch, and the time spent
+    /// handling every request. Returns the longest throttle time of them,
+    /// for which the connection is muted.
+    fn record(
+        &self,
+        broker: &Broker,
+        api_key: i16,
+        request_size: usize,
+        response: Option<&Message>,
+        elapsed: Duration,
+    ) -> u64 {
+        let now_ms = util::now_ms();
+        let record = |quota_type, value| {
+            broker
+                .quotas
+                .record(quota_type, &self.user, &self.client_id, value, now_ms)
+        };
+
+        let mut throttle_time_ms =
+            record(QuotaType::RequestPercentage, elapsed.as_secs_f64() * 100.0);
+        match api_key {
+            API_KEY_PRODUCE => {
+                throttle_time_ms =
+                    throttle_time_ms.max(record(QuotaType::ProducerByteRate, request_size as f64));
+            }
+            API_KEY_FETCH => {
+                let size = response.map_or(0, |message| message.byte_size());
+                throttle_time_ms =
+                    throttle_time_ms.max(record(QuotaType::ConsumerByteRate, size as f64));
+            }
+            _ => {}
+        }
+
+        if throttle_time_ms > 0 {
+            let until = Instant::now() + Duration::from_millis(throttle_time_ms);
+            *self.throttled_until.lock().unwrap() = Some(until);
+        }
+        throttle_time_ms
+    }
+
+    /// Records the request and reports the throttle time in its response.
+    fn record_response(
+        &self,
+        broker: &Broker,
+        api_key: i16,
+        request_size: usize,
+        mut response: Option<Message>,
+        elapsed: Duration,
+    ) -> Option<Message> {
+        let throttle_time_ms =
+            self.record(broker, api_key, request_size, response.as_ref(), elapsed);
+        if let Some(message) = response.as_mut() {
+            message.set_throttle_time_ms(throttle_time_ms.min(i32::MAX as u64) as i32);
+        }
+        response
+    }
+}
+
+/// How a request was handled.
+pub(crate) enum Handled {
+    /// The response, or `None` for requests which get no response.
+    Response(Option<Message>),
+    /// A request whose response waits for an event, such as a fetch waiting
+    /// for data. It is completed with [`DelayedRequest::try_complete`].
+    Delayed(DelayedRequest),
+}
+
+/// A request parked until it can complete. Its quotas are recorded once it
+/// does, counting only the time spent handling it, not waiting.
+pub(crate) struct DelayedRequest {
+    fetch: fetch::DelayedFetch,
+    client: QuotaClient,
+    request_size: usize,
+    elapsed: Duration,
+}
+
+impl DelayedRequest {
+    /// Waits until the request may complete: until an event it waits for
+    /// happens or its deadline passes.
+    pub(crate) async fn wait(&mut self, broker: &Broker) {
+        self.fetch.wait(broker).await
+    }
+
+    /// Returns the response if the request can complete now, or `None` if
+    /// it must wait again. Reads the logs, so runs on a request thread.
+    pub(crate) fn try_complete(&mut self, broker: &Broker) -> Result<Option<Message>> {
+        let started = Instant::now();
+        let response = self.fetch.try_complete(broker)?;
+        self.elapsed += started.elapsed();
+        Ok(response.and_then(|message| {
+            self.client.record_response(
+                broker,
+                API_KEY_FETCH,
+                self.request_size,
+                Some(message),
+                self.elapsed,
+            )
+        }))
+    }
+}
+
+/// Whether the API may be used before the client is authenticated. Such
+/// requests are not throttled either.
+fn precedes_authentication(api_key: i16) -> bool {
+    matches!(
+        api_key,
+        API_KEY_API_VERSIONS | API_KEY_SASL_HANDSHAKE | API_KEY_SASL_AUTHENTICATE
+    )
+}
+
+/// Handles one request frame. Returns `Handled::Response(None)` for
+/// requests which get no response. Until the client is authenticated, only
+/// the requests needed to authenticate are served; any other closes the
+/// connection.
+pub(crate) fn handle(bytes: Vec<u8>, broker: &Broker, conn: &mut Connection) -> Result<Handled> {
+    // Without a request header the response cannot even be correlated.
+    if bytes.len() < 8 {
         return Err(KafkaError::DeserializationError(
             "Request too short to contain correlation ID".to_string(),
         ));
@@ -29,15 +291,209 @@ pub(crate) fn handle(bytes: Vec<u8>) -> Result<Message> {
 
     let api_key: i16 = i16::from_be_bytes([bytes[0], bytes[1]]);
     let api_version: i16 = i16::from_be_bytes([bytes[2], bytes[3]]);
+    let correlation_id = i32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+
+    if conn.auth.has_failed() {
+        return Err(KafkaError::AuthenticationError(
+            "request after failed authentication".to_string(),
+        ));
+    }
+    if !conn.auth.is_authenticated() && !precedes_authentication(api_key) {
+        return Err(KafkaError::AuthenticationError(format!(
+            "unexpected request with API key {api_key} before authentication"
+        )));
+    }
+
+    let started = Instant::now();
+    let de = Deserializer::new(&bytes[..]);
+    let result = match api_key {
+        API_KEY_PRODUCE => produce::run(api_version, de, broker, conn).map(Handled::Response),
+        API_KEY_FETCH => fetch::run(api_version, de, broker, conn).map(|fetched| match fetched {
+            fetch::Fetched::Complete(message) => Handled::Response(Some(message)),
+            fetch::Fetched::Delayed(fetch) => Handled::Delayed(DelayedRequest {
+                fetch,
+                client: QuotaClient::new(conn, &bytes),
+                request_size: bytes.len(),
+                elapsed: Duration::ZERO,
+            }),
+        }),
+        API_KEY_SASL_HANDSHAKE => sasl_handshake::run(api_version, de, conn)
+            .map(|message| Handled::Response(Some(message))),
+        API_KEY_SASL_AUTHENTICATE => sasl_authenticate::run(api_version, de, broker, conn)
+            .map(|message| Handled::Response(Some(message))),
+        _ => route_request(api_key, api_version, de, broker, conn)
+            .map(|message| Handled::Response(Some(message))),
+    };
+    let handled = match result {
+        Ok(handled) => handled,
+        Err(e) => {
+            let response = error_code(&e)
+                .and_then(|code| error_response(api_key, api_version, correlation_id, code));
+            let Some(response) = response else {
+                return Err(e);
+            };
+            eprintln!("Error handling request with API key {api_key} v{api_version}: {e}");
+            Handled::Response(Some(response))
+        }
+    };
+
+    Ok(match handled {
+        Handled::Response(message) if !precedes_authentication(api_key) => {
+            let client = QuotaClient::new(conn, &bytes);
+            Handled::Response(client.record_response(
+                broker,
+                api_key,
+                bytes.len(),
+                message,
+                started.elapsed(),
+            ))
+        }
+        Handled::Delayed(mut delayed) => {
+            delayed.elapsed = started.elapsed();
+            Handled::Delayed(delayed)
+        }
+        handled => handled,
+    })
+}
+
+/// Reads the next part of a request, such as its header or body.
+fn parse<T: for<'de> Deserialize<'de>, R: Read>(de: &mut Deserializer<R>) -> Result<T> {
+    T::deserialize(de).map_err(malformed)
+}
+
+/// Reads a request of `api_version`: its header, which is flexible along
+/// with the body, then its body. Returns the correlation ID and the body.
+fn read_request<T: messages::Request, R: Read>(
+    api_version: i16,
+    de: &mut Deserializer<R>,
+) -> Result<(i32, T)> {
+    let correlation_id = if T::is_flexible(api_version) {
+        parse::<RequestHeaderV2, _>(de)?.correlation_id
+    } else {
+        parse::<RequestHeaderV1, _>(de)?.correlation_id
+    };
+    let body = T::read(api_version, de).map_err(malformed)?;
+    Ok((correlation_id, body))
+}
+
+/// Marks an error in reading a request as such, to tell it apart from the
+/// same errors in reading the logs while handling it.
+fn malformed(e: KafkaError) -> KafkaError {
+    match e {
+        KafkaError::DeserializationError(_) => KafkaError::MalformedRequest(Box::new(e)),
+        KafkaError::IoError(ref io) if io.kind() == std::io::ErrorKind::UnexpectedEof => {
+            KafkaError::MalformedRequest(Box::new(e))
+        }
+        e => e,
+    }
+}
+
+/// The error code reported to the client for a request failing with `e`,
+/// or `None` when the connection should be closed instead.
+fn error_code(e: &KafkaError) -> Option<ErrorCode> {
+    match e {
+        KafkaError::UnsupportedVersion { .. } => Some(ErrorCode::UnsupportedVersion),
+        KafkaError::MalformedRequest(e) => match **e {
+            // The request ends before the frame does.
+            KafkaError::IoError(_) => Some(ErrorCode::CorruptMessage),
+            _ => Some(ErrorCode::InvalidRequest),
+        },
+        _ => None,
+    }
+}
+
+/// Whether the responses of `api_version` of the API have a flexible
+/// header. ApiVersions responses never do, so that clients can read them
+/// before they know which versions are supported.
+fn flexible_header(api_key: i16, api_version: i16) -> bool {
+    api_key != API_KEY_API_VERSIONS
+        && messages::Response::is_flexible(api_key, api_version).unwrap_or(false)
+}
+
+/// A response reporting `error_code` for a request of a known API which
+/// could not be handled, in the requested version. A version this broker
+/// does not implement gets the body of the nearest one it does, as long as
+/// that has the same header; otherwise there is no response, and the
+/// connection is closed. Bodies only say what needs no request: the
+/// top-level error code, if there is one.
+fn error_response(
+    api_key: i16,
+    api_version: i16,
+    correlation_id: i32,
+    error_code: ErrorCode,
+) -> Option<Message> {
+    let flexible = flexible_header(api_key, api_version);
+    let header = if flexible {
+        ResponseHeader::V1 {
+            correlation_id,
+            tagged_fields: TaggedFields::new(None),
+        }
+    } else {
+        ResponseHeader::V0 { correlation_id }
+    };
+    if api_key == API_KEY_API_VERSIONS {
+        let body = api_versions::error_body(api_version, error_code);
+        return Some(Message::new(header, Some(body)));
+    }
 
-    route_request(api_key, api_version, Deserializer::new(&bytes[..]))
+    let (min_version, max_version) = api_versions::supported_versions(api_key)?;
+    let version = api_version.clamp(min_version, max_version);
+    if flexible_header(api_key, version) != flexible {
+        return None;
+    }
+    let body = messages::Response::error(api_key, version, error_code as i16)?;
+    Some(Message::new(header, Some(ResponseBody::Error(body))))
 }
 
-fn route_request<R: Read>(api_key: i16, api_version: i16, de: Deserializer<R>) -> Result<Message> {
+fn route_request<R: Read>(
+    api_key: i16,
+    api_version: i16,
+    de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
     match api_key {
-        API_KEY_FETCH => fetch::run(api_version, de),
+        API_KEY_OFFSET_COMMIT => offset_commit::run(api_version, de, broker, conn),
+        API_KEY_OFFSET_FETCH => offset_fetch::run(api_version, de, broker, conn),
+        API_KEY_DESCRIBE_GROUPS => describe_groups::run(api_version, de, broker, conn),
+        API_KEY_LIST_GROUPS => list_groups::run(api_version, de, broker, conn),
         API_KEY_API_VERSIONS => api_versions::run(api_version, de),
-        API_KEY_DESCRIBE_TOPIC_PARTITIONS => describe_topic_partitions::run(api_version, de),
+        API_KEY_INIT_PRODUCER_ID => init_producer_id::run(api_version, de, broker, conn),
+        API_KEY_ADD_PARTITIONS_TO_TXN => add_partitions_to_txn::run(api_version, de, broker, conn),
+        API_KEY_ADD_OFFSETS_TO_TXN => add_offsets_to_txn::run(api_version, de, broker, conn),
+        API_KEY_END_TXN => end_txn::run(api_version, de, broker, conn),
+        API_KEY_WRITE_TXN_MARKERS => write_txn_markers::run(api_version, de, broker, conn),
+        API_KEY_TXN_OFFSET_COMMIT => txn_offset_commit::run(api_version, de, broker, conn),
+        API_KEY_DESCRIBE_ACLS => describe_acls::run(api_version, de, broker, conn),
+        API_KEY_CREATE_ACLS => create_acls::run(api_version, de, broker, conn),
+        API_KEY_DELETE_ACLS => delete_acls::run(api_version, de, broker, conn),
+        API_KEY_DESCRIBE_CONFIGS => describe_configs::run(api_version, de, broker, conn),
+        API_KEY_ALTER_CONFIGS => alter_configs::run(api_version, de, broker, conn),
+        API_KEY_DELETE_GROUPS => delete_groups::run(api_version, de, broker, conn),
+        API_KEY_INCREMENTAL_ALTER_CONFIGS => {
+            incremental_alter_configs::run(api_version, de, broker, conn)
+        }
+        API_KEY_OFFSET_DELETE => offset_delete::run(api_version, de, broker, conn),
+        API_KEY_DESCRIBE_CLIENT_QUOTAS => {
+            describe_client_quotas::run(api_version, de, broker, conn)
+        }
+        API_KEY_ALTER_CLIENT_QUOTAS => alter_client_quotas::run(api_version, de, broker, conn),
+        API_KEY_DESCRIBE_USER_SCRAM_CREDENTIALS => {
+            describe_user_scram_credentials::run(api_version, de, broker, conn)
+        }
+        API_KEY_ALTER_USER_SCRAM_CREDENTIALS => {
+            alter_user_scram_credentials::run(api_version, de, broker, conn)
+        }
+        API_KEY_DESCRIBE_CLUSTER => describe_cluster::run(api_version, de, broker, conn),
+        API_KEY_DESCRIBE_PRODUCERS => describe_producers::run(api_version, de, broker, conn),
+        API_KEY_DESCRIBE_TRANSACTIONS => describe_transactions::run(api_version, de, broker, conn),
+        API_KEY_LIST_TRANSACTIONS => list_transactions::run(api_version, de, broker, conn),
+        API_KEY_CONSUMER_GROUP_HEARTBEAT => {
+            consumer_group_heartbeat::run(api_version, de, broker, conn)
+        }
+        API_KEY_DESCRIBE_TOPIC_PARTITIONS => {
+            describe_topic_partitions::run(api_version, de, broker, conn)
+        }
         _ => Err(KafkaError::UnsupportedVersion {
             api_key,
             api_version,
@@ -45,19 +501,564 @@ fn route_request<R: Read>(api_key: i16, api_version: i16, de: Deserializer<R>) -
     }
 }
 
-fn read_meta_records() -> Result<Vec<RecordVariant>> {
-    read_meta("/tmp/kraft-combined-logs/__cluster_metadata-0/00000000000000000000.log")
+fn read_meta_records(broker: &Broker) -> Result<Vec<RecordVariant>> {
+    Ok(read_meta_values(broker)?
+        .into_iter()
+        .map(|value| value.value)
+        .collect())
 }
 
-fn read_meta<P: AsRef<Path>>(path: P) -> Result<Vec<RecordVariant>> {
-    let file = File::open(path)?;
-    let records: Vec<RecordVariant> = RecordBatch::from_reader(file)?
-        .into_iter()
-        .flat_map(|record_batch| {
-            record_batch
-                .into_iter()
-                .map(|record| record.value.into_inner().value)
+/// The records of the metadata log along with their tagged fields.
+fn read_meta_values(broker: &Broker) -> Result<Vec<Value>> {
+    broker.logs.read_metadata()
+}
+
+/// Whether the metadata log has a partition `tp`.
+fn partition_exists(metadata: &[RecordVariant], tp: &TopicPartition) -> bool {
+    let topic_id = metadata.iter().find_map(|record| match record {
+        RecordVariant::Topic(t) if t.name.as_str() == tp.topic => Some(t.topic_id),
+        _ => None,
+    });
+    metadata.iter().any(|record| {
+        matches!(record, RecordVariant::Partition(p)
+            if Some(p.topic_id) == topic_id && p.partition_id == tp.partition)
+    })
+}
+
+/// Checks `operation` on the configs of a resource. The configs of brokers
+/// belong to the cluster.
+fn authorize_configs(
+    broker: &Broker,
+    conn: &Connection,
+    operation: AclOperation,
+    resource_type: dynamic_config::ResourceType,
+    name: &str,
+) -> std::result::Result<(), ConfigError> {
+    let (authorized, error_code) = match resource_type {
+        dynamic_config::ResourceType::Topic => (
+            conn.authorize(broker, operation, ResourceType::Topic, name),
+            ErrorCode::TopicAuthorizationFailed,
+        ),
+        dynamic_config::ResourceType::Broker => (
+            conn.authorize(broker, operation, ResourceType::Cluster, CLUSTER_NAME),
+            ErrorCode::ClusterAuthorizationFailed,
+        ),
+    };
+    if authorized {
+        Ok(())
+    } else {
+        Err((error_code, String::new()))
+    }
+}
+
+/// Whether the metadata log has a topic named `name`.
+fn topic_exists(metadata: &[RecordVariant], name: &str) -> bool {
+    metadata
+        .iter()
+        .any(|record| matches!(record, RecordVariant::Topic(t) if t.name.as_str() == name))
+}
+
+/// A broker with the topic `foo` of one partition, and an anonymous client.
+#[cfg(test)]
+fn client(name: &str, properties: &str) -> (Arc<Broker>, Connection) {
+    use crate::types::{CompactArray, CompactString, Partition, Topic, Uuid};
+
+    let broker = Broker::start_for_test(name, properties);
+    let topic_id = Uuid::from([1; 16]);
+    broker
+        .logs
+        .append_metadata(vec![
+            RecordVariant::Topic(Topic {
+                name: CompactString::new("foo".to_string()),
+                topic_id,
+            }),
+            RecordVariant::Partition(Partition {
+                partition_id: 0,
+                topic_id,
+                replicas: CompactArray::new(Some(vec![1])),
+                isr: CompactArray::new(Some(vec![1])),
+                removing_replicas: CompactArray::new(Some(vec![])),
+                adding_replicas: CompactArray::new(Some(vec![])),
+                leader: 1,
+                leader_epoch: 0,
+                partition_epoch: 0,
+                directories: CompactArray::new(Some(vec![])),
+            }),
+        ])
+        .unwrap();
+
+    let listener = broker.config.listeners[0].clone();
+    let auth = Authenticator::new(listener.endpoint.security_protocol, None);
+    let conn = Connection {
+        listener,
+        host: "127.0.0.1".to_string(),
+        auth,
+        throttled_until: Arc::new(Mutex::new(None)),
+    };
+    (broker, conn)
+}
+
+/// Like [`client`], with ACLs enabled and the client allowed only the
+/// operations `allowed` on the named resources.
+#[cfg(test)]
+fn acl_client(
+    name: &str,
+    allowed: &[(ResourceType, &str, AclOperation)],
+) -> (Arc<Broker>, Connection) {
+    use crate::acl::{AclBinding, PatternType, PermissionType};
+
+    let (broker, conn) = client(
+        name,
+        "authorizer.class.name=org.apache.kafka.metadata.authorizer.StandardAuthorizer",
+    );
+    let bindings = allowed
+        .iter()
+        .map(|&(resource_type, resource_name, operation)| AclBinding {
+            resource_type,
+            resource_name: resource_name.to_string(),
+            pattern_type: PatternType::Literal,
+            principal: conn.auth.principal().to_string(),
+            host: "*".to_string(),
+            operation,
+            permission_type: PermissionType::Allow,
         })
         .collect();
-    Ok(records)
+    let authorizer = broker.authorizer.as_ref().unwrap();
+    let created = authorizer.create_acls(bindings);
+    assert!(created.iter().all(|result| result.is_ok()));
+    (broker, conn)
+}
+
+/// A request of `api_key`, with a v2 header and correlation ID 7.
+#[cfg(test)]
+fn request(api_key: i16, api_version: i16, body: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![];
+    bytes.extend(api_key.to_be_bytes());
+    bytes.extend(api_version.to_be_bytes());
+    bytes.extend(7i32.to_be_bytes());
+    bytes.extend([0xff, 0xff]); // client_id
+    bytes.push(0x00); // tagged_fields
+    bytes.extend(body);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_response() {
+        let message =
+            error_response(API_KEY_END_TXN, 9, 42, ErrorCode::UnsupportedVersion).unwrap();
+        let bytes = crate::ser::to_bytes(&message).unwrap();
+        assert_eq!(
+            bytes,
+            [
+                0x00, 0x00, 0x00, 0x0c, // message_size
+                0x00, 0x00, 0x00, 0x2a, // correlation_id
+                0x00, // tagged_fields
+                0x00, 0x00, 0x00, 0x00, // throttle_time_ms
+                0x00, 0x23, // error_code
+                0x00, // tagged_fields
+            ]
+        );
+
+        // The body is encoded in the requested version, after a header of
+        // the same version.
+        let message = error_response(API_KEY_FETCH, 4, 42, ErrorCode::UnsupportedVersion).unwrap();
+        assert!(matches!(message.header(), ResponseHeader::V0 { .. }));
+        assert_eq!(
+            crate::ser::to_bytes(&message).unwrap(),
+            [
+                0x00, 0x00, 0x00, 0x0c, // message_size
+                0x00, 0x00, 0x00, 0x2a, // correlation_id
+                0x00, 0x00, 0x00, 0x00, // throttle_time_ms
+                0x00, 0x00, 0x00, 0x00, // responses
+            ]
+        );
+        let message =
+            error_response(API_KEY_INIT_PRODUCER_ID, 2, 42, ErrorCode::InvalidRequest).unwrap();
+        assert_eq!(
+            crate::ser::to_bytes(&message).unwrap()[9..],
+            [
+                0x00, 0x00, 0x00, 0x00, // throttle_time_ms
+                0x00, 0x2a, // error_code
+                0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // producer_id
+                0x00, 0x00, // producer_epoch
+                0x00, // tagged_fields
+            ]
+        );
+        let message =
+            error_response(API_KEY_OFFSET_COMMIT, 8, 42, ErrorCode::UnsupportedVersion).unwrap();
+        assert!(matches!(message.header(), ResponseHeader::V1 { .. }));
+        let message =
+            error_response(API_KEY_API_VERSIONS, 4, 42, ErrorCode::UnsupportedVersion).unwrap();
+        assert!(matches!(message.header(), ResponseHeader::V0 { .. }));
+
+        // Versions below the first flexible one get a v0 header, and a body
+        // only if this broker implements a version without flexible bodies.
+        let message = error_response(API_KEY_FETCH, 3, 42, ErrorCode::UnsupportedVersion).unwrap();
+        assert!(matches!(message.header(), ResponseHeader::V0 { .. }));
+        for (api_key, api_version) in [
+            (API_KEY_OFFSET_COMMIT, 7),
+            (API_KEY_PRODUCE, 3),
+            (API_KEY_END_TXN, 2),
+        ] {
+            assert!(
+                error_response(api_key, api_version, 42, ErrorCode::UnsupportedVersion).is_none()
+            );
+        }
+
+        // Only errors in reading the request are reported as such.
+        let eof = || std::io::Error::from(std::io::ErrorKind::UnexpectedEof);
+        assert_eq!(
+            error_code(&malformed(KafkaError::DeserializationError(String::new()))),
+            Some(ErrorCode::InvalidRequest)
+        );
+        assert_eq!(
+            error_code(&malformed(KafkaError::IoError(eof()))),
+            Some(ErrorCode::CorruptMessage)
+        );
+        assert_eq!(
+            error_code(&KafkaError::DeserializationError(String::new())),
+            None
+        );
+        assert_eq!(error_code(&KafkaError::IoError(eof())), None);
+        assert_eq!(
+            error_code(&KafkaError::AuthenticationError(String::new())),
+            None
+        );
+        assert!(error_response(1000, 0, 42, ErrorCode::UnsupportedVersion).is_none());
+    }
+
+    /// The error codes of the response to a request, in the order of its
+    /// fields.
+    fn response_error_codes(
+        broker: &Broker,
+        conn: &Connection,
+        api_key: i16,
+        api_version: i16,
+        body: &[u8],
+    ) -> Vec<i16> {
+        let request = request(api_key, api_version, body);
+        let de = Deserializer::new(&request[..]);
+        let message = route_request(api_key, api_version, de, broker, conn).unwrap();
+        let bytes = crate::ser::to_bytes(&message).unwrap();
+        // The size, the correlation ID and the tagged fields of the header.
+        let header_size = if flexible_header(api_key, api_version) {
+            9
+        } else {
+            8
+        };
+        let mut de = Deserializer::new(&bytes[header_size..]);
+        messages::Response::read(api_key, api_version, &mut de)
+            .unwrap()
+            .error_codes()
+    }
+
+    /// A request, the operations its client is allowed, and the error codes
+    /// of the response.
+    struct AuthCase {
+        api_key: i16,
+        api_version: i16,
+        body: Vec<u8>,
+        allowed: &'static [(ResourceType, &'static str, AclOperation)],
+        error_codes: &'static [ErrorCode],
+    }
+
+    #[test]
+    fn test_authorization() {
+        use AclOperation::{Describe, Write};
+        use ErrorCode::{
+            ClusterAuthorizationFailed, GroupAuthorizationFailed, NoError,
+            TopicAuthorizationFailed, TransactionalIdAuthorizationFailed, TransactionalIdNotFound,
+        };
+        use ResourceType::{Topic, TransactionalId};
+
+        let add_offsets = [
+            &[0x03, b't', b'x'][..], // transactional_id
+            &[0x00; 8],              // producer_id
+            &[0x00, 0x00],           // producer_epoch
+            &[0x02, b'g', 0x00],     // group_id, tagged_fields
+        ]
+        .concat();
+        let add_partitions = [
+            &[0x03, b't', b'x'][..], // transactional_id
+            &[0x00; 8],              // producer_id
+            &[0x00, 0x00],           // producer_epoch
+            &[0x02, 0x04],
+            b"foo",                                                  // topics
+            &[0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01], // partitions
+            &[0x00, 0x00],                                           // tagged_fields
+        ]
+        .concat();
+        let alter_configs = |op: &[u8]| {
+            [
+                &[0x03][..], // resources
+                &[0x02, 0x04],
+                b"foo", // topic foo
+                &[0x02, 0x0d],
+                b"retention.ms",
+                op,
+                &[0x02, b'1', 0x00], // configs
+                &[0x00],
+                &[0x04, 0x02, b'1', 0x01, 0x00], // broker 1, no configs
+                &[0x00, 0x00],                   // validate_only, tagged_fields
+            ]
+            .concat()
+        };
+        let alter_scram_credentials = [
+            &[0x02, 0x04][..],
+            b"bob",
+            &[0x02, 0x00], // deletions
+            &[0x02, 0x06],
+            b"alice",
+            &[0x02, 0x00, 0x00, 0x10, 0x00], // mechanism, iterations
+            &[0x02, 0x01, 0x02, 0x02, 0x00], // salt, salted_password
+            &[0x00],                         // tagged_fields
+        ]
+        .concat();
+        let describe_configs = [
+            &[0x03][..], // resources
+            &[0x02, 0x04],
+            b"foo",
+            &[0x00, 0x00],                   // topic foo, all keys
+            &[0x04, 0x02, b'1', 0x00, 0x00], // broker 1, all keys
+            &[0x00, 0x00, 0x00], // include_synonyms, include_documentation, tagged_fields
+        ]
+        .concat();
+        let describe_producers = [
+            &[0x02, 0x04][..],
+            b"foo",                          // topics
+            &[0x02, 0x00, 0x00, 0x00, 0x00], // partition_indexes
+            &[0x00, 0x00],                   // tagged_fields
+        ]
+        .concat();
+        let end_txn = [
+            &[0x03, b't', b'x'][..], // transactional_id
+            &[0x00; 8],              // producer_id
+            &[0x00, 0x00],           // producer_epoch
+            &[0x01, 0x00],           // committed, tagged_fields
+        ]
+        .concat();
+        let init_producer_id = |transactional_id: &[u8]| {
+            [
+                transactional_id,
+                &[0x00, 0x00, 0xea, 0x60], // transaction_timeout_ms
+                &[0xff; 8],                // producer_id
+                &[0xff, 0xff, 0x00],       // producer_epoch, tagged_fields
+            ]
+            .concat()
+        };
+        let txn_offset_commit = [
+            &[0x03, b't', b'x'][..], // transactional_id
+            &[0x02, b'g'],           // group_id
+            &[0x00; 8],              // producer_id
+            &[0x00, 0x00],           // producer_epoch
+            &[0xff; 4],              // generation_id
+            &[0x01, 0x00],           // member_id, group_instance_id
+            &[0x02, 0x04],
+            b"foo",                                            // topics
+            &[0x02, 0x00, 0x00, 0x00, 0x00],                   // partition_index
+            &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2a], // committed_offset
+            &[0xff, 0xff, 0xff, 0xff, 0x00, 0x00], // committed_leader_epoch, committed_metadata
+            &[0x00, 0x00, 0x00],                   // tagged_fields
+        ]
+        .concat();
+        let write_txn_markers = [
+            &[0x02][..],                                       // markers
+            &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xe8], // producer_id
+            &[0x00, 0x00, 0x01],                               // producer_epoch, transaction_result
+            &[0x02, 0x04],
+            b"foo",                                // topics
+            &[0x02, 0x00, 0x00, 0x00, 0x00, 0x00], // partition_indexes, tagged_fields
+            &[0x00, 0x00, 0x00, 0x00, 0x00],       // coordinator_epoch, tagged_fields
+            &[0x00],                               // tagged_fields
+        ]
+        .concat();
+
+        let cases = [
+            AuthCase {
+                api_key: API_KEY_ADD_OFFSETS_TO_TXN,
+                api_version: 4,
+                body: add_offsets.clone(),
+                allowed: &[],
+                error_codes: &[TransactionalIdAuthorizationFailed],
+            },
+            // The group is checked once the transactional ID may be written.
+            AuthCase {
+                api_key: API_KEY_ADD_OFFSETS_TO_TXN,
+                api_version: 4,
+                body: add_offsets,
+                allowed: &[(TransactionalId, "tx", Write)],
+                error_codes: &[GroupAuthorizationFailed],
+            },
+            AuthCase {
+                api_key: API_KEY_ADD_PARTITIONS_TO_TXN,
+                api_version: 3,
+                body: add_partitions.clone(),
+                allowed: &[],
+                error_codes: &[
+                    TransactionalIdAuthorizationFailed,
+                    TransactionalIdAuthorizationFailed,
+                ],
+            },
+            // The topics are checked once the transactional ID may be written.
+            AuthCase {
+                api_key: API_KEY_ADD_PARTITIONS_TO_TXN,
+                api_version: 3,
+                body: add_partitions,
+                allowed: &[(TransactionalId, "tx", Write)],
+                error_codes: &[TopicAuthorizationFailed, TopicAuthorizationFailed],
+            },
+            AuthCase {
+                api_key: API_KEY_ALTER_CONFIGS,
+                api_version: 2,
+                body: alter_configs(&[]),
+                allowed: &[],
+                error_codes: &[TopicAuthorizationFailed, ClusterAuthorizationFailed],
+            },
+            AuthCase {
+                api_key: API_KEY_ALTER_USER_SCRAM_CREDENTIALS,
+                api_version: 0,
+                body: alter_scram_credentials,
+                allowed: &[],
+                error_codes: &[ClusterAuthorizationFailed, ClusterAuthorizationFailed],
+            },
+            AuthCase {
+                api_key: API_KEY_DESCRIBE_CLUSTER,
+                api_version: 0,
+                body: vec![0x00, 0x00], // include_cluster_authorized_operations, tagged_fields
+                allowed: &[],
+                error_codes: &[ClusterAuthorizationFailed],
+            },
+            AuthCase {
+                api_key: API_KEY_DESCRIBE_CLUSTER,
+                api_version: 1,
+                // include_cluster_authorized_operations, endpoint_type,
+                // tagged_fields
+                body: vec![0x00, 0x01, 0x00],
+                allowed: &[],
+                error_codes: &[ClusterAuthorizationFailed],
+            },
+            AuthCase {
+                api_key: API_KEY_DESCRIBE_CONFIGS,
+                api_version: 4,
+                body: describe_configs,
+                allowed: &[],
+                error_codes: &[TopicAuthorizationFailed, ClusterAuthorizationFailed],
+            },
+            AuthCase {
+                api_key: API_KEY_DESCRIBE_PRODUCERS,
+                api_version: 0,
+                body: describe_producers,
+                allowed: &[],
+                error_codes: &[TopicAuthorizationFailed],
+            },
+            AuthCase {
+                api_key: API_KEY_DESCRIBE_TRANSACTIONS,
+                api_version: 0,
+                body: [&[0x02, 0x03][..], b"tx", &[0x00]].concat(),
+                allowed: &[],
+                error_codes: &[TransactionalIdAuthorizationFailed],
+            },
+            AuthCase {
+                api_key: API_KEY_DESCRIBE_TRANSACTIONS,
+                api_version: 0,
+                body: [&[0x02, 0x03][..], b"tx", &[0x00]].concat(),
+                allowed: &[(TransactionalId, "tx", Describe)],
+                error_codes: &[TransactionalIdNotFound],
+            },
+            AuthCase {
+                api_key: API_KEY_DESCRIBE_USER_SCRAM_CREDENTIALS,
+                api_version: 0,
+                body: vec![0x00, 0x00], // users, tagged_fields
+                allowed: &[],
+                error_codes: &[ClusterAuthorizationFailed],
+            },
+            AuthCase {
+                api_key: API_KEY_END_TXN,
+                api_version: 4,
+                body: end_txn,
+                allowed: &[],
+                error_codes: &[TransactionalIdAuthorizationFailed],
+            },
+            AuthCase {
+                api_key: API_KEY_INCREMENTAL_ALTER_CONFIGS,
+                api_version: 1,
+                body: alter_configs(&[0x00]), // OP_SET
+                allowed: &[],
+                error_codes: &[TopicAuthorizationFailed, ClusterAuthorizationFailed],
+            },
+            AuthCase {
+                api_key: API_KEY_INIT_PRODUCER_ID,
+                api_version: 4,
+                body: init_producer_id(&[0x03, b't', b'x']),
+                allowed: &[],
+                error_codes: &[TransactionalIdAuthorizationFailed],
+            },
+            AuthCase {
+                api_key: API_KEY_INIT_PRODUCER_ID,
+                api_version: 4,
+                body: init_producer_id(&[0x00]),
+                allowed: &[],
+                error_codes: &[ClusterAuthorizationFailed],
+            },
+            // Writing to some topic is enough for an idempotent producer.
+            AuthCase {
+                api_key: API_KEY_INIT_PRODUCER_ID,
+                api_version: 4,
+                body: init_producer_id(&[0x00]),
+                allowed: &[(Topic, "foo", Write)],
+                error_codes: &[NoError],
+            },
+            AuthCase {
+                api_key: API_KEY_TXN_OFFSET_COMMIT,
+                api_version: 4,
+                body: txn_offset_commit.clone(),
+                allowed: &[],
+                error_codes: &[TransactionalIdAuthorizationFailed],
+            },
+            // The group is checked once the transactional ID may be written.
+            AuthCase {
+                api_key: API_KEY_TXN_OFFSET_COMMIT,
+                api_version: 4,
+                body: txn_offset_commit,
+                allowed: &[(TransactionalId, "tx", Write)],
+                error_codes: &[GroupAuthorizationFailed],
+            },
+            AuthCase {
+                api_key: API_KEY_WRITE_TXN_MARKERS,
+                api_version: 1,
+                body: write_txn_markers,
+                allowed: &[],
+                error_codes: &[ClusterAuthorizationFailed],
+            },
+        ];
+        for (i, case) in cases.iter().enumerate() {
+            let (broker, conn) = acl_client(&format!("authorization-{i}"), case.allowed);
+            let error_codes =
+                response_error_codes(&broker, &conn, case.api_key, case.api_version, &case.body);
+            let expected: Vec<i16> = case.error_codes.iter().map(|&code| code as i16).collect();
+            assert_eq!(error_codes, expected, "case {i}, API {}", case.api_key);
+            std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_error_bodies_read_back() {
+        for api_key in 0..100 {
+            let Some((min_version, max_version)) = api_versions::supported_versions(api_key) else {
+                continue;
+            };
+            for version in min_version..=max_version {
+                let error_code = ErrorCode::InvalidRequest as i16;
+                let body = messages::Response::error(api_key, version, error_code).unwrap();
+                let bytes = crate::ser::to_bytes(&body).unwrap();
+                let mut de = Deserializer::new(&bytes[..]);
+                let read = messages::Response::read(api_key, version, &mut de).unwrap();
+                assert_eq!(read, body, "API {api_key} version {version}");
+            }
+        }
+    }
 }
@@ -0,0 +1,144 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, ResourceType},
+    de::Deserializer,
+    group::OffsetAndMetadata,
+    log::TopicPartition,
+    types::{ByteSize, CompactArray, CompactString, TaggedFields},
+    util,
+};
+
+use super::{
+    API_KEY_OFFSET_COMMIT, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::offset_commit_request::OffsetCommitRequest, read_request,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        8 | 9 => {
+            let (correlation_id, OffsetCommitRequest::V8(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+
+            let now = util::now_ms();
+            let offsets: Vec<(TopicPartition, OffsetAndMetadata)> = req_body
+                .topics
+                .into_iter()
+                .flat_map(|topic| {
+                    let name = topic.name.as_str().to_string();
+                    topic.partitions.into_iter().map(move |partition| {
+                        (
+                            TopicPartition::new(name.clone(), partition.partition_index),
+                            OffsetAndMetadata {
+                                offset: partition.committed_offset,
+                                leader_epoch: partition.committed_leader_epoch,
+                                metadata: partition
+                                    .committed_metadata
+                                    .as_ref()
+                                    .cloned()
+                                    .unwrap_or_default(),
+                                commit_timestamp: now,
+                            },
+                        )
+                    })
+                })
+                .collect();
+
+            let group_id = req_body.group_id.as_str();
+            let results =
+                if !conn.authorize(broker, AclOperation::Read, ResourceType::Group, group_id) {
+                    offsets
+                        .into_iter()
+                        .map(|(tp, _)| (tp, ErrorCode::GroupAuthorizationFailed))
+                        .collect()
+                } else {
+                    // Only the offsets of topics the client may read are committed.
+                    let (offsets, unauthorized): (Vec<_>, Vec<_>) =
+                        offsets.into_iter().partition(|(tp, _)| {
+                            conn.authorize(
+                                broker,
+                                AclOperation::Read,
+                                ResourceType::Topic,
+                                &tp.topic,
+                            )
+                        });
+                    let mut results = broker.groups.commit_offsets(
+                        group_id,
+                        req_body.generation_id_or_member_epoch,
+                        req_body.member_id.as_str(),
+                        offsets,
+                    );
+                    results.extend(
+                        unauthorized
+                            .into_iter()
+                            .map(|(tp, _)| (tp, ErrorCode::TopicAuthorizationFailed)),
+                    );
+                    results
+                };
+
+            let mut topics: BTreeMap<String, Vec<OffsetCommitResponsePartition>> = BTreeMap::new();
+            for (tp, error_code) in results {
+                topics
+                    .entry(tp.topic)
+                    .or_default()
+                    .push(OffsetCommitResponsePartition {
+                        partition_index: tp.partition,
+                        error_code,
+                        tagged_fields: TaggedFields::new(None),
+                    });
+            }
+
+            let res_body = ResponseBody::OffsetCommit(OffsetCommitResponseBody {
+                throttle_time_ms: 0,
+                topics: topics
+                    .into_iter()
+                    .map(|(name, partitions)| OffsetCommitResponseTopic {
+                        name: CompactString::new(name),
+                        partitions: CompactArray::new(Some(partitions)),
+                        tagged_fields: TaggedFields::new(None),
+                    })
+                    .collect(),
+                tagged_fields: TaggedFields::new(None),
+            });
+
+            Ok(Message::new(res_header, Some(res_body)))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_OFFSET_COMMIT,
+            api_version,
+        }),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct OffsetCommitResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    topics: CompactArray<OffsetCommitResponseTopic>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct OffsetCommitResponseTopic {
+    name: CompactString,
+    partitions: CompactArray<OffsetCommitResponsePartition>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct OffsetCommitResponsePartition {
+    partition_index: i32,
+    error_code: ErrorCode,
+    tagged_fields: TaggedFields,
+}
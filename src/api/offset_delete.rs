@@ -0,0 +1,122 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, ResourceType},
+    de::Deserializer,
+    log::TopicPartition,
+    types::{Array, ByteSize},
+};
+
+use super::{
+    API_KEY_OFFSET_DELETE, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::offset_delete_request::OffsetDeleteRequest, read_request,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        0 => {
+            let (correlation_id, OffsetDeleteRequest::V0(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let res_header = ResponseHeader::V0 { correlation_id };
+
+            let partitions: Vec<TopicPartition> = req_body
+                .topics
+                .into_iter()
+                .flat_map(|topic| {
+                    let name = topic.name;
+                    topic.partitions.into_iter().map(move |partition| {
+                        TopicPartition::new(name.clone(), partition.partition_index)
+                    })
+                })
+                .collect();
+
+            // Only the offsets of topics the client may read are deleted.
+            let (partitions, unauthorized): (Vec<_>, Vec<_>) =
+                partitions.into_iter().partition(|tp| {
+                    conn.authorize(broker, AclOperation::Read, ResourceType::Topic, &tp.topic)
+                });
+            let result = if conn.authorize(
+                broker,
+                AclOperation::Delete,
+                ResourceType::Group,
+                &req_body.group_id,
+            ) {
+                broker.groups.delete_offsets(&req_body.group_id, partitions)
+            } else {
+                Err(ErrorCode::GroupAuthorizationFailed)
+            };
+            let res_body = match result {
+                Ok(mut results) => {
+                    results.extend(
+                        unauthorized
+                            .into_iter()
+                            .map(|tp| (tp, ErrorCode::TopicAuthorizationFailed)),
+                    );
+                    let mut topics: BTreeMap<String, Vec<OffsetDeleteResponsePartition>> =
+                        BTreeMap::new();
+                    for (tp, error_code) in results {
+                        topics
+                            .entry(tp.topic)
+                            .or_default()
+                            .push(OffsetDeleteResponsePartition {
+                                partition_index: tp.partition,
+                                error_code,
+                            });
+                    }
+                    OffsetDeleteResponseBody {
+                        error_code: ErrorCode::NoError,
+                        throttle_time_ms: 0,
+                        topics: topics
+                            .into_iter()
+                            .map(|(name, partitions)| OffsetDeleteResponseTopic {
+                                name,
+                                partitions: Array::new(Some(partitions)),
+                            })
+                            .collect(),
+                    }
+                }
+                Err(error_code) => OffsetDeleteResponseBody {
+                    error_code,
+                    throttle_time_ms: 0,
+                    topics: Array::new(Some(vec![])),
+                },
+            };
+
+            Ok(Message::new(
+                res_header,
+                Some(ResponseBody::OffsetDelete(res_body)),
+            ))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_OFFSET_DELETE,
+            api_version,
+        }),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct OffsetDeleteResponseBody {
+    error_code: ErrorCode,
+    pub(crate) throttle_time_ms: i32,
+    topics: Array<OffsetDeleteResponseTopic>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct OffsetDeleteResponseTopic {
+    name: String,
+    partitions: Array<OffsetDeleteResponsePartition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct OffsetDeleteResponsePartition {
+    partition_index: i32,
+    error_code: ErrorCode,
+}
@@ -0,0 +1,205 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, ResourceType},
+    de::Deserializer,
+    log::TopicPartition,
+    types::{ByteSize, CompactArray, CompactNullableString, CompactString, TaggedFields},
+};
+
+use super::{
+    Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::offset_fetch_request::{OffsetFetchRequest, OffsetFetchRequestTopicsV8},
+    read_request,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    let (correlation_id, req_body) = read_request(api_version, &mut de)?;
+    let groups: Vec<FetchGroup> = match req_body {
+        OffsetFetchRequest::V8(req_body) => req_body
+            .groups
+            .into_iter()
+            .map(|group| (group.group_id, None, group.topics))
+            .collect(),
+        OffsetFetchRequest::V9(req_body) => req_body
+            .groups
+            .into_iter()
+            .map(|group| {
+                let member_id = group.member_id.as_ref().cloned().unwrap_or_default();
+                (
+                    group.group_id,
+                    Some((member_id, group.member_epoch)),
+                    group.topics,
+                )
+            })
+            .collect(),
+    };
+
+    let res_header = ResponseHeader::V1 {
+        correlation_id,
+        tagged_fields: TaggedFields::new(None),
+    };
+
+    let res_body = ResponseBody::OffsetFetch(OffsetFetchResponseBody {
+        throttle_time_ms: 0,
+        groups: groups
+            .into_iter()
+            .map(|(group_id, member, topics)| fetch_group(broker, conn, group_id, member, topics))
+            .collect(),
+        tagged_fields: TaggedFields::new(None),
+    });
+
+    Ok(Message::new(res_header, Some(res_body)))
+}
+
+/// A requested group: its id, the member fetching (v9+) and the topics.
+type FetchGroup = (
+    CompactString,
+    Option<(String, i32)>,
+    CompactArray<OffsetFetchRequestTopicsV8>,
+);
+
+fn fetch_group(
+    broker: &Broker,
+    conn: &Connection,
+    group_id: CompactString,
+    member: Option<(String, i32)>,
+    topics: CompactArray<OffsetFetchRequestTopicsV8>,
+) -> OffsetFetchResponseGroup {
+    if !conn.authorize(
+        broker,
+        AclOperation::Describe,
+        ResourceType::Group,
+        group_id.as_str(),
+    ) {
+        return OffsetFetchResponseGroup {
+            group_id,
+            topics: CompactArray::new(Some(vec![])),
+            error_code: ErrorCode::GroupAuthorizationFailed,
+            tagged_fields: TaggedFields::new(None),
+        };
+    }
+    if let Some((member_id, member_epoch)) = member
+        && let Err(error_code) =
+            broker
+                .groups
+                .validate_offset_fetch(group_id.as_str(), &member_id, member_epoch)
+    {
+        return OffsetFetchResponseGroup {
+            group_id,
+            topics: CompactArray::new(Some(vec![])),
+            error_code,
+            tagged_fields: TaggedFields::new(None),
+        };
+    }
+
+    let partitions: Option<Vec<TopicPartition>> = topics.as_opt_slice().map(|topics| {
+        topics
+            .iter()
+            .flat_map(|topic| {
+                topic
+                    .partition_indexes
+                    .as_opt_slice()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|partition| TopicPartition::new(topic.name.as_str(), *partition))
+            })
+            .collect()
+    });
+
+    // Topics the client may not describe are left out of a fetch of every
+    // offset of the group, and fail when requested.
+    let requested = partitions.is_some();
+    let mut topics: BTreeMap<String, Vec<OffsetFetchResponsePartition>> = BTreeMap::new();
+    for (tp, offset) in broker.groups.fetch_offsets(group_id.as_str(), partitions) {
+        let authorized = conn.authorize(
+            broker,
+            AclOperation::Describe,
+            ResourceType::Topic,
+            &tp.topic,
+        );
+        if !authorized && !requested {
+            continue;
+        }
+        let partition = match offset {
+            _ if !authorized => OffsetFetchResponsePartition {
+                partition_index: tp.partition,
+                committed_offset: -1,
+                committed_leader_epoch: -1,
+                metadata: CompactNullableString::new(Some(String::new())),
+                error_code: ErrorCode::TopicAuthorizationFailed,
+                tagged_fields: TaggedFields::new(None),
+            },
+            Some(offset) => OffsetFetchResponsePartition {
+                partition_index: tp.partition,
+                committed_offset: offset.offset,
+                committed_leader_epoch: offset.leader_epoch,
+                metadata: CompactNullableString::new(Some(offset.metadata)),
+                error_code: ErrorCode::NoError,
+                tagged_fields: TaggedFields::new(None),
+            },
+            None => OffsetFetchResponsePartition {
+                partition_index: tp.partition,
+                committed_offset: -1,
+                committed_leader_epoch: -1,
+                metadata: CompactNullableString::new(Some(String::new())),
+                error_code: ErrorCode::NoError,
+                tagged_fields: TaggedFields::new(None),
+            },
+        };
+        topics.entry(tp.topic).or_default().push(partition);
+    }
+
+    OffsetFetchResponseGroup {
+        group_id,
+        topics: topics
+            .into_iter()
+            .map(|(name, partitions)| OffsetFetchResponseTopic {
+                name: CompactString::new(name),
+                partitions: CompactArray::new(Some(partitions)),
+                tagged_fields: TaggedFields::new(None),
+            })
+            .collect(),
+        error_code: ErrorCode::NoError,
+        tagged_fields: TaggedFields::new(None),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct OffsetFetchResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    groups: CompactArray<OffsetFetchResponseGroup>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct OffsetFetchResponseGroup {
+    group_id: CompactString,
+    topics: CompactArray<OffsetFetchResponseTopic>,
+    error_code: ErrorCode,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct OffsetFetchResponseTopic {
+    name: CompactString,
+    partitions: CompactArray<OffsetFetchResponsePartition>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct OffsetFetchResponsePartition {
+    partition_index: i32,
+    committed_offset: i64,
+    committed_leader_epoch: i32,
+    metadata: CompactNullableString,
+    error_code: ErrorCode,
+    tagged_fields: TaggedFields,
+}
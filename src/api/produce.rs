@@ -0,0 +1,215 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, ResourceType},
+    cluster::is_internal_topic,
+    de::Deserializer,
+    log::TopicPartition,
+    types::{
+        ByteSize, CompactArray, CompactNullableBytes, CompactNullableString, CompactString,
+        RecordBatch, TaggedFields, VarintBytes, has_compressed_batch,
+    },
+};
+
+use super::{
+    API_KEY_PRODUCE, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::produce_request::ProduceRequest, partition_exists, read_meta_records, read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+/// Produce requests with `acks=0` get no response at all, so unlike the
+/// other handlers this one returns an optional message.
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Option<Message>> {
+    match api_version {
+        9..=11 => {
+            let (correlation_id, ProduceRequest::V9(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+
+            // Without the metadata log it is unknown which partitions exist.
+            let metadata = read_meta_records(broker)
+                .inspect_err(|e| eprintln!("Failed to read the metadata log: {e}"))
+                .ok();
+            let acks = req_body.acks;
+
+            let responses = req_body
+                .topic_data
+                .into_iter()
+                .map(|topic| {
+                    let authorized = conn.authorize(
+                        broker,
+                        AclOperation::Write,
+                        ResourceType::Topic,
+                        topic.name.as_str(),
+                    );
+                    let partition_responses = topic
+                        .partition_data
+                        .into_iter()
+                        .map(|partition| {
+                            let tp = TopicPartition::new(topic.name.as_str(), partition.index);
+                            let result = if !matches!(acks, -1..=1) {
+                                Err(ErrorCode::InvalidRequiredAcksException)
+                            } else if !authorized {
+                                Err(ErrorCode::TopicAuthorizationFailed)
+                            } else if is_internal_topic(&tp.topic) {
+                                // Only the coordinators write their state.
+                                Err(ErrorCode::InvalidTopicException)
+                            } else {
+                                match &metadata {
+                                    None => Err(ErrorCode::KafkaStorageError),
+                                    Some(metadata) if !partition_exists(metadata, &tp) => {
+                                        Err(ErrorCode::UnknownTopicOrPartition)
+                                    }
+                                    Some(_) => append(
+                                        broker,
+                                        req_body.transactional_id.as_ref(),
+                                        &tp,
+                                        partition.records,
+                                    ),
+                                }
+                            };
+                            partition_response(partition.index, result)
+                        })
+                        .collect();
+                    ProduceResponseTopic {
+                        name: topic.name,
+                        partition_responses,
+                        tagged_fields: TaggedFields::new(None),
+                    }
+                })
+                .collect();
+
+            if acks == 0 {
+                return Ok(None);
+            }
+
+            let res_body = ResponseBody::Produce(ProduceResponseBody {
+                responses,
+                throttle_time_ms: 0,
+                tagged_fields: TaggedFields::new(None),
+            });
+
+            Ok(Some(Message::new(res_header, Some(res_body))))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_PRODUCE,
+            api_version,
+        }),
+    }
+}
+
+/// Appends the batches of one partition and returns the base offset of the
+/// first one.
+fn append(
+    broker: &Broker,
+    transactional_id: Option<&String>,
+    tp: &TopicPartition,
+    records: CompactNullableBytes,
+) -> std::result::Result<i64, ErrorCode> {
+    let bytes = records.as_opt_bytes().unwrap_or_default();
+    // Without codecs, compressed records can be neither validated nor read.
+    if has_compressed_batch(bytes) {
+        return Err(ErrorCode::UnsupportedCompressionType);
+    }
+    let batches = match RecordBatch::<VarintBytes>::from_reader(bytes) {
+        Ok(batches) if !batches.is_empty() => batches,
+        _ => return Err(ErrorCode::CorruptMessage),
+    };
+
+    for batch in batches.iter() {
+        if !batch.is_valid().unwrap_or(false) {
+            return Err(ErrorCode::CorruptMessage);
+        }
+        // Transactional batches are only accepted once AddPartitionsToTxn
+        // added the partition to the producer's transaction.
+        if batch.is_transactional()
+            && !transactional_id.is_some_and(|transactional_id| {
+                broker.txns.is_in_transaction(
+                    transactional_id,
+                    batch.producer_id,
+                    batch.producer_epoch,
+                    tp,
+                )
+            })
+        {
+            return Err(ErrorCode::InvalidTxnState);
+        }
+    }
+
+    let storage_error = |e: crate::KafkaError| {
+        eprintln!("Failed to append to {tp}: {e}");
+        ErrorCode::KafkaStorageError
+    };
+    let log = broker.logs.get_or_create(tp).map_err(storage_error)?;
+    let mut log = log.lock().unwrap();
+
+    let mut base_offset = None;
+    for batch in batches {
+        let offset = log.append_from_client(batch).map_err(storage_error)??;
+        base_offset.get_or_insert(offset);
+    }
+    broker.logs.purgatory().notify(tp);
+    Ok(base_offset.unwrap_or_default())
+}
+
+fn partition_response(
+    index: i32,
+    result: std::result::Result<i64, ErrorCode>,
+) -> ProduceResponsePartition {
+    let (error_code, base_offset) = match result {
+        Ok(base_offset) => (ErrorCode::NoError, base_offset),
+        Err(error_code) => (error_code, -1),
+    };
+    ProduceResponsePartition {
+        index,
+        error_code,
+        base_offset,
+        log_append_time_ms: -1,
+        log_start_offset: 0,
+        record_errors: CompactArray::new(Some(vec![])),
+        error_message: CompactNullableString::new(None),
+        tagged_fields: TaggedFields::new(None),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct ProduceResponseBody {
+    responses: CompactArray<ProduceResponseTopic>,
+    pub(crate) throttle_time_ms: i32,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct ProduceResponseTopic {
+    name: CompactString,
+    partition_responses: CompactArray<ProduceResponsePartition>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct ProduceResponsePartition {
+    index: i32,
+    error_code: ErrorCode,
+    base_offset: i64,
+    log_append_time_ms: i64,
+    log_start_offset: i64,
+    record_errors: CompactArray<BatchIndexAndErrorMessage>,
+    error_message: CompactNullableString,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct BatchIndexAndErrorMessage {
+    batch_index: i32,
+    batch_index_error_message: CompactNullableString,
+    tagged_fields: TaggedFields,
+}
@@ -1,4 +1,11 @@
-use crate::types::{ByteSizeExt, TaggedFields};
+use super::{
+    describe_topic_partitions::DescribeTopicPartitionsResponseBody,
+    messages::{
+        Response, api_versions_response::ApiVersionsResponse,
+        describe_cluster_response::DescribeClusterResponse,
+    },
+};
+use crate::types::{ByteSize, ByteSizeExt, KafkaEncode, TaggedFields, Versioned};
 
 use serde::{
     Serialize,
@@ -29,20 +36,90 @@ impl ByteSizeExt for ResponseHeader {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
 #[serde(untagged)]
 pub(crate) enum ResponseBody {
-    ApiVersions(super::api_versions::ApiVersionsResponseBody),
+    AddOffsetsToTxn(super::add_offsets_to_txn::AddOffsetsToTxnResponseBody),
+    AddPartitionsToTxn(super::add_partitions_to_txn::AddPartitionsToTxnResponseBodyV3),
+    AlterClientQuotas(super::alter_client_quotas::AlterClientQuotasResponseBody),
+    AlterConfigs(super::alter_configs::AlterConfigsResponseBody),
+    AlterUserScramCredentials(
+        super::alter_user_scram_credentials::AlterUserScramCredentialsResponseBody,
+    ),
+    ApiVersions(ApiVersionsResponse),
+    ConsumerGroupHeartbeat(super::consumer_group_heartbeat::ConsumerGroupHeartbeatResponseBody),
+    CreateAcls(super::create_acls::CreateAclsResponseBody),
+    DeleteAcls(super::delete_acls::DeleteAclsResponseBody),
+    DeleteGroups(super::delete_groups::DeleteGroupsResponseBody),
+    DescribeAcls(super::describe_acls::DescribeAclsResponseBody),
+    DescribeClientQuotas(super::describe_client_quotas::DescribeClientQuotasResponseBody),
+    DescribeCluster(DescribeClusterResponse),
+    DescribeConfigs(super::describe_configs::DescribeConfigsResponseBody),
+    DescribeGroups(super::describe_groups::DescribeGroupsResponseBody),
+    DescribeProducers(super::describe_producers::DescribeProducersResponseBody),
     DescribeTopicPartitions(super::describe_topic_partitions::DescribeTopicPartitionsResponseBody),
+    DescribeTransactions(super::describe_transactions::DescribeTransactionsResponseBody),
+    DescribeUserScramCredentials(
+        super::describe_user_scram_credentials::DescribeUserScramCredentialsResponseBody,
+    ),
+    EndTxn(super::end_txn::EndTxnResponseBody),
+    /// A response which only reports an error, of any API.
+    Error(Response),
     Fetch(super::fetch::FetchResponseBody),
+    IncrementalAlterConfigs(super::incremental_alter_configs::IncrementalAlterConfigsResponseBody),
+    InitProducerId(super::init_producer_id::InitProducerIdResponseBody),
+    ListGroups(super::list_groups::ListGroupsResponseBody),
+    ListTransactions(super::list_transactions::ListTransactionsResponseBody),
+    OffsetCommit(super::offset_commit::OffsetCommitResponseBody),
+    OffsetDelete(super::offset_delete::OffsetDeleteResponseBody),
+    OffsetFetch(super::offset_fetch::OffsetFetchResponseBody),
+    Produce(super::produce::ProduceResponseBody),
+    SaslAuthenticate(Versioned<super::sasl_authenticate::SaslAuthenticateResponseBody>),
+    SaslHandshake(super::sasl_handshake::SaslHandshakeResponseBody),
+    TxnOffsetCommit(super::txn_offset_commit::TxnOffsetCommitResponseBody),
+    WriteTxnMarkers(super::write_txn_markers::WriteTxnMarkersResponseBody),
 }
 
-impl ByteSizeExt for ResponseBody {
-    fn byte_size(&self) -> usize {
+impl ResponseBody {
+    /// Reports how long the client is throttled for, in the bodies which
+    /// have the field.
+    fn set_throttle_time_ms(&mut self, ms: i32) {
         match self {
-            Self::ApiVersions(body) => body.byte_size(),
-            Self::DescribeTopicPartitions(body) => body.byte_size(),
-            Self::Fetch(body) => body.byte_size(),
+            Self::AddOffsetsToTxn(body) => body.throttle_time_ms = ms,
+            Self::AddPartitionsToTxn(body) => body.throttle_time_ms = ms,
+            Self::AlterClientQuotas(body) => body.throttle_time_ms = ms,
+            Self::AlterConfigs(body) => body.throttle_time_ms = ms,
+            Self::AlterUserScramCredentials(body) => body.throttle_time_ms = ms,
+            Self::ApiVersions(body) => body.set_throttle_time_ms(ms),
+            Self::ConsumerGroupHeartbeat(body) => body.throttle_time_ms = ms,
+            Self::CreateAcls(body) => body.throttle_time_ms = ms,
+            Self::DeleteAcls(body) => body.throttle_time_ms = ms,
+            Self::DeleteGroups(body) => body.throttle_time_ms = ms,
+            Self::DescribeAcls(body) => body.throttle_time_ms = ms,
+            Self::DescribeClientQuotas(body) => body.throttle_time_ms = ms,
+            Self::DescribeCluster(body) => body.set_throttle_time_ms(ms),
+            Self::DescribeConfigs(body) => body.throttle_time_ms = ms,
+            Self::DescribeGroups(body) => body.throttle_time_ms = ms,
+            Self::DescribeProducers(body) => body.throttle_time_ms = ms,
+            Self::DescribeTopicPartitions(DescribeTopicPartitionsResponseBody::V0 {
+                throttle_time_ms,
+                ..
+            }) => *throttle_time_ms = ms,
+            Self::DescribeTransactions(body) => body.throttle_time_ms = ms,
+            Self::DescribeUserScramCredentials(body) => body.throttle_time_ms = ms,
+            Self::EndTxn(body) => body.throttle_time_ms = ms,
+            Self::Error(body) => body.set_throttle_time_ms(ms),
+            Self::Fetch(body) => body.throttle_time_ms = ms,
+            Self::IncrementalAlterConfigs(body) => body.throttle_time_ms = ms,
+            Self::InitProducerId(body) => body.throttle_time_ms = ms,
+            Self::ListGroups(body) => body.throttle_time_ms = ms,
+            Self::ListTransactions(body) => body.throttle_time_ms = ms,
+            Self::OffsetCommit(body) => body.throttle_time_ms = ms,
+            Self::OffsetDelete(body) => body.throttle_time_ms = ms,
+            Self::OffsetFetch(body) => body.throttle_time_ms = ms,
+            Self::Produce(body) => body.throttle_time_ms = ms,
+            Self::TxnOffsetCommit(body) => body.throttle_time_ms = ms,
+            Self::SaslAuthenticate(_) | Self::SaslHandshake(_) | Self::WriteTxnMarkers(_) => {}
         }
     }
 }
@@ -57,6 +134,22 @@ impl Message {
     pub(crate) fn new(header: ResponseHeader, body: Option<ResponseBody>) -> Self {
         Self { header, body }
     }
+
+    pub(crate) fn set_throttle_time_ms(&mut self, ms: i32) {
+        if let Some(body) = self.body.as_mut() {
+            body.set_throttle_time_ms(ms);
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn header(&self) -> &ResponseHeader {
+        &self.header
+    }
+
+    #[cfg(test)]
+    pub(crate) fn into_body(self) -> Option<ResponseBody> {
+        self.body
+    }
 }
 
 impl ByteSizeExt for Message {
@@ -88,6 +181,7 @@ impl ser::Serialize for Message {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ErrorCode {
     UnknownServerError = -1,
@@ -123,8 +217,41 @@ pub enum ErrorCode {
     TopicAuthorizationFailed = 29,
     GroupAuthorizationFailed = 30,
     ClusterAuthorizationFailed = 31,
+    UnsupportedSaslMechanism = 33,
+    IllegalSaslState = 34,
     UnsupportedVersion = 35,
+    InvalidConfig = 40,
+    InvalidRequest = 42,
+    OutOfOrderSequenceNumber = 45,
+    DuplicateSequenceNumber = 46,
+    InvalidProducerEpoch = 47,
+    InvalidTxnState = 48,
+    InvalidProducerIdMapping = 49,
+    InvalidTransactionTimeout = 50,
+    ConcurrentTransactions = 51,
+    TransactionalIdAuthorizationFailed = 53,
+    SecurityDisabled = 54,
+    OperationNotAttempted = 55,
+    KafkaStorageError = 56,
+    SaslAuthenticationFailed = 58,
+    NonEmptyGroup = 68,
+    GroupIdNotFound = 69,
+    FetchSessionIdNotFound = 70,
+    InvalidFetchSessionEpoch = 71,
+    UnsupportedCompressionType = 76,
+    GroupMaxSizeReached = 81,
+    GroupSubscribedToTopic = 86,
+    ProducerFenced = 90,
+    ResourceNotFound = 91,
+    DuplicateResource = 92,
+    UnacceptableCredential = 93,
     UnknownTopicId = 100,
+    TransactionalIdNotFound = 105,
+    FencedMemberEpoch = 110,
+    UnreleasedInstanceId = 111,
+    UnsupportedAssignor = 112,
+    StaleMemberEpoch = 113,
+    UnsupportedEndpointType = 119,
 }
 
 impl ser::Serialize for ErrorCode {
@@ -136,6 +263,21 @@ impl ser::Serialize for ErrorCode {
     }
 }
 
+impl KafkaEncode for ErrorCode {
+    fn encode<S: SerializeSeq>(
+        &self,
+        _version: i16,
+        _compact: bool,
+        seq: &mut S,
+    ) -> Result<(), S::Error> {
+        seq.serialize_element(self)
+    }
+
+    fn encoded_size(&self, _version: i16, _compact: bool) -> usize {
+        self.byte_size()
+    }
+}
+
 impl ByteSizeExt for ErrorCode {
     fn byte_size(&self) -> usize {
         (*self as i16).byte_size()
@@ -0,0 +1,80 @@
+use crate::{
+    Broker, Result,
+    de::Deserializer,
+    types::{KafkaEncode, TaggedFields, Versioned},
+};
+
+use super::{
+    Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::sasl_authenticate_request::SaslAuthenticateRequest, read_request,
+};
+use std::io::Read;
+
+/// Sessions do not expire, so clients never re-authenticate.
+const SESSION_LIFETIME_MS: i64 = 0;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &mut Connection,
+) -> Result<Message> {
+    let (correlation_id, req_body) = read_request(api_version, &mut de)?;
+    let (res_header, auth_bytes) = match &req_body {
+        SaslAuthenticateRequest::V0(req_body) => (
+            ResponseHeader::V0 { correlation_id },
+            req_body.auth_bytes.as_bytes(),
+        ),
+        SaslAuthenticateRequest::V2(req_body) => (
+            ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            },
+            req_body.auth_bytes.as_bytes(),
+        ),
+    };
+
+    let (error_code, error_message, auth_bytes) = authenticate(conn, auth_bytes, broker);
+    let res_body = SaslAuthenticateResponseBody {
+        error_code,
+        error_message,
+        auth_bytes: auth_bytes.into(),
+        session_lifetime_ms: SESSION_LIFETIME_MS,
+        tagged_fields: TaggedFields::new(None),
+    };
+    Ok(Message::new(
+        res_header,
+        Some(ResponseBody::SaslAuthenticate(Versioned::new(
+            api_version,
+            res_body,
+        ))),
+    ))
+}
+
+fn authenticate(
+    conn: &mut Connection,
+    message: &[u8],
+    broker: &Broker,
+) -> (ErrorCode, Option<String>, Vec<u8>) {
+    let plain_users = &conn.listener.sasl_plain_users;
+    match conn
+        .auth
+        .authenticate(message, plain_users, &broker.credentials)
+    {
+        Ok(reply) => (ErrorCode::NoError, None, reply),
+        Err((error_code, error_message)) => (error_code, Some(error_message), vec![]),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, KafkaEncode)]
+pub(crate) struct SaslAuthenticateResponseBody {
+    error_code: ErrorCode,
+    #[kafka(compact = "2+")]
+    error_message: Option<String>,
+    #[kafka(compact = "2+")]
+    auth_bytes: bytes::Bytes,
+    #[kafka(versions = "1+")]
+    session_lifetime_ms: i64,
+    #[kafka(versions = "2+")]
+    tagged_fields: TaggedFields,
+}
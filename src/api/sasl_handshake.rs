@@ -0,0 +1,51 @@
+use crate::{
+    Result,
+    de::Deserializer,
+    types::{Array, ByteSize},
+};
+
+use super::{
+    API_KEY_SASL_HANDSHAKE, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::sasl_handshake_request::SaslHandshakeRequest, read_request,
+};
+use serde::Serialize;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    conn: &mut Connection,
+) -> Result<Message> {
+    match api_version {
+        1 => {
+            let (correlation_id, SaslHandshakeRequest::V1(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let enabled_mechanisms = &conn.listener.sasl_enabled_mechanisms;
+            let error_code = match conn.auth.handshake(&req_body.mechanism, enabled_mechanisms) {
+                Ok(()) => ErrorCode::NoError,
+                Err((error_code, _)) => error_code,
+            };
+
+            let res_header = ResponseHeader::V0 { correlation_id };
+            let res_body = SaslHandshakeResponseBody {
+                error_code,
+                mechanisms: Array::new(Some(enabled_mechanisms.clone())),
+            };
+            Ok(Message::new(
+                res_header,
+                Some(ResponseBody::SaslHandshake(res_body)),
+            ))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_SASL_HANDSHAKE,
+            api_version,
+        }),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct SaslHandshakeResponseBody {
+    error_code: ErrorCode,
+    mechanisms: Array<String>,
+}
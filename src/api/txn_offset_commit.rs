@@ -0,0 +1,248 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, ResourceType},
+    de::Deserializer,
+    group::{GROUP_METADATA_TOPIC, OffsetAndMetadata},
+    log::TopicPartition,
+    types::{ByteSize, CompactArray, CompactString, TaggedFields},
+    util,
+};
+
+use super::{
+    API_KEY_TXN_OFFSET_COMMIT, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::txn_offset_commit_request::TxnOffsetCommitRequest, read_request,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        3 | 4 => {
+            let (correlation_id, TxnOffsetCommitRequest::V3(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+
+            let now = util::now_ms();
+            let offsets: Vec<(TopicPartition, OffsetAndMetadata)> = req_body
+                .topics
+                .into_iter()
+                .flat_map(|topic| {
+                    let name = topic.name.as_str().to_string();
+                    topic.partitions.into_iter().map(move |partition| {
+                        (
+                            TopicPartition::new(name.clone(), partition.partition_index),
+                            OffsetAndMetadata {
+                                offset: partition.committed_offset,
+                                leader_epoch: partition.committed_leader_epoch,
+                                metadata: partition
+                                    .committed_metadata
+                                    .as_ref()
+                                    .cloned()
+                                    .unwrap_or_default(),
+                                commit_timestamp: now,
+                            },
+                        )
+                    })
+                })
+                .collect();
+
+            let transactional_id = req_body.transactional_id.as_str();
+            let group_id = req_body.group_id.as_str();
+            // The offsets only become visible through the marker written to
+            // the group's partition, which AddOffsetsToTxn adds to the
+            // transaction.
+            let offsets_partition =
+                TopicPartition::new(GROUP_METADATA_TOPIC, broker.groups.partition_for(group_id));
+            let failed = |offsets: Vec<(TopicPartition, OffsetAndMetadata)>, error_code| {
+                offsets
+                    .into_iter()
+                    .map(|(tp, _)| (tp, error_code))
+                    .collect()
+            };
+            let results = if !conn.authorize(
+                broker,
+                AclOperation::Write,
+                ResourceType::TransactionalId,
+                transactional_id,
+            ) {
+                failed(offsets, ErrorCode::TransactionalIdAuthorizationFailed)
+            } else if !conn.authorize(broker, AclOperation::Read, ResourceType::Group, group_id) {
+                failed(offsets, ErrorCode::GroupAuthorizationFailed)
+            } else if broker.txns.is_in_transaction(
+                transactional_id,
+                req_body.producer_id,
+                req_body.producer_epoch,
+                &offsets_partition,
+            ) {
+                // Only the offsets of topics the client may read are committed.
+                let (offsets, unauthorized): (Vec<_>, Vec<_>) =
+                    offsets.into_iter().partition(|(tp, _)| {
+                        conn.authorize(broker, AclOperation::Read, ResourceType::Topic, &tp.topic)
+                    });
+                let mut results: Vec<(TopicPartition, ErrorCode)> =
+                    broker.groups.commit_transactional_offsets(
+                        group_id,
+                        req_body.producer_id,
+                        req_body.producer_epoch,
+                        req_body.generation_id,
+                        req_body.member_id.as_str(),
+                        offsets,
+                    );
+                results.extend(failed(unauthorized, ErrorCode::TopicAuthorizationFailed));
+                results
+            } else {
+                failed(offsets, ErrorCode::InvalidTxnState)
+            };
+
+            let mut topics: BTreeMap<String, Vec<TxnOffsetCommitResponsePartition>> =
+                BTreeMap::new();
+            for (tp, error_code) in results {
+                topics
+                    .entry(tp.topic)
+                    .or_default()
+                    .push(TxnOffsetCommitResponsePartition {
+                        partition_index: tp.partition,
+                        error_code,
+                        tagged_fields: TaggedFields::new(None),
+                    });
+            }
+
+            let res_body = ResponseBody::TxnOffsetCommit(TxnOffsetCommitResponseBody {
+                throttle_time_ms: 0,
+                topics: topics
+                    .into_iter()
+                    .map(|(name, partitions)| TxnOffsetCommitResponseTopic {
+                        name: CompactString::new(name),
+                        partitions: CompactArray::new(Some(partitions)),
+                        tagged_fields: TaggedFields::new(None),
+                    })
+                    .collect(),
+                tagged_fields: TaggedFields::new(None),
+            });
+
+            Ok(Message::new(res_header, Some(res_body)))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_TXN_OFFSET_COMMIT,
+            api_version,
+        }),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct TxnOffsetCommitResponseBody {
+    pub(crate) throttle_time_ms: i32,
+    topics: CompactArray<TxnOffsetCommitResponseTopic>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct TxnOffsetCommitResponseTopic {
+    name: CompactString,
+    partitions: CompactArray<TxnOffsetCommitResponsePartition>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct TxnOffsetCommitResponsePartition {
+    partition_index: i32,
+    error_code: ErrorCode,
+    tagged_fields: TaggedFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{client, request};
+
+    fn commit(
+        broker: &Broker,
+        conn: &Connection,
+        producer_id: i64,
+        epoch: i16,
+    ) -> Vec<(String, i32, ErrorCode)> {
+        let body = [
+            &[0x03, b't', b'x'][..],    // transactional_id
+            &[0x02, b'g'],              // group_id
+            &producer_id.to_be_bytes(), // producer_id
+            &epoch.to_be_bytes(),       // producer_epoch
+            &[0xff; 4],                 // generation_id
+            &[0x01, 0x00],              // member_id, group_instance_id
+            &[0x02, 0x04],
+            b"foo",                                            // topics
+            &[0x02, 0x00, 0x00, 0x00, 0x00],                   // partition_index
+            &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2a], // committed_offset
+            &[0xff, 0xff, 0xff, 0xff, 0x00, 0x00], // committed_leader_epoch, committed_metadata
+            &[0x00, 0x00, 0x00],                   // tagged_fields
+        ]
+        .concat();
+        let request = request(API_KEY_TXN_OFFSET_COMMIT, 4, &body);
+        let message = run(4, Deserializer::new(&request[..]), broker, conn).unwrap();
+        let Some(ResponseBody::TxnOffsetCommit(body)) = message.into_body() else {
+            panic!("expected a TxnOffsetCommit response");
+        };
+        body.topics
+            .as_opt_slice()
+            .unwrap()
+            .iter()
+            .flat_map(|topic| {
+                let partitions = topic.partitions.as_opt_slice().unwrap();
+                partitions.iter().map(|partition| {
+                    (
+                        topic.name.as_str().to_string(),
+                        partition.partition_index,
+                        partition.error_code,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_txn_offset_commit() {
+        let (broker, conn) = client("txn-offset-commit", "");
+        let (producer_id, epoch) = broker
+            .txns
+            .init_producer_id(Some("tx"), 60_000, None, &broker.groups)
+            .unwrap();
+
+        // The group's partition must be added to the transaction first.
+        assert_eq!(
+            commit(&broker, &conn, producer_id, epoch),
+            [("foo".to_string(), 0, ErrorCode::InvalidTxnState)]
+        );
+        let offsets_partition =
+            TopicPartition::new(GROUP_METADATA_TOPIC, broker.groups.partition_for("g"));
+        broker
+            .txns
+            .add_partitions("tx", producer_id, epoch, &[offsets_partition])
+            .unwrap();
+        assert_eq!(
+            commit(&broker, &conn, producer_id, epoch),
+            [("foo".to_string(), 0, ErrorCode::NoError)]
+        );
+
+        // The offset is visible once the transaction is committed.
+        let tp = TopicPartition::new("foo", 0);
+        assert_eq!(broker.groups.fetch_offsets("g", None), []);
+        broker
+            .txns
+            .end_txn("tx", producer_id, epoch, true, &broker.groups)
+            .unwrap();
+        let offsets = broker.groups.fetch_offsets("g", None);
+        assert_eq!(offsets.len(), 1);
+        assert_eq!(offsets[0].0, tp);
+        assert_eq!(offsets[0].1.as_ref().unwrap().offset, 42);
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{
+    Broker, Result,
+    acl::{AclOperation, CLUSTER_NAME, ResourceType},
+    de::Deserializer,
+    group::GROUP_METADATA_TOPIC,
+    log::TopicPartition,
+    txn::{self, TxnMarker},
+    types::{ByteSize, CompactArray, CompactString, ControlRecordType, TaggedFields},
+};
+
+use super::{
+    API_KEY_WRITE_TXN_MARKERS, Connection, ErrorCode, Message, ResponseBody, ResponseHeader,
+    messages::write_txn_markers_request::WriteTxnMarkersRequest, partition_exists,
+    read_meta_records, read_request,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Read;
+
+pub(crate) fn run<R: Read>(
+    api_version: i16,
+    mut de: Deserializer<R>,
+    broker: &Broker,
+    conn: &Connection,
+) -> Result<Message> {
+    match api_version {
+        1 => {
+            let (correlation_id, WriteTxnMarkersRequest::V1(req_body)) =
+                read_request(api_version, &mut de)?;
+
+            let res_header = ResponseHeader::V1 {
+                correlation_id,
+                tagged_fields: TaggedFields::new(None),
+            };
+
+            // Only brokers write markers.
+            let authorized = conn.authorize(
+                broker,
+                AclOperation::ClusterAction,
+                ResourceType::Cluster,
+                CLUSTER_NAME,
+            );
+            let metadata = read_meta_records(broker).unwrap_or_default();
+            let markers = req_body
+                .markers
+                .into_iter()
+                .map(|marker| {
+                    let partitions: Vec<TopicPartition> = marker
+                        .topics
+                        .into_iter()
+                        .flat_map(|topic| {
+                            let name = topic.name.as_str().to_string();
+                            topic
+                                .partition_indexes
+                                .into_iter()
+                                .map(move |partition| TopicPartition::new(name.clone(), partition))
+                        })
+                        .collect();
+                    // `__consumer_offsets` is not in the metadata log.
+                    let (known, unknown): (Vec<_>, Vec<_>) =
+                        partitions.into_iter().partition(|tp| {
+                            tp.topic == GROUP_METADATA_TOPIC || partition_exists(&metadata, tp)
+                        });
+
+                    let marker = TxnMarker {
+                        producer_id: marker.producer_id,
+                        producer_epoch: marker.producer_epoch,
+                        result: if marker.transaction_result {
+                            ControlRecordType::Commit
+                        } else {
+                            ControlRecordType::Abort
+                        },
+                        coordinator_epoch: marker.coordinator_epoch,
+                        partitions: known,
+                    };
+                    let results: Vec<(TopicPartition, ErrorCode)> = if authorized {
+                        txn::write_txn_markers(&broker.logs, &broker.groups, &marker)
+                            .into_iter()
+                            .chain(
+                                unknown
+                                    .into_iter()
+                                    .map(|tp| (tp, ErrorCode::UnknownTopicOrPartition)),
+                            )
+                            .collect()
+                    } else {
+                        marker
+                            .partitions
+                            .iter()
+                            .cloned()
+                            .chain(unknown)
+                            .map(|tp| (tp, ErrorCode::ClusterAuthorizationFailed))
+                            .collect()
+                    };
+
+                    let mut topics: BTreeMap<String, Vec<WritableTxnMarkerPartitionResult>> =
+                        BTreeMap::new();
+                    for (tp, error_code) in results {
+                        topics.entry(tp.topic).or_default().push(
+                            WritableTxnMarkerPartitionResult {
+                                partition_index: tp.partition,
+                                error_code,
+                                tagged_fields: TaggedFields::new(None),
+                            },
+                        );
+                    }
+
+                    WritableTxnMarkerResult {
+                        producer_id: marker.producer_id,
+                        topics: topics
+                            .into_iter()
+                            .map(|(name, partitions)| WritableTxnMarkerTopicResult {
+                                name: CompactString::new(name),
+                                partitions: CompactArray::new(Some(partitions)),
+                                tagged_fields: TaggedFields::new(None),
+                            })
+                            .collect(),
+                        tagged_fields: TaggedFields::new(None),
+                    }
+                })
+                .collect();
+
+            let res_body = ResponseBody::WriteTxnMarkers(WriteTxnMarkersResponseBody {
+                markers,
+                tagged_fields: TaggedFields::new(None),
+            });
+
+            Ok(Message::new(res_header, Some(res_body)))
+        }
+        _ => Err(crate::KafkaError::UnsupportedVersion {
+            api_key: API_KEY_WRITE_TXN_MARKERS,
+            api_version,
+        }),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct WriteTxnMarkersResponseBody {
+    markers: CompactArray<WritableTxnMarkerResult>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct WritableTxnMarkerResult {
+    producer_id: i64,
+    topics: CompactArray<WritableTxnMarkerTopicResult>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct WritableTxnMarkerTopicResult {
+    name: CompactString,
+    partitions: CompactArray<WritableTxnMarkerPartitionResult>,
+    tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ByteSize)]
+pub(crate) struct WritableTxnMarkerPartitionResult {
+    partition_index: i32,
+    error_code: ErrorCode,
+    tagged_fields: TaggedFields,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{client, request};
+    use crate::types::VarintBytes;
+
+    #[test]
+    fn test_write_txn_markers() {
+        let (broker, conn) = client("write-txn-markers", "");
+        let body = [
+            &[0x02][..],                                       // markers
+            &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xe8], // producer_id
+            &[0x00, 0x00, 0x01],                               // producer_epoch, transaction_result
+            &[0x02, 0x04],
+            b"foo",                                                        // topics
+            &[0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00], // partition_indexes, tagged_fields
+            &[0x00, 0x00, 0x00, 0x00, 0x00], // coordinator_epoch, tagged_fields
+            &[0x00],                         // tagged_fields
+        ]
+        .concat();
+        let request = request(API_KEY_WRITE_TXN_MARKERS, 1, &body);
+        let message = run(1, Deserializer::new(&request[..]), &broker, &conn).unwrap();
+        let Some(ResponseBody::WriteTxnMarkers(body)) = message.into_body() else {
+            panic!("expected a WriteTxnMarkers response");
+        };
+
+        let marker = &body.markers.as_opt_slice().unwrap()[0];
+        assert_eq!(marker.producer_id, 1000);
+        let results: Vec<(i32, ErrorCode)> = marker
+            .topics
+            .as_opt_slice()
+            .unwrap()
+            .iter()
+            .flat_map(|topic| topic.partitions.as_opt_slice().unwrap())
+            .map(|partition| (partition.partition_index, partition.error_code))
+            .collect();
+        assert_eq!(
+            results,
+            [
+                (0, ErrorCode::NoError),
+                (1, ErrorCode::UnknownTopicOrPartition)
+            ]
+        );
+
+        let log = broker
+            .logs
+            .get(&TopicPartition::new("foo", 0))
+            .unwrap()
+            .unwrap();
+        let batches = log.lock().unwrap().read::<VarintBytes>().unwrap();
+        let control_types: Vec<_> = batches.iter().filter_map(|b| b.control_type()).collect();
+        assert_eq!(control_types, [ControlRecordType::Commit]);
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+}
@@ -0,0 +1,274 @@
+use crate::{
+    Config, Result,
+    acl::{Authorizer, StandardAuthorizer},
+    cluster::ClusterMetadata,
+    dynamic_config::ConfigManager,
+    fetch_session::FetchSessionCache,
+    group::GroupCoordinator,
+    log::LogManager,
+    quota::{ConnectionQuotas, QuotaManager},
+    sasl::ScramCredentials,
+    tls::TlsAcceptor,
+    txn::TransactionCoordinator,
+    util,
+};
+
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// State shared by every connection handled by this broker.
+#[derive(Debug)]
+pub struct Broker {
+    pub(crate) config: Config,
+    pub(crate) logs: Arc<LogManager>,
+    pub(crate) cluster: ClusterMetadata,
+    pub(crate) groups: GroupCoordinator,
+    pub(crate) txns: TransactionCoordinator,
+    pub(crate) fetch_sessions: FetchSessionCache,
+    pub(crate) configs: ConfigManager,
+    pub(crate) credentials: ScramCredentials,
+    pub(crate) quotas: QuotaManager,
+    pub(crate) connections: ConnectionQuotas,
+    /// Set when `authorizer.class.name` enables ACLs.
+    pub(crate) authorizer: Option<Box<dyn Authorizer>>,
+    /// The TLS acceptors of the SSL and SASL_SSL listeners, by name.
+    pub(crate) tls: HashMap<String, TlsAcceptor>,
+    /// A permit for each request which may be queued for or handled by the
+    /// request threads at once, as `queued.max.requests`.
+    pub(crate) request_permits: Semaphore,
+    /// The housekeeping threads, and the admin server when there is one.
+    background: Arc<BackgroundTasks>,
+}
+
+impl Broker {
+    /// Loads the persisted state and starts the background housekeeping
+    /// tasks. The tasks stop on shutdown, or once the returned broker is
+    /// dropped.
+    pub fn start(config: Config) -> Result<Arc<Self>> {
+        let tls = config
+            .listeners
+            .iter()
+            .filter(|listener| listener.endpoint.security_protocol.uses_tls())
+            .map(|listener| {
+                Ok((
+                    listener.endpoint.name.clone(),
+                    TlsAcceptor::new(&listener.ssl)?,
+                ))
+            })
+            .collect::<Result<_>>()?;
+        let logs = Arc::new(LogManager::new(&config.log_dir));
+        let cluster = ClusterMetadata::start(&config, Arc::clone(&logs))?;
+        let groups = GroupCoordinator::new(&config, Arc::clone(&logs));
+        groups.load()?;
+        let txns = TransactionCoordinator::new(&config, Arc::clone(&logs));
+        txns.load(&groups)?;
+        let configs = ConfigManager::new(&config, Arc::clone(&logs));
+        configs.load()?;
+        let credentials = ScramCredentials::new(Arc::clone(&logs));
+        credentials.load()?;
+        let quotas = QuotaManager::new(&config, Arc::clone(&logs));
+        quotas.load()?;
+        let authorizer: Option<Box<dyn Authorizer>> = if config.authorizer_enabled {
+            let authorizer = StandardAuthorizer::new(&config, Arc::clone(&logs));
+            authorizer.load()?;
+            Some(Box::new(authorizer))
+        } else {
+            None
+        };
+
+        let fetch_sessions =
+            FetchSessionCache::new(config.max_incremental_fetch_session_cache_slots);
+        let connections = ConnectionQuotas::new(&config);
+        let request_permits = Semaphore::new(config.queued_max_requests);
+
+        let broker = Arc::new(Self {
+            config,
+            logs,
+            cluster,
+            groups,
+            txns,
+            fetch_sessions,
+            configs,
+            credentials,
+            quotas,
+            connections,
+            authorizer,
+            tls,
+            request_permits,
+            background: Arc::default(),
+        });
+
+        spawn_periodic(
+            &broker,
+            |broker| broker.config.offsets_retention_check_interval_ms,
+            |broker| {
+                if let Err(e) = broker.groups.expire_offsets(util::now_ms()) {
+                    eprintln!("Error expiring offsets: {e}");
+                }
+            },
+        );
+        spawn_periodic(
+            &broker,
+            |broker| broker.config.group_consumer_heartbeat_interval_ms as u64,
+            |broker| {
+                if let Err(e) = broker.groups.expire_members(util::now_ms()) {
+                    eprintln!("Error expiring group members: {e}");
+                }
+            },
+        );
+        spawn_periodic(
+            &broker,
+            |broker| broker.config.producer_id_expiration_check_interval_ms,
+            |broker| {
+                let expiration_ms = broker.config.producer_id_expiration_ms;
+                if let Err(e) = broker
+                    .logs
+                    .checkpoint_producer_state(util::now_ms(), expiration_ms)
+                {
+                    eprintln!("Error checkpointing producer state: {e}");
+                }
+            },
+        );
+        spawn_periodic(
+            &broker,
+            |broker| {
+                broker
+                    .config
+                    .transaction_abort_timed_out_transaction_cleanup_interval_ms
+            },
+            |broker| {
+                broker
+                    .txns
+                    .abort_timed_out_transactions(util::now_ms(), &broker.groups);
+            },
+        );
+        spawn_periodic(
+            &broker,
+            |broker| broker.config.quota_window_num as u64 * broker.config.quota_window_size_ms,
+            |broker| broker.quotas.expire_sensors(util::now_ms()),
+        );
+
+        Ok(broker)
+    }
+
+    /// Runs `task` on a thread of its own, which shutdown stops and joins.
+    /// The task should return once [`BackgroundTasks::sleep`] reports the
+    /// broker is stopping.
+    pub(crate) fn spawn_background<F>(&self, task: F)
+    where
+        F: FnOnce(&BackgroundTasks) + Send + 'static,
+    {
+        let background = Arc::clone(&self.background);
+        let thread = std::thread::spawn(move || task(&background));
+        self.background.threads.lock().unwrap().push(thread);
+    }
+
+    /// Stops and joins the background threads, then flushes the logs to
+    /// disk and checkpoints their state: the producer state snapshots and
+    /// the recovery points. The metadata log is flushed with the others; it
+    /// is replayed from its start when the broker starts, so no snapshot of
+    /// it is written. Called once no more requests are handled.
+    pub(crate) fn shutdown(&self) -> Result<()> {
+        self.background.stop();
+        self.logs
+            .checkpoint_producer_state(util::now_ms(), self.config.producer_id_expiration_ms)?;
+        self.logs.flush()?;
+        self.logs.write_recovery_checkpoint()
+    }
+}
+
+#[cfg(test)]
+impl Broker {
+    /// Starts a broker with `properties` for the test `name`, in a log
+    /// directory of its own which starts out empty.
+    pub(crate) fn start_for_test(name: &str, properties: &str) -> Arc<Self> {
+        let dir =
+            std::env::temp_dir().join(format!("kafka-broker-test-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let properties = format!("log.dirs={}\n{properties}", dir.display());
+        Self::start(Config::from_properties(&properties).unwrap()).unwrap()
+    }
+}
+
+/// Threads running alongside the request handlers until the broker shuts
+/// down.
+#[derive(Debug, Default)]
+pub(crate) struct BackgroundTasks {
+    stopping: Mutex<bool>,
+    wake: Condvar,
+    threads: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl BackgroundTasks {
+    /// Sleeps for `timeout`, or less if the broker starts stopping, and
+    /// returns whether it is stopping.
+    pub(crate) fn sleep(&self, timeout: Duration) -> bool {
+        let stopping = self.stopping.lock().unwrap();
+        let (stopping, _) = self
+            .wake
+            .wait_timeout_while(stopping, timeout, |stopping| !*stopping)
+            .unwrap();
+        *stopping
+    }
+
+    /// Wakes every thread and waits for them to return.
+    fn stop(&self) {
+        *self.stopping.lock().unwrap() = true;
+        self.wake.notify_all();
+        let threads = std::mem::take(&mut *self.threads.lock().unwrap());
+        for thread in threads {
+            if thread.join().is_err() {
+                eprintln!("A background task panicked");
+            }
+        }
+    }
+}
+
+/// Runs `task` every `interval` milliseconds until the broker shuts down or
+/// is dropped.
+fn spawn_periodic<I, F>(broker: &Arc<Broker>, interval: I, task: F)
+where
+    I: Fn(&Broker) -> u64 + Send + 'static,
+    F: Fn(&Broker) + Send + 'static,
+{
+    let weak: Weak<Broker> = Arc::downgrade(broker);
+    broker.spawn_background(move |background| {
+        loop {
+            let interval = match weak.upgrade() {
+                Some(broker) => interval(&broker),
+                None => break,
+            };
+            if background.sleep(Duration::from_millis(interval)) {
+                break;
+            }
+
+            let Some(broker) = weak.upgrade() else {
+                break;
+            };
+            task(&broker);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shutdown_joins_background_tasks() {
+        let broker = Broker::start_for_test("shutdown", "");
+        let (stopped, joined) = std::sync::mpsc::channel();
+        broker.spawn_background(move |background| {
+            while !background.sleep(Duration::from_secs(3600)) {}
+            stopped.send(()).unwrap();
+        });
+
+        broker.shutdown().unwrap();
+        assert!(joined.try_recv().is_ok());
+        assert!(broker.background.threads.lock().unwrap().is_empty());
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+}
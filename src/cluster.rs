@@ -0,0 +1,251 @@
+use crate::{
+    Config, Result,
+    config::parse_properties,
+    group::GROUP_METADATA_TOPIC,
+    log::LogManager,
+    txn::TRANSACTION_STATE_TOPIC,
+    types::{
+        BrokerEndpoint, CompactArray, CompactNullableString, CompactString, Partition,
+        RecordVariant, RegisterBrokerRecord, TaggedFields, Topic, Uuid,
+    },
+};
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+const META_PROPERTIES: &str = "meta.properties";
+
+/// A broker as clients see it: the endpoint of its latest registration.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BrokerRegistration {
+    pub(crate) broker_id: i32,
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) rack: Option<String>,
+    pub(crate) fenced: bool,
+}
+
+/// The identity of the cluster and the brokers registered with it.
+#[derive(Debug)]
+pub(crate) struct ClusterMetadata {
+    cluster_id: String,
+    node_id: i32,
+    logs: Arc<LogManager>,
+}
+
+impl ClusterMetadata {
+    /// Reads the cluster ID of the log directory, formatting it first if it
+    /// has none, and registers this broker in the metadata log.
+    pub(crate) fn start(config: &Config, logs: Arc<LogManager>) -> Result<Self> {
+        let cluster = Self {
+            cluster_id: cluster_id(&config.log_dir, config.node_id)?,
+            node_id: config.node_id,
+            logs,
+        };
+        cluster.register(config)?;
+        cluster.register_internal_topics(config)?;
+        Ok(cluster)
+    }
+
+    pub(crate) fn cluster_id(&self) -> &str {
+        &self.cluster_id
+    }
+
+    /// This broker acts as the controller of its single-node cluster.
+    pub(crate) fn controller_id(&self) -> i32 {
+        self.node_id
+    }
+
+    /// The brokers of the cluster, by ID, as of their latest registration,
+    /// with their endpoint for clients of the listener `listener_name`.
+    pub(crate) fn brokers(&self, listener_name: &str) -> Result<Vec<BrokerRegistration>> {
+        let mut brokers = BTreeMap::new();
+        for value in self.logs.read_metadata()? {
+            if let RecordVariant::RegisterBroker(registration) = value.value {
+                brokers.insert(registration.broker_id, registration);
+            }
+        }
+
+        Ok(brokers
+            .into_values()
+            .filter_map(|registration| {
+                let end_points = registration.end_points.as_opt_slice().unwrap_or_default();
+                let endpoint = end_points
+                    .iter()
+                    .find(|endpoint| endpoint.name.as_str() == listener_name)?;
+                Some(BrokerRegistration {
+                    broker_id: registration.broker_id,
+                    host: endpoint.host.as_str().to_string(),
+                    port: endpoint.port,
+                    rack: registration.rack.as_ref().cloned(),
+                    fenced: registration.fenced,
+                })
+            })
+            .collect())
+    }
+
+    /// Appends the registration of this broker. Its epoch is the offset of
+    /// the record, as in KRaft.
+    fn register(&self, config: &Config) -> Result<()> {
+        let end_points: Vec<BrokerEndpoint> = config
+            .advertised_listeners()
+            .into_iter()
+            .map(|endpoint| {
+                let host = match endpoint.host.as_str() {
+                    "" | "0.0.0.0" => "localhost",
+                    host => host,
+                };
+                BrokerEndpoint {
+                    name: CompactString::new(endpoint.name.clone()),
+                    host: CompactString::new(host.to_string()),
+                    port: endpoint.port,
+                    security_protocol: endpoint.security_protocol as i16,
+                    tagged_fields: TaggedFields::new(None),
+                }
+            })
+            .collect();
+        self.logs.append_metadata_with(|offset| {
+            vec![RecordVariant::RegisterBroker(RegisterBrokerRecord {
+                broker_id: self.node_id,
+                is_migrating_zk_broker: false,
+                incarnation_id: Uuid::random(),
+                broker_epoch: offset,
+                end_points: CompactArray::new(Some(end_points)),
+                features: CompactArray::new(Some(vec![])),
+                rack: CompactNullableString::new(config.rack.clone()),
+                fenced: false,
+                in_controlled_shutdown: false,
+                log_dirs: CompactArray::new(Some(vec![])),
+            })]
+        })
+    }
+
+    /// Appends the topic and partition records of the internal topics which
+    /// are not in the metadata log yet, led by this broker, so they are
+    /// described like any other topic.
+    fn register_internal_topics(&self, config: &Config) -> Result<()> {
+        let registered: Vec<String> = self
+            .logs
+            .read_metadata()?
+            .into_iter()
+            .filter_map(|value| match value.value {
+                RecordVariant::Topic(topic) => Some(topic.name.as_str().to_string()),
+                _ => None,
+            })
+            .collect();
+
+        let mut records = vec![];
+        for (name, num_partitions) in [
+            (GROUP_METADATA_TOPIC, config.offsets_topic_num_partitions),
+            (
+                TRANSACTION_STATE_TOPIC,
+                config.transaction_state_log_num_partitions,
+            ),
+        ] {
+            if registered.iter().any(|registered| registered == name) {
+                continue;
+            }
+            let topic_id = Uuid::random();
+            records.push(RecordVariant::Topic(Topic {
+                name: CompactString::new(name.to_string()),
+                topic_id,
+            }));
+            records.extend((0..num_partitions).map(|partition_id| {
+                RecordVariant::Partition(Partition {
+                    partition_id,
+                    topic_id,
+                    replicas: CompactArray::new(Some(vec![self.node_id])),
+                    isr: CompactArray::new(Some(vec![self.node_id])),
+                    removing_replicas: CompactArray::new(Some(vec![])),
+                    adding_replicas: CompactArray::new(Some(vec![])),
+                    leader: self.node_id,
+                    leader_epoch: 0,
+                    partition_epoch: 0,
+                    directories: CompactArray::new(Some(vec![])),
+                })
+            }));
+        }
+        self.logs.append_metadata(records)
+    }
+}
+
+/// Whether `name` is one of the topics the broker keeps its own state in,
+/// which clients may read but not write.
+pub(crate) fn is_internal_topic(name: &str) -> bool {
+    name == GROUP_METADATA_TOPIC || name == TRANSACTION_STATE_TOPIC
+}
+
+/// Reads `cluster.id` from the `meta.properties` of the log directory, or
+/// writes a new one there.
+fn cluster_id(log_dir: &Path, node_id: i32) -> Result<String> {
+    let path = log_dir.join(META_PROPERTIES);
+    if path.exists() {
+        let properties = parse_properties(&std::fs::read_to_string(&path)?);
+        if let Some(cluster_id) = properties.get("cluster.id") {
+            return Ok(cluster_id.clone());
+        }
+    }
+
+    let cluster_id = Uuid::random().to_string();
+    std::fs::create_dir_all(log_dir)?;
+    std::fs::write(
+        &path,
+        format!("version=1\ncluster.id={cluster_id}\nnode.id={node_id}\n"),
+    )?;
+    Ok(cluster_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_broker() {
+        let dir = std::env::temp_dir().join(format!("kafka-cluster-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let contents = format!(
+            "log.dirs={}\nlisteners=PLAINTEXT://:19092,SSL://kafka.local:19093\nbroker.rack=r1",
+            dir.display()
+        );
+        let config = Config::from_properties(&contents).unwrap();
+
+        let cluster = ClusterMetadata::start(&config, Arc::new(LogManager::new(&dir))).unwrap();
+        let cluster_id = cluster.cluster_id().to_string();
+        assert_eq!(
+            cluster.brokers("PLAINTEXT").unwrap(),
+            [BrokerRegistration {
+                broker_id: 1,
+                host: "localhost".to_string(),
+                port: 19092,
+                rack: Some("r1".to_string()),
+                fenced: false,
+            }]
+        );
+
+        // A restart keeps the cluster ID and replaces the registration.
+        let cluster = ClusterMetadata::start(&config, Arc::new(LogManager::new(&dir))).unwrap();
+        assert_eq!(cluster.cluster_id(), cluster_id);
+        assert_eq!(cluster.brokers("SSL").unwrap()[0].host, "kafka.local");
+        assert_eq!(cluster.brokers("SSL").unwrap().len(), 1);
+        assert!(cluster.brokers("INTERNAL").unwrap().is_empty());
+
+        // The internal topics are registered once.
+        let metadata = cluster.logs.read_metadata().unwrap();
+        let topics: Vec<&str> = metadata
+            .iter()
+            .filter_map(|value| match &value.value {
+                RecordVariant::Topic(topic) => Some(topic.name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(topics, [GROUP_METADATA_TOPIC, TRANSACTION_STATE_TOPIC]);
+        let partitions = metadata
+            .iter()
+            .filter(|value| matches!(value.value, RecordVariant::Partition(_)))
+            .count();
+        assert_eq!(partitions, 100);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,728 @@
+use crate::{KafkaError, Result};
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_LOG_DIR: &str = "/tmp/kraft-combined-logs";
+/// The `authorizer.class.name` enabling ACLs, as in KRaft clusters.
+const STANDARD_AUTHORIZER: &str = "org.apache.kafka.metadata.authorizer.StandardAuthorizer";
+const DEFAULT_SECURITY_PROTOCOL_MAP: &str =
+    "PLAINTEXT:PLAINTEXT,SSL:SSL,SASL_PLAINTEXT:SASL_PLAINTEXT,SASL_SSL:SASL_SSL";
+
+/// The security protocol of a listener.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SecurityProtocol {
+    Plaintext = 0,
+    Ssl = 1,
+    SaslPlaintext = 2,
+    SaslSsl = 3,
+}
+
+impl SecurityProtocol {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "PLAINTEXT" => Some(Self::Plaintext),
+            "SSL" => Some(Self::Ssl),
+            "SASL_PLAINTEXT" => Some(Self::SaslPlaintext),
+            "SASL_SSL" => Some(Self::SaslSsl),
+            _ => None,
+        }
+    }
+
+    /// Whether clients must authenticate with SASL first.
+    pub(crate) fn uses_sasl(self) -> bool {
+        matches!(self, Self::SaslPlaintext | Self::SaslSsl)
+    }
+
+    pub(crate) fn uses_tls(self) -> bool {
+        matches!(self, Self::Ssl | Self::SaslSsl)
+    }
+}
+
+/// A listener of the form `NAME://host:port`, as in `listeners`. The
+/// security protocol of the name comes from `listener.security.protocol.map`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Endpoint {
+    pub(crate) name: String,
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) security_protocol: SecurityProtocol,
+}
+
+impl Endpoint {
+    /// Parses the comma-separated endpoints of `key`.
+    fn parse_list(
+        value: &str,
+        key: &str,
+        protocols: &HashMap<String, SecurityProtocol>,
+    ) -> Result<Vec<Self>> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| Self::parse(v, key, protocols))
+            .collect()
+    }
+
+    fn parse(
+        value: &str,
+        key: &str,
+        protocols: &HashMap<String, SecurityProtocol>,
+    ) -> Result<Self> {
+        let invalid = || KafkaError::InvalidConfig(format!("invalid value for {key}: {value}"));
+
+        let (name, address) = value.split_once("://").ok_or_else(invalid)?;
+        let (host, port) = address.rsplit_once(':').ok_or_else(invalid)?;
+        let security_protocol = *protocols.get(name).ok_or_else(|| {
+            KafkaError::InvalidConfig(format!("no security protocol defined for listener {name}"))
+        })?;
+        Ok(Self {
+            name: name.to_string(),
+            host: host.to_string(),
+            port: port.parse().map_err(|_| invalid())?,
+            security_protocol,
+        })
+    }
+
+    /// The address to bind. An empty host binds every interface.
+    pub(crate) fn bind_address(&self) -> String {
+        let host = if self.host.is_empty() {
+            "0.0.0.0"
+        } else {
+            &self.host
+        };
+        format!("{host}:{}", self.port)
+    }
+}
+
+/// Whether TLS listeners ask clients for a certificate, from
+/// `ssl.client.auth`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum ClientAuth {
+    #[default]
+    None,
+    Requested,
+    Required,
+}
+
+/// The certificates of TLS listeners, from the `ssl.*` properties. Only
+/// PEM stores are supported.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SslConfig {
+    /// A PEM file with the private key and the certificate chain of the
+    /// broker.
+    pub(crate) keystore_location: Option<PathBuf>,
+    /// A PEM file with the CA certificates client certificates are verified
+    /// against.
+    pub(crate) truststore_location: Option<PathBuf>,
+    pub(crate) client_auth: ClientAuth,
+}
+
+/// A listener with the security settings of its clients. Each setting can
+/// be given for a single listener with the `listener.name.<name>.` prefix.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Listener {
+    pub(crate) endpoint: Endpoint,
+    pub(crate) ssl: SslConfig,
+    /// The SASL mechanisms clients may authenticate with.
+    pub(crate) sasl_enabled_mechanisms: Vec<String>,
+    /// The users SASL/PLAIN accepts, with their passwords, from the
+    /// `user_<name>` options of the JAAS config.
+    pub(crate) sasl_plain_users: HashMap<String, String>,
+}
+
+impl Listener {
+    fn new(endpoint: Endpoint) -> Self {
+        Self {
+            endpoint,
+            ssl: SslConfig::default(),
+            sasl_enabled_mechanisms: vec![
+                "PLAIN".to_string(),
+                "SCRAM-SHA-256".to_string(),
+                "SCRAM-SHA-512".to_string(),
+            ],
+            sasl_plain_users: HashMap::new(),
+        }
+    }
+
+    fn from_properties(endpoint: Endpoint, props: &HashMap<String, String>) -> Result<Self> {
+        let prefix = format!("listener.name.{}.", endpoint.name.to_lowercase());
+        let prop = |key: &str| {
+            props
+                .get(&format!("{prefix}{key}"))
+                .or_else(|| props.get(key))
+        };
+
+        let mut listener = Self::new(endpoint);
+        for key in ["ssl.keystore.type", "ssl.truststore.type"] {
+            if let Some(v) = prop(key)
+                && v != "PEM"
+            {
+                return Err(KafkaError::InvalidConfig(format!(
+                    "unsupported {key}: {v}, only PEM is supported"
+                )));
+            }
+        }
+        if let Some(v) = prop("ssl.keystore.location") {
+            listener.ssl.keystore_location = Some(PathBuf::from(v));
+        }
+        if let Some(v) = prop("ssl.truststore.location") {
+            listener.ssl.truststore_location = Some(PathBuf::from(v));
+        }
+        if let Some(v) = prop("ssl.client.auth") {
+            listener.ssl.client_auth = match v.as_str() {
+                "none" => ClientAuth::None,
+                "requested" => ClientAuth::Requested,
+                "required" => ClientAuth::Required,
+                _ => {
+                    return Err(KafkaError::InvalidConfig(format!(
+                        "invalid value for ssl.client.auth: {v}"
+                    )));
+                }
+            };
+        }
+        if let Some(v) = prop("sasl.enabled.mechanisms") {
+            listener.sasl_enabled_mechanisms = v
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect();
+        }
+        if let Some(v) = prop("plain.sasl.jaas.config").or_else(|| props.get("sasl.jaas.config")) {
+            listener.sasl_plain_users = jaas_users(v);
+        }
+        Ok(listener)
+    }
+}
+
+/// Broker settings read from a `server.properties` file. Keys that are not
+/// understood are ignored.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub(crate) node_id: i32,
+    pub(crate) log_dir: PathBuf,
+    pub(crate) offsets_topic_num_partitions: i32,
+    pub(crate) offsets_retention_ms: i64,
+    pub(crate) offsets_retention_check_interval_ms: u64,
+    pub(crate) offset_metadata_max_bytes: usize,
+    pub(crate) group_consumer_heartbeat_interval_ms: i32,
+    pub(crate) group_consumer_session_timeout_ms: i64,
+    pub(crate) group_consumer_assignors: Vec<String>,
+    pub(crate) group_consumer_max_size: usize,
+    pub(crate) producer_id_expiration_ms: i64,
+    pub(crate) producer_id_expiration_check_interval_ms: u64,
+    pub(crate) transaction_state_log_num_partitions: i32,
+    pub(crate) transaction_max_timeout_ms: i32,
+    pub(crate) transaction_abort_timed_out_transaction_cleanup_interval_ms: u64,
+    pub(crate) max_incremental_fetch_session_cache_slots: usize,
+    pub(crate) admin_listener: Option<String>,
+    pub(crate) listeners: Vec<Listener>,
+    /// The endpoints registered for clients, by listener name, where they
+    /// differ from `listeners`.
+    pub(crate) advertised_listeners: Vec<Endpoint>,
+    /// Listeners of the controller, which are not advertised to clients.
+    pub(crate) controller_listener_names: Vec<String>,
+    pub(crate) rack: Option<String>,
+    /// Whether requests are authorized by ACLs, set by `authorizer.class.name`.
+    pub(crate) authorizer_enabled: bool,
+    /// Principals allowed every operation, from the `;`-separated
+    /// `super.users`.
+    pub(crate) super_users: Vec<String>,
+    pub(crate) allow_everyone_if_no_acl_found: bool,
+    /// Client quotas are measured over this many windows of
+    /// `quota_window_size_ms`.
+    pub(crate) quota_window_num: u32,
+    pub(crate) quota_window_size_ms: u64,
+    /// Threads handling requests, and requests which may wait for them.
+    pub(crate) num_io_threads: usize,
+    pub(crate) queued_max_requests: usize,
+    /// Requests larger than this close their connection.
+    pub(crate) socket_request_max_bytes: usize,
+    pub(crate) max_connections: usize,
+    pub(crate) max_connections_per_ip: usize,
+    /// Connections which send no request for this long are closed.
+    pub(crate) connections_max_idle_ms: u64,
+    /// How long a shutdown waits for in-flight requests before closing
+    /// their connections.
+    pub(crate) shutdown_timeout_ms: u64,
+    /// Every property of the file, reported as static broker configs.
+    pub(crate) properties: HashMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            node_id: 1,
+            log_dir: PathBuf::from(DEFAULT_LOG_DIR),
+            offsets_topic_num_partitions: 50,
+            offsets_retention_ms: 10080 * 60 * 1000,
+            offsets_retention_check_interval_ms: 600_000,
+            offset_metadata_max_bytes: 4096,
+            group_consumer_heartbeat_interval_ms: 5000,
+            group_consumer_session_timeout_ms: 45000,
+            group_consumer_assignors: vec!["uniform".to_string(), "range".to_string()],
+            group_consumer_max_size: i32::MAX as usize,
+            producer_id_expiration_ms: 86_400_000,
+            producer_id_expiration_check_interval_ms: 600_000,
+            transaction_state_log_num_partitions: 50,
+            transaction_max_timeout_ms: 900_000,
+            transaction_abort_timed_out_transaction_cleanup_interval_ms: 10_000,
+            max_incremental_fetch_session_cache_slots: 1000,
+            admin_listener: None,
+            listeners: vec![Listener::new(Endpoint {
+                name: "PLAINTEXT".to_string(),
+                host: "127.0.0.1".to_string(),
+                port: 9092,
+                security_protocol: SecurityProtocol::Plaintext,
+            })],
+            advertised_listeners: vec![],
+            controller_listener_names: vec![],
+            rack: None,
+            authorizer_enabled: false,
+            super_users: vec![],
+            allow_everyone_if_no_acl_found: false,
+            quota_window_num: 11,
+            quota_window_size_ms: 1000,
+            num_io_threads: 8,
+            queued_max_requests: 500,
+            socket_request_max_bytes: 104_857_600,
+            max_connections: i32::MAX as usize,
+            max_connections_per_ip: i32::MAX as usize,
+            connections_max_idle_ms: 600_000,
+            shutdown_timeout_ms: 30_000,
+            properties: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::from_properties(&contents)
+    }
+
+    /// Address of the HTTP admin endpoint, if enabled via `admin.listener`.
+    /// The endpoint is not authenticated, so without a host it only listens
+    /// on localhost.
+    pub fn admin_listener(&self) -> Option<&str> {
+        self.admin_listener.as_deref()
+    }
+
+    /// The endpoints clients should connect to, one per listener which is
+    /// not a controller listener.
+    pub(crate) fn advertised_listeners(&self) -> Vec<&Endpoint> {
+        self.listeners
+            .iter()
+            .map(|listener| &listener.endpoint)
+            .filter(|endpoint| !self.controller_listener_names.contains(&endpoint.name))
+            .map(|endpoint| {
+                self.advertised_listeners
+                    .iter()
+                    .find(|advertised| advertised.name == endpoint.name)
+                    .unwrap_or(endpoint)
+            })
+            .collect()
+    }
+
+    pub(crate) fn from_properties(contents: &str) -> Result<Self> {
+        let props = parse_properties(contents);
+
+        let mut config = Self::default();
+
+        if let Some(v) = props.get("node.id") {
+            config.node_id = parse(v, "node.id")?;
+        }
+        if let Some(v) = props.get("log.dirs").or_else(|| props.get("log.dir")) {
+            // Only a single log directory is supported.
+            let dir = v.split(',').next().unwrap_or(DEFAULT_LOG_DIR);
+            config.log_dir = PathBuf::from(dir.trim());
+        }
+        if let Some(v) = props.get("offsets.topic.num.partitions") {
+            config.offsets_topic_num_partitions = parse(v, "offsets.topic.num.partitions")?;
+        }
+        if let Some(v) = props.get("offsets.retention.minutes") {
+            let minutes: i64 = parse(v, "offsets.retention.minutes")?;
+            config.offsets_retention_ms = minutes * 60 * 1000;
+        }
+        if let Some(v) = props.get("offsets.retention.check.interval.ms") {
+            config.offsets_retention_check_interval_ms =
+                parse(v, "offsets.retention.check.interval.ms")?;
+        }
+        if let Some(v) = props.get("offset.metadata.max.bytes") {
+            config.offset_metadata_max_bytes = parse(v, "offset.metadata.max.bytes")?;
+        }
+        if let Some(v) = props.get("group.consumer.heartbeat.interval.ms") {
+            config.group_consumer_heartbeat_interval_ms =
+                parse(v, "group.consumer.heartbeat.interval.ms")?;
+        }
+        if let Some(v) = props.get("group.consumer.session.timeout.ms") {
+            config.group_consumer_session_timeout_ms =
+                parse(v, "group.consumer.session.timeout.ms")?;
+        }
+        if let Some(v) = props.get("group.consumer.assignors") {
+            config.group_consumer_assignors = v
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect();
+        }
+        if let Some(v) = props.get("group.consumer.max.size") {
+            config.group_consumer_max_size = parse(v, "group.consumer.max.size")?;
+        }
+        if let Some(v) = props.get("producer.id.expiration.ms") {
+            config.producer_id_expiration_ms = parse(v, "producer.id.expiration.ms")?;
+        }
+        if let Some(v) = props.get("producer.id.expiration.check.interval.ms") {
+            config.producer_id_expiration_check_interval_ms =
+                parse(v, "producer.id.expiration.check.interval.ms")?;
+        }
+        if let Some(v) = props.get("transaction.state.log.num.partitions") {
+            config.transaction_state_log_num_partitions =
+                parse(v, "transaction.state.log.num.partitions")?;
+        }
+        if let Some(v) = props.get("transaction.max.timeout.ms") {
+            config.transaction_max_timeout_ms = parse(v, "transaction.max.timeout.ms")?;
+        }
+        if let Some(v) = props.get("transaction.abort.timed.out.transaction.cleanup.interval.ms") {
+            config.transaction_abort_timed_out_transaction_cleanup_interval_ms = parse(
+                v,
+                "transaction.abort.timed.out.transaction.cleanup.interval.ms",
+            )?;
+        }
+        if let Some(v) = props.get("max.incremental.fetch.session.cache.slots") {
+            config.max_incremental_fetch_session_cache_slots =
+                parse(v, "max.incremental.fetch.session.cache.slots")?;
+        }
+        if let Some(v) = props.get("admin.listener") {
+            config.admin_listener = admin_address(v);
+        }
+        let protocols = security_protocol_map(
+            props
+                .get("listener.security.protocol.map")
+                .map_or(DEFAULT_SECURITY_PROTOCOL_MAP, String::as_str),
+        )?;
+        let endpoints = match props.get("listeners") {
+            Some(v) => Endpoint::parse_list(v, "listeners", &protocols)?,
+            None => vec![config.listeners[0].endpoint.clone()],
+        };
+        if endpoints.is_empty() {
+            return Err(KafkaError::InvalidConfig(
+                "listeners must not be empty".to_string(),
+            ));
+        }
+        for (i, endpoint) in endpoints.iter().enumerate() {
+            if endpoints[..i]
+                .iter()
+                .any(|other| other.name == endpoint.name || other.port == endpoint.port)
+            {
+                return Err(KafkaError::InvalidConfig(format!(
+                    "each listener must have a different name and port: {}",
+                    endpoint.name
+                )));
+            }
+        }
+        config.listeners = endpoints
+            .into_iter()
+            .map(|endpoint| Listener::from_properties(endpoint, &props))
+            .collect::<Result<_>>()?;
+        if let Some(v) = props.get("advertised.listeners") {
+            config.advertised_listeners =
+                Endpoint::parse_list(v, "advertised.listeners", &protocols)?;
+        }
+        if let Some(v) = props.get("controller.listener.names") {
+            config.controller_listener_names = v
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect();
+        }
+        if let Some(v) = props.get("broker.rack") {
+            config.rack = Some(v.clone()).filter(|v| !v.is_empty());
+        }
+        if let Some(v) = props.get("authorizer.class.name") {
+            config.authorizer_enabled = match v.as_str() {
+                "" => false,
+                STANDARD_AUTHORIZER => true,
+                _ => {
+                    return Err(KafkaError::InvalidConfig(format!(
+                        "unsupported authorizer.class.name: {v}"
+                    )));
+                }
+            };
+        }
+        if let Some(v) = props.get("super.users") {
+            config.super_users = v
+                .split(';')
+                .map(|principal| principal.trim().to_string())
+                .filter(|principal| !principal.is_empty())
+                .collect();
+        }
+        if let Some(v) = props.get("allow.everyone.if.no.acl.found") {
+            config.allow_everyone_if_no_acl_found = parse(v, "allow.everyone.if.no.acl.found")?;
+        }
+        if let Some(v) = props.get("quota.window.num") {
+            config.quota_window_num = parse(v, "quota.window.num")?;
+        }
+        if let Some(v) = props.get("quota.window.size.seconds") {
+            let seconds: u64 = parse(v, "quota.window.size.seconds")?;
+            config.quota_window_size_ms = seconds * 1000;
+        }
+        if let Some(v) = props.get("num.io.threads") {
+            config.num_io_threads = parse(v, "num.io.threads")?;
+        }
+        if let Some(v) = props.get("queued.max.requests") {
+            config.queued_max_requests = parse(v, "queued.max.requests")?;
+        }
+        if let Some(v) = props.get("socket.request.max.bytes") {
+            config.socket_request_max_bytes = parse(v, "socket.request.max.bytes")?;
+        }
+        if let Some(v) = props.get("max.connections") {
+            config.max_connections = parse(v, "max.connections")?;
+        }
+        if let Some(v) = props.get("max.connections.per.ip") {
+            config.max_connections_per_ip = parse(v, "max.connections.per.ip")?;
+        }
+        if let Some(v) = props.get("connections.max.idle.ms") {
+            config.connections_max_idle_ms = parse(v, "connections.max.idle.ms")?;
+        }
+        if let Some(v) = props.get("shutdown.timeout.ms") {
+            config.shutdown_timeout_ms = parse(v, "shutdown.timeout.ms")?;
+        }
+
+        if config.offsets_topic_num_partitions <= 0 {
+            return Err(KafkaError::InvalidConfig(
+                "offsets.topic.num.partitions must be positive".to_string(),
+            ));
+        }
+        if config.transaction_state_log_num_partitions <= 0 {
+            return Err(KafkaError::InvalidConfig(
+                "transaction.state.log.num.partitions must be positive".to_string(),
+            ));
+        }
+        if config.quota_window_num == 0 || config.quota_window_size_ms == 0 {
+            return Err(KafkaError::InvalidConfig(
+                "quota.window.num and quota.window.size.seconds must be positive".to_string(),
+            ));
+        }
+        if config.num_io_threads == 0 || config.queued_max_requests == 0 {
+            return Err(KafkaError::InvalidConfig(
+                "num.io.threads and queued.max.requests must be positive".to_string(),
+            ));
+        }
+        if config.socket_request_max_bytes == 0 || config.max_connections_per_ip == 0 {
+            return Err(KafkaError::InvalidConfig(
+                "socket.request.max.bytes and max.connections.per.ip must be positive".to_string(),
+            ));
+        }
+        config.properties = props;
+
+        Ok(config)
+    }
+}
+
+/// Reads the `key=value` lines of a Java properties file.
+pub(crate) fn parse_properties(contents: &str) -> HashMap<String, String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('!'))
+        .filter_map(|line| {
+            line.split_once(['=', ':'])
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Reads `listener.security.protocol.map`, such as
+/// `CLIENT:SASL_SSL,CONTROLLER:PLAINTEXT`.
+fn security_protocol_map(value: &str) -> Result<HashMap<String, SecurityProtocol>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let invalid = || {
+                KafkaError::InvalidConfig(format!(
+                    "invalid value for listener.security.protocol.map: {entry}"
+                ))
+            };
+            let (name, protocol) = entry.split_once(':').ok_or_else(invalid)?;
+            let protocol = SecurityProtocol::from_name(protocol.trim()).ok_or_else(invalid)?;
+            Ok((name.trim().to_string(), protocol))
+        })
+        .collect()
+}
+
+/// The `user_<name>="<password>"` options of a JAAS config such as
+/// `PlainLoginModule required user_alice="alice-secret";`.
+fn jaas_users(value: &str) -> HashMap<String, String> {
+    let mut users = HashMap::new();
+    let mut rest = value;
+    while let Some((option, after)) = rest.split_once("=\"") {
+        let Some((password, after)) = after.split_once('"') else {
+            break;
+        };
+        let key = option
+            .rsplit(char::is_whitespace)
+            .next()
+            .unwrap_or_default();
+        if let Some(user) = key.strip_prefix("user_") {
+            users.insert(user.to_string(), password.to_string());
+        }
+        rest = after;
+    }
+    users
+}
+
+/// The address of `admin.listener`, which is a port or a host and port.
+fn admin_address(value: &str) -> Option<String> {
+    match value.rsplit_once(':') {
+        _ if value.is_empty() => None,
+        None | Some(("", _)) => Some(format!("127.0.0.1:{}", value.trim_start_matches(':'))),
+        Some(_) => Some(value.to_string()),
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &str, key: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| KafkaError::InvalidConfig(format!("invalid value for {key}: {value}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_properties() {
+        let contents = "\
+# comment
+node.id=3
+log.dirs=/var/lib/kafka,/var/lib/kafka2
+offsets.retention.minutes = 5
+admin.listener=127.0.0.1:9093
+listeners=PLAINTEXT://:19092
+advertised.listeners=PLAINTEXT://kafka.local:19092
+";
+        let config = Config::from_properties(contents).unwrap();
+        assert_eq!(config.node_id, 3);
+        assert_eq!(config.log_dir, PathBuf::from("/var/lib/kafka"));
+        assert_eq!(config.offsets_retention_ms, 5 * 60 * 1000);
+        assert_eq!(config.offsets_topic_num_partitions, 50);
+        assert_eq!(config.admin_listener(), Some("127.0.0.1:9093"));
+        assert_eq!(config.listeners[0].endpoint.bind_address(), "0.0.0.0:19092");
+        assert_eq!(config.advertised_listeners()[0].host, "kafka.local");
+
+        // Without a host, the admin endpoint only listens on localhost.
+        for port in ["9093", ":9093"] {
+            let config = Config::from_properties(&format!("admin.listener={port}")).unwrap();
+            assert_eq!(config.admin_listener(), Some("127.0.0.1:9093"));
+        }
+        let config = Config::from_properties("admin.listener=0.0.0.0:9093").unwrap();
+        assert_eq!(config.admin_listener(), Some("0.0.0.0:9093"));
+    }
+
+    #[test]
+    fn test_from_properties_invalid_value() {
+        assert!(Config::from_properties("node.id=abc").is_err());
+        assert!(Config::from_properties("offsets.topic.num.partitions=0").is_err());
+        assert!(Config::from_properties("listeners=INTERNAL://:9092").is_err());
+        assert!(Config::from_properties("ssl.keystore.type=JKS").is_err());
+        assert!(Config::from_properties("ssl.client.auth=always").is_err());
+        assert!(Config::from_properties("listeners=PLAINTEXT://:9092,PLAINTEXT://:9093").is_err());
+        assert!(Config::from_properties("listeners=PLAINTEXT://:9092,SSL://:9092").is_err());
+        assert!(Config::from_properties("listener.security.protocol.map=CLIENT:TLS").is_err());
+        assert!(Config::from_properties("quota.window.num=0").is_err());
+        assert!(Config::from_properties("num.io.threads=0").is_err());
+        assert!(Config::from_properties("max.connections.per.ip=0").is_err());
+    }
+
+    #[test]
+    fn test_from_properties_ssl() {
+        let contents = "\
+listeners=SSL://:9093
+ssl.keystore.type=PEM
+ssl.keystore.location=/etc/kafka/broker.pem
+ssl.truststore.location=/etc/kafka/ca.pem
+ssl.client.auth=required
+";
+        let config = Config::from_properties(contents).unwrap();
+        assert_eq!(
+            config.listeners[0].endpoint.security_protocol,
+            SecurityProtocol::Ssl
+        );
+        assert_eq!(
+            config.listeners[0].ssl,
+            SslConfig {
+                keystore_location: Some(PathBuf::from("/etc/kafka/broker.pem")),
+                truststore_location: Some(PathBuf::from("/etc/kafka/ca.pem")),
+                client_auth: ClientAuth::Required,
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_properties_sasl() {
+        let contents = r#"
+listeners=SASL_PLAINTEXT://:9092
+sasl.enabled.mechanisms=PLAIN
+listener.name.sasl_plaintext.plain.sasl.jaas.config=org.apache.kafka.common.security.plain.PlainLoginModule required username="admin" password="admin-secret" user_admin="admin-secret" user_alice="alice-secret";
+"#;
+        let config = Config::from_properties(contents).unwrap();
+        assert_eq!(config.listeners[0].sasl_enabled_mechanisms, ["PLAIN"]);
+        assert_eq!(
+            config.listeners[0].sasl_plain_users,
+            HashMap::from([
+                ("admin".to_string(), "admin-secret".to_string()),
+                ("alice".to_string(), "alice-secret".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_from_properties_listeners() {
+        let contents = "\
+listeners=CLIENT://:9092,INTERNAL://:9093,CONTROLLER://:9094
+advertised.listeners=CLIENT://kafka.local:9092
+listener.security.protocol.map=CLIENT:SASL_SSL,INTERNAL:PLAINTEXT,CONTROLLER:PLAINTEXT
+controller.listener.names=CONTROLLER
+ssl.keystore.location=/etc/kafka/broker.pem
+listener.name.client.sasl.enabled.mechanisms=SCRAM-SHA-512
+";
+        let config = Config::from_properties(contents).unwrap();
+        let protocols: Vec<_> = config
+            .listeners
+            .iter()
+            .map(|listener| {
+                (
+                    listener.endpoint.name.as_str(),
+                    listener.endpoint.security_protocol,
+                )
+            })
+            .collect();
+        assert_eq!(
+            protocols,
+            [
+                ("CLIENT", SecurityProtocol::SaslSsl),
+                ("INTERNAL", SecurityProtocol::Plaintext),
+                ("CONTROLLER", SecurityProtocol::Plaintext),
+            ]
+        );
+        assert_eq!(
+            config.listeners[0].sasl_enabled_mechanisms,
+            ["SCRAM-SHA-512"]
+        );
+        assert_eq!(config.listeners[1].sasl_enabled_mechanisms.len(), 3);
+        assert_eq!(
+            config.listeners[1].ssl.keystore_location,
+            Some(PathBuf::from("/etc/kafka/broker.pem"))
+        );
+
+        let advertised: Vec<_> = config
+            .advertised_listeners()
+            .into_iter()
+            .map(|endpoint| (endpoint.name.as_str(), endpoint.host.as_str()))
+            .collect();
+        assert_eq!(advertised, [("CLIENT", "kafka.local"), ("INTERNAL", "")]);
+    }
+}
@@ -96,6 +96,15 @@ impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<R> {
         visitor.visit_i64(i64::from_be_bytes(bytes))
     }
 
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let mut bytes = [0u8; 8];
+        self.rdr.read_exact(&mut bytes)?;
+        visitor.visit_f64(f64::from_be_bytes(bytes))
+    }
+
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
@@ -191,7 +200,7 @@ impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<R> {
     }
 
     forward_to_deserialize_any! {
-        u64 f32 f64 char unit bytes
+        u64 f32 char unit bytes
         unit_struct newtype_struct option
         identifier ignored_any map
     }
@@ -4,12 +4,61 @@ mod seed;
 pub(crate) use deserializer::Deserializer;
 pub(crate) use seed::*;
 
-use crate::KafkaError;
+use crate::{KafkaError, Result};
 
-use serde::de;
+use serde::de::{self, DeserializeOwned};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Reads the next size-prefixed frame, such as a request, without its size.
+/// Frames larger than `max_size` are rejected before they are read.
+pub(crate) async fn read_frame<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    max_size: usize,
+) -> Result<Vec<u8>> {
+    let size = reader.read_i32().await?;
+    if size < 0 || size as usize > max_size {
+        return Err(KafkaError::DeserializationError(format!(
+            "invalid frame size {size}, the maximum is {max_size}"
+        )));
+    }
+
+    let mut frame = vec![0u8; size as usize];
+    reader.read_exact(&mut frame).await?;
+    Ok(frame)
+}
+
+/// Decodes a value held in memory, such as the data of a tagged field.
+pub(crate) fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    T::deserialize(&mut Deserializer::new(bytes))
+}
 
 impl de::Error for KafkaError {
     fn custom<T: std::fmt::Display>(msg: T) -> Self {
         KafkaError::DeserializationError(msg.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_frame() {
+        let bytes = [&3i32.to_be_bytes()[..], &[1, 2, 3]].concat();
+        assert_eq!(read_frame(&mut &bytes[..], 3).await.unwrap(), [1, 2, 3]);
+
+        // Oversized and negative sizes are rejected without reading on.
+        for size in [4i32, -1] {
+            let bytes = [&size.to_be_bytes()[..], &[1, 2, 3, 4]].concat();
+            let result = read_frame(&mut &bytes[..], 3).await;
+            assert!(matches!(result, Err(KafkaError::DeserializationError(_))));
+        }
+
+        // A frame cut short is an unexpected end of the stream.
+        let bytes = [&3i32.to_be_bytes()[..], &[1, 2]].concat();
+        let result = read_frame(&mut &bytes[..], 3).await;
+        assert!(
+            matches!(result, Err(KafkaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof)
+        );
+    }
+}
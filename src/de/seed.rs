@@ -3,6 +3,7 @@ use crate::util;
 use serde::de;
 use std::fmt;
 
+#[allow(dead_code)]
 pub(crate) struct VarintLenSeed;
 
 impl<'de> de::Visitor<'de> for VarintLenSeed {
@@ -33,6 +34,7 @@ impl<'de> de::DeserializeSeed<'de> for VarintLenSeed {
     }
 }
 
+#[allow(dead_code)]
 pub(crate) struct VarintI32Seed;
 
 impl<'de> de::Visitor<'de> for VarintI32Seed {
@@ -0,0 +1,519 @@
+mod registry;
+
+pub(crate) use registry::ConfigType;
+
+use crate::{
+    Config, Result,
+    api::ErrorCode,
+    log::LogManager,
+    types::{CompactNullableString, CompactString, ConfigRecord, RecordVariant},
+};
+
+use registry::ConfigKey;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// The kinds of resources whose configs can be described and altered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ResourceType {
+    Topic = 2,
+    Broker = 4,
+}
+
+impl ResourceType {
+    pub(crate) fn from_i8(value: i8) -> Option<Self> {
+        match value {
+            2 => Some(Self::Topic),
+            4 => Some(Self::Broker),
+            _ => None,
+        }
+    }
+}
+
+/// Where the value of a config comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConfigSource {
+    Topic = 1,
+    DynamicBroker = 2,
+    DynamicDefaultBroker = 3,
+    StaticBroker = 4,
+    Default = 5,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ConfigSynonym {
+    pub(crate) name: String,
+    pub(crate) value: Option<String>,
+    pub(crate) source: ConfigSource,
+}
+
+/// A config of a resource. The synonyms are every place the config could be
+/// set, in order of precedence; the first one provides the value.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DescribedConfig {
+    pub(crate) name: String,
+    pub(crate) value: Option<String>,
+    pub(crate) source: ConfigSource,
+    pub(crate) config_type: ConfigType,
+    pub(crate) documentation: &'static str,
+    pub(crate) synonyms: Vec<ConfigSynonym>,
+}
+
+/// An operation of IncrementalAlterConfigs.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum AlterConfigOp {
+    Set(String),
+    Delete,
+    /// Adds items to a list config.
+    Append(String),
+    /// Removes items from a list config.
+    Subtract(String),
+}
+
+/// Why describing or altering the configs of a resource failed.
+pub(crate) type ConfigError = (ErrorCode, String);
+
+/// Keeps the configs set on topics and brokers with AlterConfigs and
+/// IncrementalAlterConfigs. They are persisted as `ConfigRecord`s in the
+/// metadata log and fall back to `server.properties` and the built-in
+/// defaults.
+#[derive(Debug)]
+pub(crate) struct ConfigManager {
+    node_id: i32,
+    static_configs: HashMap<String, String>,
+    logs: Arc<LogManager>,
+    /// Overrides per resource. The cluster-wide broker default is the
+    /// broker resource with an empty name.
+    overrides: Mutex<HashMap<(ResourceType, String), BTreeMap<String, String>>>,
+}
+
+impl ConfigManager {
+    pub(crate) fn new(config: &Config, logs: Arc<LogManager>) -> Self {
+        Self {
+            node_id: config.node_id,
+            static_configs: config.properties.clone(),
+            logs,
+            overrides: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Replays the `ConfigRecord`s of the metadata log.
+    pub(crate) fn load(&self) -> Result<()> {
+        let metadata = self.logs.read_metadata()?;
+
+        let mut overrides = self.overrides.lock().unwrap();
+        for value in metadata {
+            if let RecordVariant::Config(config) = value.value {
+                let Some(resource_type) = ResourceType::from_i8(config.resource_type) else {
+                    continue;
+                };
+                apply_record(&mut overrides, resource_type, config);
+            }
+        }
+        Ok(())
+    }
+
+    /// Describes every known config of the resource. The topic is expected
+    /// to exist; brokers are named by their node ID, or with an empty name
+    /// for the cluster-wide default.
+    pub(crate) fn describe(
+        &self,
+        resource_type: ResourceType,
+        resource_name: &str,
+    ) -> std::result::Result<Vec<DescribedConfig>, ConfigError> {
+        let overrides = self.overrides.lock().unwrap();
+        let resource = (resource_type, resource_name.to_string());
+
+        let described = match resource_type {
+            ResourceType::Topic => registry::TOPIC_CONFIGS
+                .iter()
+                .map(|key| {
+                    let mut synonyms = vec![];
+                    if let Some(value) = overrides.get(&resource).and_then(|o| o.get(key.name)) {
+                        synonyms.push(ConfigSynonym {
+                            name: key.name.to_string(),
+                            value: Some(value.clone()),
+                            source: ConfigSource::Topic,
+                        });
+                    }
+                    let broker_key = key
+                        .broker_synonym
+                        .and_then(registry::broker_config)
+                        .unwrap_or(key);
+                    let node_id = self.node_id.to_string();
+                    synonyms.extend(self.broker_synonyms(&overrides, broker_key, &node_id));
+                    describe_key(key, synonyms)
+                })
+                .collect(),
+            ResourceType::Broker => {
+                self.validate_broker(resource_name)?;
+                registry::BROKER_CONFIGS
+                    .iter()
+                    .map(|key| {
+                        let synonyms = self.broker_synonyms(&overrides, key, resource_name);
+                        describe_key(key, synonyms)
+                    })
+                    .collect()
+            }
+        };
+        Ok(described)
+    }
+
+    /// Replaces every config set on the resource by `configs`, as
+    /// AlterConfigs does.
+    pub(crate) fn alter(
+        &self,
+        resource_type: ResourceType,
+        resource_name: &str,
+        configs: Vec<(String, Option<String>)>,
+        validate_only: bool,
+    ) -> std::result::Result<(), ConfigError> {
+        let mut updated = BTreeMap::new();
+        for (name, value) in configs {
+            let key = self.config_key(resource_type, &name)?;
+            if let Some(value) = value {
+                key.validate(&value)
+                    .map_err(|message| (ErrorCode::InvalidConfig, message))?;
+                updated.insert(name, value);
+            }
+        }
+        self.update(resource_type, resource_name, updated, validate_only)
+    }
+
+    /// Applies the operations of IncrementalAlterConfigs, leaving the other
+    /// configs of the resource as they are.
+    pub(crate) fn incremental_alter(
+        &self,
+        resource_type: ResourceType,
+        resource_name: &str,
+        ops: Vec<(String, AlterConfigOp)>,
+        validate_only: bool,
+    ) -> std::result::Result<(), ConfigError> {
+        let mut names: Vec<&str> = ops.iter().map(|(name, _)| name.as_str()).collect();
+        names.sort_unstable();
+        if names.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err((
+                ErrorCode::InvalidRequest,
+                "Error due to duplicate config keys".to_string(),
+            ));
+        }
+
+        let resource = (resource_type, resource_name.to_string());
+        let mut updated = self
+            .overrides
+            .lock()
+            .unwrap()
+            .get(&resource)
+            .cloned()
+            .unwrap_or_default();
+
+        for (name, op) in ops {
+            let key = self.config_key(resource_type, &name)?;
+            let current = updated.get(&name).map_or(key.default, String::as_str);
+            let value = match op {
+                AlterConfigOp::Set(value) => value,
+                AlterConfigOp::Delete => {
+                    updated.remove(&name);
+                    continue;
+                }
+                AlterConfigOp::Append(_) | AlterConfigOp::Subtract(_)
+                    if key.config_type != ConfigType::List =>
+                {
+                    return Err((
+                        ErrorCode::InvalidConfig,
+                        format!("Config value append is not allowed for config key: {name}"),
+                    ));
+                }
+                AlterConfigOp::Append(value) => {
+                    let mut items: Vec<&str> = registry::split_list(current).collect();
+                    for item in registry::split_list(&value) {
+                        if !items.contains(&item) {
+                            items.push(item);
+                        }
+                    }
+                    items.join(",")
+                }
+                AlterConfigOp::Subtract(value) => {
+                    let removed: Vec<&str> = registry::split_list(&value).collect();
+                    registry::split_list(current)
+                        .filter(|item| !removed.contains(item))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                }
+            };
+            key.validate(&value)
+                .map_err(|message| (ErrorCode::InvalidConfig, message))?;
+            updated.insert(name, value);
+        }
+
+        self.update(resource_type, resource_name, updated, validate_only)
+    }
+
+    /// Persists the difference between the current and the updated configs
+    /// of the resource as one batch of `ConfigRecord`s.
+    fn update(
+        &self,
+        resource_type: ResourceType,
+        resource_name: &str,
+        updated: BTreeMap<String, String>,
+        validate_only: bool,
+    ) -> std::result::Result<(), ConfigError> {
+        if resource_type == ResourceType::Broker {
+            self.validate_broker(resource_name)?;
+        }
+        if validate_only {
+            return Ok(());
+        }
+
+        let mut overrides = self.overrides.lock().unwrap();
+        let resource = (resource_type, resource_name.to_string());
+        let current = overrides.get(&resource).cloned().unwrap_or_default();
+
+        let removed = current
+            .keys()
+            .filter(|name| !updated.contains_key(*name))
+            .map(|name| (name.clone(), None));
+        let changed = updated
+            .iter()
+            .filter(|(name, value)| current.get(*name) != Some(value))
+            .map(|(name, value)| (name.clone(), Some(value.clone())));
+        let records: Vec<ConfigRecord> = removed
+            .chain(changed)
+            .map(|(name, value)| ConfigRecord {
+                resource_type: resource_type as i8,
+                resource_name: CompactString::new(resource_name.to_string()),
+                name: CompactString::new(name),
+                value: CompactNullableString::new(value),
+            })
+            .collect();
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        self.logs
+            .append_metadata(records.iter().cloned().map(RecordVariant::Config).collect())
+            .map_err(|e| {
+                eprintln!("Failed to write config records: {e}");
+                (ErrorCode::KafkaStorageError, e.to_string())
+            })?;
+        for record in records {
+            apply_record(&mut overrides, resource_type, record);
+        }
+        Ok(())
+    }
+
+    fn config_key(
+        &self,
+        resource_type: ResourceType,
+        name: &str,
+    ) -> std::result::Result<&'static ConfigKey, ConfigError> {
+        let (key, kind) = match resource_type {
+            ResourceType::Topic => (registry::topic_config(name), "topic"),
+            ResourceType::Broker => (registry::broker_config(name), "broker"),
+        };
+        key.ok_or_else(|| {
+            (
+                ErrorCode::InvalidConfig,
+                format!("Unknown {kind} config name: {name}"),
+            )
+        })
+    }
+
+    /// Brokers are only known by the node ID of this broker, or by the empty
+    /// name of the cluster-wide default.
+    fn validate_broker(&self, resource_name: &str) -> std::result::Result<(), ConfigError> {
+        if resource_name.is_empty() || resource_name == self.node_id.to_string() {
+            Ok(())
+        } else {
+            Err((
+                ErrorCode::InvalidRequest,
+                format!(
+                    "Unexpected broker id, expected {} or empty string, but received {resource_name}",
+                    self.node_id
+                ),
+            ))
+        }
+    }
+
+    /// Where a broker config can be set, from the broker's own override to
+    /// the built-in default.
+    fn broker_synonyms(
+        &self,
+        overrides: &HashMap<(ResourceType, String), BTreeMap<String, String>>,
+        key: &ConfigKey,
+        broker: &str,
+    ) -> Vec<ConfigSynonym> {
+        let synonym = |value: &String, source| ConfigSynonym {
+            name: key.name.to_string(),
+            value: Some(value.clone()),
+            source,
+        };
+        let dynamic = |broker: &str| {
+            overrides
+                .get(&(ResourceType::Broker, broker.to_string()))
+                .and_then(|o| o.get(key.name))
+        };
+
+        let mut synonyms = vec![];
+        if !broker.is_empty()
+            && let Some(value) = dynamic(broker)
+        {
+            synonyms.push(synonym(value, ConfigSource::DynamicBroker));
+        }
+        if let Some(value) = dynamic("") {
+            synonyms.push(synonym(value, ConfigSource::DynamicDefaultBroker));
+        }
+        if let Some(value) = self.static_configs.get(key.name) {
+            synonyms.push(synonym(value, ConfigSource::StaticBroker));
+        }
+        synonyms.push(synonym(&key.default.to_string(), ConfigSource::Default));
+        synonyms
+    }
+}
+
+fn describe_key(key: &ConfigKey, synonyms: Vec<ConfigSynonym>) -> DescribedConfig {
+    let (value, source) = synonyms
+        .first()
+        .map(|synonym| (synonym.value.clone(), synonym.source))
+        .unwrap_or((None, ConfigSource::Default));
+    DescribedConfig {
+        name: key.name.to_string(),
+        value,
+        source,
+        config_type: key.config_type,
+        documentation: key.documentation,
+        synonyms,
+    }
+}
+
+fn apply_record(
+    overrides: &mut HashMap<(ResourceType, String), BTreeMap<String, String>>,
+    resource_type: ResourceType,
+    record: ConfigRecord,
+) {
+    let resource = (resource_type, record.resource_name.as_str().to_string());
+    let name = record.name.as_str().to_string();
+    match record.value.as_ref() {
+        Some(value) => {
+            overrides
+                .entry(resource)
+                .or_default()
+                .insert(name, value.clone());
+        }
+        None => {
+            if let Some(configs) = overrides.get_mut(&resource) {
+                configs.remove(&name);
+                if configs.is_empty() {
+                    overrides.remove(&resource);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager(dir: &std::path::Path) -> ConfigManager {
+        let config = Config::from_properties("log.retention.ms=1000").unwrap();
+        let manager = ConfigManager::new(&config, Arc::new(LogManager::new(dir)));
+        manager.load().unwrap();
+        manager
+    }
+
+    fn find<'a>(configs: &'a [DescribedConfig], name: &str) -> &'a DescribedConfig {
+        configs.iter().find(|c| c.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_alter_and_describe_configs() {
+        let dir = std::env::temp_dir().join(format!("kafka-config-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let configs = manager(&dir);
+        let described = configs.describe(ResourceType::Topic, "foo").unwrap();
+        let retention = find(&described, "retention.ms");
+        assert_eq!(retention.value.as_deref(), Some("1000"));
+        assert_eq!(retention.source, ConfigSource::StaticBroker);
+        assert_eq!(retention.synonyms.len(), 2);
+
+        configs
+            .incremental_alter(
+                ResourceType::Topic,
+                "foo",
+                vec![
+                    (
+                        "retention.ms".to_string(),
+                        AlterConfigOp::Set("5".to_string()),
+                    ),
+                    (
+                        "cleanup.policy".to_string(),
+                        AlterConfigOp::Append("compact".to_string()),
+                    ),
+                ],
+                false,
+            )
+            .unwrap();
+        configs
+            .alter(
+                ResourceType::Broker,
+                "",
+                vec![("log.retention.ms".to_string(), Some("2000".to_string()))],
+                false,
+            )
+            .unwrap();
+        assert_eq!(
+            configs
+                .alter(
+                    ResourceType::Topic,
+                    "foo",
+                    vec![("segment.bytes".to_string(), Some("1".to_string()))],
+                    false,
+                )
+                .unwrap_err()
+                .0,
+            ErrorCode::InvalidConfig
+        );
+        assert_eq!(
+            configs.describe(ResourceType::Broker, "2").unwrap_err().0,
+            ErrorCode::InvalidRequest
+        );
+
+        // The overrides are read back from the metadata log.
+        let configs = manager(&dir);
+        let described = configs.describe(ResourceType::Topic, "foo").unwrap();
+        let retention = find(&described, "retention.ms");
+        assert_eq!(retention.value.as_deref(), Some("5"));
+        assert_eq!(
+            retention
+                .synonyms
+                .iter()
+                .map(|s| s.source)
+                .collect::<Vec<_>>(),
+            [
+                ConfigSource::Topic,
+                ConfigSource::DynamicDefaultBroker,
+                ConfigSource::StaticBroker,
+                ConfigSource::Default,
+            ]
+        );
+        let policy = find(&described, "cleanup.policy");
+        assert_eq!(policy.value.as_deref(), Some("delete,compact"));
+
+        configs
+            .incremental_alter(
+                ResourceType::Topic,
+                "foo",
+                vec![("retention.ms".to_string(), AlterConfigOp::Delete)],
+                false,
+            )
+            .unwrap();
+        let described = configs.describe(ResourceType::Topic, "foo").unwrap();
+        let retention = find(&described, "retention.ms");
+        assert_eq!(retention.value.as_deref(), Some("2000"));
+        assert_eq!(retention.source, ConfigSource::DynamicDefaultBroker);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
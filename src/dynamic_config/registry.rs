@@ -0,0 +1,204 @@
+/// Type of a config value, as reported by DescribeConfigs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConfigType {
+    Int = 3,
+    Long = 5,
+    List = 7,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Validator {
+    None,
+    /// A number not below the bound.
+    AtLeast(i64),
+    /// A comma-separated list of the given values.
+    ValidList(&'static [&'static str]),
+}
+
+/// A config that can be described and altered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ConfigKey {
+    pub(crate) name: &'static str,
+    pub(crate) config_type: ConfigType,
+    pub(crate) default: &'static str,
+    /// The broker config providing the default of a topic config.
+    pub(crate) broker_synonym: Option<&'static str>,
+    pub(crate) documentation: &'static str,
+    validator: Validator,
+}
+
+const CLEANUP_POLICIES: &[&str] = &["delete", "compact"];
+
+/// Configs which can be overridden per topic.
+pub(crate) const TOPIC_CONFIGS: &[ConfigKey] = &[
+    ConfigKey {
+        name: "cleanup.policy",
+        config_type: ConfigType::List,
+        default: "delete",
+        broker_synonym: Some("log.cleanup.policy"),
+        documentation: "The retention policy to use on old log segments.",
+        validator: Validator::ValidList(CLEANUP_POLICIES),
+    },
+    ConfigKey {
+        name: "max.message.bytes",
+        config_type: ConfigType::Int,
+        default: "1048588",
+        broker_synonym: Some("message.max.bytes"),
+        documentation: "The largest record batch size allowed.",
+        validator: Validator::AtLeast(0),
+    },
+    ConfigKey {
+        name: "retention.bytes",
+        config_type: ConfigType::Long,
+        default: "-1",
+        broker_synonym: Some("log.retention.bytes"),
+        documentation: "The maximum size a partition can grow to before old segments are \
+                        discarded.",
+        validator: Validator::None,
+    },
+    ConfigKey {
+        name: "retention.ms",
+        config_type: ConfigType::Long,
+        default: "604800000",
+        broker_synonym: Some("log.retention.ms"),
+        documentation: "How long a log is retained before old segments are discarded.",
+        validator: Validator::AtLeast(-1),
+    },
+    ConfigKey {
+        name: "segment.bytes",
+        config_type: ConfigType::Int,
+        default: "1073741824",
+        broker_synonym: Some("log.segment.bytes"),
+        documentation: "The segment file size for the log.",
+        validator: Validator::AtLeast(14),
+    },
+];
+
+/// Broker configs which can be updated dynamically, for one broker or as
+/// the cluster-wide default.
+pub(crate) const BROKER_CONFIGS: &[ConfigKey] = &[
+    ConfigKey {
+        name: "log.cleanup.policy",
+        config_type: ConfigType::List,
+        default: "delete",
+        broker_synonym: None,
+        documentation: "The default cleanup policy for segments beyond the retention window.",
+        validator: Validator::ValidList(CLEANUP_POLICIES),
+    },
+    ConfigKey {
+        name: "log.retention.bytes",
+        config_type: ConfigType::Long,
+        default: "-1",
+        broker_synonym: None,
+        documentation: "The maximum size of the log before deleting it.",
+        validator: Validator::None,
+    },
+    ConfigKey {
+        name: "log.retention.ms",
+        config_type: ConfigType::Long,
+        default: "604800000",
+        broker_synonym: None,
+        documentation: "The number of milliseconds to keep a log file before deleting it.",
+        validator: Validator::AtLeast(-1),
+    },
+    ConfigKey {
+        name: "log.segment.bytes",
+        config_type: ConfigType::Int,
+        default: "1073741824",
+        broker_synonym: None,
+        documentation: "The maximum size of a single log file.",
+        validator: Validator::AtLeast(14),
+    },
+    ConfigKey {
+        name: "message.max.bytes",
+        config_type: ConfigType::Int,
+        default: "1048588",
+        broker_synonym: None,
+        documentation: "The largest record batch size allowed by Kafka.",
+        validator: Validator::AtLeast(0),
+    },
+];
+
+impl ConfigKey {
+    /// Checks that `value` parses as the config's type and passes its
+    /// validator, returning the reason otherwise.
+    pub(crate) fn validate(&self, value: &str) -> Result<(), String> {
+        let invalid = |reason: &str| {
+            Err(format!(
+                "Invalid value {value} for configuration {}: {reason}",
+                self.name
+            ))
+        };
+
+        let number = match self.config_type {
+            ConfigType::Int => match value.trim().parse::<i32>() {
+                Ok(n) => Some(n as i64),
+                Err(_) => return invalid("Not a number of type INT"),
+            },
+            ConfigType::Long => match value.trim().parse::<i64>() {
+                Ok(n) => Some(n),
+                Err(_) => return invalid("Not a number of type LONG"),
+            },
+            ConfigType::List => None,
+        };
+
+        match (self.validator, number) {
+            (Validator::AtLeast(min), Some(n)) if n < min => {
+                invalid(&format!("Value must be at least {min}"))
+            }
+            (Validator::ValidList(valid), _) => {
+                match split_list(value).find(|item| !valid.contains(item)) {
+                    Some(item) => invalid(&format!(
+                        "String must be one of: {}, but was {item}",
+                        valid.join(", ")
+                    )),
+                    None => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+pub(crate) fn topic_config(name: &str) -> Option<&'static ConfigKey> {
+    TOPIC_CONFIGS.iter().find(|key| key.name == name)
+}
+
+pub(crate) fn broker_config(name: &str) -> Option<&'static ConfigKey> {
+    BROKER_CONFIGS.iter().find(|key| key.name == name)
+}
+
+/// The items of a list config, skipping blanks.
+pub(crate) fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let retention = topic_config("retention.ms").unwrap();
+        assert_eq!(retention.validate("-1"), Ok(()));
+        assert!(retention.validate("-2").is_err());
+        assert!(retention.validate("soon").is_err());
+
+        let policy = topic_config("cleanup.policy").unwrap();
+        assert_eq!(policy.validate("compact, delete"), Ok(()));
+        assert!(policy.validate("compact,forever").is_err());
+
+        let max_message_bytes = topic_config("max.message.bytes").unwrap();
+        assert!(max_message_bytes.validate("4294967296").is_err());
+        assert_eq!(topic_config("unknown.config"), None);
+
+        for key in TOPIC_CONFIGS {
+            assert_eq!(key.validate(key.default), Ok(()), "{}", key.name);
+            let synonym = key.broker_synonym.and_then(broker_config).unwrap();
+            assert_eq!(synonym.default, key.default);
+        }
+    }
+}
@@ -15,6 +15,17 @@ pub enum KafkaError {
         "Unsupported Version: API Version {api_version} is not supported for API Key {api_key}"
     )]
     UnsupportedVersion { api_key: i16, api_version: i16 },
+
+    #[error("Invalid Config: {0}")]
+    InvalidConfig(String),
+
+    #[error("Authentication Error: {0}")]
+    AuthenticationError(String),
+
+    /// A request which could not be read, as opposed to failures in
+    /// handling it, such as reading a log.
+    #[error("Malformed Request: {0}")]
+    MalformedRequest(Box<KafkaError>),
 }
 
 impl From<std::string::FromUtf8Error> for KafkaError {
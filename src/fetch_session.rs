@@ -0,0 +1,285 @@
+use crate::{api::ErrorCode, types::Uuid};
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+/// The epoch of the first incremental fetch of a new session.
+const FIRST_INCREMENTAL_EPOCH: i32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct TopicIdPartition {
+    pub(crate) topic_id: Uuid,
+    pub(crate) partition: i32,
+}
+
+/// What a session remembers about one of its partitions: where the consumer
+/// fetches from, and the offsets last sent back, so unchanged partitions can
+/// be left out of incremental responses.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CachedPartition {
+    pub(crate) fetch_offset: i64,
+    pub(crate) max_bytes: i32,
+    high_watermark: i64,
+    last_stable_offset: i64,
+    log_start_offset: i64,
+}
+
+impl CachedPartition {
+    /// A partition whose offsets have not been sent yet, so its first
+    /// response always counts as a change.
+    pub(crate) fn new(fetch_offset: i64, max_bytes: i32) -> Self {
+        Self {
+            fetch_offset,
+            max_bytes,
+            high_watermark: -1,
+            last_stable_offset: -1,
+            log_start_offset: -1,
+        }
+    }
+
+    /// Remembers the offsets of a response and returns whether any of them
+    /// changed since the previous one.
+    fn update_offsets(
+        &mut self,
+        high_watermark: i64,
+        last_stable_offset: i64,
+        log_start_offset: i64,
+    ) -> bool {
+        let offsets = (high_watermark, last_stable_offset, log_start_offset);
+        let changed = offsets
+            != (
+                self.high_watermark,
+                self.last_stable_offset,
+                self.log_start_offset,
+            );
+        self.high_watermark = high_watermark;
+        self.last_stable_offset = last_stable_offset;
+        self.log_start_offset = log_start_offset;
+        changed
+    }
+}
+
+#[derive(Debug)]
+struct FetchSession {
+    /// The epoch expected from the next incremental fetch.
+    epoch: i32,
+    partitions: BTreeMap<TopicIdPartition, CachedPartition>,
+    last_used_ms: i64,
+}
+
+#[derive(Debug, Default)]
+struct Sessions {
+    sessions: HashMap<i32, FetchSession>,
+    last_id: i32,
+}
+
+/// The incremental fetch sessions of KIP-227. A consumer which opened a
+/// session only sends the partitions whose fetch position changed, and only
+/// gets back the partitions with new data or offsets. Once every slot is
+/// taken, the least recently used session is evicted.
+#[derive(Debug)]
+pub(crate) struct FetchSessionCache {
+    max_slots: usize,
+    inner: Mutex<Sessions>,
+}
+
+impl FetchSessionCache {
+    pub(crate) fn new(max_slots: usize) -> Self {
+        Self {
+            max_slots,
+            inner: Mutex::new(Sessions::default()),
+        }
+    }
+
+    /// Opens a session for the partitions of a full fetch and returns its ID,
+    /// or 0 when sessions are disabled.
+    pub(crate) fn create(
+        &self,
+        partitions: BTreeMap<TopicIdPartition, CachedPartition>,
+        now: i64,
+    ) -> i32 {
+        if self.max_slots == 0 {
+            return 0;
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        while inner.sessions.len() >= self.max_slots {
+            let Some(lru) = inner
+                .sessions
+                .iter()
+                .min_by_key(|(_, session)| session.last_used_ms)
+                .map(|(&id, _)| id)
+            else {
+                break;
+            };
+            inner.sessions.remove(&lru);
+        }
+
+        let mut id = inner.last_id;
+        loop {
+            id = if id == i32::MAX { 1 } else { id + 1 };
+            if !inner.sessions.contains_key(&id) {
+                break;
+            }
+        }
+        inner.last_id = id;
+        inner.sessions.insert(
+            id,
+            FetchSession {
+                epoch: FIRST_INCREMENTAL_EPOCH,
+                partitions,
+                last_used_ms: now,
+            },
+        );
+        id
+    }
+
+    pub(crate) fn remove(&self, session_id: i32) {
+        self.inner.lock().unwrap().sessions.remove(&session_id);
+    }
+
+    /// Applies an incremental fetch to its session: the partitions in the
+    /// request are added or get a new fetch position and the forgotten ones
+    /// are dropped. Returns every partition of the session to fetch.
+    pub(crate) fn update(
+        &self,
+        session_id: i32,
+        epoch: i32,
+        updated: Vec<(TopicIdPartition, i64, i32)>,
+        forgotten: &[TopicIdPartition],
+        now: i64,
+    ) -> std::result::Result<Vec<(TopicIdPartition, CachedPartition)>, ErrorCode> {
+        let mut inner = self.inner.lock().unwrap();
+        let session = inner
+            .sessions
+            .get_mut(&session_id)
+            .ok_or(ErrorCode::FetchSessionIdNotFound)?;
+        if session.epoch != epoch {
+            return Err(ErrorCode::InvalidFetchSessionEpoch);
+        }
+
+        for (key, fetch_offset, max_bytes) in updated {
+            session
+                .partitions
+                .entry(key)
+                .and_modify(|cached| {
+                    cached.fetch_offset = fetch_offset;
+                    cached.max_bytes = max_bytes;
+                })
+                .or_insert_with(|| CachedPartition::new(fetch_offset, max_bytes));
+        }
+        for key in forgotten {
+            session.partitions.remove(key);
+        }
+        session.epoch = if epoch == i32::MAX { 1 } else { epoch + 1 };
+        session.last_used_ms = now;
+
+        Ok(session
+            .partitions
+            .iter()
+            .map(|(&key, &cached)| (key, cached))
+            .collect())
+    }
+
+    /// Remembers the offsets sent back for a partition of the session and
+    /// returns whether they changed.
+    pub(crate) fn update_offsets(
+        &self,
+        session_id: i32,
+        key: &TopicIdPartition,
+        high_watermark: i64,
+        last_stable_offset: i64,
+        log_start_offset: i64,
+    ) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .sessions
+            .get_mut(&session_id)
+            .and_then(|session| session.partitions.get_mut(key))
+            .is_none_or(|cached| {
+                cached.update_offsets(high_watermark, last_stable_offset, log_start_offset)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(partition: i32) -> TopicIdPartition {
+        TopicIdPartition {
+            topic_id: Uuid::default(),
+            partition,
+        }
+    }
+
+    fn partitions(keys: &[i32]) -> BTreeMap<TopicIdPartition, CachedPartition> {
+        keys.iter()
+            .map(|&p| (key(p), CachedPartition::new(0, 1024)))
+            .collect()
+    }
+
+    #[test]
+    fn test_incremental_update() {
+        let cache = FetchSessionCache::new(10);
+        let id = cache.create(partitions(&[0, 1]), 0);
+        assert_ne!(id, 0);
+
+        assert_eq!(
+            cache.update(id, 2, vec![], &[], 0),
+            Err(ErrorCode::InvalidFetchSessionEpoch)
+        );
+        assert_eq!(
+            cache.update(id + 1, 1, vec![], &[], 0),
+            Err(ErrorCode::FetchSessionIdNotFound)
+        );
+
+        let fetched = cache
+            .update(
+                id,
+                1,
+                vec![(key(1), 5, 2048), (key(2), 0, 1024)],
+                &[key(0)],
+                0,
+            )
+            .unwrap();
+        assert_eq!(
+            fetched,
+            [
+                (key(1), CachedPartition::new(5, 2048)),
+                (key(2), CachedPartition::new(0, 1024)),
+            ]
+        );
+
+        // Only changed offsets are reported.
+        assert!(cache.update_offsets(id, &key(1), 10, 10, 0));
+        assert!(!cache.update_offsets(id, &key(1), 10, 10, 0));
+        assert!(cache.update_offsets(id, &key(1), 11, 10, 0));
+
+        assert!(cache.update(id, 2, vec![], &[], 0).is_ok());
+        cache.remove(id);
+        assert_eq!(
+            cache.update(id, 3, vec![], &[], 0),
+            Err(ErrorCode::FetchSessionIdNotFound)
+        );
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = FetchSessionCache::new(2);
+        let first = cache.create(partitions(&[0]), 0);
+        let second = cache.create(partitions(&[0]), 1);
+        // Using the first session makes the second the least recently used.
+        cache.update(first, 1, vec![], &[], 2).unwrap();
+
+        let third = cache.create(partitions(&[0]), 3);
+        assert!(cache.update(first, 2, vec![], &[], 4).is_ok());
+        assert_eq!(
+            cache.update(second, 1, vec![], &[], 4),
+            Err(ErrorCode::FetchSessionIdNotFound)
+        );
+        assert!(cache.update(third, 1, vec![], &[], 4).is_ok());
+
+        assert_eq!(FetchSessionCache::new(0).create(partitions(&[0]), 0), 0);
+    }
+}
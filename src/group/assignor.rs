@@ -0,0 +1,272 @@
+use crate::types::Uuid;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Partitions of each topic, keyed by topic id.
+pub(crate) type Assignment = BTreeMap<Uuid, BTreeSet<i32>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TopicMetadata {
+    pub(crate) id: Uuid,
+    pub(crate) name: String,
+    pub(crate) num_partitions: i32,
+}
+
+/// What the assignor knows about a member: the topics it subscribes to and
+/// its current target assignment, used to keep assignments sticky.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct MemberSpec {
+    pub(crate) subscribed_topics: BTreeSet<Uuid>,
+    pub(crate) current: Assignment,
+}
+
+/// A server side assignor as introduced by KIP-848. It computes the target
+/// assignment of every member of a consumer group.
+pub(crate) trait PartitionAssignor: Send + Sync {
+    fn assign(
+        &self,
+        members: &BTreeMap<String, MemberSpec>,
+        topics: &BTreeMap<Uuid, TopicMetadata>,
+    ) -> BTreeMap<String, Assignment>;
+}
+
+static UNIFORM: UniformAssignor = UniformAssignor;
+static RANGE: RangeAssignor = RangeAssignor;
+
+/// Looks up a server side assignor by the name used in
+/// `group.consumer.assignors` and in heartbeat requests.
+pub(crate) fn lookup(name: &str) -> Option<&'static dyn PartitionAssignor> {
+    match name {
+        "uniform" => Some(&UNIFORM),
+        "range" => Some(&RANGE),
+        _ => None,
+    }
+}
+
+/// Spreads partitions as evenly as possible over the subscribed members
+/// while keeping partitions on their current owner when that does not
+/// unbalance the group.
+#[derive(Debug)]
+pub(crate) struct UniformAssignor;
+
+impl PartitionAssignor for UniformAssignor {
+    fn assign(
+        &self,
+        members: &BTreeMap<String, MemberSpec>,
+        topics: &BTreeMap<Uuid, TopicMetadata>,
+    ) -> BTreeMap<String, Assignment> {
+        let subscribers = |topic_id: &Uuid| -> Vec<&String> {
+            members
+                .iter()
+                .filter(|(_, spec)| spec.subscribed_topics.contains(topic_id))
+                .map(|(member_id, _)| member_id)
+                .collect()
+        };
+
+        // Keep every partition which is still valid on its current owner.
+        let mut owners: BTreeMap<(Uuid, i32), &String> = BTreeMap::new();
+        for (member_id, spec) in members {
+            for (topic_id, partitions) in &spec.current {
+                let Some(topic) = topics.get(topic_id) else {
+                    continue;
+                };
+                if !spec.subscribed_topics.contains(topic_id) {
+                    continue;
+                }
+                for partition in partitions {
+                    if *partition < topic.num_partitions {
+                        owners.entry((*topic_id, *partition)).or_insert(member_id);
+                    }
+                }
+            }
+        }
+
+        let mut counts: BTreeMap<&String, usize> = members.keys().map(|m| (m, 0)).collect();
+        for member_id in owners.values() {
+            *counts.get_mut(member_id).unwrap() += 1;
+        }
+
+        // Hand out the remaining partitions to the least loaded subscriber.
+        for (topic_id, topic) in topics {
+            let candidates = subscribers(topic_id);
+            if candidates.is_empty() {
+                continue;
+            }
+            for partition in 0..topic.num_partitions {
+                if owners.contains_key(&(*topic_id, partition)) {
+                    continue;
+                }
+                let member_id = least_loaded(&candidates, &counts);
+                owners.insert((*topic_id, partition), member_id);
+                *counts.get_mut(member_id).unwrap() += 1;
+            }
+        }
+
+        // Move partitions away from members holding at least two more
+        // partitions than another subscriber until the group is balanced.
+        loop {
+            let mut moved = false;
+            for ((topic_id, _), owner) in owners.iter_mut() {
+                let candidates = subscribers(topic_id);
+                let target = least_loaded(&candidates, &counts);
+                if counts[target] + 1 < counts[*owner] {
+                    *counts.get_mut(*owner).unwrap() -= 1;
+                    *counts.get_mut(target).unwrap() += 1;
+                    *owner = target;
+                    moved = true;
+                }
+            }
+            if !moved {
+                break;
+            }
+        }
+
+        let mut assignments: BTreeMap<String, Assignment> = members
+            .keys()
+            .map(|member_id| (member_id.clone(), Assignment::new()))
+            .collect();
+        for ((topic_id, partition), member_id) in owners {
+            assignments
+                .get_mut(member_id)
+                .unwrap()
+                .entry(topic_id)
+                .or_default()
+                .insert(partition);
+        }
+        assignments
+    }
+}
+
+fn least_loaded<'a>(candidates: &[&'a String], counts: &BTreeMap<&String, usize>) -> &'a String {
+    candidates
+        .iter()
+        .min_by_key(|member_id| (counts[**member_id], **member_id))
+        .copied()
+        .expect("at least one candidate")
+}
+
+/// Assigns contiguous ranges of each topic's partitions to the members
+/// subscribed to it, in member id order.
+#[derive(Debug)]
+pub(crate) struct RangeAssignor;
+
+impl PartitionAssignor for RangeAssignor {
+    fn assign(
+        &self,
+        members: &BTreeMap<String, MemberSpec>,
+        topics: &BTreeMap<Uuid, TopicMetadata>,
+    ) -> BTreeMap<String, Assignment> {
+        let mut assignments: BTreeMap<String, Assignment> = members
+            .keys()
+            .map(|member_id| (member_id.clone(), Assignment::new()))
+            .collect();
+
+        for (topic_id, topic) in topics {
+            let subscribers: Vec<&String> = members
+                .iter()
+                .filter(|(_, spec)| spec.subscribed_topics.contains(topic_id))
+                .map(|(member_id, _)| member_id)
+                .collect();
+            if subscribers.is_empty() {
+                continue;
+            }
+
+            let quota = topic.num_partitions as usize / subscribers.len();
+            let extra = topic.num_partitions as usize % subscribers.len();
+            let mut start = 0;
+            for (i, member_id) in subscribers.into_iter().enumerate() {
+                let len = quota + usize::from(i < extra);
+                if len > 0 {
+                    assignments
+                        .get_mut(member_id)
+                        .unwrap()
+                        .insert(*topic_id, (start..start + len as i32).collect());
+                }
+                start += len as i32;
+            }
+        }
+
+        assignments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn topic(n: u8, num_partitions: i32) -> (Uuid, TopicMetadata) {
+        let id = Uuid::from([n; 16]);
+        (
+            id,
+            TopicMetadata {
+                id,
+                name: format!("topic-{n}"),
+                num_partitions,
+            },
+        )
+    }
+
+    fn member(topics: &[Uuid]) -> MemberSpec {
+        MemberSpec {
+            subscribed_topics: topics.iter().copied().collect(),
+            current: Assignment::new(),
+        }
+    }
+
+    fn count(assignment: &Assignment) -> usize {
+        assignment.values().map(BTreeSet::len).sum()
+    }
+
+    #[test]
+    fn test_range_assignor() {
+        let (a, topic_a) = topic(1, 5);
+        let topics = BTreeMap::from([(a, topic_a)]);
+        let members = BTreeMap::from([
+            ("m1".to_string(), member(&[a])),
+            ("m2".to_string(), member(&[a])),
+        ]);
+
+        let assignments = RangeAssignor.assign(&members, &topics);
+        assert_eq!(assignments["m1"][&a], BTreeSet::from([0, 1, 2]));
+        assert_eq!(assignments["m2"][&a], BTreeSet::from([3, 4]));
+    }
+
+    #[test]
+    fn test_uniform_assignor_balances_across_topics() {
+        let (a, topic_a) = topic(1, 3);
+        let (b, topic_b) = topic(2, 3);
+        let topics = BTreeMap::from([(a, topic_a), (b, topic_b)]);
+        let members = BTreeMap::from([
+            ("m1".to_string(), member(&[a, b])),
+            ("m2".to_string(), member(&[a, b])),
+            ("m3".to_string(), member(&[b])),
+        ]);
+
+        let assignments = UniformAssignor.assign(&members, &topics);
+        assert_eq!(assignments.values().map(count).sum::<usize>(), 6);
+        for assignment in assignments.values() {
+            assert_eq!(count(assignment), 2);
+        }
+        assert!(!assignments["m3"].contains_key(&a));
+    }
+
+    #[test]
+    fn test_uniform_assignor_is_sticky() {
+        let (a, topic_a) = topic(1, 4);
+        let topics = BTreeMap::from([(a, topic_a)]);
+        let mut members = BTreeMap::from([("m1".to_string(), member(&[a]))]);
+        members.get_mut("m1").unwrap().current = Assignment::from([(a, BTreeSet::from([2, 3]))]);
+        members.insert("m2".to_string(), member(&[a]));
+
+        let assignments = UniformAssignor.assign(&members, &topics);
+        assert_eq!(assignments["m1"][&a], BTreeSet::from([2, 3]));
+        assert_eq!(assignments["m2"][&a], BTreeSet::from([0, 1]));
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert!(lookup("uniform").is_some());
+        assert!(lookup("range").is_some());
+        assert!(lookup("sticky").is_none());
+    }
+}
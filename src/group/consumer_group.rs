@@ -0,0 +1,1024 @@
+use crate::{
+    api::ErrorCode,
+    types::{CompactArray, CompactNullableString, CompactString, TaggedFields, Uuid},
+};
+
+use super::assignor::{self, Assignment, MemberSpec, TopicMetadata};
+use super::records::{
+    ConsumerGroupCurrentMemberAssignmentValue, ConsumerGroupMemberMetadataValue,
+    ConsumerGroupMetadataValue, ConsumerGroupPartitionMetadataValue,
+    ConsumerGroupTargetAssignmentMemberValue, ConsumerGroupTargetAssignmentMetadataValue, GroupKey,
+    GroupMemberKey, GroupRecord, TopicMetadataValue, TopicPartitionsValue,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Member epoch sent by a member joining the group.
+pub(crate) const JOIN_GROUP_MEMBER_EPOCH: i32 = 0;
+/// Member epoch sent by a member leaving the group.
+pub(crate) const LEAVE_GROUP_MEMBER_EPOCH: i32 = -1;
+/// Member epoch sent by a static member leaving the group temporarily.
+pub(crate) const LEAVE_GROUP_STATIC_MEMBER_EPOCH: i32 = -2;
+
+/// Settings of the consumer group protocol, taken from the broker config.
+#[derive(Debug, Clone)]
+pub(crate) struct ConsumerGroupConfig {
+    pub(crate) heartbeat_interval_ms: i32,
+    pub(crate) session_timeout_ms: i64,
+    pub(crate) assignors: Vec<String>,
+    pub(crate) max_size: usize,
+}
+
+/// A ConsumerGroupHeartbeat request, decoded from the wire.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct HeartbeatRequest {
+    pub(crate) group_id: String,
+    pub(crate) member_id: String,
+    pub(crate) member_epoch: i32,
+    pub(crate) instance_id: Option<String>,
+    pub(crate) rack_id: Option<String>,
+    pub(crate) client_id: String,
+    pub(crate) client_host: String,
+    pub(crate) rebalance_timeout_ms: i32,
+    pub(crate) subscribed_topic_names: Option<Vec<String>>,
+    pub(crate) subscribed_topic_regex: Option<String>,
+    pub(crate) server_assignor: Option<String>,
+    pub(crate) owned_topic_partitions: Option<Assignment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct HeartbeatResponse {
+    pub(crate) member_id: String,
+    pub(crate) member_epoch: i32,
+    pub(crate) heartbeat_interval_ms: i32,
+    pub(crate) assignment: Option<Assignment>,
+}
+
+/// Reconciliation state of a member, as stored in the
+/// ConsumerGroupCurrentMemberAssignment record.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum MemberState {
+    /// The member still owns partitions it has to revoke.
+    UnrevokedPartitions = 0,
+    /// The member waits for partitions still owned by other members.
+    UnreleasedPartitions = 1,
+    #[default]
+    Stable = 2,
+}
+
+impl From<i8> for MemberState {
+    fn from(value: i8) -> Self {
+        match value {
+            0 => Self::UnrevokedPartitions,
+            1 => Self::UnreleasedPartitions,
+            _ => Self::Stable,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ConsumerGroupMember {
+    pub(crate) member_id: String,
+    pub(crate) instance_id: Option<String>,
+    pub(crate) rack_id: Option<String>,
+    pub(crate) client_id: String,
+    pub(crate) client_host: String,
+    pub(crate) subscribed_topic_names: BTreeSet<String>,
+    pub(crate) subscribed_topic_regex: Option<String>,
+    pub(crate) server_assignor: Option<String>,
+    pub(crate) rebalance_timeout_ms: i32,
+    pub(crate) member_epoch: i32,
+    pub(crate) previous_member_epoch: i32,
+    pub(crate) state: MemberState,
+    pub(crate) assigned: Assignment,
+    pub(crate) pending_revocation: Assignment,
+    // The following are not persisted.
+    pub(crate) last_heartbeat_ms: i64,
+    pub(crate) revocation_deadline_ms: Option<i64>,
+}
+
+impl ConsumerGroupMember {
+    fn new(member_id: String) -> Self {
+        Self {
+            member_id,
+            previous_member_epoch: -1,
+            ..Self::default()
+        }
+    }
+
+    fn metadata_value(&self) -> ConsumerGroupMemberMetadataValue {
+        ConsumerGroupMemberMetadataValue {
+            instance_id: CompactNullableString::new(self.instance_id.clone()),
+            rack_id: CompactNullableString::new(self.rack_id.clone()),
+            client_id: CompactString::new(self.client_id.clone()),
+            client_host: CompactString::new(self.client_host.clone()),
+            subscribed_topic_names: self
+                .subscribed_topic_names
+                .iter()
+                .cloned()
+                .map(CompactString::new)
+                .collect(),
+            subscribed_topic_regex: CompactNullableString::new(self.subscribed_topic_regex.clone()),
+            server_assignor: CompactNullableString::new(self.server_assignor.clone()),
+            rebalance_timeout_ms: self.rebalance_timeout_ms,
+            tagged_fields: TaggedFields::new(None),
+        }
+    }
+
+    fn apply_metadata(&mut self, value: &ConsumerGroupMemberMetadataValue) {
+        self.instance_id = value.instance_id.as_ref().cloned();
+        self.rack_id = value.rack_id.as_ref().cloned();
+        self.client_id = value.client_id.as_str().to_string();
+        self.client_host = value.client_host.as_str().to_string();
+        self.subscribed_topic_names = value
+            .subscribed_topic_names
+            .as_opt_slice()
+            .unwrap_or_default()
+            .iter()
+            .map(|name| name.as_str().to_string())
+            .collect();
+        self.subscribed_topic_regex = value.subscribed_topic_regex.as_ref().cloned();
+        self.server_assignor = value.server_assignor.as_ref().cloned();
+        self.rebalance_timeout_ms = value.rebalance_timeout_ms;
+    }
+
+    fn assignment_value(&self) -> ConsumerGroupCurrentMemberAssignmentValue {
+        ConsumerGroupCurrentMemberAssignmentValue {
+            member_epoch: self.member_epoch,
+            previous_member_epoch: self.previous_member_epoch,
+            state: self.state as i8,
+            assigned_partitions: to_value(&self.assigned),
+            partitions_pending_revocation: to_value(&self.pending_revocation),
+            tagged_fields: TaggedFields::new(None),
+        }
+    }
+
+    fn apply_assignment(&mut self, value: &ConsumerGroupCurrentMemberAssignmentValue) {
+        self.member_epoch = value.member_epoch;
+        self.previous_member_epoch = value.previous_member_epoch;
+        self.state = MemberState::from(value.state);
+        self.assigned = from_value(&value.assigned_partitions);
+        self.pending_revocation = from_value(&value.partitions_pending_revocation);
+    }
+
+    fn has_same_assignment(&self, other: &Self) -> bool {
+        self.member_epoch == other.member_epoch
+            && self.previous_member_epoch == other.previous_member_epoch
+            && self.state == other.state
+            && self.assigned == other.assigned
+            && self.pending_revocation == other.pending_revocation
+    }
+
+    /// Moves the member towards its target assignment. Partitions which are
+    /// no longer part of the target are revoked first, and partitions are
+    /// only given to the member once no other member owns them anymore.
+    fn reconcile(
+        &self,
+        target_epoch: i32,
+        target: &Assignment,
+        owned: Option<&Assignment>,
+        owned_by_others: &BTreeSet<(Uuid, i32)>,
+    ) -> Self {
+        let mut member = self.clone();
+
+        if member.state == MemberState::UnrevokedPartitions {
+            let revoked = owned.is_some_and(|owned| {
+                partitions(owned).is_disjoint(&partitions(&member.pending_revocation))
+            });
+            if !revoked {
+                return member;
+            }
+            member.pending_revocation.clear();
+        }
+
+        let assigned = partitions(&member.assigned);
+        let target = partitions(target);
+
+        let revoke: BTreeSet<(Uuid, i32)> = assigned.difference(&target).copied().collect();
+        if !revoke.is_empty() {
+            member.assigned = to_assignment(assigned.intersection(&target).copied());
+            member.pending_revocation = to_assignment(revoke);
+            member.state = MemberState::UnrevokedPartitions;
+            return member;
+        }
+
+        let (unreleased, free): (BTreeSet<_>, BTreeSet<_>) = target
+            .difference(&assigned)
+            .partition(|tp| owned_by_others.contains(tp));
+        member.assigned = to_assignment(assigned.union(&free).copied());
+        member.state = if unreleased.is_empty() {
+            MemberState::Stable
+        } else {
+            MemberState::UnreleasedPartitions
+        };
+        if member.member_epoch != target_epoch {
+            member.previous_member_epoch = member.member_epoch;
+            member.member_epoch = target_epoch;
+        }
+        member
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConsumerGroupState {
+    Empty,
+    /// The target assignment has not been computed for the group epoch yet.
+    Assigning,
+    /// Members are still converging towards the target assignment.
+    Reconciling,
+    Stable,
+}
+
+impl fmt::Display for ConsumerGroupState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Empty => "Empty",
+            Self::Assigning => "Assigning",
+            Self::Reconciling => "Reconciling",
+            Self::Stable => "Stable",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A consumer group using the KIP-848 rebalance protocol.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ConsumerGroup {
+    pub(crate) group_id: String,
+    pub(crate) group_epoch: i32,
+    pub(crate) members: BTreeMap<String, ConsumerGroupMember>,
+    pub(crate) subscription_metadata: BTreeMap<Uuid, TopicMetadata>,
+    pub(crate) target_assignment_epoch: i32,
+    pub(crate) target_assignment: BTreeMap<String, Assignment>,
+    // Not persisted: when the group last became empty, from which its
+    // committed offsets start to expire.
+    pub(crate) empty_since_ms: Option<i64>,
+}
+
+impl ConsumerGroup {
+    pub(crate) fn new(group_id: &str) -> Self {
+        Self {
+            group_id: group_id.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn state(&self) -> ConsumerGroupState {
+        if self.members.is_empty() {
+            ConsumerGroupState::Empty
+        } else if self.group_epoch != self.target_assignment_epoch {
+            ConsumerGroupState::Assigning
+        } else if self.members.values().any(|member| {
+            member.member_epoch != self.target_assignment_epoch
+                || member.state != MemberState::Stable
+        }) {
+            ConsumerGroupState::Reconciling
+        } else {
+            ConsumerGroupState::Stable
+        }
+    }
+
+    /// Starts the offset retention clock when the last member leaves and
+    /// stops it when a member joins.
+    pub(crate) fn update_empty_since(&mut self, now: i64) {
+        if !self.members.is_empty() {
+            self.empty_since_ms = None;
+        } else if self.empty_since_ms.is_none() {
+            self.empty_since_ms = Some(now);
+        }
+    }
+
+    /// Whether any member of the group subscribes to `topic`.
+    pub(crate) fn is_subscribed_to(&self, topic: &str) -> bool {
+        self.members
+            .values()
+            .any(|member| member.subscribed_topic_names.contains(topic))
+    }
+
+    /// Records removing the group from the log. Only empty groups can be
+    /// deleted, so there are no member records left.
+    pub(crate) fn tombstones(&self) -> Vec<GroupRecord> {
+        vec![
+            GroupRecord::TargetAssignmentMetadata(self.key(), None),
+            GroupRecord::PartitionMetadata(self.key(), None),
+            GroupRecord::GroupMetadata(self.key(), None),
+        ]
+    }
+
+    /// Applies a record read from or about to be written to the log.
+    pub(crate) fn replay(&mut self, record: &GroupRecord) {
+        match record {
+            GroupRecord::OffsetCommit(..) => {}
+            GroupRecord::GroupMetadata(_, value) => {
+                self.group_epoch = value.as_ref().map_or(0, |v| v.epoch);
+            }
+            GroupRecord::PartitionMetadata(_, value) => {
+                self.subscription_metadata = value
+                    .as_ref()
+                    .and_then(|v| v.topics.as_opt_slice())
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|topic| {
+                        (
+                            topic.topic_id,
+                            TopicMetadata {
+                                id: topic.topic_id,
+                                name: topic.topic_name.as_str().to_string(),
+                                num_partitions: topic.num_partitions,
+                            },
+                        )
+                    })
+                    .collect();
+            }
+            GroupRecord::MemberMetadata(key, Some(value)) => {
+                self.member_mut(&key.member_id).apply_metadata(value);
+            }
+            GroupRecord::MemberMetadata(key, None) => {
+                self.members.remove(&key.member_id);
+            }
+            GroupRecord::TargetAssignmentMetadata(_, value) => {
+                self.target_assignment_epoch = value.as_ref().map_or(0, |v| v.assignment_epoch);
+            }
+            GroupRecord::TargetAssignmentMember(key, Some(value)) => {
+                self.target_assignment
+                    .insert(key.member_id.clone(), from_value(&value.topic_partitions));
+            }
+            GroupRecord::TargetAssignmentMember(key, None) => {
+                self.target_assignment.remove(&key.member_id);
+            }
+            GroupRecord::CurrentMemberAssignment(key, Some(value)) => {
+                self.member_mut(&key.member_id).apply_assignment(value);
+            }
+            GroupRecord::CurrentMemberAssignment(key, None) => {
+                if let Some(member) = self.members.get_mut(&key.member_id) {
+                    let empty = ConsumerGroupMember::new(key.member_id.clone());
+                    member.apply_assignment(&empty.assignment_value());
+                }
+            }
+        }
+    }
+
+    /// Handles a heartbeat and returns the records describing the state
+    /// changes. The records are already applied to `self`.
+    pub(crate) fn heartbeat(
+        &mut self,
+        request: &HeartbeatRequest,
+        topics: &[TopicMetadata],
+        config: &ConsumerGroupConfig,
+        now: i64,
+    ) -> Result<(HeartbeatResponse, Vec<GroupRecord>), ErrorCode> {
+        validate(request, config)?;
+
+        let mut records = vec![];
+
+        if matches!(
+            request.member_epoch,
+            LEAVE_GROUP_MEMBER_EPOCH | LEAVE_GROUP_STATIC_MEMBER_EPOCH
+        ) {
+            if !self.members.contains_key(&request.member_id) {
+                return Err(ErrorCode::UnknownMemberIdException);
+            }
+            self.remove_member(&request.member_id, &mut records);
+            let response = HeartbeatResponse {
+                member_id: request.member_id.clone(),
+                member_epoch: request.member_epoch,
+                heartbeat_interval_ms: 0,
+                assignment: None,
+            };
+            return Ok((response, records));
+        }
+
+        let (member, is_new) = if request.member_epoch == JOIN_GROUP_MEMBER_EPOCH {
+            let member_id = if request.member_id.is_empty() {
+                Uuid::random().to_string()
+            } else {
+                request.member_id.clone()
+            };
+            if let Some(instance_id) = request.instance_id.as_ref() {
+                let taken = self.members.values().any(|m| {
+                    m.member_id != member_id && m.instance_id.as_ref() == Some(instance_id)
+                });
+                if taken {
+                    return Err(ErrorCode::UnreleasedInstanceId);
+                }
+            }
+            if !self.members.contains_key(&member_id) && self.members.len() >= config.max_size {
+                return Err(ErrorCode::GroupMaxSizeReached);
+            }
+            (ConsumerGroupMember::new(member_id), true)
+        } else {
+            let member = self
+                .members
+                .get(&request.member_id)
+                .ok_or(ErrorCode::UnknownMemberIdException)?;
+            check_member_epoch(member, request)?;
+            (member.clone(), false)
+        };
+        let member_id = member.member_id.clone();
+        let rejoining = is_new && self.members.contains_key(&member_id);
+
+        let mut updated = member.clone();
+        if is_new {
+            updated.instance_id = request.instance_id.clone();
+        }
+        if let Some(rack_id) = request.rack_id.as_ref() {
+            updated.rack_id = Some(rack_id.clone());
+        }
+        updated.client_id = request.client_id.clone();
+        updated.client_host = request.client_host.clone();
+        if request.rebalance_timeout_ms != -1 {
+            updated.rebalance_timeout_ms = request.rebalance_timeout_ms;
+        }
+        if let Some(names) = request.subscribed_topic_names.as_ref() {
+            updated.subscribed_topic_names = names.iter().cloned().collect();
+        }
+        if let Some(server_assignor) = request.server_assignor.as_ref() {
+            updated.server_assignor = Some(server_assignor.clone());
+        }
+
+        let mut bump_epoch = is_new
+            || updated.subscribed_topic_names != member.subscribed_topic_names
+            || updated.server_assignor != member.server_assignor;
+        if is_new || updated.metadata_value() != member.metadata_value() {
+            self.emit(
+                &mut records,
+                GroupRecord::MemberMetadata(
+                    self.member_key(&member_id),
+                    Some(updated.metadata_value()),
+                ),
+            );
+        }
+        if rejoining {
+            // A rejoining member starts over with an empty assignment.
+            self.emit(
+                &mut records,
+                GroupRecord::CurrentMemberAssignment(
+                    self.member_key(&member_id),
+                    Some(member.assignment_value()),
+                ),
+            );
+        }
+
+        let subscription_metadata = self.compute_subscription_metadata(topics);
+        if subscription_metadata != self.subscription_metadata {
+            let value = ConsumerGroupPartitionMetadataValue {
+                topics: subscription_metadata
+                    .values()
+                    .map(|topic| TopicMetadataValue {
+                        topic_id: topic.id,
+                        topic_name: CompactString::new(topic.name.clone()),
+                        num_partitions: topic.num_partitions,
+                        partition_metadata: CompactArray::new(Some(vec![])),
+                        tagged_fields: TaggedFields::new(None),
+                    })
+                    .collect(),
+                tagged_fields: TaggedFields::new(None),
+            };
+            self.emit(
+                &mut records,
+                GroupRecord::PartitionMetadata(self.key(), Some(value)),
+            );
+            bump_epoch = true;
+        }
+
+        if bump_epoch {
+            self.bump_epoch(&mut records);
+        }
+
+        if self.group_epoch > self.target_assignment_epoch {
+            self.update_target_assignment(config, &mut records);
+        }
+
+        let current = self.members[&member_id].clone();
+        let target = self
+            .target_assignment
+            .get(&member_id)
+            .cloned()
+            .unwrap_or_default();
+        let reconciled = current.reconcile(
+            self.target_assignment_epoch,
+            &target,
+            request.owned_topic_partitions.as_ref(),
+            &self.owned_by_others(&member_id),
+        );
+        let changed = !reconciled.has_same_assignment(&current);
+        if changed {
+            self.emit(
+                &mut records,
+                GroupRecord::CurrentMemberAssignment(
+                    self.member_key(&member_id),
+                    Some(reconciled.assignment_value()),
+                ),
+            );
+        }
+
+        let member = self.members.get_mut(&member_id).unwrap();
+        member.last_heartbeat_ms = now;
+        member.revocation_deadline_ms = match member.state {
+            MemberState::UnrevokedPartitions => Some(
+                current
+                    .revocation_deadline_ms
+                    .filter(|_| current.state == MemberState::UnrevokedPartitions)
+                    .unwrap_or(now + member.rebalance_timeout_ms as i64),
+            ),
+            _ => None,
+        };
+
+        let send_assignment = is_new || changed || request.owned_topic_partitions.is_some();
+        let response = HeartbeatResponse {
+            member_id,
+            member_epoch: member.member_epoch,
+            heartbeat_interval_ms: config.heartbeat_interval_ms,
+            assignment: send_assignment.then(|| member.assigned.clone()),
+        };
+        Ok((response, records))
+    }
+
+    /// Validates the member of an offset commit. Commits without a member
+    /// are only accepted while the group has no members.
+    pub(crate) fn validate_offset_commit(
+        &self,
+        member_id: &str,
+        member_epoch: i32,
+    ) -> Result<(), ErrorCode> {
+        if member_epoch < 0 && member_id.is_empty() && self.members.is_empty() {
+            return Ok(());
+        }
+        self.validate_member(member_id, member_epoch)
+    }
+
+    /// Validates the member of an offset fetch. Fetches which do not carry a
+    /// member epoch, e.g. from admin clients, are always accepted.
+    pub(crate) fn validate_offset_fetch(
+        &self,
+        member_id: &str,
+        member_epoch: i32,
+    ) -> Result<(), ErrorCode> {
+        if member_epoch < 0 {
+            return Ok(());
+        }
+        self.validate_member(member_id, member_epoch)
+    }
+
+    fn validate_member(&self, member_id: &str, member_epoch: i32) -> Result<(), ErrorCode> {
+        let member = self
+            .members
+            .get(member_id)
+            .ok_or(ErrorCode::UnknownMemberIdException)?;
+        if member.member_epoch != member_epoch {
+            return Err(ErrorCode::StaleMemberEpoch);
+        }
+        Ok(())
+    }
+
+    /// Members whose session timed out or which did not revoke their
+    /// partitions within the rebalance timeout.
+    pub(crate) fn expired_members(&self, now: i64, session_timeout_ms: i64) -> Vec<String> {
+        self.members
+            .values()
+            .filter(|member| {
+                member.last_heartbeat_ms + session_timeout_ms < now
+                    || member
+                        .revocation_deadline_ms
+                        .is_some_and(|deadline| deadline < now)
+            })
+            .map(|member| member.member_id.clone())
+            .collect()
+    }
+
+    /// Removes a member from the group and bumps the group epoch so that the
+    /// partitions it owned get reassigned.
+    pub(crate) fn remove_member(&mut self, member_id: &str, records: &mut Vec<GroupRecord>) {
+        let key = self.member_key(member_id);
+        self.emit(
+            records,
+            GroupRecord::CurrentMemberAssignment(key.clone(), None),
+        );
+        self.emit(
+            records,
+            GroupRecord::TargetAssignmentMember(key.clone(), None),
+        );
+        self.emit(records, GroupRecord::MemberMetadata(key, None));
+        self.bump_epoch(records);
+    }
+
+    fn bump_epoch(&mut self, records: &mut Vec<GroupRecord>) {
+        let value = ConsumerGroupMetadataValue {
+            epoch: self.group_epoch + 1,
+            tagged_fields: TaggedFields::new(None),
+        };
+        self.emit(records, GroupRecord::GroupMetadata(self.key(), Some(value)));
+    }
+
+    fn update_target_assignment(
+        &mut self,
+        config: &ConsumerGroupConfig,
+        records: &mut Vec<GroupRecord>,
+    ) {
+        let topic_ids: BTreeMap<&str, Uuid> = self
+            .subscription_metadata
+            .values()
+            .map(|topic| (topic.name.as_str(), topic.id))
+            .collect();
+        let specs: BTreeMap<String, MemberSpec> = self
+            .members
+            .values()
+            .map(|member| {
+                let spec = MemberSpec {
+                    subscribed_topics: member
+                        .subscribed_topic_names
+                        .iter()
+                        .filter_map(|name| topic_ids.get(name.as_str()).copied())
+                        .collect(),
+                    current: self
+                        .target_assignment
+                        .get(&member.member_id)
+                        .cloned()
+                        .unwrap_or_default(),
+                };
+                (member.member_id.clone(), spec)
+            })
+            .collect();
+
+        let assignor = assignor::lookup(self.preferred_assignor(config))
+            .or_else(|| assignor::lookup("uniform"))
+            .unwrap();
+        let target = assignor.assign(&specs, &self.subscription_metadata);
+
+        for (member_id, assignment) in target {
+            if self.target_assignment.get(&member_id) == Some(&assignment) {
+                continue;
+            }
+            let value = ConsumerGroupTargetAssignmentMemberValue {
+                topic_partitions: to_value(&assignment),
+                tagged_fields: TaggedFields::new(None),
+            };
+            self.emit(
+                records,
+                GroupRecord::TargetAssignmentMember(self.member_key(&member_id), Some(value)),
+            );
+        }
+
+        let value = ConsumerGroupTargetAssignmentMetadataValue {
+            assignment_epoch: self.group_epoch,
+            tagged_fields: TaggedFields::new(None),
+        };
+        self.emit(
+            records,
+            GroupRecord::TargetAssignmentMetadata(self.key(), Some(value)),
+        );
+    }
+
+    /// The assignor requested by most members, or the first configured one.
+    pub(crate) fn preferred_assignor<'a>(&'a self, config: &'a ConsumerGroupConfig) -> &'a str {
+        let mut votes: BTreeMap<&str, usize> = BTreeMap::new();
+        for member in self.members.values() {
+            if let Some(name) = member.server_assignor.as_deref() {
+                *votes.entry(name).or_default() += 1;
+            }
+        }
+        votes
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .map(|(name, _)| name)
+            .or(config.assignors.first().map(String::as_str))
+            .unwrap_or("uniform")
+    }
+
+    fn compute_subscription_metadata(
+        &self,
+        topics: &[TopicMetadata],
+    ) -> BTreeMap<Uuid, TopicMetadata> {
+        let subscribed: BTreeSet<&String> = self
+            .members
+            .values()
+            .flat_map(|member| member.subscribed_topic_names.iter())
+            .collect();
+        topics
+            .iter()
+            .filter(|topic| subscribed.contains(&topic.name))
+            .map(|topic| (topic.id, topic.clone()))
+            .collect()
+    }
+
+    fn owned_by_others(&self, member_id: &str) -> BTreeSet<(Uuid, i32)> {
+        self.members
+            .values()
+            .filter(|member| member.member_id != member_id)
+            .flat_map(|member| {
+                partitions(&member.assigned)
+                    .into_iter()
+                    .chain(partitions(&member.pending_revocation))
+            })
+            .collect()
+    }
+
+    fn member_mut(&mut self, member_id: &str) -> &mut ConsumerGroupMember {
+        self.members
+            .entry(member_id.to_string())
+            .or_insert_with(|| ConsumerGroupMember::new(member_id.to_string()))
+    }
+
+    fn emit(&mut self, records: &mut Vec<GroupRecord>, record: GroupRecord) {
+        self.replay(&record);
+        records.push(record);
+    }
+
+    fn key(&self) -> GroupKey {
+        GroupKey {
+            group_id: self.group_id.clone(),
+        }
+    }
+
+    fn member_key(&self, member_id: &str) -> GroupMemberKey {
+        GroupMemberKey {
+            group_id: self.group_id.clone(),
+            member_id: member_id.to_string(),
+        }
+    }
+}
+
+fn validate(request: &HeartbeatRequest, config: &ConsumerGroupConfig) -> Result<(), ErrorCode> {
+    if request.group_id.is_empty() {
+        return Err(ErrorCode::InvalidRequest);
+    }
+    if request.instance_id.as_deref() == Some("") {
+        return Err(ErrorCode::InvalidRequest);
+    }
+    // Regular expression subscriptions are not supported yet.
+    if request.subscribed_topic_regex.is_some() {
+        return Err(ErrorCode::InvalidRequest);
+    }
+    if let Some(name) = request.server_assignor.as_ref()
+        && !config.assignors.contains(name)
+    {
+        return Err(ErrorCode::UnsupportedAssignor);
+    }
+
+    match request.member_epoch {
+        JOIN_GROUP_MEMBER_EPOCH => {
+            let subscribed = request
+                .subscribed_topic_names
+                .as_ref()
+                .is_some_and(|names| !names.is_empty());
+            let owns_partitions = request
+                .owned_topic_partitions
+                .as_ref()
+                .is_some_and(|owned| owned.values().any(|p| !p.is_empty()));
+            if request.rebalance_timeout_ms == -1 || !subscribed || owns_partitions {
+                return Err(ErrorCode::InvalidRequest);
+            }
+        }
+        LEAVE_GROUP_STATIC_MEMBER_EPOCH if request.instance_id.is_none() => {
+            return Err(ErrorCode::InvalidRequest);
+        }
+        epoch if epoch < LEAVE_GROUP_STATIC_MEMBER_EPOCH => {
+            return Err(ErrorCode::InvalidRequest);
+        }
+        _ if request.member_id.is_empty() => return Err(ErrorCode::InvalidRequest),
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// A member may only use its current epoch, or its previous one when the
+/// response bumping the epoch got lost.
+fn check_member_epoch(
+    member: &ConsumerGroupMember,
+    request: &HeartbeatRequest,
+) -> Result<(), ErrorCode> {
+    if request.member_epoch > member.member_epoch {
+        return Err(ErrorCode::FencedMemberEpoch);
+    }
+    if request.member_epoch < member.member_epoch {
+        let owns_subset = request
+            .owned_topic_partitions
+            .as_ref()
+            .is_some_and(|owned| partitions(owned).is_subset(&partitions(&member.assigned)));
+        if request.member_epoch != member.previous_member_epoch || !owns_subset {
+            return Err(ErrorCode::FencedMemberEpoch);
+        }
+    }
+    Ok(())
+}
+
+fn partitions(assignment: &Assignment) -> BTreeSet<(Uuid, i32)> {
+    assignment
+        .iter()
+        .flat_map(|(topic_id, partitions)| partitions.iter().map(|p| (*topic_id, *p)))
+        .collect()
+}
+
+fn to_assignment(partitions: impl IntoIterator<Item = (Uuid, i32)>) -> Assignment {
+    let mut assignment = Assignment::new();
+    for (topic_id, partition) in partitions {
+        assignment.entry(topic_id).or_default().insert(partition);
+    }
+    assignment
+}
+
+fn to_value(assignment: &Assignment) -> CompactArray<TopicPartitionsValue> {
+    assignment
+        .iter()
+        .map(|(topic_id, partitions)| TopicPartitionsValue {
+            topic_id: *topic_id,
+            partitions: partitions.iter().copied().collect(),
+            tagged_fields: TaggedFields::new(None),
+        })
+        .collect()
+}
+
+fn from_value(value: &CompactArray<TopicPartitionsValue>) -> Assignment {
+    value
+        .as_opt_slice()
+        .unwrap_or_default()
+        .iter()
+        .map(|topic| {
+            let partitions = topic
+                .partitions
+                .as_opt_slice()
+                .unwrap_or_default()
+                .iter()
+                .copied()
+                .collect();
+            (topic.topic_id, partitions)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ConsumerGroupConfig {
+        ConsumerGroupConfig {
+            heartbeat_interval_ms: 5000,
+            session_timeout_ms: 45000,
+            assignors: vec!["uniform".to_string(), "range".to_string()],
+            max_size: 10,
+        }
+    }
+
+    fn topics() -> Vec<TopicMetadata> {
+        vec![TopicMetadata {
+            id: Uuid::from([1; 16]),
+            name: "foo".to_string(),
+            num_partitions: 2,
+        }]
+    }
+
+    fn join(member_id: &str) -> HeartbeatRequest {
+        HeartbeatRequest {
+            group_id: "group".to_string(),
+            member_id: member_id.to_string(),
+            member_epoch: 0,
+            rebalance_timeout_ms: 30000,
+            subscribed_topic_names: Some(vec!["foo".to_string()]),
+            owned_topic_partitions: Some(Assignment::new()),
+            ..HeartbeatRequest::default()
+        }
+    }
+
+    fn heartbeat(member_id: &str, member_epoch: i32, owned: Assignment) -> HeartbeatRequest {
+        HeartbeatRequest {
+            group_id: "group".to_string(),
+            member_id: member_id.to_string(),
+            member_epoch,
+            rebalance_timeout_ms: -1,
+            owned_topic_partitions: Some(owned),
+            ..HeartbeatRequest::default()
+        }
+    }
+
+    #[test]
+    fn test_join_and_rebalance() {
+        let topic_id = Uuid::from([1; 16]);
+        let mut group = ConsumerGroup::new("group");
+
+        let (res, _) = group.heartbeat(&join(""), &topics(), &config(), 0).unwrap();
+        let m1 = res.member_id.clone();
+        assert!(!m1.is_empty());
+        assert_eq!(res.member_epoch, 1);
+        let full = Assignment::from([(topic_id, BTreeSet::from([0, 1]))]);
+        assert_eq!(res.assignment, Some(full.clone()));
+
+        // A second member joins: it has to wait until m1 revokes a partition.
+        let (res, _) = group
+            .heartbeat(&join("m2"), &topics(), &config(), 0)
+            .unwrap();
+        assert_eq!(res.member_epoch, 2);
+        assert_eq!(res.assignment, Some(Assignment::new()));
+        assert_eq!(group.members["m2"].state, MemberState::UnreleasedPartitions);
+        assert_eq!(group.state(), ConsumerGroupState::Reconciling);
+
+        let (res, _) = group
+            .heartbeat(&heartbeat(&m1, 1, full.clone()), &topics(), &config(), 0)
+            .unwrap();
+        assert_eq!(res.member_epoch, 1);
+        let m1_target = group.target_assignment[&m1].clone();
+        assert_eq!(res.assignment, Some(m1_target.clone()));
+        assert_eq!(group.members[&m1].state, MemberState::UnrevokedPartitions);
+
+        // Once m1 revoked the partition it moves to the new epoch and m2
+        // receives the released partition.
+        let (res, _) = group
+            .heartbeat(&heartbeat(&m1, 1, m1_target), &topics(), &config(), 0)
+            .unwrap();
+        assert_eq!(res.member_epoch, 2);
+
+        let (res, _) = group
+            .heartbeat(
+                &heartbeat("m2", 2, Assignment::new()),
+                &topics(),
+                &config(),
+                0,
+            )
+            .unwrap();
+        assert_eq!(res.assignment, Some(group.target_assignment["m2"].clone()));
+        assert_eq!(group.members["m2"].state, MemberState::Stable);
+        assert_eq!(group.state(), ConsumerGroupState::Stable);
+    }
+
+    #[test]
+    fn test_fenced_and_unknown_members() {
+        let mut group = ConsumerGroup::new("group");
+        let (res, _) = group
+            .heartbeat(&join("m1"), &topics(), &config(), 0)
+            .unwrap();
+        assert_eq!(res.member_epoch, 1);
+
+        let err = group
+            .heartbeat(
+                &heartbeat("m1", 5, Assignment::new()),
+                &topics(),
+                &config(),
+                0,
+            )
+            .unwrap_err();
+        assert_eq!(err, ErrorCode::FencedMemberEpoch);
+
+        let err = group
+            .heartbeat(
+                &heartbeat("m3", 1, Assignment::new()),
+                &topics(),
+                &config(),
+                0,
+            )
+            .unwrap_err();
+        assert_eq!(err, ErrorCode::UnknownMemberIdException);
+
+        let mut request = join("m4");
+        request.server_assignor = Some("sticky".to_string());
+        let err = group
+            .heartbeat(&request, &topics(), &config(), 0)
+            .unwrap_err();
+        assert_eq!(err, ErrorCode::UnsupportedAssignor);
+    }
+
+    #[test]
+    fn test_leave_and_replay() {
+        let mut group = ConsumerGroup::new("group");
+        let mut log = vec![];
+        let (_, records) = group
+            .heartbeat(&join("m1"), &topics(), &config(), 0)
+            .unwrap();
+        log.extend(records);
+        let (_, records) = group
+            .heartbeat(&join("m2"), &topics(), &config(), 0)
+            .unwrap();
+        log.extend(records);
+
+        let mut replayed = ConsumerGroup::new("group");
+        log.iter().for_each(|record| replayed.replay(record));
+        assert_eq!(replayed, group);
+
+        let (res, records) = group
+            .heartbeat(
+                &heartbeat("m2", -1, Assignment::new()),
+                &topics(),
+                &config(),
+                0,
+            )
+            .unwrap();
+        assert_eq!(res.member_epoch, -1);
+        assert!(!group.members.contains_key("m2"));
+        assert_eq!(group.group_epoch, 3);
+        log.extend(records);
+
+        let mut replayed = ConsumerGroup::new("group");
+        log.iter().for_each(|record| replayed.replay(record));
+        assert_eq!(replayed.members.len(), 1);
+        assert_eq!(replayed.group_epoch, 3);
+    }
+
+    #[test]
+    fn test_expired_members() {
+        let mut group = ConsumerGroup::new("group");
+        group
+            .heartbeat(&join("m1"), &topics(), &config(), 1000)
+            .unwrap();
+        assert!(group.expired_members(2000, 45000).is_empty());
+        assert_eq!(group.expired_members(50000, 45000), vec!["m1".to_string()]);
+    }
+}
@@ -0,0 +1,951 @@
+mod assignor;
+mod consumer_group;
+mod offsets;
+mod records;
+
+pub(crate) use assignor::{Assignment, TopicMetadata};
+pub(crate) use consumer_group::{HeartbeatRequest, HeartbeatResponse};
+pub(crate) use offsets::OffsetAndMetadata;
+
+use crate::{
+    Config, Result,
+    api::ErrorCode,
+    log::{LogManager, TopicPartition},
+    types::{ControlRecordType, RecordBatch, VarintBytes},
+    util,
+};
+
+use consumer_group::{ConsumerGroup, ConsumerGroupConfig};
+use offsets::OffsetCache;
+use records::{GroupRecord, OffsetCommitKey};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+pub(crate) const GROUP_METADATA_TOPIC: &str = "__consumer_offsets";
+
+/// Protocol type of groups using the consumer protocol.
+const CONSUMER_PROTOCOL_TYPE: &str = "consumer";
+
+/// A group as listed by ListGroups.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct GroupOverview {
+    pub(crate) group_id: String,
+    pub(crate) protocol_type: String,
+    pub(crate) state: String,
+    pub(crate) group_type: String,
+}
+
+/// A group as described by DescribeGroups.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct GroupDescription {
+    pub(crate) group_id: String,
+    pub(crate) state: String,
+    pub(crate) protocol_type: String,
+    pub(crate) protocol_data: String,
+    pub(crate) members: Vec<MemberDescription>,
+}
+
+/// How far a group's committed offset is behind the end of a partition.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PartitionLag {
+    pub(crate) tp: TopicPartition,
+    pub(crate) committed_offset: i64,
+    /// `None` when the partition has no log on this broker.
+    pub(crate) log_end_offset: Option<i64>,
+}
+
+impl PartitionLag {
+    pub(crate) fn lag(&self) -> Option<i64> {
+        self.log_end_offset
+            .map(|end| (end - self.committed_offset).max(0))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MemberDescription {
+    pub(crate) member_id: String,
+    pub(crate) instance_id: Option<String>,
+    pub(crate) client_id: String,
+    pub(crate) client_host: String,
+    pub(crate) subscribed_topics: Vec<String>,
+    /// Assigned partitions by topic name.
+    pub(crate) assignment: Vec<(String, Vec<i32>)>,
+}
+
+/// Keeps consumer group state and committed offsets, persisted as records in
+/// the `__consumer_offsets` topic.
+#[derive(Debug)]
+pub(crate) struct GroupCoordinator {
+    logs: Arc<LogManager>,
+    num_partitions: i32,
+    retention_ms: i64,
+    metadata_max_bytes: usize,
+    consumer: ConsumerGroupConfig,
+    offsets: Mutex<OffsetCache>,
+    groups: Mutex<HashMap<String, ConsumerGroup>>,
+}
+
+impl GroupCoordinator {
+    pub(crate) fn new(config: &Config, logs: Arc<LogManager>) -> Self {
+        Self {
+            logs,
+            num_partitions: config.offsets_topic_num_partitions,
+            retention_ms: config.offsets_retention_ms,
+            metadata_max_bytes: config.offset_metadata_max_bytes,
+            consumer: ConsumerGroupConfig {
+                heartbeat_interval_ms: config.group_consumer_heartbeat_interval_ms,
+                session_timeout_ms: config.group_consumer_session_timeout_ms,
+                assignors: config.group_consumer_assignors.clone(),
+                max_size: config.group_consumer_max_size,
+            },
+            offsets: Mutex::new(OffsetCache::default()),
+            groups: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Rebuilds the in-memory state by replaying every `__consumer_offsets`
+    /// partition found on disk.
+    pub(crate) fn load(&self) -> Result<()> {
+        let mut groups = self.groups.lock().unwrap();
+        let mut offsets = self.offsets.lock().unwrap();
+
+        for tp in self.logs.partitions_on_disk(GROUP_METADATA_TOPIC)? {
+            let log = self.logs.get_or_create(&tp)?;
+            let batches = log.lock().unwrap().read::<VarintBytes>()?;
+            for batch in batches {
+                if batch.is_control() {
+                    if let Some(control_type) = batch.control_type() {
+                        offsets.complete_pending(
+                            batch.producer_id,
+                            control_type == ControlRecordType::Commit,
+                            |group_id| self.partition_for(group_id) == tp.partition,
+                        );
+                    }
+                    continue;
+                }
+
+                let producer_id = batch.is_transactional().then_some(batch.producer_id);
+                for record in batch {
+                    match GroupRecord::from_record(&record)? {
+                        Some(record @ GroupRecord::OffsetCommit(..)) => match producer_id {
+                            Some(producer_id) => offsets.apply_pending(producer_id, record),
+                            None => offsets.apply(record),
+                        },
+                        Some(GroupRecord::GroupMetadata(key, None)) => {
+                            groups.remove(&key.group_id);
+                        }
+                        Some(record) => {
+                            groups
+                                .entry(record.group_id().to_string())
+                                .or_insert_with_key(|group_id| ConsumerGroup::new(group_id))
+                                .replay(&record);
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+
+        // Give every member a full session to heartbeat again, and the
+        // offsets of empty groups a full retention period.
+        let now = util::now_ms();
+        for group in groups.values_mut() {
+            for member in group.members.values_mut() {
+                member.last_heartbeat_ms = now;
+            }
+            group.update_empty_since(now);
+        }
+
+        Ok(())
+    }
+
+    /// The `__consumer_offsets` partition that owns `group_id`.
+    pub(crate) fn partition_for(&self, group_id: &str) -> i32 {
+        (util::java_string_hash(group_id) & 0x7FFF_FFFF) % self.num_partitions
+    }
+
+    /// Handles a ConsumerGroupHeartbeat. The resulting records are written
+    /// to the log before the in-memory group is updated.
+    pub(crate) fn consumer_group_heartbeat(
+        &self,
+        request: HeartbeatRequest,
+        topics: &[TopicMetadata],
+    ) -> std::result::Result<HeartbeatResponse, ErrorCode> {
+        let mut groups = self.groups.lock().unwrap();
+
+        let mut group = groups
+            .get(&request.group_id)
+            .cloned()
+            .unwrap_or_else(|| ConsumerGroup::new(&request.group_id));
+        let now = util::now_ms();
+        let (response, records) = group.heartbeat(&request, topics, &self.consumer, now)?;
+        group.update_empty_since(now);
+
+        if let Err(e) = self.append(self.partition_for(&request.group_id), records) {
+            eprintln!("Failed to write group {}: {e}", request.group_id);
+            return Err(ErrorCode::CoordinatorNotAvailable);
+        }
+        groups.insert(request.group_id, group);
+
+        Ok(response)
+    }
+
+    /// Removes the members which missed their session timeout or did not
+    /// revoke their partitions in time.
+    pub(crate) fn expire_members(&self, now: i64) -> Result<usize> {
+        let mut groups = self.groups.lock().unwrap();
+
+        let mut expired = 0;
+        for group in groups.values_mut() {
+            let members = group.expired_members(now, self.consumer.session_timeout_ms);
+            if members.is_empty() {
+                continue;
+            }
+
+            let mut updated = group.clone();
+            let mut records = vec![];
+            for member_id in &members {
+                updated.remove_member(member_id, &mut records);
+            }
+            self.append(self.partition_for(&group.group_id), records)?;
+
+            updated.update_empty_since(now);
+            *group = updated;
+            expired += members.len();
+        }
+
+        Ok(expired)
+    }
+
+    /// Every known group: consumer groups and groups which only have
+    /// committed offsets, e.g. from simple consumers.
+    pub(crate) fn list_groups(&self) -> Vec<GroupOverview> {
+        let groups = self.groups.lock().unwrap();
+        let offsets = self.offsets.lock().unwrap();
+
+        let mut overviews: BTreeMap<String, GroupOverview> = offsets
+            .group_ids()
+            .map(|group_id| {
+                let overview = GroupOverview {
+                    group_id: group_id.clone(),
+                    protocol_type: String::new(),
+                    state: "Empty".to_string(),
+                    group_type: "classic".to_string(),
+                };
+                (group_id.clone(), overview)
+            })
+            .collect();
+        for group in groups.values() {
+            let overview = GroupOverview {
+                group_id: group.group_id.clone(),
+                protocol_type: CONSUMER_PROTOCOL_TYPE.to_string(),
+                state: group.state().to_string(),
+                group_type: "consumer".to_string(),
+            };
+            overviews.insert(group.group_id.clone(), overview);
+        }
+
+        overviews.into_values().collect()
+    }
+
+    /// Describes a group, or returns `None` if the coordinator does not
+    /// know about it.
+    pub(crate) fn describe_group(&self, group_id: &str) -> Option<GroupDescription> {
+        let groups = self.groups.lock().unwrap();
+
+        let Some(group) = groups.get(group_id) else {
+            let offsets = self.offsets.lock().unwrap();
+            return offsets
+                .group_ids()
+                .any(|id| id == group_id)
+                .then(|| GroupDescription {
+                    group_id: group_id.to_string(),
+                    state: "Empty".to_string(),
+                    protocol_type: String::new(),
+                    protocol_data: String::new(),
+                    members: vec![],
+                });
+        };
+
+        let topic_name = |topic_id: &crate::types::Uuid| {
+            group
+                .subscription_metadata
+                .get(topic_id)
+                .map(|topic| topic.name.clone())
+                .unwrap_or_else(|| topic_id.to_string())
+        };
+        let members = group
+            .members
+            .values()
+            .map(|member| MemberDescription {
+                member_id: member.member_id.clone(),
+                instance_id: member.instance_id.clone(),
+                client_id: member.client_id.clone(),
+                client_host: member.client_host.clone(),
+                subscribed_topics: member.subscribed_topic_names.iter().cloned().collect(),
+                assignment: member
+                    .assigned
+                    .iter()
+                    .map(|(topic_id, partitions)| {
+                        (topic_name(topic_id), partitions.iter().copied().collect())
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Some(GroupDescription {
+            group_id: group.group_id.clone(),
+            state: group.state().to_string(),
+            protocol_type: CONSUMER_PROTOCOL_TYPE.to_string(),
+            protocol_data: group.preferred_assignor(&self.consumer).to_string(),
+            members,
+        })
+    }
+
+    /// Deletes empty groups together with their committed offsets.
+    pub(crate) fn delete_group(&self, group_id: &str) -> ErrorCode {
+        let mut groups = self.groups.lock().unwrap();
+        let mut offsets = self.offsets.lock().unwrap();
+
+        let group = groups.get(group_id);
+        let committed = offsets.offsets(group_id);
+        if group.is_none() && committed.is_empty() {
+            return ErrorCode::GroupIdNotFound;
+        }
+        if group.is_some_and(|group| !group.members.is_empty()) {
+            return ErrorCode::NonEmptyGroup;
+        }
+
+        let mut records: Vec<GroupRecord> = committed
+            .iter()
+            .map(|(tp, _)| offsets::tombstone(group_id, tp))
+            .collect();
+        if let Some(group) = group {
+            records.extend(group.tombstones());
+        }
+        if let Err(e) = self.append(self.partition_for(group_id), records) {
+            eprintln!("Failed to delete group {group_id}: {e}");
+            return ErrorCode::CoordinatorNotAvailable;
+        }
+
+        groups.remove(group_id);
+        for (tp, _) in committed {
+            offsets.remove(group_id, &tp);
+        }
+        ErrorCode::NoError
+    }
+
+    /// Deletes committed offsets of a group. Offsets of topics the group is
+    /// still subscribed to are kept.
+    pub(crate) fn delete_offsets(
+        &self,
+        group_id: &str,
+        partitions: Vec<TopicPartition>,
+    ) -> std::result::Result<Vec<(TopicPartition, ErrorCode)>, ErrorCode> {
+        let groups = self.groups.lock().unwrap();
+        let mut offsets = self.offsets.lock().unwrap();
+
+        let group = groups.get(group_id);
+        if group.is_none() && !offsets.group_ids().any(|id| id == group_id) {
+            return Err(ErrorCode::GroupIdNotFound);
+        }
+
+        let results: Vec<(TopicPartition, ErrorCode)> = partitions
+            .into_iter()
+            .map(|tp| {
+                if group.is_some_and(|group| group.is_subscribed_to(&tp.topic)) {
+                    (tp, ErrorCode::GroupSubscribedToTopic)
+                } else {
+                    (tp, ErrorCode::NoError)
+                }
+            })
+            .collect();
+
+        let deleted: Vec<&TopicPartition> = results
+            .iter()
+            .filter(|(tp, error_code)| {
+                *error_code == ErrorCode::NoError && offsets.get(group_id, tp).is_some()
+            })
+            .map(|(tp, _)| tp)
+            .collect();
+        let records = deleted
+            .iter()
+            .map(|tp| offsets::tombstone(group_id, tp))
+            .collect();
+        if let Err(e) = self.append(self.partition_for(group_id), records) {
+            eprintln!("Failed to delete offsets of group {group_id}: {e}");
+            return Err(ErrorCode::CoordinatorNotAvailable);
+        }
+        for tp in deleted {
+            offsets.remove(group_id, tp);
+        }
+
+        Ok(results)
+    }
+
+    /// The lag of every partition the group has committed offsets for, or
+    /// `None` if the group is unknown.
+    pub(crate) fn lag(&self, group_id: &str) -> Result<Option<Vec<PartitionLag>>> {
+        let committed = self.offsets.lock().unwrap().offsets(group_id);
+        if committed.is_empty() && !self.groups.lock().unwrap().contains_key(group_id) {
+            return Ok(None);
+        }
+
+        committed
+            .into_iter()
+            .map(|(tp, offset)| {
+                let log_end_offset = self
+                    .logs
+                    .get(&tp)?
+                    .map(|log| log.lock().unwrap().log_end_offset());
+                Ok(PartitionLag {
+                    tp,
+                    committed_offset: offset.offset,
+                    log_end_offset,
+                })
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Some)
+    }
+
+    /// Validates the member of an OffsetFetch against the consumer group.
+    pub(crate) fn validate_offset_fetch(
+        &self,
+        group_id: &str,
+        member_id: &str,
+        member_epoch: i32,
+    ) -> std::result::Result<(), ErrorCode> {
+        let groups = self.groups.lock().unwrap();
+        match groups.get(group_id) {
+            Some(group) => group.validate_offset_fetch(member_id, member_epoch),
+            None if member_epoch >= 0 => Err(ErrorCode::UnknownMemberIdException),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn commit_offsets(
+        &self,
+        group_id: &str,
+        generation_id_or_member_epoch: i32,
+        member_id: &str,
+        offsets: Vec<(TopicPartition, OffsetAndMetadata)>,
+    ) -> Vec<(TopicPartition, ErrorCode)> {
+        self.commit(
+            group_id,
+            generation_id_or_member_epoch,
+            member_id,
+            offsets,
+            None,
+        )
+    }
+
+    /// Commits offsets within the transaction of a producer. They become
+    /// visible once the commit marker is written to the group's partition.
+    pub(crate) fn commit_transactional_offsets(
+        &self,
+        group_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        generation_id_or_member_epoch: i32,
+        member_id: &str,
+        offsets: Vec<(TopicPartition, OffsetAndMetadata)>,
+    ) -> Vec<(TopicPartition, ErrorCode)> {
+        self.commit(
+            group_id,
+            generation_id_or_member_epoch,
+            member_id,
+            offsets,
+            Some((producer_id, producer_epoch)),
+        )
+    }
+
+    /// Appends a transaction marker to a `__consumer_offsets` partition and
+    /// ends the transaction for the offsets of the groups in it.
+    pub(crate) fn write_txn_marker(
+        &self,
+        partition: i32,
+        marker: RecordBatch<VarintBytes>,
+    ) -> Result<()> {
+        let mut cache = self.offsets.lock().unwrap();
+
+        let producer_id = marker.producer_id;
+        let commit = marker.control_type() == Some(ControlRecordType::Commit);
+        self.write_batch(partition, marker)?;
+
+        cache.complete_pending(producer_id, commit, |group_id| {
+            self.partition_for(group_id) == partition
+        });
+        Ok(())
+    }
+
+    fn commit(
+        &self,
+        group_id: &str,
+        generation_id_or_member_epoch: i32,
+        member_id: &str,
+        offsets: Vec<(TopicPartition, OffsetAndMetadata)>,
+        producer: Option<(i64, i16)>,
+    ) -> Vec<(TopicPartition, ErrorCode)> {
+        let group_error = if group_id.is_empty() {
+            Some(ErrorCode::InvalidGroupIdException)
+        } else {
+            let groups = self.groups.lock().unwrap();
+            match groups.get(group_id) {
+                Some(group) => group
+                    .validate_offset_commit(member_id, generation_id_or_member_epoch)
+                    .err(),
+                // Without a consumer group only commits from simple
+                // consumers (without group membership) are accepted.
+                None if generation_id_or_member_epoch >= 0 => {
+                    Some(ErrorCode::UnknownMemberIdException)
+                }
+                None => None,
+            }
+        };
+        if let Some(error_code) = group_error {
+            return offsets
+                .into_iter()
+                .map(|(tp, _)| (tp, error_code))
+                .collect();
+        }
+
+        let (accepted, rejected): (Vec<_>, Vec<_>) = offsets
+            .into_iter()
+            .partition(|(_, offset)| offset.metadata.len() <= self.metadata_max_bytes);
+
+        let mut results: Vec<(TopicPartition, ErrorCode)> = rejected
+            .into_iter()
+            .map(|(tp, _)| (tp, ErrorCode::OffsetMetadataTooLargeCode))
+            .collect();
+
+        let mut cache = self.offsets.lock().unwrap();
+
+        let records = accepted
+            .iter()
+            .map(|(tp, offset)| {
+                GroupRecord::OffsetCommit(
+                    OffsetCommitKey {
+                        group: group_id.to_string(),
+                        topic: tp.topic.clone(),
+                        partition: tp.partition,
+                    },
+                    Some(offset.clone().into()),
+                )
+            })
+            .collect();
+
+        let partition = self.partition_for(group_id);
+        let appended = match producer {
+            Some((producer_id, producer_epoch)) => {
+                self.append_transactional(partition, records, producer_id, producer_epoch)
+            }
+            None => self.append(partition, records),
+        };
+        let error_code = match appended {
+            Ok(()) => ErrorCode::NoError,
+            Err(e) => {
+                eprintln!("Failed to write offsets of group {group_id}: {e}");
+                ErrorCode::CoordinatorNotAvailable
+            }
+        };
+
+        for (tp, offset) in accepted {
+            if error_code == ErrorCode::NoError {
+                match producer {
+                    Some((producer_id, _)) => {
+                        cache.put_pending(producer_id, group_id, tp.clone(), offset)
+                    }
+                    None => cache.put(group_id, tp.clone(), offset),
+                }
+            }
+            results.push((tp, error_code));
+        }
+
+        results
+    }
+
+    /// Returns the committed offsets of the group, either for the given
+    /// partitions or for every partition the group has committed to.
+    pub(crate) fn fetch_offsets(
+        &self,
+        group_id: &str,
+        partitions: Option<Vec<TopicPartition>>,
+    ) -> Vec<(TopicPartition, Option<OffsetAndMetadata>)> {
+        let cache = self.offsets.lock().unwrap();
+
+        match partitions {
+            Some(partitions) => partitions
+                .into_iter()
+                .map(|tp| {
+                    let offset = cache.get(group_id, &tp).cloned();
+                    (tp, offset)
+                })
+                .collect(),
+            None => cache
+                .offsets(group_id)
+                .into_iter()
+                .map(|(tp, offset)| (tp, Some(offset)))
+                .collect(),
+        }
+    }
+
+    /// Removes offsets kept longer than `offsets.retention.minutes` and
+    /// writes tombstones for them. As in KIP-211, the offsets of a group
+    /// with members only expire for topics it does not subscribe to, and
+    /// the retention period of an empty group starts when it became empty.
+    pub(crate) fn expire_offsets(&self, now: i64) -> Result<usize> {
+        let groups = self.groups.lock().unwrap();
+        let mut cache = self.offsets.lock().unwrap();
+
+        let expired = cache.expired(now, self.retention_ms, |group_id, tp, offset| {
+            match groups.get(group_id) {
+                // Committed without group membership, e.g. by simple consumers.
+                None => Some(offset.commit_timestamp),
+                Some(group) if group.members.is_empty() => Some(
+                    group
+                        .empty_since_ms
+                        .map_or(offset.commit_timestamp, |empty_since| {
+                            empty_since.max(offset.commit_timestamp)
+                        }),
+                ),
+                Some(group) if group.is_subscribed_to(&tp.topic) => None,
+                Some(_) => Some(offset.commit_timestamp),
+            }
+        });
+
+        let mut by_partition: HashMap<i32, Vec<(String, TopicPartition)>> = HashMap::new();
+        for (group_id, tp) in expired {
+            by_partition
+                .entry(self.partition_for(&group_id))
+                .or_default()
+                .push((group_id, tp));
+        }
+
+        let mut expired = 0;
+        for (partition, entries) in by_partition {
+            let records = entries
+                .iter()
+                .map(|(group_id, tp)| offsets::tombstone(group_id, tp))
+                .collect();
+            self.append(partition, records)?;
+
+            for (group_id, tp) in entries {
+                cache.remove(&group_id, &tp);
+                expired += 1;
+            }
+        }
+
+        Ok(expired)
+    }
+
+    fn append(&self, partition: i32, records: Vec<GroupRecord>) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        self.write_batch(partition, into_batch(records)?)
+    }
+
+    fn append_transactional(
+        &self,
+        partition: i32,
+        records: Vec<GroupRecord>,
+        producer_id: i64,
+        producer_epoch: i16,
+    ) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let batch = into_batch(records)?.into_transactional(producer_id, producer_epoch)?;
+        self.write_batch(partition, batch)
+    }
+
+    fn write_batch(&self, partition: i32, batch: RecordBatch<VarintBytes>) -> Result<()> {
+        let tp = TopicPartition::new(GROUP_METADATA_TOPIC, partition);
+        let log = self.logs.get_or_create(&tp)?;
+        log.lock().unwrap().append(batch)?;
+        self.logs.purgatory().notify(&tp);
+        Ok(())
+    }
+}
+
+fn into_batch(records: Vec<GroupRecord>) -> Result<RecordBatch<VarintBytes>> {
+    let records = records
+        .into_iter()
+        .enumerate()
+        .map(|(i, record)| record.into_record(i as i32))
+        .collect::<Result<Vec<_>>>()?;
+    RecordBatch::new(util::now_ms(), records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coordinator(name: &str) -> (GroupCoordinator, std::path::PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("kafka-group-test-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let config = Config {
+            log_dir: dir.clone(),
+            ..Config::default()
+        };
+        let logs = Arc::new(LogManager::new(&dir));
+        (GroupCoordinator::new(&config, logs), dir)
+    }
+
+    fn offset(offset: i64) -> OffsetAndMetadata {
+        OffsetAndMetadata {
+            offset,
+            leader_epoch: -1,
+            metadata: String::new(),
+            commit_timestamp: util::now_ms(),
+        }
+    }
+
+    #[test]
+    fn test_commit_and_reload_offsets() {
+        let (coordinator, dir) = coordinator("reload");
+        let tp = TopicPartition::new("foo", 0);
+
+        let results = coordinator.commit_offsets("group", -1, "", vec![(tp.clone(), offset(5))]);
+        assert_eq!(results, vec![(tp.clone(), ErrorCode::NoError)]);
+
+        let logs = Arc::new(LogManager::new(&dir));
+        let reloaded = GroupCoordinator::new(&Config::default(), logs);
+        reloaded.load().unwrap();
+        let fetched = reloaded.fetch_offsets("group", None);
+        assert_eq!(fetched.len(), 1);
+        assert_eq!(fetched[0].1.as_ref().map(|o| o.offset), Some(5));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_expire_offsets() {
+        let (coordinator, dir) = coordinator("expire");
+        let tp = TopicPartition::new("foo", 0);
+
+        coordinator.commit_offsets("group", -1, "", vec![(tp.clone(), offset(5))]);
+        assert_eq!(coordinator.expire_offsets(util::now_ms()).unwrap(), 0);
+
+        let later = util::now_ms() + coordinator.retention_ms;
+        assert_eq!(coordinator.expire_offsets(later).unwrap(), 1);
+        assert_eq!(
+            coordinator.fetch_offsets("group", Some(vec![tp.clone()])),
+            vec![(tp, None)]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_consumer_group_commit_and_reload() {
+        let (coordinator, dir) = coordinator("consumer");
+        let topics = vec![TopicMetadata {
+            id: crate::types::Uuid::from([1; 16]),
+            name: "foo".to_string(),
+            num_partitions: 1,
+        }];
+        let request = HeartbeatRequest {
+            group_id: "group".to_string(),
+            member_epoch: 0,
+            rebalance_timeout_ms: 30000,
+            subscribed_topic_names: Some(vec!["foo".to_string()]),
+            ..HeartbeatRequest::default()
+        };
+        let response = coordinator
+            .consumer_group_heartbeat(request, &topics)
+            .unwrap();
+        assert_eq!(response.member_epoch, 1);
+
+        let tp = TopicPartition::new("foo", 0);
+        let member_id = response.member_id.as_str();
+        assert_eq!(
+            coordinator.commit_offsets("group", 0, member_id, vec![(tp.clone(), offset(1))]),
+            vec![(tp.clone(), ErrorCode::StaleMemberEpoch)]
+        );
+        assert_eq!(
+            coordinator.commit_offsets("group", -1, "", vec![(tp.clone(), offset(1))]),
+            vec![(tp.clone(), ErrorCode::UnknownMemberIdException)]
+        );
+        assert_eq!(
+            coordinator.commit_offsets("group", 1, member_id, vec![(tp.clone(), offset(1))]),
+            vec![(tp.clone(), ErrorCode::NoError)]
+        );
+
+        let logs = Arc::new(LogManager::new(&dir));
+        let reloaded = GroupCoordinator::new(&Config::default(), logs);
+        reloaded.load().unwrap();
+        assert_eq!(
+            reloaded.validate_offset_fetch("group", member_id, 1),
+            Ok(())
+        );
+        assert_eq!(reloaded.expire_members(util::now_ms()).unwrap(), 0);
+        assert_eq!(reloaded.expire_members(i64::MAX / 2).unwrap(), 1);
+        assert_eq!(
+            reloaded.validate_offset_fetch("group", member_id, 1),
+            Err(ErrorCode::UnknownMemberIdException)
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_expire_consumer_group_offsets() {
+        let (coordinator, dir) = coordinator("expire_group");
+        let topics = vec![TopicMetadata {
+            id: crate::types::Uuid::from([1; 16]),
+            name: "foo".to_string(),
+            num_partitions: 1,
+        }];
+        let request = HeartbeatRequest {
+            group_id: "group".to_string(),
+            member_epoch: 0,
+            rebalance_timeout_ms: 30000,
+            subscribed_topic_names: Some(vec!["foo".to_string()]),
+            ..HeartbeatRequest::default()
+        };
+        let response = coordinator
+            .consumer_group_heartbeat(request, &topics)
+            .unwrap();
+
+        let foo = TopicPartition::new("foo", 0);
+        let bar = TopicPartition::new("bar", 0);
+        let member_id = response.member_id.as_str();
+        coordinator.commit_offsets(
+            "group",
+            1,
+            member_id,
+            vec![(foo.clone(), offset(1)), (bar.clone(), offset(2))],
+        );
+
+        // Only the offsets of topics the group no longer subscribes to
+        // expire while it has members.
+        let later = util::now_ms() + coordinator.retention_ms;
+        assert_eq!(coordinator.expire_offsets(later).unwrap(), 1);
+        assert_eq!(
+            coordinator.fetch_offsets("group", Some(vec![bar.clone()])),
+            vec![(bar, None)]
+        );
+
+        // The retention period of an empty group starts when it empties.
+        let empty_since = later + 1;
+        assert_eq!(coordinator.expire_members(empty_since).unwrap(), 1);
+        assert_eq!(coordinator.expire_offsets(empty_since).unwrap(), 0);
+        let expiry = empty_since + coordinator.retention_ms;
+        assert_eq!(coordinator.expire_offsets(expiry - 1).unwrap(), 0);
+        assert_eq!(coordinator.expire_offsets(expiry).unwrap(), 1);
+        assert_eq!(
+            coordinator.fetch_offsets("group", Some(vec![foo.clone()])),
+            vec![(foo, None)]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_list_describe_and_delete_groups() {
+        let (coordinator, dir) = coordinator("delete");
+        let tp = TopicPartition::new("foo", 0);
+        coordinator.commit_offsets("simple", -1, "", vec![(tp.clone(), offset(1))]);
+
+        let request = HeartbeatRequest {
+            group_id: "consumer".to_string(),
+            member_epoch: 0,
+            rebalance_timeout_ms: 30000,
+            subscribed_topic_names: Some(vec!["foo".to_string()]),
+            ..HeartbeatRequest::default()
+        };
+        let response = coordinator.consumer_group_heartbeat(request, &[]).unwrap();
+
+        let groups = coordinator.list_groups();
+        assert_eq!(
+            groups
+                .iter()
+                .map(|g| (g.group_id.as_str(), g.state.as_str(), g.group_type.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("consumer", "Stable", "consumer"),
+                ("simple", "Empty", "classic")
+            ]
+        );
+
+        let description = coordinator.describe_group("consumer").unwrap();
+        assert_eq!(description.protocol_type, "consumer");
+        assert_eq!(description.members[0].member_id, response.member_id);
+        assert_eq!(description.members[0].subscribed_topics, vec!["foo"]);
+        assert!(coordinator.describe_group("unknown").is_none());
+
+        assert_eq!(
+            coordinator.delete_group("consumer"),
+            ErrorCode::NonEmptyGroup
+        );
+        assert_eq!(
+            coordinator.delete_group("unknown"),
+            ErrorCode::GroupIdNotFound
+        );
+        assert_eq!(coordinator.delete_group("simple"), ErrorCode::NoError);
+
+        let logs = Arc::new(LogManager::new(&dir));
+        let reloaded = GroupCoordinator::new(&Config::default(), logs);
+        reloaded.load().unwrap();
+        assert_eq!(reloaded.list_groups().len(), 1);
+        assert!(reloaded.fetch_offsets("simple", None).is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_delete_offsets_and_lag() {
+        let (coordinator, dir) = coordinator("lag");
+        let foo = TopicPartition::new("foo", 0);
+        let bar = TopicPartition::new("bar", 0);
+        coordinator.commit_offsets(
+            "group",
+            -1,
+            "",
+            vec![(foo.clone(), offset(1)), (bar.clone(), offset(2))],
+        );
+
+        let log = coordinator.logs.get_or_create(&foo).unwrap();
+        let value = VarintBytes::new(Some(b"v".to_vec()));
+        let records = (0..3)
+            .map(|i| crate::types::Record::new(i, VarintBytes::new(None), value.clone()))
+            .collect();
+        log.lock()
+            .unwrap()
+            .append(RecordBatch::new(0, records).unwrap())
+            .unwrap();
+
+        let lag = coordinator.lag("group").unwrap().unwrap();
+        assert_eq!(
+            lag.iter().map(|l| (&l.tp, l.lag())).collect::<Vec<_>>(),
+            vec![(&bar, None), (&foo, Some(2))]
+        );
+        assert_eq!(coordinator.lag("unknown").unwrap(), None);
+
+        assert_eq!(
+            coordinator.delete_offsets("group", vec![foo.clone()]),
+            Ok(vec![(foo.clone(), ErrorCode::NoError)])
+        );
+        assert_eq!(
+            coordinator.delete_offsets("unknown", vec![foo.clone()]),
+            Err(ErrorCode::GroupIdNotFound)
+        );
+        assert_eq!(
+            coordinator.fetch_offsets("group", Some(vec![foo.clone()])),
+            vec![(foo, None)]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_partition_for() {
+        let (coordinator, _) = coordinator("partition");
+        assert_eq!(
+            coordinator.partition_for("my-consumer-group"),
+            1_513_705_513 % 50
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use crate::log::TopicPartition;
+
+use super::records::{GroupRecord, OffsetCommitKey, OffsetCommitValue};
+
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct OffsetAndMetadata {
+    pub(crate) offset: i64,
+    pub(crate) leader_epoch: i32,
+    pub(crate) metadata: String,
+    pub(crate) commit_timestamp: i64,
+}
+
+impl From<OffsetCommitValue> for OffsetAndMetadata {
+    fn from(value: OffsetCommitValue) -> Self {
+        Self {
+            offset: value.offset,
+            leader_epoch: value.leader_epoch,
+            metadata: value.metadata,
+            commit_timestamp: value.commit_timestamp,
+        }
+    }
+}
+
+impl From<OffsetAndMetadata> for OffsetCommitValue {
+    fn from(value: OffsetAndMetadata) -> Self {
+        Self {
+            offset: value.offset,
+            leader_epoch: value.leader_epoch,
+            metadata: value.metadata,
+            commit_timestamp: value.commit_timestamp,
+        }
+    }
+}
+
+/// In-memory view of the committed offsets stored in `__consumer_offsets`.
+#[derive(Debug, Default)]
+pub(crate) struct OffsetCache {
+    groups: HashMap<String, BTreeMap<TopicPartition, OffsetAndMetadata>>,
+    /// Offsets committed within transactions which have not ended yet, by
+    /// producer id.
+    pending: HashMap<i64, Vec<(String, TopicPartition, OffsetAndMetadata)>>,
+}
+
+impl OffsetCache {
+    pub(crate) fn apply(&mut self, record: GroupRecord) {
+        match record {
+            GroupRecord::OffsetCommit(key, Some(value)) => {
+                let tp = TopicPartition::new(key.topic, key.partition);
+                self.put(&key.group, tp, value.into());
+            }
+            GroupRecord::OffsetCommit(key, None) => {
+                let tp = TopicPartition::new(key.topic, key.partition);
+                self.remove(&key.group, &tp);
+            }
+            _ => {}
+        }
+    }
+
+    /// Like [`Self::apply`] for a record written within the transaction of
+    /// `producer_id`.
+    pub(crate) fn apply_pending(&mut self, producer_id: i64, record: GroupRecord) {
+        if let GroupRecord::OffsetCommit(key, Some(value)) = record {
+            let tp = TopicPartition::new(key.topic, key.partition);
+            self.put_pending(producer_id, &key.group, tp, value.into());
+        }
+    }
+
+    pub(crate) fn put_pending(
+        &mut self,
+        producer_id: i64,
+        group_id: &str,
+        tp: TopicPartition,
+        offset: OffsetAndMetadata,
+    ) {
+        self.pending
+            .entry(producer_id)
+            .or_default()
+            .push((group_id.to_string(), tp, offset));
+    }
+
+    /// Ends the transaction of `producer_id` for the groups accepted by
+    /// `owned`: committed offsets become visible and aborted ones are
+    /// dropped.
+    pub(crate) fn complete_pending<F>(&mut self, producer_id: i64, commit: bool, owned: F)
+    where
+        F: Fn(&str) -> bool,
+    {
+        let Some(pending) = self.pending.remove(&producer_id) else {
+            return;
+        };
+        let (completed, remaining): (Vec<_>, Vec<_>) = pending
+            .into_iter()
+            .partition(|(group_id, _, _)| owned(group_id));
+
+        if commit {
+            for (group_id, tp, offset) in completed {
+                self.put(&group_id, tp, offset);
+            }
+        }
+        if !remaining.is_empty() {
+            self.pending.insert(producer_id, remaining);
+        }
+    }
+
+    pub(crate) fn put(&mut self, group_id: &str, tp: TopicPartition, offset: OffsetAndMetadata) {
+        self.groups
+            .entry(group_id.to_string())
+            .or_default()
+            .insert(tp, offset);
+    }
+
+    pub(crate) fn remove(
+        &mut self,
+        group_id: &str,
+        tp: &TopicPartition,
+    ) -> Option<OffsetAndMetadata> {
+        let offsets = self.groups.get_mut(group_id)?;
+        let removed = offsets.remove(tp);
+        if offsets.is_empty() {
+            self.groups.remove(group_id);
+        }
+        removed
+    }
+
+    pub(crate) fn get(&self, group_id: &str, tp: &TopicPartition) -> Option<&OffsetAndMetadata> {
+        self.groups
+            .get(group_id)
+            .and_then(|offsets| offsets.get(tp))
+    }
+
+    pub(crate) fn offsets(&self, group_id: &str) -> Vec<(TopicPartition, OffsetAndMetadata)> {
+        self.groups
+            .get(group_id)
+            .map(|offsets| {
+                offsets
+                    .iter()
+                    .map(|(tp, offset)| (tp.clone(), offset.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Groups with at least one committed offset.
+    pub(crate) fn group_ids(&self) -> impl Iterator<Item = &String> {
+        self.groups.keys()
+    }
+
+    /// Offsets whose retention period has passed. `expires_from` gives the
+    /// time the retention period of an offset starts at, or `None` if the
+    /// offset does not expire.
+    pub(crate) fn expired<F>(
+        &self,
+        now: i64,
+        retention_ms: i64,
+        expires_from: F,
+    ) -> Vec<(String, TopicPartition)>
+    where
+        F: Fn(&str, &TopicPartition, &OffsetAndMetadata) -> Option<i64>,
+    {
+        self.groups
+            .iter()
+            .flat_map(|(group_id, offsets)| {
+                offsets
+                    .iter()
+                    .filter(|&(tp, offset)| {
+                        expires_from(group_id, tp, offset)
+                            .is_some_and(|from| from + retention_ms <= now)
+                    })
+                    .map(move |(tp, _)| (group_id.clone(), tp.clone()))
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn tombstone(group_id: &str, tp: &TopicPartition) -> GroupRecord {
+    GroupRecord::OffsetCommit(
+        OffsetCommitKey {
+            group: group_id.to_string(),
+            topic: tp.topic.clone(),
+            partition: tp.partition,
+        },
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offset(offset: i64, commit_timestamp: i64) -> OffsetAndMetadata {
+        OffsetAndMetadata {
+            offset,
+            leader_epoch: -1,
+            metadata: String::new(),
+            commit_timestamp,
+        }
+    }
+
+    #[test]
+    fn test_apply_commit_and_tombstone() {
+        let mut cache = OffsetCache::default();
+        let tp = TopicPartition::new("t", 0);
+
+        cache.apply(GroupRecord::OffsetCommit(
+            OffsetCommitKey {
+                group: "g".into(),
+                topic: "t".into(),
+                partition: 0,
+            },
+            Some(offset(10, 0).into()),
+        ));
+        assert_eq!(cache.get("g", &tp), Some(&offset(10, 0)));
+
+        cache.apply(tombstone("g", &tp));
+        assert_eq!(cache.get("g", &tp), None);
+        assert!(cache.offsets("g").is_empty());
+    }
+
+    #[test]
+    fn test_complete_pending() {
+        let mut cache = OffsetCache::default();
+        let tp = TopicPartition::new("foo", 0);
+        cache.put_pending(7, "a", tp.clone(), offset(1, 0));
+        cache.put_pending(7, "b", tp.clone(), offset(2, 0));
+        assert!(cache.get("a", &tp).is_none());
+
+        cache.complete_pending(7, true, |group_id| group_id == "a");
+        assert_eq!(cache.get("a", &tp), Some(&offset(1, 0)));
+        assert!(cache.get("b", &tp).is_none());
+
+        cache.complete_pending(7, false, |_| true);
+        assert!(cache.get("b", &tp).is_none());
+        assert!(cache.pending.is_empty());
+    }
+
+    #[test]
+    fn test_expired() {
+        let mut cache = OffsetCache::default();
+        cache.put("g", TopicPartition::new("t", 0), offset(1, 100));
+        cache.put("g", TopicPartition::new("t", 1), offset(1, 500));
+
+        let committed =
+            |_: &str, _: &TopicPartition, offset: &OffsetAndMetadata| Some(offset.commit_timestamp);
+        assert_eq!(
+            cache.expired(1000, 900, committed),
+            vec![("g".to_string(), TopicPartition::new("t", 0))]
+        );
+        assert_eq!(cache.expired(1500, 1000, committed).len(), 2);
+        assert!(cache.expired(1500, 1000, |_, _, _| None).is_empty());
+    }
+}
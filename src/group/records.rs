@@ -0,0 +1,356 @@
+use crate::{
+    KafkaError, Result,
+    de::Deserializer,
+    ser::Serializer,
+    types::{
+        CompactArray, CompactNullableString, CompactString, Record, TaggedFields, Uuid, VarintBytes,
+    },
+};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+const OFFSET_COMMIT_KEY_VERSION: i16 = 1;
+const OFFSET_COMMIT_VALUE_VERSION: i16 = 3;
+const CONSUMER_GROUP_METADATA_KEY_VERSION: i16 = 3;
+const CONSUMER_GROUP_PARTITION_METADATA_KEY_VERSION: i16 = 4;
+const CONSUMER_GROUP_MEMBER_METADATA_KEY_VERSION: i16 = 5;
+const CONSUMER_GROUP_TARGET_ASSIGNMENT_METADATA_KEY_VERSION: i16 = 6;
+const CONSUMER_GROUP_TARGET_ASSIGNMENT_MEMBER_KEY_VERSION: i16 = 7;
+const CONSUMER_GROUP_CURRENT_MEMBER_ASSIGNMENT_KEY_VERSION: i16 = 8;
+// All consumer group values are flexible and only have version 0.
+const CONSUMER_GROUP_VALUE_VERSION: i16 = 0;
+
+/// Key of a committed offset in `__consumer_offsets`. Key versions 0 and 1
+/// share this layout.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct OffsetCommitKey {
+    pub(crate) group: String,
+    pub(crate) topic: String,
+    pub(crate) partition: i32,
+}
+
+/// Value of a committed offset in `__consumer_offsets` (version 3).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct OffsetCommitValue {
+    pub(crate) offset: i64,
+    pub(crate) leader_epoch: i32,
+    pub(crate) metadata: String,
+    pub(crate) commit_timestamp: i64,
+}
+
+/// Key of the records holding group wide state of a consumer group.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct GroupKey {
+    pub(crate) group_id: String,
+}
+
+/// Key of the records holding the state of a single consumer group member.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct GroupMemberKey {
+    pub(crate) group_id: String,
+    pub(crate) member_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ConsumerGroupMetadataValue {
+    pub(crate) epoch: i32,
+    pub(crate) tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ConsumerGroupPartitionMetadataValue {
+    pub(crate) topics: CompactArray<TopicMetadataValue>,
+    pub(crate) tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct TopicMetadataValue {
+    pub(crate) topic_id: Uuid,
+    pub(crate) topic_name: CompactString,
+    pub(crate) num_partitions: i32,
+    pub(crate) partition_metadata: CompactArray<PartitionMetadataValue>,
+    pub(crate) tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct PartitionMetadataValue {
+    pub(crate) partition: i32,
+    pub(crate) racks: CompactArray<CompactString>,
+    pub(crate) tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ConsumerGroupMemberMetadataValue {
+    pub(crate) instance_id: CompactNullableString,
+    pub(crate) rack_id: CompactNullableString,
+    pub(crate) client_id: CompactString,
+    pub(crate) client_host: CompactString,
+    pub(crate) subscribed_topic_names: CompactArray<CompactString>,
+    pub(crate) subscribed_topic_regex: CompactNullableString,
+    pub(crate) server_assignor: CompactNullableString,
+    pub(crate) rebalance_timeout_ms: i32,
+    pub(crate) tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ConsumerGroupTargetAssignmentMetadataValue {
+    pub(crate) assignment_epoch: i32,
+    pub(crate) tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ConsumerGroupTargetAssignmentMemberValue {
+    pub(crate) topic_partitions: CompactArray<TopicPartitionsValue>,
+    pub(crate) tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ConsumerGroupCurrentMemberAssignmentValue {
+    pub(crate) member_epoch: i32,
+    pub(crate) previous_member_epoch: i32,
+    pub(crate) state: i8,
+    pub(crate) assigned_partitions: CompactArray<TopicPartitionsValue>,
+    pub(crate) partitions_pending_revocation: CompactArray<TopicPartitionsValue>,
+    pub(crate) tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct TopicPartitionsValue {
+    pub(crate) topic_id: Uuid,
+    pub(crate) partitions: CompactArray<i32>,
+    pub(crate) tagged_fields: TaggedFields,
+}
+
+/// A record stored in `__consumer_offsets`. A `None` value is a tombstone.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum GroupRecord {
+    OffsetCommit(OffsetCommitKey, Option<OffsetCommitValue>),
+    GroupMetadata(GroupKey, Option<ConsumerGroupMetadataValue>),
+    PartitionMetadata(GroupKey, Option<ConsumerGroupPartitionMetadataValue>),
+    MemberMetadata(GroupMemberKey, Option<ConsumerGroupMemberMetadataValue>),
+    TargetAssignmentMetadata(GroupKey, Option<ConsumerGroupTargetAssignmentMetadataValue>),
+    TargetAssignmentMember(
+        GroupMemberKey,
+        Option<ConsumerGroupTargetAssignmentMemberValue>,
+    ),
+    CurrentMemberAssignment(
+        GroupMemberKey,
+        Option<ConsumerGroupCurrentMemberAssignmentValue>,
+    ),
+}
+
+impl GroupRecord {
+    pub(crate) fn group_id(&self) -> &str {
+        match self {
+            Self::OffsetCommit(key, _) => &key.group,
+            Self::GroupMetadata(key, _)
+            | Self::PartitionMetadata(key, _)
+            | Self::TargetAssignmentMetadata(key, _) => &key.group_id,
+            Self::MemberMetadata(key, _)
+            | Self::TargetAssignmentMember(key, _)
+            | Self::CurrentMemberAssignment(key, _) => &key.group_id,
+        }
+    }
+
+    pub(crate) fn into_record(self, offset_delta: i32) -> Result<Record<VarintBytes>> {
+        let (key, value) = match self {
+            Self::OffsetCommit(key, value) => (
+                encode(OFFSET_COMMIT_KEY_VERSION, &key)?,
+                value
+                    .map(|v| encode(OFFSET_COMMIT_VALUE_VERSION, &v))
+                    .transpose()?,
+            ),
+            Self::GroupMetadata(key, value) => (
+                encode(CONSUMER_GROUP_METADATA_KEY_VERSION, &key)?,
+                encode_value(value)?,
+            ),
+            Self::PartitionMetadata(key, value) => (
+                encode(CONSUMER_GROUP_PARTITION_METADATA_KEY_VERSION, &key)?,
+                encode_value(value)?,
+            ),
+            Self::MemberMetadata(key, value) => (
+                encode(CONSUMER_GROUP_MEMBER_METADATA_KEY_VERSION, &key)?,
+                encode_value(value)?,
+            ),
+            Self::TargetAssignmentMetadata(key, value) => (
+                encode(CONSUMER_GROUP_TARGET_ASSIGNMENT_METADATA_KEY_VERSION, &key)?,
+                encode_value(value)?,
+            ),
+            Self::TargetAssignmentMember(key, value) => (
+                encode(CONSUMER_GROUP_TARGET_ASSIGNMENT_MEMBER_KEY_VERSION, &key)?,
+                encode_value(value)?,
+            ),
+            Self::CurrentMemberAssignment(key, value) => (
+                encode(CONSUMER_GROUP_CURRENT_MEMBER_ASSIGNMENT_KEY_VERSION, &key)?,
+                encode_value(value)?,
+            ),
+        };
+        Ok(Record::new(
+            offset_delta,
+            VarintBytes::new(Some(key)),
+            VarintBytes::new(value),
+        ))
+    }
+
+    /// Decodes a record read back from the log. Records with key versions
+    /// this broker does not know about are skipped and yield `None`.
+    pub(crate) fn from_record(record: &Record<VarintBytes>) -> Result<Option<Self>> {
+        let key = record.key.as_opt_bytes().ok_or_else(|| {
+            KafkaError::DeserializationError("group record without a key".to_string())
+        })?;
+        let value = record.value.as_opt_bytes();
+
+        let mut de = Deserializer::new(key);
+        let version: i16 = Deserialize::deserialize(&mut de)?;
+
+        match version {
+            0 | 1 => {
+                let key: OffsetCommitKey = Deserialize::deserialize(&mut de)?;
+                let value = value.map(decode_offset_commit_value).transpose()?;
+                Ok(Some(Self::OffsetCommit(key, value)))
+            }
+            CONSUMER_GROUP_METADATA_KEY_VERSION => Ok(Some(Self::GroupMetadata(
+                Deserialize::deserialize(&mut de)?,
+                decode_value(value)?,
+            ))),
+            CONSUMER_GROUP_PARTITION_METADATA_KEY_VERSION => Ok(Some(Self::PartitionMetadata(
+                Deserialize::deserialize(&mut de)?,
+                decode_value(value)?,
+            ))),
+            CONSUMER_GROUP_MEMBER_METADATA_KEY_VERSION => Ok(Some(Self::MemberMetadata(
+                Deserialize::deserialize(&mut de)?,
+                decode_value(value)?,
+            ))),
+            CONSUMER_GROUP_TARGET_ASSIGNMENT_METADATA_KEY_VERSION => {
+                Ok(Some(Self::TargetAssignmentMetadata(
+                    Deserialize::deserialize(&mut de)?,
+                    decode_value(value)?,
+                )))
+            }
+            CONSUMER_GROUP_TARGET_ASSIGNMENT_MEMBER_KEY_VERSION => {
+                Ok(Some(Self::TargetAssignmentMember(
+                    Deserialize::deserialize(&mut de)?,
+                    decode_value(value)?,
+                )))
+            }
+            CONSUMER_GROUP_CURRENT_MEMBER_ASSIGNMENT_KEY_VERSION => {
+                Ok(Some(Self::CurrentMemberAssignment(
+                    Deserialize::deserialize(&mut de)?,
+                    decode_value(value)?,
+                )))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+fn encode<T: Serialize>(version: i16, value: &T) -> Result<Vec<u8>> {
+    let mut buf: Vec<u8> = Vec::new();
+    let mut serializer = Serializer::new(&mut buf);
+    (version, value).serialize(&mut serializer)?;
+    Ok(buf)
+}
+
+fn encode_value<T: Serialize>(value: Option<T>) -> Result<Option<Vec<u8>>> {
+    value
+        .map(|v| encode(CONSUMER_GROUP_VALUE_VERSION, &v))
+        .transpose()
+}
+
+fn decode_value<T: DeserializeOwned>(bytes: Option<&[u8]>) -> Result<Option<T>> {
+    let Some(bytes) = bytes else {
+        return Ok(None);
+    };
+
+    let mut de = Deserializer::new(bytes);
+    let version: i16 = Deserialize::deserialize(&mut de)?;
+    match version {
+        CONSUMER_GROUP_VALUE_VERSION => Ok(Some(Deserialize::deserialize(&mut de)?)),
+        _ => Err(KafkaError::DeserializationError(format!(
+            "unsupported consumer group value version: {version}"
+        ))),
+    }
+}
+
+fn decode_offset_commit_value(bytes: &[u8]) -> Result<OffsetCommitValue> {
+    let mut de = Deserializer::new(bytes);
+    let version: i16 = Deserialize::deserialize(&mut de)?;
+    match version {
+        OFFSET_COMMIT_VALUE_VERSION => Ok(Deserialize::deserialize(&mut de)?),
+        _ => Err(KafkaError::DeserializationError(format!(
+            "unsupported offset commit value version: {version}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_commit_record_roundtrip() {
+        let key = OffsetCommitKey {
+            group: "g".into(),
+            topic: "t".into(),
+            partition: 2,
+        };
+        let value = OffsetCommitValue {
+            offset: 42,
+            leader_epoch: -1,
+            metadata: "".into(),
+            commit_timestamp: 1000,
+        };
+
+        let record = GroupRecord::OffsetCommit(key.clone(), Some(value.clone()))
+            .into_record(0)
+            .unwrap();
+        assert_eq!(
+            record.key.as_opt_bytes().unwrap(),
+            &[
+                0x00, 0x01, 0x00, 0x01, b'g', 0x00, 0x01, b't', 0x00, 0x00, 0x00, 0x02
+            ]
+        );
+        assert_eq!(
+            GroupRecord::from_record(&record).unwrap(),
+            Some(GroupRecord::OffsetCommit(key.clone(), Some(value)))
+        );
+
+        let tombstone = GroupRecord::OffsetCommit(key.clone(), None)
+            .into_record(1)
+            .unwrap();
+        assert_eq!(
+            GroupRecord::from_record(&tombstone).unwrap(),
+            Some(GroupRecord::OffsetCommit(key, None))
+        );
+    }
+
+    #[test]
+    fn test_current_member_assignment_record_roundtrip() {
+        let key = GroupMemberKey {
+            group_id: "g".into(),
+            member_id: "m".into(),
+        };
+        let value = ConsumerGroupCurrentMemberAssignmentValue {
+            member_epoch: 3,
+            previous_member_epoch: 2,
+            state: 0,
+            assigned_partitions: CompactArray::new(Some(vec![TopicPartitionsValue {
+                topic_id: Uuid::default(),
+                partitions: CompactArray::new(Some(vec![0, 1])),
+                tagged_fields: TaggedFields::new(None),
+            }])),
+            partitions_pending_revocation: CompactArray::new(Some(vec![])),
+            tagged_fields: TaggedFields::new(None),
+        };
+
+        let record = GroupRecord::CurrentMemberAssignment(key.clone(), Some(value.clone()))
+            .into_record(0)
+            .unwrap();
+        assert_eq!(&record.key.as_opt_bytes().unwrap()[..2], &[0x00, 0x08]);
+        assert_eq!(&record.value.as_opt_bytes().unwrap()[..2], &[0x00, 0x00]);
+        assert_eq!(
+            GroupRecord::from_record(&record).unwrap(),
+            Some(GroupRecord::CurrentMemberAssignment(key, Some(value)))
+        );
+    }
+}
@@ -1,26 +1,309 @@
+mod acl;
+mod admin;
 mod api;
+mod broker;
+mod cluster;
+mod config;
 pub(crate) mod de;
+mod dynamic_config;
 mod error;
+mod fetch_session;
+mod group;
+mod log;
+mod producer;
+mod quota;
+mod sasl;
 pub(crate) mod ser;
+mod tls;
+mod txn;
 pub(crate) mod types;
 pub(crate) mod util;
 
-use ser::Serializer;
-use serde::Serialize;
-
+pub use admin::serve_admin;
+pub use broker::Broker;
+pub use config::Config;
 pub use error::KafkaError;
 pub type Result<T> = std::result::Result<T, KafkaError>;
 
-use std::io::{Read, Write};
+use api::{Connection, DelayedRequest, Handled, Message};
+use config::Listener;
+use sasl::Authenticator;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+/// Requests of a connection which may be read ahead of their responses.
+/// Reading pauses while this many are pending.
+const MAX_IN_FLIGHT_REQUESTS: usize = 64;
+
+/// Binds every listener of the broker and serves client connections on an
+/// async runtime, handling requests on at most `num.io.threads` threads.
+/// On SIGTERM or SIGINT, stops accepting connections and requests, answers
+/// the requests already read and shuts the broker down, returning within
+/// `shutdown.timeout.ms` even if that is not done yet.
+pub fn serve_listeners(broker: Arc<Broker>) -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .max_blocking_threads(broker.config.num_io_threads)
+        .enable_all()
+        .build()?;
+    let deadline = runtime.block_on(accept_all(Arc::clone(&broker)))?;
+    // The requests still being handled finish before the logs are flushed,
+    // unless the timeout is reached first.
+    runtime.shutdown_timeout(deadline.saturating_duration_since(Instant::now()));
+
+    let (done, finished) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = done.send(broker.shutdown());
+    });
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    match finished.recv_timeout(remaining) {
+        Ok(result) => result,
+        Err(_) => {
+            eprintln!("Exiting before the logs are checkpointed: shutdown timed out");
+            Ok(())
+        }
+    }
+}
+
+/// Serves the listeners until shutdown is requested, and returns the
+/// deadline by which the shutdown must complete.
+async fn accept_all(broker: Arc<Broker>) -> Result<Instant> {
+    let mut sockets = vec![];
+    for listener in &broker.config.listeners {
+        let socket = TcpListener::bind(listener.endpoint.bind_address()).await?;
+        sockets.push((listener.clone(), socket));
+    }
+
+    let (stop, signal) = watch::channel(false);
+    let (open, mut closed) = mpsc::channel(1);
+    let shutdown = Shutdown {
+        signal,
+        _open: open,
+    };
+    for (listener, socket) in sockets {
+        let broker = Arc::clone(&broker);
+        let mut shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            loop {
+                let accepted = tokio::select! {
+                    accepted = socket.accept() => accepted,
+                    () = shutdown.requested() => break,
+                };
+                let (stream, addr) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        eprintln!("Error accepting connection: {e}");
+                        continue;
+                    }
+                };
+                if let Err(reason) = broker.connections.open(addr.ip()) {
+                    eprintln!("Closing connection from {}: {reason}", addr.ip());
+                    continue;
+                }
+                handle_connection(
+                    stream,
+                    addr.ip(),
+                    &listener,
+                    Arc::clone(&broker),
+                    shutdown.clone(),
+                );
+            }
+        });
+    }
+    drop(shutdown);
+
+    shutdown_signal().await?;
+    let _ = stop.send(true);
+    let timeout = Duration::from_millis(broker.config.shutdown_timeout_ms);
+    let deadline = Instant::now() + timeout;
+    // Completes once every listener and connection has dropped its handle.
+    let closing = tokio::time::timeout_at(deadline.into(), closed.recv());
+    if closing.await.is_err() {
+        eprintln!("Closing connections with requests in flight after {timeout:?}");
+    }
+    Ok(deadline)
+}
+
+/// Waits for SIGTERM or SIGINT.
+async fn shutdown_signal() -> Result<()> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        _ = terminate.recv() => {}
+        result = tokio::signal::ctrl_c() => result?,
+    }
+    Ok(())
+}
+
+/// Held by every listener and connection while the broker runs, to learn
+/// when it shuts down.
+#[derive(Debug, Clone)]
+struct Shutdown {
+    signal: watch::Receiver<bool>,
+    /// Dropped with the handle, so the broker knows when all are gone.
+    _open: mpsc::Sender<()>,
+}
+
+impl Shutdown {
+    /// A shutdown which is never requested.
+    #[cfg(test)]
+    fn never() -> Self {
+        Self {
+            signal: watch::channel(false).1,
+            _open: mpsc::channel(1).0,
+        }
+    }
+
+    /// Completes once shutdown is requested.
+    async fn requested(&mut self) {
+        if self.signal.wait_for(|&stop| stop).await.is_err() {
+            std::future::pending().await
+        }
+    }
+}
+
+/// Serves a client connection of `listener` from `ip`, over TLS when the
+/// listener uses SSL or SASL_SSL. The connection must have been counted
+/// by the broker's connection quotas.
+fn handle_connection(
+    stream: TcpStream,
+    ip: IpAddr,
+    listener: &Listener,
+    broker: Arc<Broker>,
+    shutdown: Shutdown,
+) {
+    let listener = listener.clone();
+    let tls = broker.tls.get(&listener.endpoint.name).cloned();
+    tokio::spawn(async move {
+        let host = ip.to_string();
+        match tls {
+            None => {
+                let conn = connection(listener, host, None);
+                serve(stream, Arc::clone(&broker), conn, shutdown).await;
+            }
+            Some(tls) => match tls.accept(stream).await {
+                Ok((stream, subject)) => {
+                    let conn = connection(listener, host, subject);
+                    serve(stream, Arc::clone(&broker), conn, shutdown).await;
+                }
+                Err(e) => eprintln!("Error in TLS handshake: {e}"),
+            },
+        }
+        broker.connections.close(ip);
+    });
+}
+
+fn connection(listener: Listener, host: String, certificate_subject: Option<String>) -> Connection {
+    let auth = Authenticator::new(listener.endpoint.security_protocol, certificate_subject);
+    Connection {
+        listener,
+        host,
+        auth,
+        throttled_until: Arc::new(Mutex::new(None)),
+    }
+}
+
+/// When a connection was last active: when a byte was last read from it,
+/// or a response last written to it.
+#[derive(Debug, Clone)]
+struct Activity(Arc<Mutex<tokio::time::Instant>>);
+
+impl Activity {
+    fn new() -> Self {
+        Self(Arc::new(Mutex::new(tokio::time::Instant::now())))
+    }
+
+    fn record(&self) {
+        *self.0.lock().unwrap() = tokio::time::Instant::now();
+    }
+
+    /// Completes once the connection has not been active for `idle`.
+    async fn idle_for(&self, idle: Duration) {
+        loop {
+            let deadline = *self.0.lock().unwrap() + idle;
+            if deadline <= tokio::time::Instant::now() {
+                return;
+            }
+            tokio::time::sleep_until(deadline).await;
+        }
+    }
+}
+
+/// Reads from a connection, recording its activity as bytes arrive.
+struct ActivityReader<R> {
+    inner: R,
+    activity: Activity,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ActivityReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let filled = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if buf.filled().len() > filled {
+            self.activity.record();
+        }
+        poll
+    }
+}
 
-pub fn handle_stream<S>(mut stream: S)
+/// A response in the order of its request: ready, or still to come from a
+/// delayed request.
+enum Pending {
+    Ready(Message),
+    Delayed(JoinHandle<Result<Option<Message>>>),
+}
+
+/// Handles the requests of a connection until it is closed. Requests are
+/// read ahead while earlier ones are handled, and handled one at a time on
+/// the request threads, so that their effects follow the request order.
+/// A delayed request is parked aside, without a thread, while the requests
+/// after it are handled; responses are still written in request order.
+/// On shutdown, stops reading and closes once the requests read are
+/// answered.
+async fn serve<S>(stream: S, broker: Arc<Broker>, mut conn: Connection, shutdown: Shutdown)
 where
-    S: Read + Write + Send + 'static,
+    S: AsyncRead + AsyncWrite + Send + 'static,
 {
-    std::thread::spawn(move || {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let activity = Activity::new();
+    let mut reader = ActivityReader {
+        inner: reader,
+        activity: activity.clone(),
+    };
+    let (request_tx, mut request_rx) = mpsc::channel(MAX_IN_FLIGHT_REQUESTS);
+    let (response_tx, mut response_rx) = mpsc::channel::<Pending>(MAX_IN_FLIGHT_REQUESTS);
+
+    let mut reading = shutdown.clone();
+    let host = conn.host.clone();
+    let max_size = broker.config.socket_request_max_bytes;
+    let idle = Duration::from_millis(broker.config.connections_max_idle_ms);
+    let reading_activity = activity.clone();
+    let read = tokio::spawn(async move {
         loop {
-            match handle_one_frame(&mut stream) {
-                Ok(_) => continue,
+            let frame = tokio::select! {
+                frame = de::read_frame(&mut reader, max_size) => frame,
+                () = reading_activity.idle_for(idle) => {
+                    eprintln!("Closing connection from {host}: idle for {idle:?}");
+                    break;
+                }
+                () = reading.requested() => break,
+            };
+            match frame {
+                Ok(frame) => {
+                    if request_tx.send(frame).await.is_err() {
+                        break;
+                    }
+                }
                 Err(KafkaError::IoError(ref e))
                     if e.kind() == std::io::ErrorKind::UnexpectedEof =>
                 {
@@ -28,47 +311,271 @@ where
                     break;
                 }
                 Err(e) => {
-                    eprintln!("Error handling frame: {e}");
+                    eprintln!("Closing connection from {host}: {e}");
                     break;
                 }
             }
         }
     });
+    let write = tokio::spawn(async move {
+        while let Some(pending) = response_rx.recv().await {
+            let message = match pending {
+                Pending::Ready(message) => message,
+                Pending::Delayed(delayed) => match delayed.await {
+                    Ok(Ok(Some(message))) => message,
+                    Ok(Ok(None)) => continue,
+                    Ok(Err(e)) => {
+                        eprintln!("Error handling frame: {e}");
+                        break;
+                    }
+                    Err(e) => {
+                        eprintln!("Error handling frame: {e}");
+                        break;
+                    }
+                },
+            };
+            if let Err(e) = ser::write(&mut writer, &message).await {
+                eprintln!("Error writing response: {e}");
+                break;
+            }
+            activity.record();
+        }
+        let _ = writer.shutdown().await;
+    });
+
+    while let Some(frame) = request_rx.recv().await {
+        // The connection is muted while the client is throttled.
+        let throttled_until = conn.throttled_until.lock().unwrap().take();
+        if let Some(until) = throttled_until {
+            tokio::time::sleep_until(until.into()).await;
+        }
+        let Ok(permit) = broker.request_permits.acquire().await else {
+            break;
+        };
+        let handled = {
+            let broker = Arc::clone(&broker);
+            tokio::task::spawn_blocking(move || {
+                let result = api::handle(frame, &broker, &mut conn);
+                (conn, result)
+            })
+            .await
+        };
+        drop(permit);
+
+        let result;
+        (conn, result) = match handled {
+            Ok(handled) => handled,
+            Err(e) => {
+                eprintln!("Error handling frame: {e}");
+                break;
+            }
+        };
+        let pending = match result {
+            Ok(Handled::Response(Some(message))) => Pending::Ready(message),
+            Ok(Handled::Response(None)) => continue,
+            Ok(Handled::Delayed(delayed)) => {
+                Pending::Delayed(tokio::spawn(complete_delayed(delayed, Arc::clone(&broker))))
+            }
+            Err(e) => {
+                eprintln!("Error handling frame: {e}");
+                break;
+            }
+        };
+        if response_tx.send(pending).await.is_err() {
+            break;
+        }
+    }
+
+    read.abort();
+    drop(response_tx);
+    let _ = write.await;
+}
+
+/// Waits for a delayed request to complete, trying to complete it on a
+/// request thread each time it may. Neither a thread nor a request permit
+/// is held while it waits.
+async fn complete_delayed(
+    mut delayed: DelayedRequest,
+    broker: Arc<Broker>,
+) -> Result<Option<Message>> {
+    loop {
+        delayed.wait(&broker).await;
+        let Ok(_permit) = broker.request_permits.acquire().await else {
+            return Ok(None);
+        };
+        let broker = Arc::clone(&broker);
+        let result;
+        (delayed, result) = tokio::task::spawn_blocking(move || {
+            let result = delayed.try_complete(&broker);
+            (delayed, result)
+        })
+        .await
+        .map_err(std::io::Error::other)?;
+        if let Some(message) = result? {
+            return Ok(Some(message));
+        }
+    }
 }
 
-fn handle_one_frame<S: Read + Write>(mut stream: S) -> Result<()> {
-    let mut size_buf = [0u8; 4];
-    fill_buf(&mut stream, &mut size_buf)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CompactArray, VarintBytes};
+    use crate::types::{CompactString, Partition, Record, RecordBatch, RecordVariant, Topic, Uuid};
+    use tokio::io::AsyncWriteExt;
+
+    const TOPIC_ID: [u8; 16] = [1; 16];
 
-    let size = i32::from_be_bytes(size_buf);
-    if size < 0 {
-        return Err(KafkaError::DeserializationError(
-            "negative frame size".to_string(),
-        ));
+    /// A size-prefixed request with a v2 header.
+    fn frame(api_key: i16, api_version: i16, correlation_id: i32, body: &[u8]) -> Vec<u8> {
+        let header = [
+            &api_key.to_be_bytes()[..],
+            &api_version.to_be_bytes(),
+            &correlation_id.to_be_bytes(),
+            &[0xff, 0xff, 0x00], // client_id, tagged_fields
+        ]
+        .concat();
+        let size = (header.len() + body.len()) as i32;
+        [&size.to_be_bytes()[..], &header, body].concat()
     }
 
-    let mut frame = vec![0u8; size as usize];
-    fill_buf(&mut stream, &mut frame)?;
+    fn fetch_body(max_wait_ms: i32) -> Vec<u8> {
+        [
+            &max_wait_ms.to_be_bytes()[..],
+            &1i32.to_be_bytes(),         // min_bytes
+            &(1i32 << 20).to_be_bytes(), // max_bytes
+            &[0x00],                     // isolation_level
+            &0i32.to_be_bytes(),         // session_id
+            &(-1i32).to_be_bytes(),      // session_epoch
+            &[0x02],                     // topics
+            &TOPIC_ID,
+            &[0x02, 0x00, 0x00, 0x00, 0x00], // partition_index
+            &(-1i32).to_be_bytes(),          // current_leader_epoch
+            &0i64.to_be_bytes(),             // fetch_offset
+            &(-1i32).to_be_bytes(),          // last_fetched_epoch
+            &(-1i64).to_be_bytes(),          // log_start_offset
+            &(1i32 << 20).to_be_bytes(),     // partition_max_bytes
+            &[0x00, 0x00],                   // tagged_fields
+            &[0x01, 0x01, 0x00],             // forgotten_topics, rack_id, tagged_fields
+        ]
+        .concat()
+    }
 
-    let msg = api::handle(frame)?;
+    fn produce_body() -> Vec<u8> {
+        let record = Record::new(0, VarintBytes::new(None), VarintBytes::new(Some(vec![1])));
+        let batch = ser::to_bytes(&RecordBatch::new(0, vec![record]).unwrap()).unwrap();
+        [
+            &[0x00, 0x00, 0x01][..],  // transactional_id, acks
+            &30_000i32.to_be_bytes(), // timeout_ms
+            &[0x02, 0x04],
+            b"foo",                          // topic_data
+            &[0x02, 0x00, 0x00, 0x00, 0x00], // index
+            &[batch.len() as u8 + 1],        // records
+            &batch,
+            &[0x00, 0x00, 0x00], // tagged_fields
+        ]
+        .concat()
+    }
 
-    let mut serializer = Serializer::new(&mut stream);
-    msg.serialize(&mut serializer)?;
+    #[tokio::test]
+    async fn test_serve_pipelined_requests() {
+        let broker = Broker::start_for_test("serve-pipelined", "");
+        broker
+            .logs
+            .append_metadata(vec![
+                RecordVariant::Topic(Topic {
+                    name: CompactString::new("foo".to_string()),
+                    topic_id: Uuid::from(TOPIC_ID),
+                }),
+                RecordVariant::Partition(Partition {
+                    partition_id: 0,
+                    topic_id: Uuid::from(TOPIC_ID),
+                    replicas: CompactArray::new(Some(vec![1])),
+                    isr: CompactArray::new(Some(vec![1])),
+                    removing_replicas: CompactArray::new(Some(vec![])),
+                    adding_replicas: CompactArray::new(Some(vec![])),
+                    leader: 1,
+                    leader_epoch: 0,
+                    partition_epoch: 0,
+                    directories: CompactArray::new(Some(vec![])),
+                }),
+            ])
+            .unwrap();
 
-    Ok(())
-}
+        let (mut client, server) = tokio::io::duplex(1 << 16);
+        let listener = broker.config.listeners[0].clone();
+        let conn = connection(listener, "127.0.0.1".to_string(), None);
+        tokio::spawn(serve(server, Arc::clone(&broker), conn, Shutdown::never()));
+
+        // The fetch waits for data, which only the produce after it brings.
+        let start = Instant::now();
+        let requests = [
+            frame(1, 16, 1, &fetch_body(60_000)),
+            frame(18, 4, 2, &[0x01, 0x01, 0x00]),
+            frame(0, 9, 3, &produce_body()),
+        ]
+        .concat();
+        client.write_all(&requests).await.unwrap();
 
-fn fill_buf<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<()> {
-    let mut read_bytes = 0;
-    while read_bytes < buf.len() {
-        let n = reader.read(&mut buf[read_bytes..])?;
-        if n == 0 {
-            return Err(KafkaError::IoError(std::io::Error::new(
-                std::io::ErrorKind::UnexpectedEof,
-                "unexpected end of file",
-            )));
+        let mut correlation_ids = vec![];
+        let mut sizes = vec![];
+        for _ in 0..3 {
+            let response = tokio::time::timeout(
+                Duration::from_secs(10),
+                de::read_frame(&mut client, 1 << 20),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+            correlation_ids.push(i32::from_be_bytes(response[..4].try_into().unwrap()));
+            sizes.push(response.len());
         }
-        read_bytes += n;
+        assert_eq!(correlation_ids, [1, 2, 3]);
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        // The fetch was answered with the produced records.
+        client
+            .write_all(&frame(1, 16, 4, &fetch_body(0)))
+            .await
+            .unwrap();
+        let response = de::read_frame(&mut client, 1 << 20).await.unwrap();
+        assert_eq!(response.len(), sizes[0]);
+
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_serve_closes_idle_connection() {
+        let broker = Broker::start_for_test("serve-idle", "connections.max.idle.ms=300");
+        let (mut client, server) = tokio::io::duplex(1 << 16);
+        let listener = broker.config.listeners[0].clone();
+        let conn = connection(listener, "127.0.0.1".to_string(), None);
+        tokio::spawn(serve(server, Arc::clone(&broker), conn, Shutdown::never()));
+
+        // A request trickling in for longer than the idle time is still read.
+        let request = frame(18, 4, 1, &[0x01, 0x01, 0x00]);
+        for (i, chunk) in request.chunks(4).enumerate() {
+            if i > 0 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            client.write_all(chunk).await.unwrap();
+        }
+        let response = de::read_frame(&mut client, 1 << 20).await.unwrap();
+        assert_eq!(response[..4], 1i32.to_be_bytes());
+
+        // Once idle, the connection is closed.
+        let start = Instant::now();
+        let closed = tokio::time::timeout(
+            Duration::from_secs(10),
+            de::read_frame(&mut client, 1 << 20),
+        )
+        .await
+        .unwrap();
+        assert!(
+            matches!(closed, Err(KafkaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof)
+        );
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        std::fs::remove_dir_all(&broker.config.log_dir).unwrap();
     }
-    Ok(())
 }
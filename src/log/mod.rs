@@ -0,0 +1,375 @@
+mod partition;
+mod producer_state;
+mod purgatory;
+mod txn_index;
+
+pub(crate) use partition::PartitionLog;
+pub(crate) use producer_state::ActiveProducer;
+pub(crate) use purgatory::{Purgatory, Watch};
+
+use crate::{
+    Result,
+    types::{Record, RecordBatch, RecordValue, RecordVariant, Value, VarintBytes},
+    util,
+};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The KRaft metadata log, which has a single partition.
+pub(crate) const METADATA_TOPIC: &str = "__cluster_metadata";
+
+/// The offset up to which every partition is known to be on disk, written
+/// to the log directory on shutdown in the format of Kafka's recovery point
+/// checkpoint. Logs are recovered from there when opened.
+const RECOVERY_POINT_CHECKPOINT: &str = "recovery-point-offset-checkpoint";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct TopicPartition {
+    pub(crate) topic: String,
+    pub(crate) partition: i32,
+}
+
+impl TopicPartition {
+    pub(crate) fn new<S: Into<String>>(topic: S, partition: i32) -> Self {
+        Self {
+            topic: topic.into(),
+            partition,
+        }
+    }
+
+    /// Parses a log directory name of the form `<topic>-<partition>`.
+    fn from_dir_name(name: &str) -> Option<Self> {
+        let (topic, partition) = name.rsplit_once('-')?;
+        let partition = partition.parse().ok()?;
+        Some(Self::new(topic, partition))
+    }
+}
+
+impl fmt::Display for TopicPartition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.topic, self.partition)
+    }
+}
+
+/// Owns the partition logs stored under the broker's log directory. Logs are
+/// opened lazily and shared between connections.
+#[derive(Debug)]
+pub(crate) struct LogManager {
+    dir: PathBuf,
+    logs: Mutex<HashMap<TopicPartition, Arc<Mutex<PartitionLog>>>>,
+    /// The recovery points checkpointed by the last clean shutdown.
+    recovery_points: HashMap<TopicPartition, i64>,
+    purgatory: Purgatory,
+}
+
+impl LogManager {
+    pub(crate) fn new<P: AsRef<Path>>(dir: P) -> Self {
+        let dir = dir.as_ref().to_path_buf();
+        Self {
+            recovery_points: read_recovery_checkpoint(&dir.join(RECOVERY_POINT_CHECKPOINT)),
+            dir,
+            logs: Mutex::new(HashMap::new()),
+            purgatory: Purgatory::default(),
+        }
+    }
+
+    /// Fetches waiting for appends to the logs. Whoever appends to a log
+    /// notifies it.
+    pub(crate) fn purgatory(&self) -> &Purgatory {
+        &self.purgatory
+    }
+
+    /// Returns the log for the partition, creating its directory if needed.
+    pub(crate) fn get_or_create(&self, tp: &TopicPartition) -> Result<Arc<Mutex<PartitionLog>>> {
+        let mut logs = self.logs.lock().unwrap();
+        if let Some(log) = logs.get(tp) {
+            return Ok(Arc::clone(log));
+        }
+        let recovery_point = self.recovery_points.get(tp).copied().unwrap_or_default();
+        let log = Arc::new(Mutex::new(PartitionLog::open(
+            self.dir.join(tp.to_string()),
+            recovery_point,
+        )?));
+        logs.insert(tp.clone(), Arc::clone(&log));
+        Ok(log)
+    }
+
+    /// Returns the log for the partition if it exists on disk.
+    pub(crate) fn get(&self, tp: &TopicPartition) -> Result<Option<Arc<Mutex<PartitionLog>>>> {
+        if !self.logs.lock().unwrap().contains_key(tp) && !self.dir.join(tp.to_string()).is_dir() {
+            return Ok(None);
+        }
+        self.get_or_create(tp).map(Some)
+    }
+
+    /// The records of the metadata log along with their tagged fields, read
+    /// under the lock of the log so that no append is seen half-written.
+    pub(crate) fn read_metadata(&self) -> Result<Vec<Value>> {
+        let Some(log) = self.get(&TopicPartition::new(METADATA_TOPIC, 0))? else {
+            return Ok(vec![]);
+        };
+        let batches = log.lock().unwrap().read::<RecordValue>()?;
+        Ok(batches
+            .into_iter()
+            .flatten()
+            .map(|record| record.value.into_inner())
+            .collect())
+    }
+
+    /// Appends `records` to the metadata log as one batch.
+    pub(crate) fn append_metadata(&self, records: Vec<RecordVariant>) -> Result<()> {
+        self.append_metadata_with(|_| records)
+    }
+
+    /// Appends the records built from the offset the first of them gets,
+    /// such as a broker registration whose epoch is its offset. Fetches
+    /// waiting on the metadata log are woken, to learn of leader changes.
+    pub(crate) fn append_metadata_with<F>(&self, records: F) -> Result<()>
+    where
+        F: FnOnce(i64) -> Vec<RecordVariant>,
+    {
+        let tp = TopicPartition::new(METADATA_TOPIC, 0);
+        let log = self.get_or_create(&tp)?;
+        let mut log = log.lock().unwrap();
+
+        let records: Vec<_> = records(log.log_end_offset())
+            .into_iter()
+            .enumerate()
+            .map(|(i, record)| {
+                Record::new(
+                    i as i32,
+                    VarintBytes::new(None),
+                    RecordValue::new(Value::new(record)),
+                )
+            })
+            .collect();
+        if records.is_empty() {
+            return Ok(());
+        }
+        log.append(RecordBatch::new(util::now_ms(), records)?)?;
+        drop(log);
+        self.purgatory.notify(&tp);
+        Ok(())
+    }
+
+    /// Forgets producers idle for `expiration_ms` and snapshots the
+    /// producer state of every open log.
+    pub(crate) fn checkpoint_producer_state(&self, now: i64, expiration_ms: i64) -> Result<()> {
+        let logs: Vec<_> = self.logs.lock().unwrap().values().cloned().collect();
+        for log in logs {
+            log.lock()
+                .unwrap()
+                .checkpoint_producer_state(now, expiration_ms)?;
+        }
+        Ok(())
+    }
+
+    /// Forces every open log to disk.
+    pub(crate) fn flush(&self) -> Result<()> {
+        let logs: Vec<_> = self.logs.lock().unwrap().values().cloned().collect();
+        for log in logs {
+            log.lock().unwrap().flush()?;
+        }
+        Ok(())
+    }
+
+    /// Records the recovery point of every partition on disk: the log end
+    /// offset of the flushed open logs, and the previous recovery point of
+    /// the logs left unopened, which have not changed since.
+    pub(crate) fn write_recovery_checkpoint(&self) -> Result<()> {
+        let open: HashMap<_, _> = self
+            .logs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(tp, log)| (tp.clone(), log.lock().unwrap().log_end_offset()))
+            .collect();
+        let offsets: Vec<_> = self
+            .all_partitions_on_disk()?
+            .into_iter()
+            .map(|tp| {
+                let offset = open.get(&tp).or_else(|| self.recovery_points.get(&tp));
+                let offset = offset.copied().unwrap_or_default();
+                (tp, offset)
+            })
+            .collect();
+
+        let mut contents = format!("0\n{}\n", offsets.len());
+        for (tp, offset) in offsets {
+            contents.push_str(&format!("{} {} {offset}\n", tp.topic, tp.partition));
+        }
+        std::fs::create_dir_all(&self.dir)?;
+        write_atomically(
+            &self.dir.join(RECOVERY_POINT_CHECKPOINT),
+            contents.as_bytes(),
+        )
+    }
+
+    /// Lists the partitions of `topic` that have a directory on disk.
+    pub(crate) fn partitions_on_disk(&self, topic: &str) -> Result<Vec<TopicPartition>> {
+        let mut partitions = self.all_partitions_on_disk()?;
+        partitions.retain(|tp| tp.topic == topic);
+        Ok(partitions)
+    }
+
+    /// Lists the partitions that have a directory on disk.
+    fn all_partitions_on_disk(&self) -> Result<Vec<TopicPartition>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut partitions: Vec<TopicPartition> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| TopicPartition::from_dir_name(&entry.file_name().to_string_lossy()))
+            .collect();
+        partitions.sort();
+        Ok(partitions)
+    }
+}
+
+/// Reads the recovery points of a checkpoint. Without a valid checkpoint,
+/// as after a crash, every log is recovered from its start.
+fn read_recovery_checkpoint(path: &Path) -> HashMap<TopicPartition, i64> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return HashMap::new(),
+        Err(e) => {
+            eprintln!("Failed to read {}: {e}", path.display());
+            return HashMap::new();
+        }
+    };
+
+    let mut lines = contents.lines();
+    let (Some("0"), Some(count)) = (lines.next(), lines.next()) else {
+        eprintln!("Ignoring {} of an unknown version", path.display());
+        return HashMap::new();
+    };
+    let entries: Option<HashMap<_, _>> = lines
+        .map(|line| match line.split(' ').collect::<Vec<_>>()[..] {
+            [topic, partition, offset] => Some((
+                TopicPartition::new(topic, partition.parse().ok()?),
+                offset.parse().ok()?,
+            )),
+            _ => None,
+        })
+        .collect();
+    match entries {
+        Some(entries) if count.parse() == Ok(entries.len()) => entries,
+        _ => {
+            eprintln!("Ignoring malformed {}", path.display());
+            HashMap::new()
+        }
+    }
+}
+
+/// Replaces the file at `path` with `contents`, so that a crash leaves
+/// either the old or the new file.
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = std::fs::File::create(&tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ProducerIds;
+
+    #[test]
+    fn test_topic_partition_from_dir_name() {
+        assert_eq!(
+            TopicPartition::from_dir_name("__consumer_offsets-12"),
+            Some(TopicPartition::new("__consumer_offsets", 12))
+        );
+        assert_eq!(
+            TopicPartition::from_dir_name("my-topic-0"),
+            Some(TopicPartition::new("my-topic", 0))
+        );
+        assert_eq!(TopicPartition::from_dir_name("no_partition"), None);
+    }
+
+    #[test]
+    fn test_read_metadata() {
+        let dir = std::env::temp_dir().join(format!("kafka-logs-metadata-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let logs = LogManager::new(&dir);
+        assert_eq!(logs.read_metadata().unwrap(), vec![]);
+
+        let record = RecordVariant::ProducerIds(ProducerIds {
+            broker_id: 1,
+            broker_epoch: -1,
+            next_producer_id: 1000,
+        });
+        logs.append_metadata(vec![record.clone()]).unwrap();
+
+        // Read from the directory of the manager, however it is configured.
+        let logs = LogManager::new(&dir);
+        assert_eq!(logs.read_metadata().unwrap(), vec![Value::new(record)]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_checkpoints() {
+        let dir =
+            std::env::temp_dir().join(format!("kafka-logs-checkpoint-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let logs = LogManager::new(&dir);
+
+        let record = |value: &[u8]| {
+            Record::new(
+                0,
+                VarintBytes::new(None),
+                VarintBytes::new(Some(value.to_vec())),
+            )
+        };
+        for (tp, count) in [
+            (TopicPartition::new("foo", 1), 2),
+            (TopicPartition::new(METADATA_TOPIC, 0), 1),
+        ] {
+            let log = logs.get_or_create(&tp).unwrap();
+            for _ in 0..count {
+                let batch = RecordBatch::new(0, vec![record(b"a")]).unwrap();
+                log.lock().unwrap().append(batch).unwrap();
+            }
+        }
+        logs.flush().unwrap();
+
+        logs.write_recovery_checkpoint().unwrap();
+        let checkpoint = dir.join(RECOVERY_POINT_CHECKPOINT);
+        assert_eq!(
+            std::fs::read_to_string(&checkpoint).unwrap(),
+            "0\n2\n__cluster_metadata 0 1\nfoo 1 2\n"
+        );
+
+        // Partitions left unopened keep their recovery point, and new ones
+        // are recovered from their start.
+        let logs = LogManager::new(&dir);
+        assert_eq!(logs.recovery_points.len(), 2);
+        let log = logs.get_or_create(&TopicPartition::new("bar", 0)).unwrap();
+        let batch = RecordBatch::new(0, vec![record(b"a")]).unwrap();
+        log.lock().unwrap().append(batch).unwrap();
+        std::fs::create_dir(dir.join("baz-0")).unwrap();
+        logs.write_recovery_checkpoint().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&checkpoint).unwrap(),
+            "0\n4\n__cluster_metadata 0 1\nbar 0 1\nbaz 0 0\nfoo 1 2\n"
+        );
+
+        // A malformed checkpoint is ignored.
+        std::fs::write(&checkpoint, "0\n2\nfoo 1 2\n").unwrap();
+        assert!(LogManager::new(&dir).recovery_points.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,490 @@
+use crate::{
+    Result,
+    api::ErrorCode,
+    ser::Serializer,
+    types::{RecordBatch, VarintBytes, valid_batches_len},
+};
+
+use super::producer_state::{ActiveProducer, ProducerStateManager};
+use super::txn_index::{AbortedTxn, TransactionIndex};
+
+use serde::{Serialize, de::DeserializeOwned};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const SEGMENT_FILE: &str = "00000000000000000000.log";
+const TXN_INDEX_FILE: &str = "00000000000000000000.txnindex";
+
+/// Where a batch is in the segment, so reads seek to it instead of decoding
+/// the batches before it.
+#[derive(Debug)]
+struct BatchPosition {
+    base_offset: i64,
+    last_offset: i64,
+    position: u64,
+    size: usize,
+}
+
+/// An append-only log for a single partition, backed by one segment file
+/// and the transaction index of that segment.
+#[derive(Debug)]
+pub(crate) struct PartitionLog {
+    path: PathBuf,
+    file: File,
+    log_end_offset: i64,
+    /// Every batch in the segment, in offset order.
+    batches: Vec<BatchPosition>,
+    producers: ProducerStateManager,
+    txn_index: TransactionIndex,
+}
+
+impl PartitionLog {
+    /// Opens the log, recovering the batches from `recovery_point` on: the
+    /// segment is truncated before the first of them a crash left torn.
+    pub(crate) fn open<P: AsRef<Path>>(dir: P, recovery_point: i64) -> Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let path = dir.as_ref().join(SEGMENT_FILE);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        let bytes = fs::read(&path)?;
+        let valid_len = valid_batches_len(&bytes, recovery_point);
+        if valid_len < bytes.len() {
+            eprintln!(
+                "Truncating {} from {} to {valid_len} bytes",
+                path.display(),
+                bytes.len()
+            );
+            file.set_len(valid_len as u64)?;
+        }
+        let batches: Vec<RecordBatch<VarintBytes>> = RecordBatch::from_reader(&bytes[..valid_len])?;
+        let log_end_offset = batches.last().map_or(0, |last| last.last_offset() + 1);
+        let mut position = 0;
+        let positions = batches
+            .iter()
+            .map(|batch| {
+                // batch_length counts the bytes after base_offset and itself.
+                let size = 12 + batch.batch_length as usize;
+                let batch_position = BatchPosition {
+                    base_offset: batch.base_offset,
+                    last_offset: batch.last_offset(),
+                    position,
+                    size,
+                };
+                position += size as u64;
+                batch_position
+            })
+            .collect();
+
+        let mut log = Self {
+            path,
+            file,
+            log_end_offset,
+            batches: positions,
+            producers: ProducerStateManager::new(&dir),
+            txn_index: TransactionIndex::open(dir.as_ref().join(TXN_INDEX_FILE), log_end_offset)?,
+        };
+
+        let snapshot_offset = log.producers.load(log.log_end_offset)?;
+        for batch in batches
+            .iter()
+            .filter(|b| b.has_producer_id() && b.base_offset >= snapshot_offset)
+        {
+            log.update_producer_state(batch)?;
+        }
+
+        Ok(log)
+    }
+
+    /// The offset the next appended record will get, i.e. the high
+    /// watermark of this single-replica log.
+    pub(crate) fn log_end_offset(&self) -> i64 {
+        self.log_end_offset
+    }
+
+    /// The offset up to which every transaction has been committed or
+    /// aborted, i.e. the end of what read_committed consumers may see.
+    pub(crate) fn last_stable_offset(&self) -> i64 {
+        self.producers
+            .first_unstable_offset()
+            .unwrap_or(self.log_end_offset)
+    }
+
+    pub(crate) fn active_producers(&self) -> Vec<ActiveProducer> {
+        self.producers.active_producers()
+    }
+
+    /// Aborted transactions with records between `start_offset` and
+    /// `end_offset` (exclusive).
+    pub(crate) fn aborted_transactions(
+        &self,
+        start_offset: i64,
+        end_offset: i64,
+    ) -> Vec<AbortedTxn> {
+        self.txn_index.collect_aborted(start_offset, end_offset)
+    }
+
+    /// Assigns the next offsets to `batch`, writes it to the segment and
+    /// returns its base offset.
+    pub(crate) fn append<V: Serialize>(&mut self, mut batch: RecordBatch<V>) -> Result<i64> {
+        batch.base_offset = self.log_end_offset;
+
+        let mut buf: Vec<u8> = Vec::new();
+        let mut serializer = Serializer::new(&mut buf);
+        batch.serialize(&mut serializer)?;
+        self.file.write_all(&buf)?;
+        let position = self
+            .batches
+            .last()
+            .map_or(0, |last| last.position + last.size as u64);
+        self.batches.push(BatchPosition {
+            base_offset: batch.base_offset,
+            last_offset: batch.last_offset(),
+            position,
+            size: buf.len(),
+        });
+
+        self.log_end_offset = batch.last_offset() + 1;
+        if batch.has_producer_id() {
+            self.update_producer_state(&batch)?;
+        }
+        Ok(batch.base_offset)
+    }
+
+    /// Updates the producer state with an appended batch and indexes the
+    /// transaction it aborted, if any. Markers indexed before a restart are
+    /// skipped when the batches after the producer snapshot are replayed.
+    fn update_producer_state<V>(&mut self, batch: &RecordBatch<V>) -> Result<()> {
+        let Some(completed) = self.producers.update(batch) else {
+            return Ok(());
+        };
+        if completed.is_aborted
+            && self
+                .txn_index
+                .last_offset()
+                .is_none_or(|last| last < completed.last_offset)
+        {
+            let last_stable_offset = self
+                .producers
+                .first_unstable_offset()
+                .unwrap_or(completed.last_offset + 1);
+            self.txn_index.append(AbortedTxn {
+                producer_id: completed.producer_id,
+                first_offset: completed.first_offset,
+                last_offset: completed.last_offset,
+                last_stable_offset,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Appends a batch sent by a producer, rejecting it with the returned
+    /// error code if it does not continue the producer's sequence.
+    pub(crate) fn append_from_client<V: Serialize>(
+        &mut self,
+        batch: RecordBatch<V>,
+    ) -> Result<std::result::Result<i64, ErrorCode>> {
+        if batch.has_producer_id()
+            && let Err(error_code) = self.producers.validate(&batch)
+        {
+            return Ok(Err(error_code));
+        }
+        self.append(batch).map(Ok)
+    }
+
+    /// Forgets idle producers and snapshots the producer state if it changed
+    /// since the last snapshot.
+    pub(crate) fn checkpoint_producer_state(&mut self, now: i64, expiration_ms: i64) -> Result<()> {
+        let expired = self.producers.remove_expired(now, expiration_ms);
+        if expired > 0 || self.producers.needs_snapshot(self.log_end_offset) {
+            self.producers.take_snapshot(self.log_end_offset)?;
+        }
+        Ok(())
+    }
+
+    /// Forces the appended batches and the transaction index to disk.
+    pub(crate) fn flush(&self) -> Result<()> {
+        self.file.sync_all()?;
+        self.txn_index.flush()
+    }
+
+    /// Reads every batch in the log, decoding record values as `V`.
+    pub(crate) fn read<V: DeserializeOwned>(&self) -> Result<Vec<RecordBatch<V>>> {
+        let file = File::open(&self.path)?;
+        RecordBatch::from_reader(BufReader::new(file))
+    }
+
+    /// Returns the encoded batches with records from `start_offset` up to,
+    /// but excluding, `end_offset`. Stops before `max_bytes` is exceeded,
+    /// but always includes the first batch so consumers make progress.
+    pub(crate) fn read_bytes(
+        &self,
+        start_offset: i64,
+        end_offset: i64,
+        max_bytes: usize,
+    ) -> Result<Vec<u8>> {
+        let first = self
+            .batches
+            .partition_point(|batch| batch.last_offset < start_offset);
+        let mut len = 0;
+        for batch in &self.batches[first..] {
+            if batch.base_offset >= end_offset || (len > 0 && len + batch.size > max_bytes) {
+                break;
+            }
+            len += batch.size;
+        }
+        if len == 0 {
+            return Ok(vec![]);
+        }
+
+        // The batches are contiguous in the segment.
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.batches[first].position))?;
+        let mut buf = vec![0; len];
+        file.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ControlRecordType, Record};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("kafka-log-test-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn record(offset_delta: i32, value: &[u8]) -> Record<VarintBytes> {
+        Record::new(
+            offset_delta,
+            VarintBytes::new(None),
+            VarintBytes::new(Some(value.to_vec())),
+        )
+    }
+
+    #[test]
+    fn test_append_and_reopen() {
+        let dir = temp_dir("append");
+
+        let mut log = PartitionLog::open(&dir, 0).unwrap();
+        assert_eq!(log.log_end_offset, 0);
+
+        let batch = RecordBatch::new(0, vec![record(0, b"a"), record(1, b"b")]).unwrap();
+        assert_eq!(log.append(batch).unwrap(), 0);
+        let batch = RecordBatch::new(0, vec![record(0, b"c")]).unwrap();
+        assert_eq!(log.append(batch).unwrap(), 2);
+        assert_eq!(log.log_end_offset, 3);
+
+        let log = PartitionLog::open(&dir, 0).unwrap();
+        assert_eq!(log.log_end_offset, 3);
+
+        let batches: Vec<RecordBatch<VarintBytes>> = log.read().unwrap();
+        let values: Vec<Vec<u8>> = batches
+            .into_iter()
+            .flatten()
+            .filter_map(|r| r.value.as_opt_bytes().map(|b| b.to_vec()))
+            .collect();
+        assert_eq!(values, vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_bytes() {
+        let dir = temp_dir("read-bytes");
+
+        let mut log = PartitionLog::open(&dir, 0).unwrap();
+        log.append(RecordBatch::new(0, vec![record(0, b"a"), record(1, b"b")]).unwrap())
+            .unwrap();
+        log.append(RecordBatch::new(0, vec![record(0, b"c")]).unwrap())
+            .unwrap();
+        // The positions of the batches before a reopen are recovered.
+        let mut log = PartitionLog::open(&dir, 0).unwrap();
+        log.append(RecordBatch::new(0, vec![record(0, b"d")]).unwrap())
+            .unwrap();
+
+        let base_offsets = |bytes: Vec<u8>| -> Vec<i64> {
+            RecordBatch::<VarintBytes>::from_reader(&bytes[..])
+                .unwrap()
+                .iter()
+                .map(|batch| batch.base_offset)
+                .collect()
+        };
+        assert_eq!(
+            base_offsets(log.read_bytes(0, 4, usize::MAX).unwrap()),
+            vec![0, 2, 3]
+        );
+        // A fetch from inside a batch starts with that batch.
+        assert_eq!(
+            base_offsets(log.read_bytes(1, 4, usize::MAX).unwrap()),
+            vec![0, 2, 3]
+        );
+        assert_eq!(
+            base_offsets(log.read_bytes(2, 3, usize::MAX).unwrap()),
+            vec![2]
+        );
+        assert_eq!(base_offsets(log.read_bytes(2, 4, 1).unwrap()), vec![2]);
+        assert!(log.read_bytes(4, 4, usize::MAX).unwrap().is_empty());
+
+        // The last two batches are the same size.
+        let size = log.read_bytes(2, 3, usize::MAX).unwrap().len();
+        assert_eq!(
+            base_offsets(log.read_bytes(2, 4, 2 * size).unwrap()),
+            vec![2, 3]
+        );
+        assert_eq!(
+            base_offsets(log.read_bytes(2, 4, 2 * size - 1).unwrap()),
+            vec![2]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_truncate_torn_batch() {
+        let dir = temp_dir("torn");
+
+        let mut log = PartitionLog::open(&dir, 0).unwrap();
+        log.append(RecordBatch::new(0, vec![record(0, b"a")]).unwrap())
+            .unwrap();
+        let segment = dir.join(SEGMENT_FILE);
+        let len = fs::metadata(&segment).unwrap().len();
+        log.append(RecordBatch::new(0, vec![record(0, b"b")]).unwrap())
+            .unwrap();
+        drop(log);
+
+        // A crash cut the second batch short.
+        let file = OpenOptions::new().write(true).open(&segment).unwrap();
+        file.set_len(len + 20).unwrap();
+
+        let mut log = PartitionLog::open(&dir, 1).unwrap();
+        assert_eq!(log.log_end_offset, 1);
+        assert_eq!(fs::metadata(&segment).unwrap().len(), len);
+        let batch = RecordBatch::new(0, vec![record(0, b"c")]).unwrap();
+        assert_eq!(log.append(batch).unwrap(), 1);
+        assert_eq!(PartitionLog::open(&dir, 2).unwrap().log_end_offset, 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_producer_state_survives_reopen() {
+        let dir = temp_dir("producer");
+
+        let idempotent = |base_sequence: i32| {
+            let mut batch = RecordBatch::new(0, vec![record(0, b"a")]).unwrap();
+            batch.producer_id = 1;
+            batch.producer_epoch = 0;
+            batch.base_sequence = base_sequence;
+            batch.seal().unwrap();
+            batch
+        };
+
+        let mut log = PartitionLog::open(&dir, 0).unwrap();
+        assert_eq!(log.append_from_client(idempotent(0)).unwrap(), Ok(0));
+        log.checkpoint_producer_state(0, i64::MAX).unwrap();
+        assert_eq!(log.append_from_client(idempotent(1)).unwrap(), Ok(1));
+
+        // The first batch comes from the snapshot, the second is replayed.
+        let mut log = PartitionLog::open(&dir, 0).unwrap();
+        assert_eq!(
+            log.append_from_client(idempotent(1)).unwrap(),
+            Err(ErrorCode::DuplicateSequenceNumber)
+        );
+        assert_eq!(
+            log.append_from_client(idempotent(3)).unwrap(),
+            Err(ErrorCode::OutOfOrderSequenceNumber)
+        );
+        assert_eq!(log.append_from_client(idempotent(2)).unwrap(), Ok(2));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_last_stable_offset_and_aborted_transactions() {
+        let dir = temp_dir("txn");
+
+        let transactional = |producer_id: i64, base_sequence: i32| {
+            let mut batch = RecordBatch::new(0, vec![record(0, b"t")])
+                .unwrap()
+                .into_transactional(producer_id, 0)
+                .unwrap();
+            batch.base_sequence = base_sequence;
+            batch.seal().unwrap();
+            batch
+        };
+        let marker = |producer_id: i64, control_type: ControlRecordType| {
+            RecordBatch::end_transaction_marker(0, producer_id, 0, control_type, 0).unwrap()
+        };
+
+        let mut log = PartitionLog::open(&dir, 0).unwrap();
+        log.append(RecordBatch::new(0, vec![record(0, b"a")]).unwrap())
+            .unwrap();
+        log.append(transactional(1, 0)).unwrap();
+        log.append(transactional(2, 0)).unwrap();
+        assert_eq!(log.last_stable_offset(), 1);
+
+        log.append(marker(1, ControlRecordType::Abort)).unwrap();
+        assert_eq!(log.last_stable_offset(), 2);
+        log.append(marker(2, ControlRecordType::Commit)).unwrap();
+        assert_eq!(log.last_stable_offset(), 5);
+
+        let aborted = AbortedTxn {
+            producer_id: 1,
+            first_offset: 1,
+            last_offset: 3,
+            last_stable_offset: 2,
+        };
+        assert_eq!(log.aborted_transactions(0, 5), vec![aborted]);
+        assert_eq!(log.aborted_transactions(4, 5), vec![]);
+
+        // The index is not duplicated when the markers are replayed.
+        let log = PartitionLog::open(&dir, 0).unwrap();
+        assert_eq!(log.aborted_transactions(0, 5), vec![aborted]);
+        assert_eq!(log.last_stable_offset(), 5);
+
+        // Reads stop at the end offset but always return the first batch.
+        let batches =
+            RecordBatch::<VarintBytes>::from_reader(&log.read_bytes(1, 2, 0).unwrap()[..]).unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].base_offset, 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fenced_abort() {
+        let dir = temp_dir("fenced");
+
+        let mut data = RecordBatch::new(0, vec![record(0, b"t")])
+            .unwrap()
+            .into_transactional(1, 0)
+            .unwrap();
+        data.seal().unwrap();
+        // The coordinator aborts with a bumped epoch when it fences the
+        // producer.
+        let marker =
+            RecordBatch::end_transaction_marker(0, 1, 1, ControlRecordType::Abort, 0).unwrap();
+
+        let mut log = PartitionLog::open(&dir, 0).unwrap();
+        log.append(data).unwrap();
+        assert_eq!(log.last_stable_offset(), 0);
+        log.append(marker).unwrap();
+        assert_eq!(log.last_stable_offset(), 2);
+        assert_eq!(
+            log.aborted_transactions(0, 2),
+            vec![AbortedTxn {
+                producer_id: 1,
+                first_offset: 0,
+                last_offset: 1,
+                last_stable_offset: 2,
+            }]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,546 @@
+use crate::{
+    KafkaError, Result,
+    api::ErrorCode,
+    de::Deserializer,
+    ser::Serializer,
+    types::{Array, ControlRecordType, RecordBatch, increment_sequence},
+    util,
+};
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Recent batches kept per producer to detect duplicates. Idempotent
+/// producers allow at most five in-flight requests per connection.
+const NUM_BATCHES_TO_RETAIN: usize = 5;
+const SNAPSHOT_SUFFIX: &str = "snapshot";
+const SNAPSHOT_VERSION: i16 = 1;
+
+/// Sequence numbers and offsets of a batch appended by a producer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct BatchMetadata {
+    pub(crate) first_sequence: i32,
+    pub(crate) last_sequence: i32,
+    pub(crate) last_offset: i64,
+    pub(crate) offset_delta: i32,
+    pub(crate) timestamp: i64,
+}
+
+/// A transaction ended by a marker appended to the log.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CompletedTxn {
+    pub(crate) producer_id: i64,
+    pub(crate) first_offset: i64,
+    /// Offset of the marker.
+    pub(crate) last_offset: i64,
+    pub(crate) is_aborted: bool,
+}
+
+/// A producer which wrote to the partition, as reported by
+/// DescribeProducers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ActiveProducer {
+    pub(crate) producer_id: i64,
+    pub(crate) producer_epoch: i16,
+    /// Sequence of the last record written, or -1 without batches.
+    pub(crate) last_sequence: i32,
+    pub(crate) last_timestamp: i64,
+    pub(crate) current_txn_first_offset: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ProducerStateEntry {
+    pub(crate) producer_epoch: i16,
+    /// The latest batches of the current epoch, oldest first.
+    pub(crate) batches: VecDeque<BatchMetadata>,
+    pub(crate) last_timestamp: i64,
+    /// Offset of the first batch of the producer's open transaction.
+    pub(crate) current_txn_first_offset: Option<i64>,
+}
+
+impl ProducerStateEntry {
+    fn new(producer_epoch: i16) -> Self {
+        Self {
+            producer_epoch,
+            batches: VecDeque::new(),
+            last_timestamp: -1,
+            current_txn_first_offset: None,
+        }
+    }
+
+    fn add_batch(&mut self, batch: BatchMetadata) {
+        if self.batches.len() == NUM_BATCHES_TO_RETAIN {
+            self.batches.pop_front();
+        }
+        self.last_timestamp = batch.timestamp;
+        self.batches.push_back(batch);
+    }
+}
+
+/// Tracks the producers which wrote to a partition, so retried batches are
+/// detected and gaps in the sequence numbers are rejected. The state is
+/// periodically written to `<offset>.snapshot` files in the partition
+/// directory, so only the batches after the latest snapshot have to be
+/// replayed when the log is opened.
+#[derive(Debug)]
+pub(crate) struct ProducerStateManager {
+    dir: PathBuf,
+    producers: BTreeMap<i64, ProducerStateEntry>,
+    last_snapshot_offset: Option<i64>,
+}
+
+impl ProducerStateManager {
+    pub(crate) fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            producers: BTreeMap::new(),
+            last_snapshot_offset: None,
+        }
+    }
+
+    /// Loads the latest snapshot which is not beyond `log_end_offset` and
+    /// returns the offset it was taken at. Batches from that offset on have
+    /// to be replayed with [`Self::update`].
+    pub(crate) fn load(&mut self, log_end_offset: i64) -> Result<i64> {
+        self.producers.clear();
+        self.last_snapshot_offset = None;
+
+        for (offset, path) in self.snapshot_files()?.into_iter().rev() {
+            if offset > log_end_offset {
+                // Written for records which did not make it to the log.
+                fs::remove_file(&path)?;
+                continue;
+            }
+            match read_snapshot(&path) {
+                Ok(producers) => {
+                    self.producers = producers;
+                    self.last_snapshot_offset = Some(offset);
+                    return Ok(offset);
+                }
+                Err(e) => {
+                    eprintln!("Ignoring producer snapshot {}: {e}", path.display());
+                    fs::remove_file(&path)?;
+                }
+            }
+        }
+
+        Ok(0)
+    }
+
+    /// Checks that `batch` continues the sequence of its producer.
+    pub(crate) fn validate<V>(&self, batch: &RecordBatch<V>) -> std::result::Result<(), ErrorCode> {
+        let Some(entry) = self.producers.get(&batch.producer_id) else {
+            return first_sequence_of_epoch(batch);
+        };
+
+        if batch.producer_epoch < entry.producer_epoch {
+            return Err(ErrorCode::InvalidProducerEpoch);
+        }
+        if batch.producer_epoch > entry.producer_epoch {
+            return first_sequence_of_epoch(batch);
+        }
+
+        if !batch.is_transactional() && entry.current_txn_first_offset.is_some() {
+            return Err(ErrorCode::InvalidTxnState);
+        }
+
+        let last_sequence = batch.last_sequence();
+        if entry
+            .batches
+            .iter()
+            .any(|b| b.first_sequence == batch.base_sequence && b.last_sequence == last_sequence)
+        {
+            return Err(ErrorCode::DuplicateSequenceNumber);
+        }
+
+        match entry.batches.back() {
+            Some(last) if batch.base_sequence != increment_sequence(last.last_sequence, 1) => {
+                Err(ErrorCode::OutOfOrderSequenceNumber)
+            }
+            Some(_) => Ok(()),
+            None => first_sequence_of_epoch(batch),
+        }
+    }
+
+    /// Records a batch which has been appended to the log. Returns the
+    /// transaction a marker batch ended.
+    pub(crate) fn update<V>(&mut self, batch: &RecordBatch<V>) -> Option<CompletedTxn> {
+        let entry = self
+            .producers
+            .entry(batch.producer_id)
+            .or_insert_with(|| ProducerStateEntry::new(batch.producer_epoch));
+
+        if batch.is_control() {
+            // Transaction markers end the open transaction and carry no
+            // sequence numbers. A coordinator fencing the producer writes
+            // the marker with a bumped epoch, so the transaction is
+            // completed before the epoch moves on.
+            if batch.producer_epoch < entry.producer_epoch {
+                return None;
+            }
+            let completed =
+                entry
+                    .current_txn_first_offset
+                    .take()
+                    .map(|first_offset| CompletedTxn {
+                        producer_id: batch.producer_id,
+                        first_offset,
+                        last_offset: batch.base_offset,
+                        is_aborted: batch.control_type() == Some(ControlRecordType::Abort),
+                    });
+            if entry.producer_epoch != batch.producer_epoch {
+                *entry = ProducerStateEntry::new(batch.producer_epoch);
+            }
+            entry.last_timestamp = batch.max_timestamp;
+            return completed;
+        }
+        if entry.producer_epoch != batch.producer_epoch {
+            *entry = ProducerStateEntry::new(batch.producer_epoch);
+        }
+        if batch.is_transactional() && entry.current_txn_first_offset.is_none() {
+            entry.current_txn_first_offset = Some(batch.base_offset);
+        }
+        entry.add_batch(BatchMetadata {
+            first_sequence: batch.base_sequence,
+            last_sequence: batch.last_sequence(),
+            last_offset: batch.last_offset(),
+            offset_delta: batch.last_offset_delta,
+            timestamp: batch.max_timestamp,
+        });
+        None
+    }
+
+    /// The first offset of the earliest open transaction, below which
+    /// every record is either committed or aborted.
+    pub(crate) fn first_unstable_offset(&self) -> Option<i64> {
+        self.producers
+            .values()
+            .filter_map(|entry| entry.current_txn_first_offset)
+            .min()
+    }
+
+    pub(crate) fn active_producers(&self) -> Vec<ActiveProducer> {
+        self.producers
+            .iter()
+            .map(|(&producer_id, entry)| ActiveProducer {
+                producer_id,
+                producer_epoch: entry.producer_epoch,
+                last_sequence: entry.batches.back().map_or(-1, |b| b.last_sequence),
+                last_timestamp: entry.last_timestamp,
+                current_txn_first_offset: entry.current_txn_first_offset,
+            })
+            .collect()
+    }
+
+    /// Forgets producers which have not written for `expiration_ms` and have
+    /// no open transaction, and returns how many were removed.
+    pub(crate) fn remove_expired(&mut self, now: i64, expiration_ms: i64) -> usize {
+        let before = self.producers.len();
+        self.producers.retain(|_, entry| {
+            entry.current_txn_first_offset.is_some() || now - entry.last_timestamp < expiration_ms
+        });
+        before - self.producers.len()
+    }
+
+    /// Writes the current state as the snapshot at `offset` and removes the
+    /// older snapshots.
+    pub(crate) fn take_snapshot(&mut self, offset: i64) -> Result<()> {
+        let entries: Array<SnapshotEntry> = self
+            .producers
+            .iter()
+            .map(|(&producer_id, entry)| {
+                let last = entry.batches.back();
+                SnapshotEntry {
+                    producer_id,
+                    producer_epoch: entry.producer_epoch,
+                    last_sequence: last.map_or(-1, |b| b.last_sequence),
+                    last_offset: last.map_or(-1, |b| b.last_offset),
+                    offset_delta: last.map_or(0, |b| b.offset_delta),
+                    timestamp: entry.last_timestamp,
+                    coordinator_epoch: -1,
+                    current_txn_first_offset: entry.current_txn_first_offset.unwrap_or(-1),
+                }
+            })
+            .collect();
+
+        let snapshot = Snapshot {
+            version: SNAPSHOT_VERSION,
+            crc: util::crc32c(&encode(&entries)?),
+            entries,
+        };
+
+        let path = self.dir.join(snapshot_file_name(offset));
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, encode(&snapshot)?)?;
+        fs::rename(&tmp, &path)?;
+
+        for (old, old_path) in self.snapshot_files()? {
+            if old != offset {
+                fs::remove_file(old_path)?;
+            }
+        }
+        self.last_snapshot_offset = Some(offset);
+        Ok(())
+    }
+
+    /// Whether the state at `offset` differs from the latest snapshot.
+    pub(crate) fn needs_snapshot(&self, offset: i64) -> bool {
+        match self.last_snapshot_offset {
+            Some(last) => last != offset,
+            None => !self.producers.is_empty(),
+        }
+    }
+
+    /// Snapshot files in the partition directory, ordered by offset.
+    fn snapshot_files(&self) -> Result<Vec<(i64, PathBuf)>> {
+        let mut files: Vec<(i64, PathBuf)> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == SNAPSHOT_SUFFIX))
+            .filter_map(|path| {
+                let offset = path.file_stem()?.to_str()?.parse().ok()?;
+                Some((offset, path))
+            })
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+}
+
+/// A producer's first batch, and the first batch after an epoch bump, must
+/// start the sequence at 0.
+fn first_sequence_of_epoch<V>(batch: &RecordBatch<V>) -> std::result::Result<(), ErrorCode> {
+    if batch.base_sequence == 0 {
+        Ok(())
+    } else {
+        Err(ErrorCode::OutOfOrderSequenceNumber)
+    }
+}
+
+fn decrement_sequence(sequence: i32, decrement: i32) -> i32 {
+    if sequence < decrement {
+        i32::MAX - (decrement - sequence) + 1
+    } else {
+        sequence - decrement
+    }
+}
+
+fn snapshot_file_name(offset: i64) -> String {
+    format!("{offset:020}.{SNAPSHOT_SUFFIX}")
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    let mut buf: Vec<u8> = Vec::new();
+    value.serialize(&mut Serializer::new(&mut buf))?;
+    Ok(buf)
+}
+
+fn read_snapshot(path: &Path) -> Result<BTreeMap<i64, ProducerStateEntry>> {
+    let bytes = fs::read(path)?;
+    let snapshot = Snapshot::deserialize(&mut Deserializer::new(&bytes[..]))?;
+
+    if snapshot.version != SNAPSHOT_VERSION {
+        return Err(KafkaError::DeserializationError(format!(
+            "unsupported producer snapshot version {}",
+            snapshot.version
+        )));
+    }
+    if snapshot.crc != util::crc32c(&encode(&snapshot.entries)?) {
+        return Err(KafkaError::DeserializationError(
+            "producer snapshot checksum mismatch".to_string(),
+        ));
+    }
+
+    Ok(snapshot
+        .entries
+        .into_iter()
+        .map(|e| {
+            let mut entry = ProducerStateEntry::new(e.producer_epoch);
+            if e.last_offset >= 0 {
+                entry.add_batch(BatchMetadata {
+                    first_sequence: decrement_sequence(e.last_sequence, e.offset_delta),
+                    last_sequence: e.last_sequence,
+                    last_offset: e.last_offset,
+                    offset_delta: e.offset_delta,
+                    timestamp: e.timestamp,
+                });
+            }
+            entry.last_timestamp = e.timestamp;
+            entry.current_txn_first_offset =
+                (e.current_txn_first_offset >= 0).then_some(e.current_txn_first_offset);
+            (e.producer_id, entry)
+        })
+        .collect())
+}
+
+/// The layout of Kafka's producer state snapshot files. Only the last batch
+/// of each producer is kept.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Snapshot {
+    version: i16,
+    /// CRC-32C of `entries`.
+    crc: u32,
+    entries: Array<SnapshotEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SnapshotEntry {
+    producer_id: i64,
+    producer_epoch: i16,
+    last_sequence: i32,
+    last_offset: i64,
+    offset_delta: i32,
+    timestamp: i64,
+    coordinator_epoch: i32,
+    current_txn_first_offset: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Record, VarintBytes};
+
+    fn batch(
+        producer_id: i64,
+        epoch: i16,
+        base_sequence: i32,
+        count: i32,
+    ) -> RecordBatch<VarintBytes> {
+        let records = (0..count)
+            .map(|i| Record::new(i, VarintBytes::new(None), VarintBytes::new(None)))
+            .collect();
+        let mut batch = RecordBatch::new(0, records).unwrap();
+        batch.producer_id = producer_id;
+        batch.producer_epoch = epoch;
+        batch.base_sequence = base_sequence;
+        batch
+    }
+
+    #[test]
+    fn test_validate_sequences() {
+        let dir = std::env::temp_dir();
+        let mut state = ProducerStateManager::new(&dir);
+
+        assert_eq!(
+            state.validate(&batch(1, 0, 3, 1)),
+            Err(ErrorCode::OutOfOrderSequenceNumber)
+        );
+        assert_eq!(state.validate(&batch(1, 0, 0, 2)), Ok(()));
+        state.update(&batch(1, 0, 0, 2));
+
+        // Retried batch.
+        assert_eq!(
+            state.validate(&batch(1, 0, 0, 2)),
+            Err(ErrorCode::DuplicateSequenceNumber)
+        );
+        // Gap in the sequence.
+        assert_eq!(
+            state.validate(&batch(1, 0, 3, 1)),
+            Err(ErrorCode::OutOfOrderSequenceNumber)
+        );
+        assert_eq!(state.validate(&batch(1, 0, 2, 1)), Ok(()));
+
+        // A bumped epoch restarts the sequence.
+        assert_eq!(
+            state.validate(&batch(1, 1, 2, 1)),
+            Err(ErrorCode::OutOfOrderSequenceNumber)
+        );
+        assert_eq!(state.validate(&batch(1, 1, 0, 1)), Ok(()));
+        state.update(&batch(1, 1, 0, 1));
+        assert_eq!(
+            state.validate(&batch(1, 0, 2, 1)),
+            Err(ErrorCode::InvalidProducerEpoch)
+        );
+    }
+
+    #[test]
+    fn test_only_recent_batches_are_deduplicated() {
+        let dir = std::env::temp_dir();
+        let mut state = ProducerStateManager::new(&dir);
+        for sequence in 0..6 {
+            state.update(&batch(1, 0, sequence, 1));
+        }
+
+        assert_eq!(
+            state.validate(&batch(1, 0, 1, 1)),
+            Err(ErrorCode::DuplicateSequenceNumber)
+        );
+        assert_eq!(
+            state.validate(&batch(1, 0, 0, 1)),
+            Err(ErrorCode::OutOfOrderSequenceNumber)
+        );
+    }
+
+    #[test]
+    fn test_transaction_markers() {
+        let dir = std::env::temp_dir();
+        let mut state = ProducerStateManager::new(&dir);
+
+        let mut data = batch(1, 0, 0, 2).into_transactional(1, 0).unwrap();
+        data.base_offset = 10;
+        state.update(&data);
+        assert_eq!(state.producers[&1].current_txn_first_offset, Some(10));
+        let active = state.active_producers();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].last_sequence, 1);
+        assert_eq!(active[0].current_txn_first_offset, Some(10));
+
+        // Non-transactional writes are rejected while the transaction is open.
+        assert_eq!(
+            state.validate(&batch(1, 0, 2, 1)),
+            Err(ErrorCode::InvalidTxnState)
+        );
+        assert_eq!(state.remove_expired(i64::MAX, 10), 0);
+
+        assert_eq!(state.first_unstable_offset(), Some(10));
+
+        let mut marker =
+            RecordBatch::end_transaction_marker(0, 1, 0, ControlRecordType::Abort, 0).unwrap();
+        marker.base_offset = 12;
+        assert_eq!(
+            state.update(&marker),
+            Some(CompletedTxn {
+                producer_id: 1,
+                first_offset: 10,
+                last_offset: 12,
+                is_aborted: true,
+            })
+        );
+        assert_eq!(state.first_unstable_offset(), None);
+        // The marker does not take part in the sequence.
+        assert_eq!(state.validate(&batch(1, 0, 2, 1)), Ok(()));
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let dir =
+            std::env::temp_dir().join(format!("kafka-producer-state-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut state = ProducerStateManager::new(&dir);
+        let mut appended = batch(7, 2, 10, 3);
+        appended.base_offset = 40;
+        appended.max_timestamp = 1000;
+        state.update(&appended);
+        state.take_snapshot(43).unwrap();
+        state.take_snapshot(50).unwrap();
+        assert!(!dir.join(snapshot_file_name(43)).exists());
+
+        let mut loaded = ProducerStateManager::new(&dir);
+        assert_eq!(loaded.load(60).unwrap(), 50);
+        assert_eq!(loaded.producers, state.producers);
+
+        // Snapshots beyond the end of the log are discarded.
+        assert_eq!(loaded.load(45).unwrap(), 0);
+        assert!(loaded.producers.is_empty());
+        assert!(!dir.join(snapshot_file_name(50)).exists());
+
+        assert_eq!(state.remove_expired(1009, 10), 0);
+        assert_eq!(state.remove_expired(1010, 10), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+use super::TopicPartition;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+use tokio::sync::Notify;
+
+/// Where delayed fetches wait for new data. Every append bumps the version of
+/// its partition and wakes the waiting fetches, which check whether one of
+/// their partitions changed. Waiting is async, so a parked fetch holds no
+/// thread.
+#[derive(Debug, Default)]
+pub(crate) struct Purgatory {
+    versions: Mutex<HashMap<TopicPartition, u64>>,
+    changed: Notify,
+}
+
+/// The versions of the partitions a fetch waits on, as of its last read.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Watch {
+    partitions: Vec<(TopicPartition, u64)>,
+}
+
+impl Purgatory {
+    /// Starts watching the partitions. Take the watch before reading them,
+    /// so appends racing with the read are not missed.
+    pub(crate) fn watch(&self, partitions: &[TopicPartition]) -> Watch {
+        let versions = self.versions.lock().unwrap();
+        Watch {
+            partitions: partitions
+                .iter()
+                .map(|tp| (tp.clone(), versions.get(tp).copied().unwrap_or_default()))
+                .collect(),
+        }
+    }
+
+    /// Wakes the fetches waiting on `tp`.
+    pub(crate) fn notify(&self, tp: &TopicPartition) {
+        let mut versions = self.versions.lock().unwrap();
+        *versions.entry(tp.clone()).or_default() += 1;
+        self.changed.notify_waiters();
+    }
+
+    /// Whether one of the watched partitions changed since the watch was
+    /// taken or last checked.
+    pub(crate) fn has_changed(&self, watch: &mut Watch) -> bool {
+        let versions = self.versions.lock().unwrap();
+        let mut changed = false;
+        for (tp, seen) in watch.partitions.iter_mut() {
+            let version = versions.get(tp).copied().unwrap_or_default();
+            if version != *seen {
+                *seen = version;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Waits until one of the watched partitions changes, and returns
+    /// `false` if `deadline` passes first.
+    pub(crate) async fn wait(&self, watch: &mut Watch, deadline: Instant) -> bool {
+        loop {
+            // Registered before checking, so a notification in between is
+            // not lost.
+            let notified = self.changed.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.has_changed(watch) {
+                return true;
+            }
+            let deadline = tokio::time::Instant::from_std(deadline);
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                return false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_wait_for_append() {
+        let purgatory = Arc::new(Purgatory::default());
+        let tp = TopicPartition::new("foo", 0);
+        let other = TopicPartition::new("foo", 1);
+
+        let mut watch = purgatory.watch(std::slice::from_ref(&tp));
+        purgatory.notify(&other);
+        let deadline = Instant::now() + Duration::from_millis(20);
+        assert!(!purgatory.wait(&mut watch, deadline).await);
+
+        let notifier = {
+            let purgatory = Arc::clone(&purgatory);
+            let tp = tp.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(20));
+                purgatory.notify(&tp);
+            })
+        };
+        let deadline = Instant::now() + Duration::from_secs(10);
+        assert!(purgatory.wait(&mut watch, deadline).await);
+        notifier.join().unwrap();
+
+        // Appends before the wait are not missed.
+        purgatory.notify(&tp);
+        assert!(purgatory.wait(&mut watch, Instant::now()).await);
+        assert!(!purgatory.has_changed(&mut watch));
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{Result, de::Deserializer, ser::Serializer};
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+const TXN_INDEX_VERSION: i16 = 0;
+/// Size of an entry on disk: version, producer id and three offsets.
+const ENTRY_SIZE: usize = 2 + 8 * 4;
+
+/// A transaction which was aborted in this partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct AbortedTxn {
+    pub(crate) producer_id: i64,
+    pub(crate) first_offset: i64,
+    /// Offset of the abort marker.
+    pub(crate) last_offset: i64,
+    /// The last stable offset once the abort marker was written.
+    pub(crate) last_stable_offset: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TxnIndexEntry {
+    version: i16,
+    producer_id: i64,
+    first_offset: i64,
+    last_offset: i64,
+    last_stable_offset: i64,
+}
+
+/// The aborted transactions of a segment, stored in the `.txnindex` file
+/// next to it, so read_committed consumers can be told which records to
+/// skip.
+#[derive(Debug)]
+pub(crate) struct TransactionIndex {
+    file: File,
+    entries: Vec<AbortedTxn>,
+}
+
+impl TransactionIndex {
+    /// Opens the index, dropping entries for markers beyond
+    /// `log_end_offset` and a partially written last entry.
+    pub(crate) fn open<P: AsRef<Path>>(path: P, log_end_offset: i64) -> Result<Self> {
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut entries = vec![];
+        for chunk in bytes.chunks_exact(ENTRY_SIZE) {
+            let entry = TxnIndexEntry::deserialize(&mut Deserializer::new(chunk))?;
+            if entry.last_offset >= log_end_offset {
+                break;
+            }
+            entries.push(AbortedTxn {
+                producer_id: entry.producer_id,
+                first_offset: entry.first_offset,
+                last_offset: entry.last_offset,
+                last_stable_offset: entry.last_stable_offset,
+            });
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        file.set_len((entries.len() * ENTRY_SIZE) as u64)?;
+        Ok(Self { file, entries })
+    }
+
+    /// Offset of the last indexed abort marker.
+    pub(crate) fn last_offset(&self) -> Option<i64> {
+        self.entries.last().map(|entry| entry.last_offset)
+    }
+
+    pub(crate) fn append(&mut self, aborted: AbortedTxn) -> Result<()> {
+        let entry = TxnIndexEntry {
+            version: TXN_INDEX_VERSION,
+            producer_id: aborted.producer_id,
+            first_offset: aborted.first_offset,
+            last_offset: aborted.last_offset,
+            last_stable_offset: aborted.last_stable_offset,
+        };
+        let mut buf: Vec<u8> = Vec::with_capacity(ENTRY_SIZE);
+        entry.serialize(&mut Serializer::new(&mut buf))?;
+        self.file.write_all(&buf)?;
+        self.entries.push(aborted);
+        Ok(())
+    }
+
+    /// Forces the appended entries to disk.
+    pub(crate) fn flush(&self) -> Result<()> {
+        self.file.sync_all()?;
+        Ok(())
+    }
+
+    /// Aborted transactions overlapping the offsets from `start_offset` up
+    /// to, but excluding, `end_offset`.
+    pub(crate) fn collect_aborted(&self, start_offset: i64, end_offset: i64) -> Vec<AbortedTxn> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.last_offset >= start_offset && entry.first_offset < end_offset)
+            .copied()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_reopen_and_collect() {
+        let dir = std::env::temp_dir().join(format!("kafka-txn-index-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("00000000000000000000.txnindex");
+
+        let aborted = |producer_id, first_offset, last_offset| AbortedTxn {
+            producer_id,
+            first_offset,
+            last_offset,
+            last_stable_offset: last_offset + 1,
+        };
+
+        let mut index = TransactionIndex::open(&path, 0).unwrap();
+        index.append(aborted(1, 0, 5)).unwrap();
+        index.append(aborted(2, 3, 9)).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), 2 * ENTRY_SIZE as u64);
+
+        let index = TransactionIndex::open(&path, 20).unwrap();
+        assert_eq!(index.collect_aborted(6, 20), vec![aborted(2, 3, 9)]);
+        assert_eq!(index.collect_aborted(0, 3), vec![aborted(1, 0, 5)]);
+        assert_eq!(index.last_offset(), Some(9));
+
+        // Markers beyond the end of the log are dropped.
+        let index = TransactionIndex::open(&path, 9).unwrap();
+        assert_eq!(index.last_offset(), Some(5));
+        assert_eq!(fs::metadata(&path).unwrap().len(), ENTRY_SIZE as u64);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,4 +1,6 @@
+use codecrafters_kafka::{Broker, Config};
 use std::net::TcpListener;
+use std::sync::Arc;
 
 fn main() {
     if let Err(e) = run() {
@@ -8,12 +10,17 @@ fn main() {
 }
 
 fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let listener = TcpListener::bind("127.0.0.1:9092")?;
+    let config = match std::env::args().nth(1) {
+        Some(path) => Config::from_file(path)?,
+        None => Config::default(),
+    };
+    let admin_listener = config.admin_listener().map(str::to_string);
+    let broker = Broker::start(config)?;
 
-    for stream in listener.incoming() {
-        let stream = stream?;
-        codecrafters_kafka::handle_stream(stream);
+    if let Some(addr) = admin_listener {
+        codecrafters_kafka::serve_admin(TcpListener::bind(addr)?, Arc::clone(&broker))?;
     }
 
+    codecrafters_kafka::serve_listeners(broker)?;
     Ok(())
 }
@@ -0,0 +1,88 @@
+use crate::Config;
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+/// Counts the open client connections to enforce `max.connections` and
+/// `max.connections.per.ip`.
+#[derive(Debug)]
+pub(crate) struct ConnectionQuotas {
+    max_connections: usize,
+    max_connections_per_ip: usize,
+    counts: Mutex<Counts>,
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    total: usize,
+    by_ip: HashMap<IpAddr, usize>,
+}
+
+impl ConnectionQuotas {
+    pub(crate) fn new(config: &Config) -> Self {
+        Self {
+            max_connections: config.max_connections,
+            max_connections_per_ip: config.max_connections_per_ip,
+            counts: Mutex::new(Counts::default()),
+        }
+    }
+
+    /// Counts a new connection from `ip`, or returns why it is refused.
+    /// Every accepted connection must be closed with [`Self::close`].
+    pub(crate) fn open(&self, ip: IpAddr) -> Result<(), String> {
+        let mut counts = self.counts.lock().unwrap();
+        let from_ip = counts.by_ip.get(&ip).copied().unwrap_or(0);
+        if from_ip >= self.max_connections_per_ip {
+            return Err(format!(
+                "max.connections.per.ip of {} reached",
+                self.max_connections_per_ip
+            ));
+        }
+        if counts.total >= self.max_connections {
+            return Err(format!(
+                "max.connections of {} reached",
+                self.max_connections
+            ));
+        }
+        counts.total += 1;
+        *counts.by_ip.entry(ip).or_default() += 1;
+        Ok(())
+    }
+
+    pub(crate) fn close(&self, ip: IpAddr) {
+        let mut counts = self.counts.lock().unwrap();
+        let Some(from_ip) = counts.by_ip.get_mut(&ip) else {
+            return;
+        };
+        *from_ip -= 1;
+        if *from_ip == 0 {
+            counts.by_ip.remove(&ip);
+        }
+        counts.total -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_limits() {
+        let config =
+            Config::from_properties("max.connections=3\nmax.connections.per.ip=2").unwrap();
+        let quotas = ConnectionQuotas::new(&config);
+        let a: IpAddr = "10.0.0.1".parse().unwrap();
+        let b: IpAddr = "10.0.0.2".parse().unwrap();
+
+        assert!(quotas.open(a).is_ok());
+        assert!(quotas.open(a).is_ok());
+        assert!(quotas.open(a).is_err());
+        assert!(quotas.open(b).is_ok());
+        assert!(quotas.open(b).is_err());
+
+        quotas.close(a);
+        assert!(quotas.open(b).is_ok());
+        assert!(quotas.open(a).is_err());
+    }
+}
@@ -0,0 +1,548 @@
+mod connection;
+mod rate;
+
+pub(crate) use connection::ConnectionQuotas;
+
+use rate::Rate;
+
+use crate::{
+    Config, Result,
+    api::ErrorCode,
+    log::LogManager,
+    types::{
+        ClientQuotaRecord, CompactNullableString, CompactString, EntityData, RecordVariant,
+        TaggedFields,
+    },
+};
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+const USER: &str = "user";
+const CLIENT_ID: &str = "client-id";
+
+/// Why describing or altering client quotas failed.
+pub(crate) type QuotaError = (ErrorCode, String);
+
+/// The components of a quota entity as on the wire: the entity type and
+/// the entity name, or `None` for the default entity of the type.
+pub(crate) type EntityComponents = Vec<(String, Option<String>)>;
+
+/// The quotas set for an entity, by type.
+pub(crate) type EntityQuotas = BTreeMap<QuotaType, f64>;
+
+/// A kind of client quota, named by its config key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum QuotaType {
+    /// Bytes per second of produce requests.
+    ProducerByteRate,
+    /// Bytes per second of fetch responses.
+    ConsumerByteRate,
+    /// Percentage of a request handler thread's time.
+    RequestPercentage,
+    /// Partition mutations per second. Stored, but never enforced as no
+    /// API mutating partitions is served.
+    ControllerMutationRate,
+}
+
+impl QuotaType {
+    pub(crate) fn key(self) -> &'static str {
+        match self {
+            Self::ProducerByteRate => "producer_byte_rate",
+            Self::ConsumerByteRate => "consumer_byte_rate",
+            Self::RequestPercentage => "request_percentage",
+            Self::ControllerMutationRate => "controller_mutation_rate",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        [
+            Self::ProducerByteRate,
+            Self::ConsumerByteRate,
+            Self::RequestPercentage,
+            Self::ControllerMutationRate,
+        ]
+        .into_iter()
+        .find(|quota_type| quota_type.key() == key)
+    }
+
+    /// Rejects values the quota cannot have. Byte rates are whole numbers.
+    fn validate(self, value: f64) -> std::result::Result<(), QuotaError> {
+        let invalid = |message: String| Err((ErrorCode::InvalidRequest, message));
+        if !value.is_finite() || value <= 0.0 {
+            return invalid(format!("Quota {} must be positive", self.key()));
+        }
+        if matches!(self, Self::ProducerByteRate | Self::ConsumerByteRate) && value.fract() != 0.0 {
+            return invalid(format!("Quota {} must be a whole number", self.key()));
+        }
+        Ok(())
+    }
+}
+
+/// A client entity quotas are set for. Each component is either absent,
+/// the default entity of its type (`Some(None)`) or a named entity.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct QuotaEntity {
+    pub(crate) user: Option<Option<String>>,
+    pub(crate) client_id: Option<Option<String>>,
+}
+
+impl QuotaEntity {
+    /// The entity of the components of a request.
+    pub(crate) fn from_components(
+        components: EntityComponents,
+    ) -> std::result::Result<Self, QuotaError> {
+        let invalid = |message: String| Err((ErrorCode::InvalidRequest, message));
+        if components.is_empty() {
+            return invalid("Invalid empty client quota entity".to_string());
+        }
+
+        let mut entity = Self::default();
+        for (entity_type, name) in components {
+            let component = match entity_type.as_str() {
+                USER => &mut entity.user,
+                CLIENT_ID => &mut entity.client_id,
+                _ => return invalid(format!("Unhandled client quota entity type: {entity_type}")),
+            };
+            if component.replace(name).is_some() {
+                return invalid(format!("Duplicate {entity_type} in client quota entity"));
+            }
+        }
+        Ok(entity)
+    }
+
+    pub(crate) fn components(&self) -> EntityComponents {
+        [(USER, &self.user), (CLIENT_ID, &self.client_id)]
+            .into_iter()
+            .filter_map(|(entity_type, name)| Some((entity_type.to_string(), name.clone()?)))
+            .collect()
+    }
+
+    fn from_record(entity: &[EntityData]) -> Option<Self> {
+        let components = entity
+            .iter()
+            .map(|data| {
+                (
+                    data.entity_type.as_str().to_string(),
+                    data.entity_name.as_ref().cloned(),
+                )
+            })
+            .collect();
+        Self::from_components(components).ok()
+    }
+
+    fn to_record(&self) -> Vec<EntityData> {
+        self.components()
+            .into_iter()
+            .map(|(entity_type, name)| EntityData {
+                entity_type: CompactString::new(entity_type),
+                entity_name: CompactNullableString::new(name),
+                tagged_fields: TaggedFields::new(None),
+            })
+            .collect()
+    }
+
+    /// The entities whose quotas apply to the client, most specific first,
+    /// as Kafka resolves them.
+    fn candidates(user: &str, client_id: &str) -> [Self; 8] {
+        let user = || Some(Some(user.to_string()));
+        let client_id = || Some(Some(client_id.to_string()));
+        let entity = |user, client_id| Self { user, client_id };
+        [
+            entity(user(), client_id()),
+            entity(user(), Some(None)),
+            entity(user(), None),
+            entity(Some(None), client_id()),
+            entity(Some(None), Some(None)),
+            entity(Some(None), None),
+            entity(None, client_id()),
+            entity(None, Some(None)),
+        ]
+    }
+}
+
+/// How DescribeClientQuotas matches one component of the entities.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ComponentFilter {
+    /// The named entity of the type.
+    Exact(String),
+    /// The default entity of the type.
+    Default,
+    /// Any named or default entity of the type.
+    Specified,
+}
+
+impl ComponentFilter {
+    fn matches(&self, component: &Option<Option<String>>) -> bool {
+        match (self, component) {
+            (Self::Exact(name), Some(Some(component))) => name == component,
+            (Self::Default, Some(None)) | (Self::Specified, Some(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A change to one quota of an entity, as requested by AlterClientQuotas.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct QuotaAlteration {
+    pub(crate) key: String,
+    pub(crate) value: f64,
+    pub(crate) remove: bool,
+}
+
+/// The usage a sensor measures: the quota type, and the user and client id
+/// of the entity the quota was set for. Clients sharing the entity of a
+/// quota share its sensor.
+type SensorKey = (QuotaType, String, String);
+
+/// The client quotas set for users and client ids, and the usage of the
+/// clients they are enforced against. Quotas are persisted as
+/// `ClientQuotaRecord`s in the metadata log.
+#[derive(Debug)]
+pub(crate) struct QuotaManager {
+    logs: Arc<LogManager>,
+    window_num: u32,
+    window_size_ms: u64,
+    quotas: Mutex<BTreeMap<QuotaEntity, EntityQuotas>>,
+    sensors: Mutex<HashMap<SensorKey, Rate>>,
+}
+
+impl QuotaManager {
+    pub(crate) fn new(config: &Config, logs: Arc<LogManager>) -> Self {
+        Self {
+            logs,
+            window_num: config.quota_window_num,
+            window_size_ms: config.quota_window_size_ms,
+            quotas: Mutex::new(BTreeMap::new()),
+            sensors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Replays the quota records of the metadata log.
+    pub(crate) fn load(&self) -> Result<()> {
+        let metadata = self.logs.read_metadata()?;
+
+        let mut quotas = self.quotas.lock().unwrap();
+        for value in metadata {
+            if let RecordVariant::ClientQuota(record) = value.value {
+                apply_record(&mut quotas, &record);
+            }
+        }
+        Ok(())
+    }
+
+    /// The quotas of the entities matching every filter, keyed by entity
+    /// type. With `strict`, entities with other components do not match.
+    pub(crate) fn describe(
+        &self,
+        filters: Vec<(String, ComponentFilter)>,
+        strict: bool,
+    ) -> std::result::Result<Vec<(QuotaEntity, EntityQuotas)>, QuotaError> {
+        let mut user_filter = None;
+        let mut client_id_filter = None;
+        for (entity_type, filter) in filters {
+            let component = match entity_type.as_str() {
+                USER => &mut user_filter,
+                CLIENT_ID => &mut client_id_filter,
+                _ => {
+                    return Err((
+                        ErrorCode::InvalidRequest,
+                        format!("Unhandled client quota entity type: {entity_type}"),
+                    ));
+                }
+            };
+            if component.replace(filter).is_some() {
+                return Err((
+                    ErrorCode::InvalidRequest,
+                    format!("Duplicate {entity_type} filter component"),
+                ));
+            }
+        }
+
+        let matches =
+            |filter: &Option<ComponentFilter>, component: &Option<Option<String>>| match filter {
+                Some(filter) => filter.matches(component),
+                None => !strict || component.is_none(),
+            };
+        Ok(self
+            .quotas
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(entity, _)| {
+                matches(&user_filter, &entity.user) && matches(&client_id_filter, &entity.client_id)
+            })
+            .map(|(entity, quotas)| (entity.clone(), quotas.clone()))
+            .collect())
+    }
+
+    /// Applies the alterations of each entity, returning the outcome for
+    /// each in order. Nothing is changed when `validate_only` is set.
+    pub(crate) fn alter(
+        &self,
+        entries: Vec<(EntityComponents, Vec<QuotaAlteration>)>,
+        validate_only: bool,
+    ) -> Vec<std::result::Result<(), QuotaError>> {
+        let mut results = vec![];
+        let mut records = vec![];
+        for (components, alterations) in entries {
+            match validate(components, alterations) {
+                Ok(entity_records) => {
+                    records.extend(entity_records);
+                    results.push(Ok(()));
+                }
+                Err(e) => results.push(Err(e)),
+            }
+        }
+        if validate_only || records.is_empty() {
+            return results;
+        }
+
+        let mut quotas = self.quotas.lock().unwrap();
+        let variants = records
+            .iter()
+            .cloned()
+            .map(RecordVariant::ClientQuota)
+            .collect();
+        if let Err(e) = self.logs.append_metadata(variants) {
+            eprintln!("Failed to write client quota records: {e}");
+            let error = (ErrorCode::KafkaStorageError, e.to_string());
+            return results
+                .into_iter()
+                .map(|result| result.and(Err(error.clone())))
+                .collect();
+        }
+        for record in &records {
+            apply_record(&mut quotas, record);
+        }
+        results
+    }
+
+    /// Records `value` against the quota of `quota_type` which applies to
+    /// the client, returning how long the client must be throttled for, in
+    /// milliseconds. Clients without a quota are neither measured nor
+    /// throttled.
+    pub(crate) fn record(
+        &self,
+        quota_type: QuotaType,
+        user: &str,
+        client_id: &str,
+        value: f64,
+        now_ms: i64,
+    ) -> u64 {
+        let quota = {
+            let quotas = self.quotas.lock().unwrap();
+            QuotaEntity::candidates(user, client_id)
+                .into_iter()
+                .find_map(|entity| {
+                    let bound = *quotas.get(&entity)?.get(&quota_type)?;
+                    Some((entity, bound))
+                })
+        };
+        let Some((entity, bound)) = quota else {
+            return 0;
+        };
+
+        // Default entities are measured per client, like named ones.
+        let tag = |component: Option<Option<String>>, name: &str| {
+            component.map_or(String::new(), |_| name.to_string())
+        };
+        let key = (
+            quota_type,
+            tag(entity.user, user),
+            tag(entity.client_id, client_id),
+        );
+        let mut sensors = self.sensors.lock().unwrap();
+        let rate = sensors
+            .entry(key)
+            .or_insert_with(|| Rate::new(self.window_num, self.window_size_ms));
+        rate.record(value, now_ms);
+
+        let measured = rate.measure(now_ms);
+        if measured <= bound {
+            return 0;
+        }
+        // The time after which the rate is back within the quota, at most
+        // the whole window.
+        let throttle_time_ms = (measured - bound) / bound * rate.window_ms(now_ms) as f64;
+        (throttle_time_ms.round() as u64).min(self.window_num as u64 * self.window_size_ms)
+    }
+
+    /// Drops the sensors of clients with no usage within the window.
+    pub(crate) fn expire_sensors(&self, now_ms: i64) {
+        self.sensors
+            .lock()
+            .unwrap()
+            .retain(|_, rate| !rate.is_idle(now_ms));
+    }
+}
+
+/// The records of the alterations of an entity, once they are validated.
+fn validate(
+    components: EntityComponents,
+    alterations: Vec<QuotaAlteration>,
+) -> std::result::Result<Vec<ClientQuotaRecord>, QuotaError> {
+    let entity = QuotaEntity::from_components(components)?;
+    alterations
+        .into_iter()
+        .map(|alteration| {
+            let quota_type = QuotaType::from_key(&alteration.key).ok_or_else(|| {
+                (
+                    ErrorCode::InvalidRequest,
+                    format!("Unknown client quota key: {}", alteration.key),
+                )
+            })?;
+            if !alteration.remove {
+                quota_type.validate(alteration.value)?;
+            }
+            Ok(ClientQuotaRecord {
+                entity: entity.to_record().into_iter().collect(),
+                key: CompactString::new(alteration.key),
+                value: alteration.value,
+                remove: alteration.remove,
+            })
+        })
+        .collect()
+}
+
+fn apply_record(quotas: &mut BTreeMap<QuotaEntity, EntityQuotas>, record: &ClientQuotaRecord) {
+    let entity = record.entity.as_opt_slice().unwrap_or_default();
+    let (Some(entity), Some(quota_type)) = (
+        QuotaEntity::from_record(entity),
+        QuotaType::from_key(record.key.as_str()),
+    ) else {
+        return;
+    };
+    if record.remove {
+        if let Some(entity_quotas) = quotas.get_mut(&entity) {
+            entity_quotas.remove(&quota_type);
+            if entity_quotas.is_empty() {
+                quotas.remove(&entity);
+            }
+        }
+    } else {
+        quotas
+            .entry(entity)
+            .or_default()
+            .insert(quota_type, record.value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alteration(key: &str, value: f64, remove: bool) -> QuotaAlteration {
+        QuotaAlteration {
+            key: key.to_string(),
+            value,
+            remove,
+        }
+    }
+
+    fn components(components: &[(&str, Option<&str>)]) -> EntityComponents {
+        components
+            .iter()
+            .map(|(entity_type, name)| (entity_type.to_string(), name.map(str::to_string)))
+            .collect()
+    }
+
+    #[test]
+    fn test_alter_describe_and_enforce_quotas() {
+        let dir = std::env::temp_dir().join(format!("kafka-quota-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let config = Config::from_properties("quota.window.num=2").unwrap();
+        let quotas = QuotaManager::new(&config, Arc::new(LogManager::new(&dir)));
+
+        let results = quotas.alter(
+            vec![
+                (
+                    components(&[("user", Some("alice"))]),
+                    vec![alteration("producer_byte_rate", 1000.0, false)],
+                ),
+                (
+                    components(&[("user", None), ("client-id", Some("app"))]),
+                    vec![alteration("request_percentage", 50.0, false)],
+                ),
+                (
+                    components(&[("ip", Some("10.0.0.1"))]),
+                    vec![alteration("connection_creation_rate", 1.0, false)],
+                ),
+                (
+                    components(&[("user", Some("bob"))]),
+                    vec![alteration("producer_byte_rate", 1.5, false)],
+                ),
+            ],
+            false,
+        );
+        let codes: Vec<Option<ErrorCode>> = results
+            .iter()
+            .map(|result| result.as_ref().err().map(|e| e.0))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                None,
+                None,
+                Some(ErrorCode::InvalidRequest),
+                Some(ErrorCode::InvalidRequest)
+            ]
+        );
+
+        // The quotas are replayed from the metadata log.
+        let quotas = QuotaManager::new(&config, Arc::new(LogManager::new(&dir)));
+        quotas.load().unwrap();
+        let described = quotas
+            .describe(vec![("user".to_string(), ComponentFilter::Specified)], true)
+            .unwrap();
+        assert_eq!(described.len(), 1);
+        assert_eq!(
+            described[0].0.components(),
+            components(&[("user", Some("alice"))])
+        );
+        let described = quotas
+            .describe(
+                vec![(
+                    "client-id".to_string(),
+                    ComponentFilter::Exact("app".to_string()),
+                )],
+                false,
+            )
+            .unwrap();
+        assert_eq!(
+            described[0].1,
+            EntityQuotas::from([(QuotaType::RequestPercentage, 50.0)])
+        );
+
+        // 3000 bytes over the minimum window of 1s is 2000 bytes/s above
+        // the quota, which takes 2s to make up for.
+        assert_eq!(
+            quotas.record(QuotaType::ProducerByteRate, "alice", "", 500.0, 0),
+            0
+        );
+        assert_eq!(
+            quotas.record(QuotaType::ProducerByteRate, "alice", "", 2500.0, 0),
+            2000
+        );
+        assert_eq!(
+            quotas.record(QuotaType::ProducerByteRate, "bob", "", 1e9, 0),
+            0
+        );
+        // The default user quota of the client id applies to every user.
+        assert_eq!(
+            quotas.record(QuotaType::RequestPercentage, "bob", "app", 75.0, 0),
+            500
+        );
+
+        let results = quotas.alter(
+            vec![(
+                components(&[("user", Some("alice"))]),
+                vec![alteration("producer_byte_rate", 0.0, true)],
+            )],
+            false,
+        );
+        assert_eq!(results, [Ok(())]);
+        assert_eq!(quotas.describe(vec![], false).unwrap().len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+
+/// A rate per second measured over a sliding window of samples, as Kafka's
+/// `Rate` metric: values are summed into samples of `window_size_ms`, and
+/// only the latest `window_num` samples count.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Rate {
+    window_num: u32,
+    window_size_ms: i64,
+    samples: VecDeque<Sample>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Sample {
+    start_ms: i64,
+    value: f64,
+}
+
+impl Rate {
+    pub(crate) fn new(window_num: u32, window_size_ms: u64) -> Self {
+        Self {
+            window_num,
+            window_size_ms: window_size_ms as i64,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn record(&mut self, value: f64, now_ms: i64) {
+        self.purge(now_ms);
+        match self.samples.back_mut() {
+            Some(sample) if now_ms < sample.start_ms + self.window_size_ms => {
+                sample.value += value;
+            }
+            _ => self.samples.push_back(Sample {
+                start_ms: now_ms,
+                value,
+            }),
+        }
+    }
+
+    /// The rate per second over the samples of the window.
+    pub(crate) fn measure(&mut self, now_ms: i64) -> f64 {
+        self.purge(now_ms);
+        let total: f64 = self.samples.iter().map(|sample| sample.value).sum();
+        total * 1000.0 / self.window_ms(now_ms) as f64
+    }
+
+    /// The time the rate is measured over. Until the window is full it is
+    /// taken as all but one of its samples, so that the first requests of a
+    /// client are not measured over a tiny interval.
+    pub(crate) fn window_ms(&self, now_ms: i64) -> i64 {
+        let elapsed = self
+            .samples
+            .front()
+            .map_or(0, |sample| now_ms - sample.start_ms);
+        let min_windows = self.window_num.saturating_sub(1).max(1) as i64;
+        elapsed.max(min_windows * self.window_size_ms)
+    }
+
+    /// Whether nothing was recorded within the window.
+    pub(crate) fn is_idle(&mut self, now_ms: i64) -> bool {
+        self.purge(now_ms);
+        self.samples.is_empty()
+    }
+
+    fn purge(&mut self, now_ms: i64) {
+        let expiration_ms = self.window_num as i64 * self.window_size_ms;
+        while self
+            .samples
+            .front()
+            .is_some_and(|sample| sample.start_ms + expiration_ms <= now_ms)
+        {
+            self.samples.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate() {
+        let mut rate = Rate::new(3, 1000);
+        rate.record(1000.0, 0);
+        rate.record(1000.0, 500);
+        // Measured over at least two windows until the window is full.
+        assert_eq!(rate.measure(500), 1000.0);
+
+        rate.record(2000.0, 1000);
+        rate.record(2000.0, 2500);
+        assert_eq!(rate.measure(2500), 2400.0);
+
+        // The first sample has left the window.
+        assert_eq!(rate.measure(3000), 2000.0);
+        assert!(!rate.is_idle(4000));
+        assert!(rate.is_idle(5500));
+    }
+}
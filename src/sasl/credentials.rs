@@ -0,0 +1,408 @@
+use super::scram::{MAX_ITERATIONS, MIN_ITERATIONS, ScramCredential, ScramMechanism};
+
+use crate::{
+    Result,
+    api::ErrorCode,
+    log::LogManager,
+    types::{
+        CompactBytes, CompactString, RecordVariant, RemoveUserScramCredentialRecord,
+        UserScramCredentialRecord,
+    },
+};
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Why describing or altering the credentials of a user failed.
+pub(crate) type CredentialError = (ErrorCode, String);
+
+/// The mechanisms a user has a credential for, with their iterations.
+pub(crate) type CredentialInfos = Vec<(ScramMechanism, i32)>;
+
+/// A credential to delete, as requested by AlterUserScramCredentials.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ScramDeletion {
+    pub(crate) name: String,
+    pub(crate) mechanism: i8,
+}
+
+/// A credential to add or replace, as requested by AlterUserScramCredentials.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ScramUpsertion {
+    pub(crate) name: String,
+    pub(crate) mechanism: i8,
+    pub(crate) iterations: i32,
+    pub(crate) salt: Vec<u8>,
+    pub(crate) salted_password: Vec<u8>,
+}
+
+/// The SCRAM credentials of the users, by name and mechanism. They are
+/// persisted as `UserScramCredentialRecord`s in the metadata log.
+#[derive(Debug)]
+pub(crate) struct ScramCredentials {
+    logs: Arc<LogManager>,
+    users: Mutex<BTreeMap<String, BTreeMap<ScramMechanism, ScramCredential>>>,
+}
+
+impl ScramCredentials {
+    pub(crate) fn new(logs: Arc<LogManager>) -> Self {
+        Self {
+            logs,
+            users: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Replays the credential records of the metadata log.
+    pub(crate) fn load(&self) -> Result<()> {
+        let metadata = self.logs.read_metadata()?;
+
+        let mut users = self.users.lock().unwrap();
+        for value in metadata {
+            apply_record(&mut users, value.value);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn get(&self, user: &str, mechanism: ScramMechanism) -> Option<ScramCredential> {
+        self.users
+            .lock()
+            .unwrap()
+            .get(user)
+            .and_then(|credentials| credentials.get(&mechanism))
+            .cloned()
+    }
+
+    /// The mechanisms and iterations of the credentials of each user, or of
+    /// every user with a credential when `users` is `None`.
+    pub(crate) fn describe(
+        &self,
+        users: Option<Vec<String>>,
+    ) -> Vec<(
+        String,
+        std::result::Result<CredentialInfos, CredentialError>,
+    )> {
+        let credentials = self.users.lock().unwrap();
+        let infos = |user: &BTreeMap<ScramMechanism, ScramCredential>| {
+            user.iter()
+                .map(|(mechanism, credential)| (*mechanism, credential.iterations))
+                .collect()
+        };
+
+        let Some(users) = users else {
+            return credentials
+                .iter()
+                .map(|(name, user)| (name.clone(), Ok(infos(user))))
+                .collect();
+        };
+
+        let mut seen = HashSet::new();
+        let duplicates: HashSet<String> = users
+            .iter()
+            .filter(|name| !seen.insert(*name))
+            .cloned()
+            .collect();
+        let mut described = HashSet::new();
+        users
+            .into_iter()
+            .filter(|name| described.insert(name.clone()))
+            .map(|name| {
+                let result = if duplicates.contains(&name) {
+                    Err((
+                        ErrorCode::DuplicateResource,
+                        "Cannot describe SCRAM credentials for the same user twice in a single request"
+                            .to_string(),
+                    ))
+                } else {
+                    credentials.get(&name).map(infos).ok_or_else(|| {
+                        (
+                            ErrorCode::ResourceNotFound,
+                            "Attempt to describe a user credential that does not exist"
+                                .to_string(),
+                        )
+                    })
+                };
+                (name, result)
+            })
+            .collect()
+    }
+
+    /// Applies the deletions and upsertions, user by user: the changes of a
+    /// user are applied only if every one of them is valid. Returns the
+    /// outcome for each user in the order they first appear.
+    pub(crate) fn alter(
+        &self,
+        deletions: Vec<ScramDeletion>,
+        upsertions: Vec<ScramUpsertion>,
+    ) -> Vec<(String, std::result::Result<(), CredentialError>)> {
+        let mut users = self.users.lock().unwrap();
+
+        let mut changes: Vec<(String, Vec<RecordVariant>)> = vec![];
+        let mut errors: BTreeMap<String, CredentialError> = BTreeMap::new();
+        let mut altered = HashSet::new();
+
+        let ops =
+            deletions
+                .into_iter()
+                .map(|deletion| (deletion.name, deletion.mechanism, None))
+                .chain(upsertions.into_iter().map(|upsertion| {
+                    (upsertion.name.clone(), upsertion.mechanism, Some(upsertion))
+                }));
+        for (name, mechanism, upsertion) in ops {
+            let index = match changes.iter().position(|(user, _)| *user == name) {
+                Some(index) => index,
+                None => {
+                    changes.push((name.clone(), vec![]));
+                    changes.len() - 1
+                }
+            };
+            let record = ScramMechanism::from_i8(mechanism)
+                .ok_or_else(|| {
+                    (
+                        ErrorCode::UnsupportedSaslMechanism,
+                        "Unknown SCRAM mechanism".to_string(),
+                    )
+                })
+                .and_then(|scram_mechanism| {
+                    if !altered.insert((name.clone(), scram_mechanism)) {
+                        return Err((
+                            ErrorCode::DuplicateResource,
+                            "A user credential cannot be altered twice in the same request"
+                                .to_string(),
+                        ));
+                    }
+                    alteration_record(&users, &name, scram_mechanism, upsertion)
+                });
+            match record {
+                Ok(record) => changes[index].1.push(record),
+                Err(error) => {
+                    errors.entry(name).or_insert(error);
+                }
+            }
+        }
+
+        let records: Vec<RecordVariant> = changes
+            .iter()
+            .filter(|(name, _)| !errors.contains_key(name))
+            .flat_map(|(_, records)| records.iter().cloned())
+            .collect();
+        let written = if records.is_empty() {
+            Ok(())
+        } else {
+            self.logs.append_metadata(records.clone()).map_err(|e| {
+                eprintln!("Failed to write SCRAM credential records: {e}");
+                (ErrorCode::KafkaStorageError, e.to_string())
+            })
+        };
+        if written.is_ok() {
+            for record in records {
+                apply_record(&mut users, record);
+            }
+        }
+
+        changes
+            .into_iter()
+            .map(|(name, _)| {
+                let result = match errors.remove(&name) {
+                    Some(error) => Err(error),
+                    None => written.clone(),
+                };
+                (name, result)
+            })
+            .collect()
+    }
+}
+
+/// The record deleting the credential of `name`, or upserting it when
+/// `upsertion` is given.
+fn alteration_record(
+    users: &BTreeMap<String, BTreeMap<ScramMechanism, ScramCredential>>,
+    name: &str,
+    mechanism: ScramMechanism,
+    upsertion: Option<ScramUpsertion>,
+) -> std::result::Result<RecordVariant, CredentialError> {
+    let unacceptable =
+        |message: &str| Err((ErrorCode::UnacceptableCredential, message.to_string()));
+    if name.is_empty() {
+        return unacceptable("Username must not be empty");
+    }
+
+    let Some(upsertion) = upsertion else {
+        let exists = users
+            .get(name)
+            .is_some_and(|credentials| credentials.contains_key(&mechanism));
+        if !exists {
+            return Err((
+                ErrorCode::ResourceNotFound,
+                "Attempt to delete a user credential that does not exist".to_string(),
+            ));
+        }
+        return Ok(RecordVariant::RemoveUserScramCredential(
+            RemoveUserScramCredentialRecord {
+                name: CompactString::new(name.to_string()),
+                mechanism: mechanism as i8,
+            },
+        ));
+    };
+
+    if upsertion.iterations < MIN_ITERATIONS {
+        return unacceptable("Too few iterations");
+    }
+    if upsertion.iterations > MAX_ITERATIONS {
+        return unacceptable("Too many iterations");
+    }
+    if upsertion.salt.is_empty() || upsertion.salted_password.is_empty() {
+        return unacceptable("Salt and salted password must not be empty");
+    }
+    let credential = ScramCredential::new(
+        mechanism,
+        upsertion.salt,
+        &upsertion.salted_password,
+        upsertion.iterations,
+    );
+    Ok(RecordVariant::UserScramCredential(
+        UserScramCredentialRecord {
+            name: CompactString::new(name.to_string()),
+            mechanism: mechanism as i8,
+            salt: CompactBytes::new(credential.salt),
+            stored_key: CompactBytes::new(credential.stored_key),
+            server_key: CompactBytes::new(credential.server_key),
+            iterations: credential.iterations,
+        },
+    ))
+}
+
+fn apply_record(
+    users: &mut BTreeMap<String, BTreeMap<ScramMechanism, ScramCredential>>,
+    record: RecordVariant,
+) {
+    match record {
+        RecordVariant::UserScramCredential(record) => {
+            let Some(mechanism) = ScramMechanism::from_i8(record.mechanism) else {
+                return;
+            };
+            users
+                .entry(record.name.as_str().to_string())
+                .or_default()
+                .insert(
+                    mechanism,
+                    ScramCredential {
+                        salt: record.salt.as_bytes().to_vec(),
+                        stored_key: record.stored_key.as_bytes().to_vec(),
+                        server_key: record.server_key.as_bytes().to_vec(),
+                        iterations: record.iterations,
+                    },
+                );
+        }
+        RecordVariant::RemoveUserScramCredential(record) => {
+            let Some(mechanism) = ScramMechanism::from_i8(record.mechanism) else {
+                return;
+            };
+            if let Some(credentials) = users.get_mut(record.name.as_str()) {
+                credentials.remove(&mechanism);
+                if credentials.is_empty() {
+                    users.remove(record.name.as_str());
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upsertion(name: &str, mechanism: i8, iterations: i32) -> ScramUpsertion {
+        ScramUpsertion {
+            name: name.to_string(),
+            mechanism,
+            iterations,
+            salt: b"salt".to_vec(),
+            salted_password: b"salted".to_vec(),
+        }
+    }
+
+    fn deletion(name: &str, mechanism: i8) -> ScramDeletion {
+        ScramDeletion {
+            name: name.to_string(),
+            mechanism,
+        }
+    }
+
+    #[test]
+    fn test_alter_and_describe_credentials() {
+        let dir = std::env::temp_dir().join(format!("kafka-scram-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let credentials = ScramCredentials::new(Arc::new(LogManager::new(&dir)));
+
+        let results = credentials.alter(
+            vec![deletion("bob", 1)],
+            vec![
+                upsertion("alice", 1, 4096),
+                upsertion("alice", 2, 8192),
+                upsertion("carol", 1, 100),
+                upsertion("dave", 3, 4096),
+                upsertion("erin", 1, 4096),
+                upsertion("erin", 1, 4096),
+            ],
+        );
+        let codes: Vec<(&str, Option<ErrorCode>)> = results
+            .iter()
+            .map(|(name, result)| (name.as_str(), result.as_ref().err().map(|e| e.0)))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                ("bob", Some(ErrorCode::ResourceNotFound)),
+                ("alice", None),
+                ("carol", Some(ErrorCode::UnacceptableCredential)),
+                ("dave", Some(ErrorCode::UnsupportedSaslMechanism)),
+                ("erin", Some(ErrorCode::DuplicateResource)),
+            ]
+        );
+
+        let expected =
+            ScramCredential::new(ScramMechanism::Sha256, b"salt".to_vec(), b"salted", 4096);
+        assert_eq!(
+            credentials.get("alice", ScramMechanism::Sha256),
+            Some(expected)
+        );
+        assert_eq!(credentials.get("erin", ScramMechanism::Sha256), None);
+
+        // The credentials are replayed from the metadata log.
+        let credentials = ScramCredentials::new(Arc::new(LogManager::new(&dir)));
+        credentials.load().unwrap();
+        assert_eq!(
+            credentials.describe(None),
+            [(
+                "alice".to_string(),
+                Ok(vec![
+                    (ScramMechanism::Sha256, 4096),
+                    (ScramMechanism::Sha512, 8192)
+                ])
+            )]
+        );
+
+        let results = credentials.alter(vec![deletion("alice", 1)], vec![]);
+        assert_eq!(results, [("alice".to_string(), Ok(()))]);
+        let described = credentials.describe(Some(vec![
+            "alice".to_string(),
+            "bob".to_string(),
+            "bob".to_string(),
+        ]));
+        assert_eq!(
+            described[0],
+            (
+                "alice".to_string(),
+                Ok(vec![(ScramMechanism::Sha512, 8192)])
+            )
+        );
+        assert_eq!(
+            described[1].1.as_ref().unwrap_err().0,
+            ErrorCode::DuplicateResource
+        );
+        assert_eq!(described.len(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
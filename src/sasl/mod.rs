@@ -0,0 +1,189 @@
+mod credentials;
+mod plain;
+mod scram;
+
+pub(crate) use credentials::{ScramCredentials, ScramDeletion, ScramUpsertion};
+pub(crate) use scram::ScramMechanism;
+
+use crate::{api::ErrorCode, config::SecurityProtocol};
+
+use scram::ScramServer;
+use std::collections::HashMap;
+
+const PLAIN: &str = "PLAIN";
+
+/// The principal of clients which did not authenticate.
+const ANONYMOUS: &str = "User:ANONYMOUS";
+
+/// Why a SASL request failed.
+pub(crate) type SaslError = (ErrorCode, String);
+
+/// The reply to a message of the client.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Step {
+    /// The client has more to send.
+    Challenge(Vec<u8>),
+    /// The client is authenticated as `user`.
+    Complete { reply: Vec<u8>, user: String },
+}
+
+/// Who the client of a connection is, and how far it is in
+/// authenticating. Clients of SASL listeners send SaslHandshake, then
+/// SaslAuthenticate until the mechanism completes; other requests are only
+/// served afterwards.
+#[derive(Debug)]
+pub(crate) struct Authenticator {
+    state: State,
+    principal: String,
+}
+
+#[derive(Debug)]
+enum State {
+    Handshake,
+    Authenticate(Mechanism),
+    Authenticated,
+    Failed,
+}
+
+#[derive(Debug)]
+enum Mechanism {
+    Plain,
+    Scram(Box<ScramServer>),
+}
+
+impl Authenticator {
+    /// Clients of listeners without SASL are authenticated from the start,
+    /// by the subject of their TLS certificate if they presented one.
+    pub(crate) fn new(
+        security_protocol: SecurityProtocol,
+        certificate_subject: Option<String>,
+    ) -> Self {
+        let state = if security_protocol.uses_sasl() {
+            State::Handshake
+        } else {
+            State::Authenticated
+        };
+        let principal = certificate_subject
+            .map(|subject| format!("User:{subject}"))
+            .unwrap_or_else(|| ANONYMOUS.to_string());
+        Self { state, principal }
+    }
+
+    /// The principal the client acts as, such as `User:alice`.
+    pub(crate) fn principal(&self) -> &str {
+        &self.principal
+    }
+
+    pub(crate) fn is_authenticated(&self) -> bool {
+        matches!(self.state, State::Authenticated)
+    }
+
+    /// Whether authentication failed, after which the connection is closed.
+    pub(crate) fn has_failed(&self) -> bool {
+        matches!(self.state, State::Failed)
+    }
+
+    /// Selects the mechanism of the following SaslAuthenticate requests.
+    pub(crate) fn handshake(
+        &mut self,
+        mechanism: &str,
+        enabled_mechanisms: &[String],
+    ) -> Result<(), SaslError> {
+        if !matches!(self.state, State::Handshake) {
+            return Err((
+                ErrorCode::IllegalSaslState,
+                "Unexpected SaslHandshake request".to_string(),
+            ));
+        }
+
+        let enabled = enabled_mechanisms.iter().any(|name| name == mechanism);
+        let mechanism = match ScramMechanism::from_name(mechanism) {
+            Some(scram) if enabled => Mechanism::Scram(Box::new(ScramServer::new(scram))),
+            None if enabled && mechanism == PLAIN => Mechanism::Plain,
+            _ => {
+                return Err((
+                    ErrorCode::UnsupportedSaslMechanism,
+                    format!("Unsupported SASL mechanism {mechanism}"),
+                ));
+            }
+        };
+        self.state = State::Authenticate(mechanism);
+        Ok(())
+    }
+
+    /// Passes a message of the client to the mechanism and returns its
+    /// reply. A failure fails the authentication of the connection.
+    pub(crate) fn authenticate(
+        &mut self,
+        message: &[u8],
+        plain_users: &HashMap<String, String>,
+        credentials: &ScramCredentials,
+    ) -> Result<Vec<u8>, SaslError> {
+        let step = match &mut self.state {
+            State::Authenticate(Mechanism::Plain) => {
+                plain::authenticate(message, plain_users).map(|user| Step::Complete {
+                    reply: vec![],
+                    user,
+                })
+            }
+            State::Authenticate(Mechanism::Scram(server)) => {
+                let mechanism = server.mechanism();
+                server.evaluate(message, |user| credentials.get(user, mechanism))
+            }
+            State::Handshake | State::Authenticated | State::Failed => {
+                return Err((
+                    ErrorCode::IllegalSaslState,
+                    "Unexpected SaslAuthenticate request".to_string(),
+                ));
+            }
+        };
+
+        match step {
+            Ok(Step::Challenge(reply)) => Ok(reply),
+            Ok(Step::Complete { reply, user }) => {
+                self.state = State::Authenticated;
+                self.principal = format!("User:{user}");
+                Ok(reply)
+            }
+            Err(message) => {
+                self.state = State::Failed;
+                Err((ErrorCode::SaslAuthenticationFailed, message))
+            }
+        }
+    }
+}
+
+/// Compares secrets in a time independent of where they differ.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handshake() {
+        let auth = Authenticator::new(SecurityProtocol::Plaintext, None);
+        assert!(auth.is_authenticated());
+        assert_eq!(auth.principal(), "User:ANONYMOUS");
+        let auth = Authenticator::new(SecurityProtocol::Ssl, Some("CN=alice".to_string()));
+        assert_eq!(auth.principal(), "User:CN=alice");
+
+        let enabled = ["PLAIN".to_string(), "SCRAM-SHA-512".to_string()];
+        let mut auth = Authenticator::new(SecurityProtocol::SaslPlaintext, None);
+        assert!(!auth.is_authenticated());
+        for mechanism in ["GSSAPI", "SCRAM-SHA-256"] {
+            assert_eq!(
+                auth.handshake(mechanism, &enabled).unwrap_err().0,
+                ErrorCode::UnsupportedSaslMechanism
+            );
+        }
+        assert!(auth.handshake("SCRAM-SHA-512", &enabled).is_ok());
+        assert_eq!(
+            auth.handshake("PLAIN", &enabled).unwrap_err().0,
+            ErrorCode::IllegalSaslState
+        );
+        assert!(!auth.is_authenticated() && !auth.has_failed());
+    }
+}
@@ -0,0 +1,55 @@
+use super::constant_time_eq;
+
+use std::collections::HashMap;
+
+/// Checks the message of a SASL/PLAIN client, `[authzid] NUL authcid NUL
+/// passwd` (RFC 4616), against the configured users. Returns the user.
+pub(crate) fn authenticate(
+    message: &[u8],
+    users: &HashMap<String, String>,
+) -> Result<String, String> {
+    let tokens: Vec<&[u8]> = message.split(|b| *b == 0).collect();
+    let [authzid, username, password] = tokens[..] else {
+        return Err(format!(
+            "Invalid SASL/PLAIN response: expected 3 tokens, got {}",
+            tokens.len()
+        ));
+    };
+    if username.is_empty() || password.is_empty() {
+        return Err("Authentication failed: username or password not specified".to_string());
+    }
+    if !authzid.is_empty() && authzid != username {
+        return Err(
+            "Authentication failed: Client requested an authorization id that is different from username"
+                .to_string(),
+        );
+    }
+
+    let invalid = || "Authentication failed: Invalid username or password".to_string();
+    let username = std::str::from_utf8(username).map_err(|_| invalid())?;
+    match users.get(username) {
+        Some(expected) if constant_time_eq(expected.as_bytes(), password) => {
+            Ok(username.to_string())
+        }
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_authenticate() {
+        let users = HashMap::from([("alice".to_string(), "alice-secret".to_string())]);
+        assert_eq!(
+            authenticate(b"\0alice\0alice-secret", &users),
+            Ok("alice".to_string())
+        );
+        assert!(authenticate(b"alice\0alice\0alice-secret", &users).is_ok());
+        assert!(authenticate(b"\0alice\0wrong", &users).is_err());
+        assert!(authenticate(b"\0bob\0alice-secret", &users).is_err());
+        assert!(authenticate(b"bob\0alice\0alice-secret", &users).is_err());
+        assert!(authenticate(b"alice-secret", &users).is_err());
+    }
+}
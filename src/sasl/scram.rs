@@ -0,0 +1,370 @@
+use super::{Step, constant_time_eq};
+
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD},
+};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256, Sha512};
+
+/// The bounds Kafka puts on the iterations of a SCRAM credential.
+pub(crate) const MIN_ITERATIONS: i32 = 4096;
+pub(crate) const MAX_ITERATIONS: i32 = 16384;
+
+/// The SCRAM mechanisms, numbered as in `UserScramCredentialRecord`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum ScramMechanism {
+    Sha256 = 1,
+    Sha512 = 2,
+}
+
+impl ScramMechanism {
+    pub(crate) fn from_i8(value: i8) -> Option<Self> {
+        match value {
+            1 => Some(Self::Sha256),
+            2 => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "SCRAM-SHA-256" => Some(Self::Sha256),
+            "SCRAM-SHA-512" => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Sha256 => "SCRAM-SHA-256",
+            Self::Sha512 => "SCRAM-SHA-512",
+        }
+    }
+
+    fn hmac(self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha256 => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("any key size is valid");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+            Self::Sha512 => {
+                let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("any key size is valid");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+
+    fn hash(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha256 => Sha256::digest(data).to_vec(),
+            Self::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+}
+
+/// What the broker keeps of a password to verify SCRAM proofs.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ScramCredential {
+    pub(crate) salt: Vec<u8>,
+    pub(crate) stored_key: Vec<u8>,
+    pub(crate) server_key: Vec<u8>,
+    pub(crate) iterations: i32,
+}
+
+impl ScramCredential {
+    /// Derives the keys from the salted password, `Hi(password, salt, i)` of
+    /// RFC 5802, which clients of AlterUserScramCredentials compute.
+    pub(crate) fn new(
+        mechanism: ScramMechanism,
+        salt: Vec<u8>,
+        salted_password: &[u8],
+        iterations: i32,
+    ) -> Self {
+        let client_key = mechanism.hmac(salted_password, b"Client Key");
+        Self {
+            salt,
+            stored_key: mechanism.hash(&client_key),
+            server_key: mechanism.hmac(salted_password, b"Server Key"),
+            iterations,
+        }
+    }
+}
+
+/// The server side of a SCRAM exchange (RFC 5802): client-first,
+/// server-first, client-final, then server-final. Channel binding is not
+/// supported.
+#[derive(Debug)]
+pub(crate) struct ScramServer {
+    mechanism: ScramMechanism,
+    state: State,
+}
+
+#[derive(Debug)]
+enum State {
+    ClientFirst,
+    ClientFinal {
+        username: String,
+        gs2_header: String,
+        client_first_bare: String,
+        server_first: String,
+        nonce: String,
+        credential: ScramCredential,
+    },
+    Complete,
+}
+
+impl ScramServer {
+    pub(crate) fn new(mechanism: ScramMechanism) -> Self {
+        Self {
+            mechanism,
+            state: State::ClientFirst,
+        }
+    }
+
+    pub(crate) fn mechanism(&self) -> ScramMechanism {
+        self.mechanism
+    }
+
+    /// Handles the next message of the client. `credential` looks up the
+    /// credential of the user the client claims to be.
+    pub(crate) fn evaluate<F>(&mut self, message: &[u8], credential: F) -> Result<Step, String>
+    where
+        F: FnOnce(&str) -> Option<ScramCredential>,
+    {
+        let message = std::str::from_utf8(message)
+            .map_err(|_| "Invalid SCRAM message: not UTF-8".to_string())?;
+
+        match std::mem::replace(&mut self.state, State::Complete) {
+            State::ClientFirst => {
+                let client_first = ClientFirst::parse(message)?;
+                let credential =
+                    credential(&client_first.username).ok_or_else(|| self.invalid_credentials())?;
+
+                let nonce = format!("{}{}", client_first.nonce, server_nonce()?);
+                let server_first = format!(
+                    "r={nonce},s={},i={}",
+                    BASE64.encode(&credential.salt),
+                    credential.iterations
+                );
+                self.state = State::ClientFinal {
+                    username: client_first.username,
+                    gs2_header: client_first.gs2_header,
+                    client_first_bare: client_first.bare,
+                    server_first: server_first.clone(),
+                    nonce,
+                    credential,
+                };
+                Ok(Step::Challenge(server_first.into_bytes()))
+            }
+            State::ClientFinal {
+                username,
+                gs2_header,
+                client_first_bare,
+                server_first,
+                nonce,
+                credential,
+            } => {
+                let invalid =
+                    |reason: &str| format!("Invalid SCRAM client final message: {reason}");
+
+                let (without_proof, proof) = message
+                    .rsplit_once(",p=")
+                    .ok_or_else(|| invalid("missing proof"))?;
+                let mut attributes = without_proof.split(',');
+                if attributes.next().and_then(|a| a.strip_prefix("c="))
+                    != Some(BASE64.encode(&gs2_header).as_str())
+                {
+                    return Err(invalid("channel binding does not match"));
+                }
+                if attributes.next().and_then(|a| a.strip_prefix("r=")) != Some(nonce.as_str()) {
+                    return Err(invalid("nonce does not match"));
+                }
+                let proof = BASE64
+                    .decode(proof)
+                    .map_err(|_| invalid("proof is not base64"))?;
+
+                let auth_message = format!("{client_first_bare},{server_first},{without_proof}");
+                let client_signature = self
+                    .mechanism
+                    .hmac(&credential.stored_key, auth_message.as_bytes());
+                if proof.len() != client_signature.len() {
+                    return Err(self.invalid_credentials());
+                }
+                let client_key: Vec<u8> = proof
+                    .iter()
+                    .zip(&client_signature)
+                    .map(|(a, b)| a ^ b)
+                    .collect();
+                if !constant_time_eq(&self.mechanism.hash(&client_key), &credential.stored_key) {
+                    return Err(self.invalid_credentials());
+                }
+
+                let server_signature = self
+                    .mechanism
+                    .hmac(&credential.server_key, auth_message.as_bytes());
+                Ok(Step::Complete {
+                    reply: format!("v={}", BASE64.encode(server_signature)).into_bytes(),
+                    user: username,
+                })
+            }
+            State::Complete => Err("SCRAM exchange is already complete".to_string()),
+        }
+    }
+
+    fn invalid_credentials(&self) -> String {
+        format!(
+            "Authentication failed during authentication due to invalid credentials with SASL mechanism {}",
+            self.mechanism.name()
+        )
+    }
+}
+
+/// The first message of the client: `gs2-header client-first-bare`, where
+/// the bare part is `n=<username>,r=<client nonce>[,extensions]`.
+struct ClientFirst {
+    gs2_header: String,
+    username: String,
+    nonce: String,
+    bare: String,
+}
+
+impl ClientFirst {
+    fn parse(message: &str) -> Result<Self, String> {
+        let invalid = |reason: &str| format!("Invalid SCRAM client first message: {reason}");
+
+        let mut parts = message.splitn(3, ',');
+        let (Some(channel_binding), Some(authzid), Some(bare)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid("missing GS2 header"));
+        };
+        if channel_binding != "n" && channel_binding != "y" {
+            return Err(invalid("channel binding is not supported"));
+        }
+
+        let mut attributes = bare.split(',');
+        let username = attributes
+            .next()
+            .and_then(|a| a.strip_prefix("n="))
+            .map(decode_username)
+            .ok_or_else(|| invalid("missing username"))?;
+        let nonce = attributes
+            .next()
+            .and_then(|a| a.strip_prefix("r="))
+            .filter(|nonce| !nonce.is_empty())
+            .ok_or_else(|| invalid("missing nonce"))?;
+        if !authzid.is_empty()
+            && authzid.strip_prefix("a=").map(decode_username) != Some(username.clone())
+        {
+            return Err(format!(
+                "Authentication failed: Invalid authorization ID {authzid} for user {username}"
+            ));
+        }
+
+        Ok(Self {
+            gs2_header: format!("{channel_binding},{authzid},"),
+            username,
+            nonce: nonce.to_string(),
+            bare: bare.to_string(),
+        })
+    }
+}
+
+/// Unescapes the `,` and `=` of a SCRAM username.
+fn decode_username(name: &str) -> String {
+    name.replace("=2C", ",").replace("=3D", "=")
+}
+
+fn server_nonce() -> Result<String, String> {
+    let mut bytes = [0u8; 24];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate a nonce: {e}"))?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `Hi(password, salt, i)` of RFC 5802, computed by clients.
+    fn salted_password(mechanism: ScramMechanism, password: &str, salt: &[u8], i: i32) -> Vec<u8> {
+        let mut u = mechanism.hmac(password.as_bytes(), &[salt, &[0, 0, 0, 1]].concat());
+        let mut result = u.clone();
+        for _ in 1..i {
+            u = mechanism.hmac(password.as_bytes(), &u);
+            result.iter_mut().zip(&u).for_each(|(r, u)| *r ^= u);
+        }
+        result
+    }
+
+    /// Runs the exchange as a client with `password`, returning the reply
+    /// to the client-final message.
+    fn exchange(mechanism: ScramMechanism, password: &str) -> Result<Step, String> {
+        let salted = salted_password(mechanism, "secret", b"salt", MIN_ITERATIONS);
+        let stored = ScramCredential::new(mechanism, b"salt".to_vec(), &salted, MIN_ITERATIONS);
+        let lookup = |user: &str| (user == "alice").then(|| stored.clone());
+
+        let mut server = ScramServer::new(mechanism);
+        let client_first_bare = "n=alice,r=cnonce";
+        let Step::Challenge(server_first) = server
+            .evaluate(format!("n,,{client_first_bare}").as_bytes(), lookup)
+            .unwrap()
+        else {
+            panic!("expected a challenge");
+        };
+        let server_first = String::from_utf8(server_first).unwrap();
+        let nonce = server_first
+            .strip_prefix("r=")
+            .and_then(|s| s.split(',').next())
+            .unwrap();
+        assert!(nonce.starts_with("cnonce") && nonce.len() > "cnonce".len());
+        assert!(server_first.ends_with(&format!(",s={},i=4096", BASE64.encode("salt"))));
+
+        let salted = salted_password(mechanism, password, b"salt", MIN_ITERATIONS);
+        let client_key = mechanism.hmac(&salted, b"Client Key");
+        let without_proof = format!("c=biws,r={nonce}");
+        let auth_message = format!("{client_first_bare},{server_first},{without_proof}");
+        let signature = mechanism.hmac(&mechanism.hash(&client_key), auth_message.as_bytes());
+        let proof: Vec<u8> = client_key
+            .iter()
+            .zip(&signature)
+            .map(|(a, b)| a ^ b)
+            .collect();
+        let client_final = format!("{without_proof},p={}", BASE64.encode(proof));
+
+        let step = server.evaluate(client_final.as_bytes(), |_| None)?;
+        let server_signature = mechanism.hmac(&stored.server_key, auth_message.as_bytes());
+        assert_eq!(
+            step,
+            Step::Complete {
+                reply: format!("v={}", BASE64.encode(server_signature)).into_bytes(),
+                user: "alice".to_string(),
+            }
+        );
+        Ok(step)
+    }
+
+    #[test]
+    fn test_scram_exchange() {
+        assert!(exchange(ScramMechanism::Sha256, "secret").is_ok());
+        assert!(exchange(ScramMechanism::Sha512, "secret").is_ok());
+        assert!(exchange(ScramMechanism::Sha256, "wrong").is_err());
+    }
+
+    #[test]
+    fn test_scram_client_first_rejected() {
+        let mut server = ScramServer::new(ScramMechanism::Sha256);
+        assert!(server.evaluate(b"n,,n=bob,r=cnonce", |_| None).is_err());
+
+        let credential = ScramCredential::new(ScramMechanism::Sha256, vec![1], b"pw", 4096);
+        let mut server = ScramServer::new(ScramMechanism::Sha256);
+        let lookup = |_: &str| Some(credential.clone());
+        assert!(server.evaluate(b"p=tls-unique,,n=bob,r=x", lookup).is_err());
+
+        let mut server = ScramServer::new(ScramMechanism::Sha256);
+        let lookup = |_: &str| Some(credential.clone());
+        assert!(server.evaluate(b"n,a=alice,n=bob,r=x", lookup).is_err());
+    }
+}
@@ -1,9 +1,28 @@
 mod serializer;
 pub(crate) use serializer::Serializer;
 
-use crate::KafkaError;
+use crate::{KafkaError, Result};
 
+use serde::Serialize;
 use serde::ser::Error;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// Encodes the value in memory, such as a response to send.
+pub(crate) fn to_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    value.serialize(&mut Serializer::new(&mut bytes))?;
+    Ok(bytes)
+}
+
+/// Encodes the value and writes it once encoded.
+pub(crate) async fn write<W, T>(writer: &mut W, value: &T) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    writer.write_all(&to_bytes(value)?).await?;
+    Ok(())
+}
 
 impl Error for KafkaError {
     fn custom<T: std::fmt::Display>(msg: T) -> Self {
@@ -0,0 +1,302 @@
+use crate::{
+    KafkaError, Result,
+    config::{ClientAuth, SslConfig},
+};
+
+use rustls::{
+    RootCertStore, ServerConfig,
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    server::WebPkiClientVerifier,
+};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+
+pub(crate) type TlsStream = tokio_rustls::server::TlsStream<TcpStream>;
+
+/// Accepts the TLS connections of an SSL or SASL_SSL listener.
+#[derive(Clone)]
+pub(crate) struct TlsAcceptor {
+    acceptor: tokio_rustls::TlsAcceptor,
+}
+
+impl std::fmt::Debug for TlsAcceptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsAcceptor").finish_non_exhaustive()
+    }
+}
+
+impl TlsAcceptor {
+    pub(crate) fn new(ssl: &SslConfig) -> Result<Self> {
+        let provider = Arc::new(ring::default_provider());
+
+        let keystore = ssl.keystore_location.as_ref().ok_or_else(|| {
+            KafkaError::InvalidConfig("ssl.keystore.location is required for TLS".to_string())
+        })?;
+        let certs = CertificateDer::pem_file_iter(keystore)
+            .and_then(|certs| certs.collect::<std::result::Result<Vec<_>, _>>())
+            .map_err(|e| pem_error(keystore, e))?;
+        let key = PrivateKeyDer::from_pem_file(keystore).map_err(|e| pem_error(keystore, e))?;
+
+        let verifier = match ssl.client_auth {
+            ClientAuth::None => WebPkiClientVerifier::no_client_auth(),
+            ClientAuth::Requested | ClientAuth::Required => {
+                let truststore = ssl.truststore_location.as_ref().ok_or_else(|| {
+                    KafkaError::InvalidConfig(
+                        "ssl.truststore.location is required for ssl.client.auth".to_string(),
+                    )
+                })?;
+                let mut roots = RootCertStore::empty();
+                for cert in CertificateDer::pem_file_iter(truststore)
+                    .map_err(|e| pem_error(truststore, e))?
+                {
+                    let cert = cert.map_err(|e| pem_error(truststore, e))?;
+                    roots.add(cert).map_err(tls_error)?;
+                }
+                let builder =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone());
+                let builder = if ssl.client_auth == ClientAuth::Requested {
+                    builder.allow_unauthenticated()
+                } else {
+                    builder
+                };
+                builder
+                    .build()
+                    .map_err(|e| KafkaError::InvalidConfig(e.to_string()))?
+            }
+        };
+
+        let config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(certs, key)
+            .map_err(tls_error)?;
+        Ok(Self {
+            acceptor: Arc::new(config).into(),
+        })
+    }
+
+    /// Completes the handshake with the client. Returns the TLS stream and,
+    /// when the client presented a certificate, the distinguished name of
+    /// its subject.
+    pub(crate) async fn accept(&self, stream: TcpStream) -> Result<(TlsStream, Option<String>)> {
+        let stream = self.acceptor.accept(stream).await?;
+        let (_, conn) = stream.get_ref();
+        let subject = match conn.peer_certificates().and_then(|certs| certs.first()) {
+            Some(cert) => Some(subject_name(cert)?),
+            None => None,
+        };
+        Ok((stream, subject))
+    }
+}
+
+/// The subject of a certificate as an RFC 2253 distinguished name, such as
+/// `CN=alice,OU=eng,O=Acme`, the way Kafka names SSL principals.
+fn subject_name(cert: &CertificateDer) -> Result<String> {
+    let (_, cert) = x509_parser::parse_x509_certificate(cert)
+        .map_err(|e| KafkaError::AuthenticationError(format!("invalid client certificate: {e}")))?;
+
+    let mut rdns: Vec<String> = cert
+        .subject()
+        .iter_rdn()
+        .map(|rdn| {
+            rdn.iter()
+                .map(|attr| {
+                    let oid = attr.attr_type().to_id_string();
+                    let key = match oid.as_str() {
+                        "2.5.4.3" => "CN",
+                        "2.5.4.6" => "C",
+                        "2.5.4.7" => "L",
+                        "2.5.4.8" => "ST",
+                        "2.5.4.9" => "STREET",
+                        "2.5.4.10" => "O",
+                        "2.5.4.11" => "OU",
+                        "0.9.2342.19200300.100.1.1" => "UID",
+                        "0.9.2342.19200300.100.1.25" => "DC",
+                        oid => oid,
+                    };
+                    let value = attr
+                        .as_str()
+                        .map(escape_attribute_value)
+                        .unwrap_or_default();
+                    format!("{key}={value}")
+                })
+                .collect::<Vec<_>>()
+                .join("+")
+        })
+        .collect();
+    // The most specific name comes first.
+    rdns.reverse();
+    Ok(rdns.join(","))
+}
+
+fn escape_attribute_value(value: &str) -> String {
+    let last = value.chars().count().saturating_sub(1);
+    let mut escaped = String::with_capacity(value.len());
+    for (i, c) in value.chars().enumerate() {
+        let leading = i == 0 && (c == ' ' || c == '#');
+        let trailing = i == last && c == ' ';
+        if leading || trailing || matches!(c, ',' | '+' | '"' | '\\' | '<' | '>' | ';') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn pem_error(path: &Path, e: rustls::pki_types::pem::Error) -> KafkaError {
+    KafkaError::InvalidConfig(format!("failed to read {}: {e}", path.display()))
+}
+
+fn tls_error(e: rustls::Error) -> KafkaError {
+    KafkaError::InvalidConfig(format!("TLS error: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{
+        BasicConstraints, Certificate, CertificateParams, CertifiedIssuer, DistinguishedName,
+        DnType, IsCa, KeyPair,
+    };
+    use rustls::{ClientConfig, ClientConnection, StreamOwned, pki_types::ServerName};
+    use std::io::{Read, Write};
+    use std::path::PathBuf;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    struct Pki {
+        dir: PathBuf,
+        ca: CertifiedIssuer<'static, KeyPair>,
+    }
+
+    impl Pki {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("kafka-tls-{name}-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            params
+                .distinguished_name
+                .push(DnType::CommonName, "Test CA");
+            let ca = CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap();
+            std::fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+            Self { dir, ca }
+        }
+
+        /// Issues a certificate, returning it with its key.
+        fn issue(&self, params: CertificateParams) -> (Certificate, KeyPair) {
+            let key = KeyPair::generate().unwrap();
+            let cert = params.signed_by(&key, &self.ca).unwrap();
+            (cert, key)
+        }
+
+        fn ssl_config(&self, client_auth: ClientAuth) -> SslConfig {
+            let mut params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+            params.distinguished_name.push(DnType::CommonName, "broker");
+            let (cert, key) = self.issue(params);
+            let pem = format!("{}{}", key.serialize_pem(), cert.pem());
+            std::fs::write(self.dir.join("broker.pem"), pem).unwrap();
+            SslConfig {
+                keystore_location: Some(self.dir.join("broker.pem")),
+                truststore_location: Some(self.dir.join("ca.pem")),
+                client_auth,
+            }
+        }
+
+        fn client_config(&self, with_certificate: bool) -> ClientConfig {
+            let mut roots = RootCertStore::empty();
+            roots.add(self.ca.der().clone()).unwrap();
+            let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_root_certificates(roots);
+            if !with_certificate {
+                return builder.with_no_client_auth();
+            }
+            let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+            params.distinguished_name = DistinguishedName::new();
+            params
+                .distinguished_name
+                .push(DnType::OrganizationName, "Acme");
+            params
+                .distinguished_name
+                .push(DnType::OrganizationalUnitName, "eng");
+            params.distinguished_name.push(DnType::CommonName, "alice");
+            let (cert, key) = self.issue(params);
+            let key = PrivateKeyDer::try_from(key.serialize_der()).unwrap();
+            builder
+                .with_client_auth_cert(vec![cert.der().clone()], key)
+                .unwrap()
+        }
+    }
+
+    impl Drop for Pki {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// Accepts one connection from a client sending `ping` and echoes it.
+    #[tokio::main(flavor = "current_thread")]
+    async fn serve_one(
+        acceptor: TlsAcceptor,
+        client_config: ClientConfig,
+    ) -> Result<Option<String>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let conn = ClientConnection::new(
+                Arc::new(client_config),
+                ServerName::try_from("localhost").unwrap(),
+            )
+            .unwrap();
+            let stream = std::net::TcpStream::connect(addr).unwrap();
+            let mut stream = StreamOwned::new(conn, stream);
+            let mut buf = [0u8; 4];
+            stream
+                .write_all(b"ping")
+                .and_then(|()| stream.read_exact(&mut buf))
+                .map(|()| buf)
+        });
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let accepted = acceptor.accept(stream).await;
+        let subject = match accepted {
+            Ok((mut stream, subject)) => {
+                let mut buf = [0u8; 4];
+                stream.read_exact(&mut buf).await.unwrap();
+                stream.write_all(&buf).await.unwrap();
+                stream.flush().await.unwrap();
+                assert_eq!(&client.join().unwrap().unwrap(), b"ping");
+                subject
+            }
+            Err(e) => {
+                drop(listener);
+                assert!(client.join().unwrap().is_err());
+                return Err(e);
+            }
+        };
+        Ok(subject)
+    }
+
+    #[test]
+    fn test_accept_with_client_certificate() {
+        let pki = Pki::new("mtls");
+        let acceptor = TlsAcceptor::new(&pki.ssl_config(ClientAuth::Required)).unwrap();
+        let subject = serve_one(acceptor.clone(), pki.client_config(true)).unwrap();
+        assert_eq!(subject.as_deref(), Some("CN=alice,OU=eng,O=Acme"));
+        assert!(serve_one(acceptor, pki.client_config(false)).is_err());
+
+        let acceptor = TlsAcceptor::new(&pki.ssl_config(ClientAuth::Requested)).unwrap();
+        assert_eq!(serve_one(acceptor, pki.client_config(false)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_escape_attribute_value() {
+        assert_eq!(escape_attribute_value("Acme, Inc."), "Acme\\, Inc.");
+        assert_eq!(escape_attribute_value("#1 "), "\\#1\\ ");
+    }
+}
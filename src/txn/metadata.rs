@@ -0,0 +1,149 @@
+use crate::{log::TopicPartition, types::Array};
+
+use super::records::{
+    TransactionLogKey, TransactionLogPartitions, TransactionLogValue, TransactionRecord,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// State of a transaction, as stored in `transaction_status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TransactionState {
+    /// The producer has no transaction in progress.
+    Empty = 0,
+    /// Partitions have been added to the transaction.
+    Ongoing = 1,
+    /// EndTxn was received and the commit markers are being written.
+    PrepareCommit = 2,
+    /// EndTxn was received and the abort markers are being written.
+    PrepareAbort = 3,
+    CompleteCommit = 4,
+    CompleteAbort = 5,
+}
+
+impl TransactionState {
+    pub(crate) const ALL: [Self; 6] = [
+        Self::Empty,
+        Self::Ongoing,
+        Self::PrepareCommit,
+        Self::PrepareAbort,
+        Self::CompleteCommit,
+        Self::CompleteAbort,
+    ];
+
+    fn from_i8(value: i8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Empty),
+            1 => Some(Self::Ongoing),
+            2 => Some(Self::PrepareCommit),
+            3 => Some(Self::PrepareAbort),
+            4 => Some(Self::CompleteCommit),
+            5 => Some(Self::CompleteAbort),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TransactionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Empty => "Empty",
+            Self::Ongoing => "Ongoing",
+            Self::PrepareCommit => "PrepareCommit",
+            Self::PrepareAbort => "PrepareAbort",
+            Self::CompleteCommit => "CompleteCommit",
+            Self::CompleteAbort => "CompleteAbort",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The coordinator's view of the transactions of one transactional id.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TransactionMetadata {
+    pub(crate) transactional_id: String,
+    pub(crate) producer_id: i64,
+    pub(crate) producer_epoch: i16,
+    pub(crate) timeout_ms: i32,
+    pub(crate) state: TransactionState,
+    pub(crate) partitions: BTreeSet<TopicPartition>,
+    /// When the current transaction started, or -1 without one.
+    pub(crate) start_timestamp_ms: i64,
+    pub(crate) last_update_timestamp_ms: i64,
+}
+
+impl TransactionMetadata {
+    pub(crate) fn new(transactional_id: &str, producer_id: i64, timeout_ms: i32, now: i64) -> Self {
+        Self {
+            transactional_id: transactional_id.to_string(),
+            producer_id,
+            producer_epoch: 0,
+            timeout_ms,
+            state: TransactionState::Empty,
+            partitions: BTreeSet::new(),
+            start_timestamp_ms: -1,
+            last_update_timestamp_ms: now,
+        }
+    }
+
+    /// Whether an ongoing transaction has run longer than its timeout.
+    pub(crate) fn is_timed_out(&self, now: i64) -> bool {
+        self.state == TransactionState::Ongoing
+            && self.start_timestamp_ms + self.timeout_ms as i64 <= now
+    }
+
+    pub(crate) fn to_record(&self) -> TransactionRecord {
+        let mut by_topic: BTreeMap<&str, Vec<i32>> = BTreeMap::new();
+        for tp in &self.partitions {
+            by_topic.entry(&tp.topic).or_default().push(tp.partition);
+        }
+        let partitions = by_topic
+            .into_iter()
+            .map(|(topic, partition_ids)| TransactionLogPartitions {
+                topic: topic.to_string(),
+                partition_ids: Array::new(Some(partition_ids)),
+            })
+            .collect();
+
+        TransactionRecord(
+            TransactionLogKey {
+                transactional_id: self.transactional_id.clone(),
+            },
+            Some(TransactionLogValue {
+                producer_id: self.producer_id,
+                producer_epoch: self.producer_epoch,
+                transaction_timeout_ms: self.timeout_ms,
+                transaction_status: self.state as i8,
+                transaction_partitions: Array::new(Some(partitions)),
+                transaction_last_update_timestamp_ms: self.last_update_timestamp_ms,
+                transaction_start_timestamp_ms: self.start_timestamp_ms,
+            }),
+        )
+    }
+
+    /// Rebuilds the metadata from a record of `__transaction_state`. Returns
+    /// `None` for values with an unknown status.
+    pub(crate) fn from_value(transactional_id: String, value: TransactionLogValue) -> Option<Self> {
+        let partitions = value
+            .transaction_partitions
+            .into_iter()
+            .flat_map(|p| {
+                let topic = p.topic;
+                p.partition_ids
+                    .into_iter()
+                    .map(move |partition| TopicPartition::new(topic.as_str(), partition))
+            })
+            .collect();
+
+        Some(Self {
+            transactional_id,
+            producer_id: value.producer_id,
+            producer_epoch: value.producer_epoch,
+            timeout_ms: value.transaction_timeout_ms,
+            state: TransactionState::from_i8(value.transaction_status)?,
+            partitions,
+            start_timestamp_ms: value.transaction_start_timestamp_ms,
+            last_update_timestamp_ms: value.transaction_last_update_timestamp_ms,
+        })
+    }
+}
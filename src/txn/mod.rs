@@ -0,0 +1,668 @@
+mod metadata;
+mod records;
+
+use crate::{
+    Config, Result,
+    api::ErrorCode,
+    group::{GROUP_METADATA_TOPIC, GroupCoordinator},
+    log::{LogManager, TopicPartition},
+    producer::ProducerIdManager,
+    types::{ControlRecordType, RecordBatch, VarintBytes},
+    util,
+};
+
+pub(crate) use metadata::{TransactionMetadata, TransactionState};
+use records::TransactionRecord;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+pub(crate) const TRANSACTION_STATE_TOPIC: &str = "__transaction_state";
+
+/// The coordinator of this broker never moves to another broker, so every
+/// marker is written with the same coordinator epoch.
+const COORDINATOR_EPOCH: i32 = 0;
+
+/// The outcome of one producer's transaction, to be written to each of the
+/// partitions the transaction touched.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TxnMarker {
+    pub(crate) producer_id: i64,
+    pub(crate) producer_epoch: i16,
+    pub(crate) result: ControlRecordType,
+    pub(crate) coordinator_epoch: i32,
+    pub(crate) partitions: Vec<TopicPartition>,
+}
+
+/// Appends the marker to every partition of the transaction. Markers in
+/// `__consumer_offsets` also make the offsets committed within the
+/// transaction visible, or drop them.
+pub(crate) fn write_txn_markers(
+    logs: &LogManager,
+    groups: &GroupCoordinator,
+    marker: &TxnMarker,
+) -> Vec<(TopicPartition, ErrorCode)> {
+    marker
+        .partitions
+        .iter()
+        .map(|tp| {
+            let result = RecordBatch::end_transaction_marker(
+                util::now_ms(),
+                marker.producer_id,
+                marker.producer_epoch,
+                marker.result,
+                marker.coordinator_epoch,
+            )
+            .and_then(|batch| {
+                if tp.topic == GROUP_METADATA_TOPIC {
+                    groups.write_txn_marker(tp.partition, batch)
+                } else {
+                    logs.get_or_create(tp)?.lock().unwrap().append(batch)?;
+                    logs.purgatory().notify(tp);
+                    Ok(())
+                }
+            });
+
+            let error_code = match result {
+                Ok(()) => ErrorCode::NoError,
+                Err(e) => {
+                    eprintln!("Failed to write transaction marker to {tp}: {e}");
+                    ErrorCode::KafkaStorageError
+                }
+            };
+            (tp.clone(), error_code)
+        })
+        .collect()
+}
+
+/// Hands out producer IDs and drives transactions from the first
+/// AddPartitionsToTxn to the markers written on EndTxn. The state of each
+/// transactional id is persisted in the `__transaction_state` topic.
+#[derive(Debug)]
+pub(crate) struct TransactionCoordinator {
+    logs: Arc<LogManager>,
+    producer_ids: ProducerIdManager,
+    num_partitions: i32,
+    max_timeout_ms: i32,
+    transactions: Mutex<HashMap<String, TransactionMetadata>>,
+}
+
+impl TransactionCoordinator {
+    pub(crate) fn new(config: &Config, logs: Arc<LogManager>) -> Self {
+        Self {
+            producer_ids: ProducerIdManager::new(config, Arc::clone(&logs)),
+            logs,
+            num_partitions: config.transaction_state_log_num_partitions,
+            max_timeout_ms: config.transaction_max_timeout_ms,
+            transactions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Rebuilds the in-memory state by replaying every `__transaction_state`
+    /// partition found on disk, then finishes the transactions whose markers
+    /// may not have been written before the broker stopped.
+    pub(crate) fn load(&self, groups: &GroupCoordinator) -> Result<()> {
+        let mut transactions = self.transactions.lock().unwrap();
+
+        for tp in self.logs.partitions_on_disk(TRANSACTION_STATE_TOPIC)? {
+            let log = self.logs.get_or_create(&tp)?;
+            let batches = log.lock().unwrap().read::<VarintBytes>()?;
+            for record in batches.into_iter().flatten() {
+                match TransactionRecord::from_record(&record)? {
+                    Some(TransactionRecord(key, Some(value))) => {
+                        let transactional_id = key.transactional_id;
+                        match TransactionMetadata::from_value(transactional_id.clone(), value) {
+                            Some(txn) => {
+                                transactions.insert(transactional_id, txn);
+                            }
+                            None => eprintln!(
+                                "Ignoring transaction {transactional_id} with an unknown status"
+                            ),
+                        }
+                    }
+                    Some(TransactionRecord(key, None)) => {
+                        transactions.remove(&key.transactional_id);
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        for txn in transactions.values_mut() {
+            let result = match txn.state {
+                TransactionState::PrepareCommit => ControlRecordType::Commit,
+                TransactionState::PrepareAbort => ControlRecordType::Abort,
+                _ => continue,
+            };
+            if let Err(error_code) = self.complete(txn, result, false, groups) {
+                eprintln!(
+                    "Failed to complete transaction {}: {error_code:?}",
+                    txn.transactional_id
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The `__transaction_state` partition that owns `transactional_id`.
+    pub(crate) fn partition_for(&self, transactional_id: &str) -> i32 {
+        (util::java_string_hash(transactional_id) & 0x7FFF_FFFF) % self.num_partitions
+    }
+
+    /// Handles an InitProducerId. Idempotent producers get a fresh producer
+    /// ID at epoch 0 every time. Transactional producers keep their producer
+    /// ID and get the next epoch, which fences older instances; their open
+    /// transaction is aborted first.
+    pub(crate) fn init_producer_id(
+        &self,
+        transactional_id: Option<&str>,
+        timeout_ms: i32,
+        expected: Option<(i64, i16)>,
+        groups: &GroupCoordinator,
+    ) -> std::result::Result<(i64, i16), ErrorCode> {
+        let Some(transactional_id) = transactional_id else {
+            return self
+                .generate_producer_id()
+                .map(|producer_id| (producer_id, 0));
+        };
+        if transactional_id.is_empty() {
+            return Err(ErrorCode::InvalidRequest);
+        }
+        if timeout_ms <= 0 || timeout_ms > self.max_timeout_ms {
+            return Err(ErrorCode::InvalidTransactionTimeout);
+        }
+
+        let mut transactions = self.transactions.lock().unwrap();
+        let now = util::now_ms();
+
+        let txn = match transactions.get(transactional_id) {
+            None => TransactionMetadata::new(
+                transactional_id,
+                self.generate_producer_id()?,
+                timeout_ms,
+                now,
+            ),
+            Some(existing) => {
+                if let Some(expected) = expected
+                    && expected != (existing.producer_id, existing.producer_epoch)
+                {
+                    return Err(ErrorCode::ProducerFenced);
+                }
+
+                let mut txn = existing.clone();
+                let pending = match txn.state {
+                    TransactionState::Ongoing | TransactionState::PrepareAbort => {
+                        Some(ControlRecordType::Abort)
+                    }
+                    TransactionState::PrepareCommit => Some(ControlRecordType::Commit),
+                    _ => None,
+                };
+                if let Some(result) = pending {
+                    let completed = self.complete(&mut txn, result, true, groups);
+                    transactions.insert(transactional_id.to_string(), txn.clone());
+                    completed?;
+                }
+
+                if txn.producer_epoch >= i16::MAX - 1 {
+                    txn.producer_id = self.generate_producer_id()?;
+                    txn.producer_epoch = 0;
+                } else {
+                    txn.producer_epoch += 1;
+                }
+                txn.timeout_ms = timeout_ms;
+                txn.state = TransactionState::Empty;
+                txn.partitions.clear();
+                txn.start_timestamp_ms = -1;
+                txn.last_update_timestamp_ms = now;
+                txn
+            }
+        };
+
+        self.write(&txn)?;
+        let producer_id_and_epoch = (txn.producer_id, txn.producer_epoch);
+        transactions.insert(transactional_id.to_string(), txn);
+        Ok(producer_id_and_epoch)
+    }
+
+    /// Adds partitions to the producer's transaction, starting a new
+    /// transaction if none is open.
+    pub(crate) fn add_partitions(
+        &self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        partitions: &[TopicPartition],
+    ) -> std::result::Result<(), ErrorCode> {
+        let mut transactions = self.transactions.lock().unwrap();
+        let txn = transactions
+            .get(transactional_id)
+            .ok_or(ErrorCode::InvalidProducerIdMapping)?;
+        validate_producer(txn, producer_id, producer_epoch)?;
+
+        match txn.state {
+            TransactionState::PrepareCommit | TransactionState::PrepareAbort => {
+                return Err(ErrorCode::ConcurrentTransactions);
+            }
+            TransactionState::Ongoing
+                if partitions.iter().all(|tp| txn.partitions.contains(tp)) =>
+            {
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let now = util::now_ms();
+        let mut updated = txn.clone();
+        if updated.state != TransactionState::Ongoing {
+            updated.state = TransactionState::Ongoing;
+            updated.start_timestamp_ms = now;
+            updated.partitions.clear();
+        }
+        updated.partitions.extend(partitions.iter().cloned());
+        updated.last_update_timestamp_ms = now;
+
+        self.write(&updated)?;
+        transactions.insert(transactional_id.to_string(), updated);
+        Ok(())
+    }
+
+    /// Handles an EndTxn: the transaction is prepared, the markers are
+    /// written to its partitions and it is completed. Retrying an EndTxn
+    /// with the same result succeeds.
+    pub(crate) fn end_txn(
+        &self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        committed: bool,
+        groups: &GroupCoordinator,
+    ) -> std::result::Result<(), ErrorCode> {
+        let mut transactions = self.transactions.lock().unwrap();
+        let txn = transactions
+            .get(transactional_id)
+            .ok_or(ErrorCode::InvalidProducerIdMapping)?;
+        validate_producer(txn, producer_id, producer_epoch)?;
+
+        let result = if committed {
+            ControlRecordType::Commit
+        } else {
+            ControlRecordType::Abort
+        };
+        match (txn.state, result) {
+            (TransactionState::Ongoing, _)
+            | (TransactionState::PrepareCommit, ControlRecordType::Commit)
+            | (TransactionState::PrepareAbort, ControlRecordType::Abort) => {}
+            (TransactionState::CompleteCommit, ControlRecordType::Commit)
+            | (TransactionState::CompleteAbort, ControlRecordType::Abort) => return Ok(()),
+            _ => return Err(ErrorCode::InvalidTxnState),
+        }
+
+        let mut txn = txn.clone();
+        let completed = self.complete(&mut txn, result, false, groups);
+        transactions.insert(transactional_id.to_string(), txn);
+        completed
+    }
+
+    /// Whether `tp` has been added to the ongoing transaction of the
+    /// producer, so it may write transactional batches to it.
+    pub(crate) fn is_in_transaction(
+        &self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        tp: &TopicPartition,
+    ) -> bool {
+        let transactions = self.transactions.lock().unwrap();
+        transactions.get(transactional_id).is_some_and(|txn| {
+            txn.state == TransactionState::Ongoing
+                && txn.producer_id == producer_id
+                && txn.producer_epoch == producer_epoch
+                && txn.partitions.contains(tp)
+        })
+    }
+
+    /// Every transaction known to the coordinator, ordered by
+    /// transactional ID.
+    pub(crate) fn list_transactions(&self) -> Vec<TransactionMetadata> {
+        let transactions = self.transactions.lock().unwrap();
+        let mut listed: Vec<TransactionMetadata> = transactions.values().cloned().collect();
+        listed.sort_by(|a, b| a.transactional_id.cmp(&b.transactional_id));
+        listed
+    }
+
+    pub(crate) fn describe_transaction(
+        &self,
+        transactional_id: &str,
+    ) -> Option<TransactionMetadata> {
+        let transactions = self.transactions.lock().unwrap();
+        transactions.get(transactional_id).cloned()
+    }
+
+    /// Aborts the transactions which have been open for longer than their
+    /// timeout, and returns how many were aborted. The producers are fenced
+    /// so they cannot keep writing to the aborted transactions.
+    pub(crate) fn abort_timed_out_transactions(
+        &self,
+        now: i64,
+        groups: &GroupCoordinator,
+    ) -> usize {
+        let mut transactions = self.transactions.lock().unwrap();
+
+        let mut aborted = 0;
+        for txn in transactions
+            .values_mut()
+            .filter(|txn| txn.is_timed_out(now))
+        {
+            match self.complete(txn, ControlRecordType::Abort, true, groups) {
+                Ok(()) => aborted += 1,
+                Err(error_code) => eprintln!(
+                    "Failed to abort transaction {}: {error_code:?}",
+                    txn.transactional_id
+                ),
+            }
+        }
+        aborted
+    }
+
+    /// Moves an ongoing or prepared transaction to its complete state. With
+    /// `fence`, the epoch is bumped before the markers are written, which
+    /// rejects further writes of the current producer.
+    fn complete(
+        &self,
+        txn: &mut TransactionMetadata,
+        result: ControlRecordType,
+        fence: bool,
+        groups: &GroupCoordinator,
+    ) -> std::result::Result<(), ErrorCode> {
+        let (prepare, complete) = match result {
+            ControlRecordType::Commit => (
+                TransactionState::PrepareCommit,
+                TransactionState::CompleteCommit,
+            ),
+            ControlRecordType::Abort => (
+                TransactionState::PrepareAbort,
+                TransactionState::CompleteAbort,
+            ),
+        };
+
+        if txn.state == TransactionState::Ongoing {
+            let mut prepared = txn.clone();
+            if fence && prepared.producer_epoch < i16::MAX - 1 {
+                prepared.producer_epoch += 1;
+            }
+            prepared.state = prepare;
+            prepared.last_update_timestamp_ms = util::now_ms();
+            self.write(&prepared)?;
+            *txn = prepared;
+        }
+
+        let marker = TxnMarker {
+            producer_id: txn.producer_id,
+            producer_epoch: txn.producer_epoch,
+            result,
+            coordinator_epoch: COORDINATOR_EPOCH,
+            partitions: txn.partitions.iter().cloned().collect(),
+        };
+        if write_txn_markers(&self.logs, groups, &marker)
+            .iter()
+            .any(|(_, error_code)| *error_code != ErrorCode::NoError)
+        {
+            // Stays prepared, so the markers are written again on retry.
+            return Err(ErrorCode::CoordinatorNotAvailable);
+        }
+
+        let mut completed = txn.clone();
+        completed.state = complete;
+        completed.partitions.clear();
+        completed.last_update_timestamp_ms = util::now_ms();
+        self.write(&completed)?;
+        *txn = completed;
+        Ok(())
+    }
+
+    fn generate_producer_id(&self) -> std::result::Result<i64, ErrorCode> {
+        self.producer_ids.generate_producer_id().map_err(|e| {
+            eprintln!("Failed to allocate a producer id: {e}");
+            ErrorCode::CoordinatorNotAvailable
+        })
+    }
+
+    fn write(&self, txn: &TransactionMetadata) -> std::result::Result<(), ErrorCode> {
+        let append = || -> Result<()> {
+            let record = txn.to_record().into_record(0)?;
+            let batch = RecordBatch::new(util::now_ms(), vec![record])?;
+
+            let tp = TopicPartition::new(
+                TRANSACTION_STATE_TOPIC,
+                self.partition_for(&txn.transactional_id),
+            );
+            self.logs
+                .get_or_create(&tp)?
+                .lock()
+                .unwrap()
+                .append(batch)?;
+            Ok(())
+        };
+        append().map_err(|e| {
+            eprintln!("Failed to write transaction {}: {e}", txn.transactional_id);
+            ErrorCode::CoordinatorNotAvailable
+        })
+    }
+}
+
+fn validate_producer(
+    txn: &TransactionMetadata,
+    producer_id: i64,
+    producer_epoch: i16,
+) -> std::result::Result<(), ErrorCode> {
+    if txn.producer_id != producer_id {
+        Err(ErrorCode::InvalidProducerIdMapping)
+    } else if txn.producer_epoch != producer_epoch {
+        Err(ErrorCode::ProducerFenced)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::OffsetAndMetadata;
+    use std::path::{Path, PathBuf};
+
+    fn coordinators(name: &str) -> (TransactionCoordinator, GroupCoordinator, PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("kafka-txn-test-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let (txns, groups) = open(&dir);
+        (txns, groups, dir)
+    }
+
+    fn open(dir: &Path) -> (TransactionCoordinator, GroupCoordinator) {
+        let config = Config {
+            log_dir: dir.to_path_buf(),
+            ..Config::default()
+        };
+        let logs = Arc::new(LogManager::new(dir));
+        (
+            TransactionCoordinator::new(&config, Arc::clone(&logs)),
+            GroupCoordinator::new(&config, logs),
+        )
+    }
+
+    fn control_types(logs: &LogManager, tp: &TopicPartition) -> Vec<ControlRecordType> {
+        let log = logs.get_or_create(tp).unwrap();
+        let batches = log.lock().unwrap().read::<VarintBytes>().unwrap();
+        batches.iter().filter_map(|b| b.control_type()).collect()
+    }
+
+    #[test]
+    fn test_commit_transaction() {
+        let (txns, groups, dir) = coordinators("commit");
+        let tp = TopicPartition::new("foo", 0);
+        let offsets_tp = TopicPartition::new(GROUP_METADATA_TOPIC, groups.partition_for("group"));
+
+        let (producer_id, epoch) = txns
+            .init_producer_id(Some("txn"), 60000, None, &groups)
+            .unwrap();
+        assert_eq!(epoch, 0);
+        assert!(!txns.is_in_transaction("txn", producer_id, epoch, &tp));
+
+        txns.add_partitions("txn", producer_id, epoch, &[tp.clone(), offsets_tp.clone()])
+            .unwrap();
+        assert!(txns.is_in_transaction("txn", producer_id, epoch, &tp));
+        assert_eq!(
+            txns.add_partitions("txn", producer_id, epoch + 1, std::slice::from_ref(&tp)),
+            Err(ErrorCode::ProducerFenced)
+        );
+
+        let offset = OffsetAndMetadata {
+            offset: 5,
+            leader_epoch: -1,
+            metadata: String::new(),
+            commit_timestamp: util::now_ms(),
+        };
+        groups.commit_transactional_offsets(
+            "group",
+            producer_id,
+            epoch,
+            -1,
+            "",
+            vec![(tp.clone(), offset.clone())],
+        );
+        assert_eq!(groups.fetch_offsets("group", None), vec![]);
+
+        assert_eq!(
+            txns.end_txn("txn", producer_id, epoch, true, &groups),
+            Ok(())
+        );
+        assert_eq!(control_types(&txns.logs, &tp), [ControlRecordType::Commit]);
+        assert_eq!(
+            groups.fetch_offsets("group", None),
+            vec![(tp.clone(), Some(offset.clone()))]
+        );
+
+        // Retries succeed, the opposite result does not.
+        assert_eq!(
+            txns.end_txn("txn", producer_id, epoch, true, &groups),
+            Ok(())
+        );
+        assert_eq!(
+            txns.end_txn("txn", producer_id, epoch, false, &groups),
+            Err(ErrorCode::InvalidTxnState)
+        );
+
+        // Both coordinators recover their state from the logs.
+        let (reloaded_txns, reloaded_groups) = open(&dir);
+        reloaded_groups.load().unwrap();
+        reloaded_txns.load(&reloaded_groups).unwrap();
+        assert_eq!(
+            reloaded_groups.fetch_offsets("group", None),
+            vec![(tp.clone(), Some(offset))]
+        );
+        assert_eq!(
+            reloaded_txns.transactions.lock().unwrap()["txn"].state,
+            TransactionState::CompleteCommit
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_init_producer_id_aborts_and_fences() {
+        let (txns, groups, dir) = coordinators("fence");
+        let tp = TopicPartition::new("foo", 0);
+
+        let (producer_id, epoch) = txns
+            .init_producer_id(Some("txn"), 60000, None, &groups)
+            .unwrap();
+        txns.add_partitions("txn", producer_id, epoch, std::slice::from_ref(&tp))
+            .unwrap();
+
+        assert_eq!(
+            txns.init_producer_id(Some("txn"), 60000, Some((producer_id, epoch + 1)), &groups),
+            Err(ErrorCode::ProducerFenced)
+        );
+        assert_eq!(
+            txns.init_producer_id(Some("txn"), i32::MAX, None, &groups),
+            Err(ErrorCode::InvalidTransactionTimeout)
+        );
+
+        let (new_producer_id, new_epoch) = txns
+            .init_producer_id(Some("txn"), 60000, None, &groups)
+            .unwrap();
+        assert_eq!(new_producer_id, producer_id);
+        assert!(new_epoch > epoch);
+        assert_eq!(control_types(&txns.logs, &tp), [ControlRecordType::Abort]);
+        assert_eq!(
+            txns.end_txn("txn", producer_id, epoch, true, &groups),
+            Err(ErrorCode::ProducerFenced)
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_abort_timed_out_transactions() {
+        let (txns, groups, dir) = coordinators("timeout");
+        let tp = TopicPartition::new("foo", 0);
+
+        let (producer_id, epoch) = txns
+            .init_producer_id(Some("txn"), 1000, None, &groups)
+            .unwrap();
+        txns.add_partitions("txn", producer_id, epoch, std::slice::from_ref(&tp))
+            .unwrap();
+
+        assert_eq!(
+            txns.abort_timed_out_transactions(util::now_ms(), &groups),
+            0
+        );
+        assert_eq!(
+            txns.abort_timed_out_transactions(util::now_ms() + 1000, &groups),
+            1
+        );
+        assert_eq!(control_types(&txns.logs, &tp), [ControlRecordType::Abort]);
+        // The producer was fenced by the abort.
+        assert_eq!(
+            txns.add_partitions("txn", producer_id, epoch, std::slice::from_ref(&tp)),
+            Err(ErrorCode::ProducerFenced)
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_list_and_describe_transactions() {
+        let (txns, groups, dir) = coordinators("list");
+        let tp = TopicPartition::new("foo", 0);
+
+        let (producer_id, epoch) = txns
+            .init_producer_id(Some("b"), 60000, None, &groups)
+            .unwrap();
+        txns.init_producer_id(Some("a"), 60000, None, &groups)
+            .unwrap();
+        txns.add_partitions("b", producer_id, epoch, std::slice::from_ref(&tp))
+            .unwrap();
+
+        let listed: Vec<(String, TransactionState)> = txns
+            .list_transactions()
+            .into_iter()
+            .map(|txn| (txn.transactional_id, txn.state))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                ("a".to_string(), TransactionState::Empty),
+                ("b".to_string(), TransactionState::Ongoing),
+            ]
+        );
+
+        let described = txns.describe_transaction("b").unwrap();
+        assert_eq!(described.producer_id, producer_id);
+        assert_eq!(described.partitions.into_iter().collect::<Vec<_>>(), [tp]);
+        assert!(described.start_timestamp_ms >= 0);
+        assert_eq!(txns.describe_transaction("c"), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
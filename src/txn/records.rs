@@ -0,0 +1,129 @@
+use crate::{
+    KafkaError, Result,
+    de::Deserializer,
+    ser::Serializer,
+    types::{Array, Record, VarintBytes},
+};
+
+use serde::{Deserialize, Serialize};
+
+const TRANSACTION_LOG_KEY_VERSION: i16 = 0;
+const TRANSACTION_LOG_VALUE_VERSION: i16 = 0;
+
+/// Key of a transaction's state in `__transaction_state`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct TransactionLogKey {
+    pub(crate) transactional_id: String,
+}
+
+/// Value of a transaction's state in `__transaction_state` (version 0).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct TransactionLogValue {
+    pub(crate) producer_id: i64,
+    pub(crate) producer_epoch: i16,
+    pub(crate) transaction_timeout_ms: i32,
+    pub(crate) transaction_status: i8,
+    pub(crate) transaction_partitions: Array<TransactionLogPartitions>,
+    pub(crate) transaction_last_update_timestamp_ms: i64,
+    pub(crate) transaction_start_timestamp_ms: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct TransactionLogPartitions {
+    pub(crate) topic: String,
+    pub(crate) partition_ids: Array<i32>,
+}
+
+/// A record stored in `__transaction_state`. A `None` value is a tombstone.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TransactionRecord(
+    pub(crate) TransactionLogKey,
+    pub(crate) Option<TransactionLogValue>,
+);
+
+impl TransactionRecord {
+    pub(crate) fn into_record(self, offset_delta: i32) -> Result<Record<VarintBytes>> {
+        let Self(key, value) = self;
+        Ok(Record::new(
+            offset_delta,
+            VarintBytes::new(Some(encode(TRANSACTION_LOG_KEY_VERSION, &key)?)),
+            VarintBytes::new(
+                value
+                    .map(|v| encode(TRANSACTION_LOG_VALUE_VERSION, &v))
+                    .transpose()?,
+            ),
+        ))
+    }
+
+    /// Decodes a record read back from the log. Records with versions this
+    /// broker does not know about are skipped and yield `None`.
+    pub(crate) fn from_record(record: &Record<VarintBytes>) -> Result<Option<Self>> {
+        let key = record.key.as_opt_bytes().ok_or_else(|| {
+            KafkaError::DeserializationError("transaction record without a key".to_string())
+        })?;
+
+        let mut de = Deserializer::new(key);
+        let version: i16 = Deserialize::deserialize(&mut de)?;
+        if version != TRANSACTION_LOG_KEY_VERSION {
+            return Ok(None);
+        }
+        let key: TransactionLogKey = Deserialize::deserialize(&mut de)?;
+
+        let Some(value) = record.value.as_opt_bytes() else {
+            return Ok(Some(Self(key, None)));
+        };
+        let mut de = Deserializer::new(value);
+        let version: i16 = Deserialize::deserialize(&mut de)?;
+        if version != TRANSACTION_LOG_VALUE_VERSION {
+            return Ok(None);
+        }
+        Ok(Some(Self(key, Some(Deserialize::deserialize(&mut de)?))))
+    }
+}
+
+fn encode<T: Serialize>(version: i16, value: &T) -> Result<Vec<u8>> {
+    let mut buf: Vec<u8> = Vec::new();
+    let mut serializer = Serializer::new(&mut buf);
+    (version, value).serialize(&mut serializer)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transaction_record_roundtrip() {
+        let record = TransactionRecord(
+            TransactionLogKey {
+                transactional_id: "txn".to_string(),
+            },
+            Some(TransactionLogValue {
+                producer_id: 1000,
+                producer_epoch: 3,
+                transaction_timeout_ms: 60000,
+                transaction_status: 1,
+                transaction_partitions: Array::new(Some(vec![TransactionLogPartitions {
+                    topic: "foo".to_string(),
+                    partition_ids: Array::new(Some(vec![0, 2])),
+                }])),
+                transaction_last_update_timestamp_ms: 20,
+                transaction_start_timestamp_ms: 10,
+            }),
+        );
+
+        let decoded =
+            TransactionRecord::from_record(&record.clone().into_record(0).unwrap()).unwrap();
+        assert_eq!(decoded, Some(record));
+
+        let tombstone = TransactionRecord(
+            TransactionLogKey {
+                transactional_id: "txn".to_string(),
+            },
+            None,
+        );
+        let decoded =
+            TransactionRecord::from_record(&tombstone.clone().into_record(0).unwrap()).unwrap();
+        assert_eq!(decoded, Some(tombstone));
+    }
+}
@@ -22,6 +22,7 @@ impl<L, T> LenPrefixEncode<L, T> {
         }
     }
 
+    #[allow(dead_code)]
     pub(crate) fn as_ref(&self) -> &T {
         &self.value
     }
@@ -420,6 +421,7 @@ impl<L, T> LenPrefixObject<L, T> {
         }
     }
 
+    #[allow(dead_code)]
     pub(crate) fn as_ref(&self) -> &T {
         &self.value
     }
@@ -596,7 +598,7 @@ mod tests {
 
     #[test]
     fn test_len_prefix_deserialization() {
-        let buf = vec![0x00, 0x05, b'h', b'e', b'l', b'l', b'o'];
+        let buf = [0x00, 0x05, b'h', b'e', b'l', b'l', b'o'];
         let mut deserializer = Deserializer::new(&buf[..]);
         let v: I16String = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, I16String::new("hello".into()));
@@ -606,12 +608,12 @@ mod tests {
         let v: I32Bytes = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, I32Bytes::new(b"world".to_vec()));
 
-        let buf = vec![0x0C, b'v', b'a', b'r', b'i', b'n', b't'];
+        let buf = [0x0C, b'v', b'a', b'r', b'i', b'n', b't'];
         let mut deserializer = Deserializer::new(&buf[..]);
         let v: VaintString = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, VaintString::new("varint".into()));
 
-        let buf = vec![0x08, b'u', b'v', b'a', b'r', b'i', b'n', b't'];
+        let buf = [0x08, b'u', b'v', b'a', b'r', b'i', b'n', b't'];
         let mut deserializer = Deserializer::new(&buf[..]);
         let v: UvarintString = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, UvarintString::new("uvarint".into()));
@@ -623,7 +625,7 @@ mod tests {
         let v: I32OptString = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, I32OptString::new(Some("optional".into())));
 
-        let buf = vec![0xFF, 0xFF, 0xFF, 0xFF];
+        let buf = [0xFF, 0xFF, 0xFF, 0xFF];
         let mut deserializer = Deserializer::new(&buf[..]);
         let v: I32OptString = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, I32OptString::new(None));
@@ -645,7 +647,7 @@ mod tests {
             ]))
         );
 
-        let buf = vec![0x00];
+        let buf = [0x00];
         let mut deserializer = Deserializer::new(&buf[..]);
         let v: UvarintSeq = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, UvarintSeq::new(None));
@@ -0,0 +1,562 @@
+//! Values whose encoding depends on the version of the message holding
+//! them, for structs deriving `KafkaEncode` and `KafkaDecode`.
+
+use super::*;
+use crate::{
+    KafkaError, Result,
+    de::{ByteSeed, Deserializer},
+    ser::Serializer,
+};
+
+use serde::{
+    Deserialize, Serialize,
+    de::{DeserializeOwned, DeserializeSeed},
+    ser::{self, SerializeSeq},
+};
+use std::io::Read;
+
+pub(crate) trait KafkaEncode {
+    /// Writes the value as encoded in `version`. Strings, bytes and arrays
+    /// take their compact form when `compact`.
+    fn encode<S: SerializeSeq>(
+        &self,
+        version: i16,
+        compact: bool,
+        seq: &mut S,
+    ) -> std::result::Result<(), S::Error>;
+
+    fn encoded_size(&self, version: i16, compact: bool) -> usize;
+}
+
+pub(crate) trait KafkaDecode: Sized {
+    /// Reads the value as encoded in `version`. Strings, bytes and arrays
+    /// take their compact form when `compact`.
+    fn decode<R: Read>(
+        version: i16,
+        compact: bool,
+        deserializer: &mut Deserializer<R>,
+    ) -> Result<Self>;
+}
+
+/// A value with the version to encode it in, such as a response body.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Versioned<T> {
+    pub(crate) version: i16,
+    pub(crate) value: T,
+}
+
+impl<T> Versioned<T> {
+    pub(crate) fn new(version: i16, value: T) -> Self {
+        Self { version, value }
+    }
+}
+
+impl<T: KafkaEncode> ser::Serialize for Versioned<T> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        let mut seq = serializer.serialize_seq(None)?;
+        self.value.encode(self.version, false, &mut seq)?;
+        seq.end()
+    }
+}
+
+impl<T: KafkaEncode> ByteSizeExt for Versioned<T> {
+    fn byte_size(&self) -> usize {
+        self.value.encoded_size(self.version, false)
+    }
+}
+
+/// Encodes the value in memory, such as the data of a tagged field.
+#[allow(dead_code)]
+pub(crate) fn encode_to_vec<T: KafkaEncode>(
+    value: &T,
+    version: i16,
+    compact: bool,
+) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut serializer = Serializer::new(&mut bytes);
+    let mut seq = ser::Serializer::serialize_seq(&mut serializer, None)?;
+    value.encode(version, compact, &mut seq)?;
+    seq.end()?;
+    Ok(bytes)
+}
+
+/// Decodes a value held in memory, such as the data of a tagged field.
+#[allow(dead_code)]
+pub(crate) fn decode_from_slice<T: KafkaDecode>(
+    bytes: &[u8],
+    version: i16,
+    compact: bool,
+) -> Result<T> {
+    T::decode(version, compact, &mut Deserializer::new(bytes))
+}
+
+/// Implements the versioned traits for types encoded the same way in
+/// every version.
+macro_rules! impl_fixed {
+    ($([$($param:ident),*] $ty:ty),* $(,)?) => {
+        $(
+            impl<$($param),*> KafkaEncode for $ty
+            where
+                Self: Serialize + ByteSizeExt,
+            {
+                fn encode<S: SerializeSeq>(
+                    &self,
+                    _version: i16,
+                    _compact: bool,
+                    seq: &mut S,
+                ) -> std::result::Result<(), S::Error> {
+                    seq.serialize_element(self)
+                }
+
+                fn encoded_size(&self, _version: i16, _compact: bool) -> usize {
+                    self.byte_size()
+                }
+            }
+
+            impl<$($param),*> KafkaDecode for $ty
+            where
+                Self: DeserializeOwned,
+            {
+                fn decode<R: Read>(
+                    _version: i16,
+                    _compact: bool,
+                    deserializer: &mut Deserializer<R>,
+                ) -> Result<Self> {
+                    Deserialize::deserialize(deserializer)
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed!(
+    [] bool,
+    [] i8,
+    [] i16,
+    [] i32,
+    [] i64,
+    [] u16,
+    [] u32,
+    [] f64,
+    [] Uuid,
+    [] TaggedFields,
+    [L, T] LenPrefixEncode<L, T>,
+    [L, T] LenPrefixEncodeOpt<L, T>,
+    [L, T] LenPrefixSeq<L, T>,
+    [T] NullableStruct<T>,
+);
+
+/// Strings, bytes and arrays: their length, then their data. Their
+/// `Option` is nullable, with a length of -1, or 0 in the compact form.
+trait LengthPrefixed: Sized {
+    /// Whether the length is an INT32 rather than an INT16 outside of the
+    /// compact form.
+    const WIDE: bool;
+
+    fn len(&self) -> usize;
+
+    fn encode_data<S: SerializeSeq>(
+        &self,
+        version: i16,
+        compact: bool,
+        seq: &mut S,
+    ) -> std::result::Result<(), S::Error>;
+
+    fn data_size(&self, version: i16, compact: bool) -> usize;
+
+    fn decode_data<R: Read>(
+        len: usize,
+        version: i16,
+        compact: bool,
+        deserializer: &mut Deserializer<R>,
+    ) -> Result<Self>;
+}
+
+fn encode_length<S: SerializeSeq>(
+    len: Option<usize>,
+    compact: bool,
+    wide: bool,
+    seq: &mut S,
+) -> std::result::Result<(), S::Error> {
+    match (compact, wide) {
+        (true, _) => seq.serialize_element(&len.map_or(Uvarint::as_none(), Uvarint::from_usize)),
+        (false, true) => seq.serialize_element(&len.map_or(-1, |len| len as i32)),
+        (false, false) => seq.serialize_element(&len.map_or(-1, |len| len as i16)),
+    }
+}
+
+fn length_size(len: Option<usize>, compact: bool, wide: bool) -> usize {
+    match (compact, wide) {
+        (true, _) => len
+            .map_or(Uvarint::as_none(), Uvarint::from_usize)
+            .byte_size(),
+        (false, true) => 4,
+        (false, false) => 2,
+    }
+}
+
+fn decode_length<R: Read>(
+    compact: bool,
+    wide: bool,
+    deserializer: &mut Deserializer<R>,
+) -> Result<Option<usize>> {
+    let len = match (compact, wide) {
+        (true, _) => {
+            let len = Uvarint::deserialize(&mut *deserializer)?;
+            (len.deref() > 0).then(|| len.as_length())
+        }
+        (false, true) => {
+            let len = i32::deserialize(&mut *deserializer)?;
+            (len >= 0).then_some(len as usize)
+        }
+        (false, false) => {
+            let len = i16::deserialize(&mut *deserializer)?;
+            (len >= 0).then_some(len as usize)
+        }
+    };
+    Ok(len)
+}
+
+impl<T: LengthPrefixed> KafkaEncode for T {
+    fn encode<S: SerializeSeq>(
+        &self,
+        version: i16,
+        compact: bool,
+        seq: &mut S,
+    ) -> std::result::Result<(), S::Error> {
+        encode_length(Some(self.len()), compact, T::WIDE, seq)?;
+        self.encode_data(version, compact, seq)
+    }
+
+    fn encoded_size(&self, version: i16, compact: bool) -> usize {
+        length_size(Some(self.len()), compact, T::WIDE) + self.data_size(version, compact)
+    }
+}
+
+impl<T: LengthPrefixed> KafkaDecode for T {
+    fn decode<R: Read>(
+        version: i16,
+        compact: bool,
+        deserializer: &mut Deserializer<R>,
+    ) -> Result<Self> {
+        let len = decode_length(compact, T::WIDE, deserializer)?.ok_or_else(|| {
+            KafkaError::DeserializationError("null value of a non-nullable field".to_string())
+        })?;
+        T::decode_data(len, version, compact, deserializer)
+    }
+}
+
+impl<T: LengthPrefixed> KafkaEncode for Option<T> {
+    fn encode<S: SerializeSeq>(
+        &self,
+        version: i16,
+        compact: bool,
+        seq: &mut S,
+    ) -> std::result::Result<(), S::Error> {
+        encode_length(self.as_ref().map(T::len), compact, T::WIDE, seq)?;
+        match self {
+            Some(value) => value.encode_data(version, compact, seq),
+            None => Ok(()),
+        }
+    }
+
+    fn encoded_size(&self, version: i16, compact: bool) -> usize {
+        length_size(self.as_ref().map(T::len), compact, T::WIDE)
+            + self
+                .as_ref()
+                .map_or(0, |value| value.data_size(version, compact))
+    }
+}
+
+impl<T: LengthPrefixed> KafkaDecode for Option<T> {
+    fn decode<R: Read>(
+        version: i16,
+        compact: bool,
+        deserializer: &mut Deserializer<R>,
+    ) -> Result<Self> {
+        decode_length(compact, T::WIDE, deserializer)?
+            .map(|len| T::decode_data(len, version, compact, deserializer))
+            .transpose()
+    }
+}
+
+fn read_bytes<R: Read>(len: usize, deserializer: &mut Deserializer<R>) -> Result<Vec<u8>> {
+    ByteSeed::new(len).deserialize(deserializer)
+}
+
+impl LengthPrefixed for String {
+    const WIDE: bool = false;
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn encode_data<S: SerializeSeq>(
+        &self,
+        _version: i16,
+        _compact: bool,
+        seq: &mut S,
+    ) -> std::result::Result<(), S::Error> {
+        seq.serialize_element(self.as_bytes())
+    }
+
+    fn data_size(&self, _version: i16, _compact: bool) -> usize {
+        self.len()
+    }
+
+    fn decode_data<R: Read>(
+        len: usize,
+        _version: i16,
+        _compact: bool,
+        deserializer: &mut Deserializer<R>,
+    ) -> Result<Self> {
+        String::from_utf8(read_bytes(len, deserializer)?)
+            .map_err(|e| KafkaError::DeserializationError(e.to_string()))
+    }
+}
+
+impl LengthPrefixed for bytes::Bytes {
+    const WIDE: bool = true;
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn encode_data<S: SerializeSeq>(
+        &self,
+        _version: i16,
+        _compact: bool,
+        seq: &mut S,
+    ) -> std::result::Result<(), S::Error> {
+        seq.serialize_element(self.as_ref())
+    }
+
+    fn data_size(&self, _version: i16, _compact: bool) -> usize {
+        self.len()
+    }
+
+    fn decode_data<R: Read>(
+        len: usize,
+        _version: i16,
+        _compact: bool,
+        deserializer: &mut Deserializer<R>,
+    ) -> Result<Self> {
+        read_bytes(len, deserializer).map(Into::into)
+    }
+}
+
+impl<T: KafkaEncode + KafkaDecode> LengthPrefixed for Vec<T> {
+    const WIDE: bool = true;
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn encode_data<S: SerializeSeq>(
+        &self,
+        version: i16,
+        compact: bool,
+        seq: &mut S,
+    ) -> std::result::Result<(), S::Error> {
+        self.iter()
+            .try_for_each(|item| item.encode(version, compact, seq))
+    }
+
+    fn data_size(&self, version: i16, compact: bool) -> usize {
+        self.iter()
+            .map(|item| item.encoded_size(version, compact))
+            .sum()
+    }
+
+    fn decode_data<R: Read>(
+        len: usize,
+        version: i16,
+        compact: bool,
+        deserializer: &mut Deserializer<R>,
+    ) -> Result<Self> {
+        (0..len)
+            .map(|_| T::decode(version, compact, deserializer))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Default, KafkaEncode, KafkaDecode)]
+    struct Topic {
+        #[kafka(compact = "2+")]
+        name: String,
+        #[kafka(compact = "2+")]
+        partitions: Vec<i32>,
+        #[kafka(versions = "2+")]
+        tagged_fields: TaggedFields,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Default, KafkaEncode, KafkaDecode)]
+    struct Request {
+        #[kafka(compact = "2+")]
+        topics: Vec<Topic>,
+        #[kafka(versions = "1+", compact = "2+")]
+        rack: Option<String>,
+        #[kafka(versions = "2+", tag = 0)]
+        epoch: Option<i32>,
+        #[kafka(versions = "2+")]
+        tagged_fields: TaggedFields,
+    }
+
+    #[test]
+    fn test_versioned_encoding() {
+        let request = Request {
+            topics: vec![Topic {
+                name: "t".to_string(),
+                partitions: vec![1],
+                ..Default::default()
+            }],
+            rack: None,
+            epoch: Some(5),
+            ..Default::default()
+        };
+
+        let v0 = encode_to_vec(&request, 0, false).unwrap();
+        assert_eq!(
+            v0,
+            [
+                0x00, 0x00, 0x00, 0x01, // topics
+                0x00, 0x01, b't', // name
+                0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, // partitions
+            ]
+        );
+        assert_eq!(v0.len(), request.encoded_size(0, false));
+        let decoded: Request = decode_from_slice(&v0, 0, false).unwrap();
+        assert_eq!(decoded.epoch, None);
+
+        let v1 = encode_to_vec(&request, 1, false).unwrap();
+        assert_eq!(v1[v0.len()..], [0xff, 0xff]);
+
+        let v2 = encode_to_vec(&request, 2, false).unwrap();
+        assert_eq!(
+            v2,
+            [
+                0x02, // topics
+                0x02, b't', // name
+                0x02, 0x00, 0x00, 0x00, 0x01, // partitions
+                0x00, // topic tagged fields
+                0x00, // rack
+                0x01, 0x00, 0x04, 0x00, 0x00, 0x00, 0x05, // tagged fields
+            ]
+        );
+        assert_eq!(v2.len(), request.encoded_size(2, false));
+        assert_eq!(
+            decode_from_slice::<Request>(&v2, 2, false).unwrap(),
+            request
+        );
+
+        let response = Versioned::new(2, request);
+        assert_eq!(crate::ser::to_bytes(&response).unwrap(), v2);
+        assert_eq!(response.byte_size(), v2.len());
+    }
+
+    #[derive(Debug, Clone, PartialEq, Default, KafkaEncode, KafkaDecode)]
+    struct Response {
+        #[kafka(compact)]
+        name: String,
+        #[kafka(versions = "0-1")]
+        session_id: i32,
+        #[kafka(versions = "1")]
+        timestamp: i64,
+        #[kafka(versions = "3+", tag = 1)]
+        leader: Option<i32>,
+        #[kafka(versions = "2+", tag = 0)]
+        epoch: Option<i16>,
+        #[kafka(versions = "2+")]
+        tagged_fields: TaggedFields,
+    }
+
+    #[test]
+    fn test_version_ranges_and_tags() {
+        let response = Response {
+            name: "n".to_string(),
+            session_id: 7,
+            timestamp: 9,
+            leader: Some(2),
+            epoch: Some(1),
+            ..Default::default()
+        };
+        let round_trip = |version: i16, expected: &[u8], decoded: Response| {
+            let buf = encode_to_vec(&response, version, false).unwrap();
+            assert_eq!(buf, expected, "version {version}");
+            assert_eq!(buf.len(), response.encoded_size(version, false));
+            assert_eq!(
+                decode_from_slice::<Response>(&buf, version, false).unwrap(),
+                decoded
+            );
+        };
+
+        round_trip(
+            0,
+            &[
+                0x02, b'n', // name
+                0x00, 0x00, 0x00, 0x07, // session_id
+            ],
+            Response {
+                name: "n".to_string(),
+                session_id: 7,
+                ..Default::default()
+            },
+        );
+        round_trip(
+            1,
+            &[
+                0x02, b'n', // name
+                0x00, 0x00, 0x00, 0x07, // session_id
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x09, // timestamp
+            ],
+            Response {
+                name: "n".to_string(),
+                session_id: 7,
+                timestamp: 9,
+                ..Default::default()
+            },
+        );
+        round_trip(
+            2,
+            &[
+                0x02, b'n', // name
+                0x01, 0x00, 0x02, 0x00, 0x01, // tagged fields
+            ],
+            Response {
+                name: "n".to_string(),
+                epoch: Some(1),
+                ..Default::default()
+            },
+        );
+        let v3 = [
+            0x02, b'n', // name
+            0x02, // tagged fields, in tag order
+            0x00, 0x02, 0x00, 0x01, // epoch
+            0x01, 0x04, 0x00, 0x00, 0x00, 0x02, // leader
+        ];
+        round_trip(
+            3,
+            &v3,
+            Response {
+                name: "n".to_string(),
+                leader: Some(2),
+                epoch: Some(1),
+                ..Default::default()
+            },
+        );
+
+        // A tag outside the versions of its field is kept as unknown.
+        let decoded: Response = decode_from_slice(&v3, 2, false).unwrap();
+        assert_eq!(decoded.leader, None);
+        assert_eq!(decoded.epoch, Some(1));
+        assert_eq!(decoded.tagged_fields.get(1), Some(&[0, 0, 0, 2][..]));
+        assert_eq!(encode_to_vec(&decoded, 2, false).unwrap(), v3);
+    }
+}
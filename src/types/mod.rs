@@ -1,4 +1,6 @@
 mod base;
+mod encode;
+mod nullable;
 mod records;
 mod tagged_field;
 mod traits;
@@ -6,6 +8,10 @@ mod uuid;
 mod varint;
 
 use base::*;
+pub(crate) use encode::*;
+#[cfg(test)]
+pub(crate) use kafka_derive::KafkaDecode;
+pub(crate) use kafka_derive::{ByteSize, KafkaEncode};
 pub(crate) use traits::*;
 pub(crate) use varint::*;
 
@@ -13,20 +19,30 @@ pub(crate) use varint::*;
 pub(crate) type CompactString = LenPrefixEncode<Uvarint, String>;
 pub(crate) type NullableString = LenPrefixEncodeOpt<i16, String>;
 pub(crate) type CompactNullableString = LenPrefixEncodeOpt<Uvarint, String>;
+#[allow(dead_code)]
 pub(crate) type Bytes = LenPrefixEncode<i32, Vec<u8>>;
 pub(crate) type CompactBytes = LenPrefixEncode<Uvarint, Vec<u8>>;
 pub(crate) type NullableBytes = LenPrefixEncodeOpt<i32, Vec<u8>>;
 pub(crate) type CompactNullableBytes = LenPrefixEncodeOpt<Uvarint, Vec<u8>>;
 pub(crate) type Array<T> = LenPrefixSeq<i32, T>;
 pub(crate) type CompactArray<T> = LenPrefixSeq<Uvarint, T>;
-pub(crate) type TaggedFields = CompactArray<TaggedField>;
-pub(crate) use tagged_field::TaggedField;
+
+pub(crate) use nullable::NullableStruct;
+pub(crate) use tagged_field::TaggedFields;
 pub(crate) use uuid::Uuid;
 
 // Record values
 pub(crate) type VarintString = LenPrefixEncode<Varint, String>;
 pub(crate) type VarintBytes = LenPrefixEncodeOpt<Varint, Vec<u8>>;
-pub(crate) use records::{Record, RecordBatch, RecordValue, RecordVariant};
+pub(crate) type VarintArray<T> = LenPrefixSeq<Varint, T>;
+pub(crate) use records::{
+    AccessControlEntryRecord, BrokerEndpoint, ClientQuotaRecord, ConfigRecord, ControlRecordType,
+    EntityData, Partition, ProducerIds, Record, RecordBatch, RecordValue, RecordVariant,
+    RegisterBrokerRecord, RemoveAccessControlEntryRecord, RemoveUserScramCredentialRecord, Topic,
+    UserScramCredentialRecord, Value, has_compressed_batch, increment_sequence, valid_batches_len,
+};
+#[cfg(test)]
+pub(crate) use tagged_field::TaggedField;
 
 #[cfg(test)]
 mod tests {
@@ -45,7 +61,7 @@ mod tests {
 
     #[test]
     fn test_compact_string_deserialization() {
-        let data = vec![6, b'h', b'e', b'l', b'l', b'o'];
+        let data = [6, b'h', b'e', b'l', b'l', b'o'];
         let mut deserializer = Deserializer::new(&data[..]);
         let v: CompactString = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, CompactString::new("hello".to_string()));
@@ -74,12 +90,12 @@ mod tests {
 
     #[test]
     fn test_nullable_string_deserialization() {
-        let data = vec![0xFF, 0xFF];
+        let data = [0xFF, 0xFF];
         let mut deserializer = Deserializer::new(&data[..]);
         let v: NullableString = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, NullableString::new(None));
 
-        let data = vec![0x00, 0x02, b'h', b'i'];
+        let data = [0x00, 0x02, b'h', b'i'];
         let mut deserializer = Deserializer::new(&data[..]);
         let v: NullableString = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, NullableString::new(Some("hi".to_string())));
@@ -111,12 +127,12 @@ mod tests {
 
     #[test]
     fn test_compact_nullable_string_deserialization() {
-        let data = vec![0];
+        let data = [0];
         let mut deserializer = Deserializer::new(&data[..]);
         let v: CompactNullableString = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, CompactNullableString::new(None));
 
-        let data = vec![3, b'h', b'i'];
+        let data = [3, b'h', b'i'];
         let mut deserializer = Deserializer::new(&data[..]);
         let v: CompactNullableString = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, CompactNullableString::new(Some("hi".to_string())));
@@ -142,7 +158,7 @@ mod tests {
 
     #[test]
     fn test_bytes_deserialization() {
-        let data = vec![0, 0, 0, 3, 1, 2, 3];
+        let data = [0, 0, 0, 3, 1, 2, 3];
         let mut deserializer = Deserializer::new(&data[..]);
         let v: Bytes = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, Bytes::new(vec![1, 2, 3]));
@@ -165,7 +181,7 @@ mod tests {
 
     #[test]
     fn test_compact_bytes_deserialization() {
-        let data = vec![4, 1, 2, 3];
+        let data = [4, 1, 2, 3];
         let mut deserializer = Deserializer::new(&data[..]);
         let v: CompactBytes = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, CompactBytes::new(vec![1, 2, 3]));
@@ -194,12 +210,12 @@ mod tests {
 
     #[test]
     fn test_nullable_bytes_deserialization() {
-        let data = vec![0xFF, 0xFF, 0xFF, 0xFF];
+        let data = [0xFF, 0xFF, 0xFF, 0xFF];
         let mut deserializer = Deserializer::new(&data[..]);
         let v: NullableBytes = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, NullableBytes::new(None));
 
-        let data = vec![0, 0, 0, 3, 1, 2, 3];
+        let data = [0, 0, 0, 3, 1, 2, 3];
         let mut deserializer = Deserializer::new(&data[..]);
         let v: NullableBytes = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, NullableBytes::new(Some(vec![1, 2, 3])));
@@ -231,12 +247,12 @@ mod tests {
 
     #[test]
     fn test_compact_nullable_bytes_deserialization() {
-        let data = vec![0];
+        let data = [0];
         let mut deserializer = Deserializer::new(&data[..]);
         let v: CompactNullableBytes = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, CompactNullableBytes::new(None));
 
-        let data = vec![4, 1, 2, 3];
+        let data = [4, 1, 2, 3];
         let mut deserializer = Deserializer::new(&data[..]);
         let v: CompactNullableBytes = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, CompactNullableBytes::new(Some(vec![1, 2, 3])));
@@ -289,7 +305,7 @@ mod tests {
             Array::new(Some(vec!["first".into(), "second".into(), "third".into()]))
         );
 
-        let data = vec![0xFF, 0xFF, 0xFF, 0xFF];
+        let data = [0xFF, 0xFF, 0xFF, 0xFF];
         let mut deserializer = Deserializer::new(&data[..]);
         let v: Array<String> = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, Array::new(None));
@@ -350,7 +366,7 @@ mod tests {
             CompactArray::new(Some(vec!["first".into(), "second".into(), "third".into()]))
         );
 
-        let data = vec![0];
+        let data = [0];
         let mut deserializer = Deserializer::new(&data[..]);
         let v: CompactArray<String> = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(v, CompactArray::new(None));
@@ -0,0 +1,123 @@
+use super::*;
+
+use serde::{
+    de,
+    ser::{self, SerializeSeq},
+};
+use std::{fmt, marker::PhantomData};
+
+/// A nullable struct field of a flexible message: an int8 marker (`-1` for
+/// null, `1` otherwise) followed by the struct when present.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NullableStruct<T>(Option<T>);
+
+impl<T> NullableStruct<T> {
+    pub(crate) fn new(value: Option<T>) -> Self {
+        Self(value)
+    }
+
+    pub(crate) fn into_inner(self) -> Option<T> {
+        self.0
+    }
+}
+
+impl<T: ByteSizeExt> ByteSizeExt for NullableStruct<T> {
+    fn byte_size(&self) -> usize {
+        1 + self.0.as_ref().map_or(0, |v| v.byte_size())
+    }
+}
+
+impl<T: ser::Serialize> ser::Serialize for NullableStruct<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match self.0.as_ref() {
+            Some(value) => {
+                let mut seq = serializer.serialize_seq(Some(2))?;
+                seq.serialize_element(&1i8)?;
+                seq.serialize_element(value)?;
+                seq.end()
+            }
+            None => {
+                let mut seq = serializer.serialize_seq(Some(1))?;
+                seq.serialize_element(&-1i8)?;
+                seq.end()
+            }
+        }
+    }
+}
+
+impl<'de, T: de::DeserializeOwned> de::Deserialize<'de> for NullableStruct<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct NullableStructVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: de::DeserializeOwned> de::Visitor<'de> for NullableStructVisitor<T> {
+            type Value = NullableStruct<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a nullable struct")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let marker: i8 = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::custom("missing nullable struct marker"))?;
+
+                if marker < 0 {
+                    return Ok(NullableStruct(None));
+                }
+
+                let value: T = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::custom("missing struct value"))?;
+                Ok(NullableStruct(Some(value)))
+            }
+        }
+
+        deserializer.deserialize_tuple(2, NullableStructVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{de::Deserializer, ser::Serializer};
+    use serde::{Deserialize, Serialize};
+
+    #[test]
+    fn test_nullable_struct_serialization() {
+        let v = NullableStruct::new(Some(7i32));
+        let mut buf: Vec<u8> = Vec::new();
+        let mut serializer = Serializer::new(&mut buf);
+        v.serialize(&mut serializer).unwrap();
+        assert_eq!(buf, vec![0x01, 0x00, 0x00, 0x00, 0x07]);
+        assert_eq!(v.byte_size(), 5);
+
+        let v = NullableStruct::<i32>::new(None);
+        let mut buf: Vec<u8> = Vec::new();
+        let mut serializer = Serializer::new(&mut buf);
+        v.serialize(&mut serializer).unwrap();
+        assert_eq!(buf, vec![0xFF]);
+        assert_eq!(v.byte_size(), 1);
+    }
+
+    #[test]
+    fn test_nullable_struct_deserialization() {
+        let data = [0x01, 0x00, 0x00, 0x00, 0x07];
+        let mut deserializer = Deserializer::new(&data[..]);
+        let v: NullableStruct<i32> = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(v, NullableStruct::new(Some(7)));
+
+        let data = [0xFF];
+        let mut deserializer = Deserializer::new(&data[..]);
+        let v: NullableStruct<i32> = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(v, NullableStruct::new(None));
+    }
+}
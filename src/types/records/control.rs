@@ -0,0 +1,113 @@
+use crate::{KafkaError, de::Deserializer, ser::Serializer};
+
+use super::{CONTROL_FLAG_MASK, Record, RecordBatch, VarintBytes};
+use serde::{Deserialize, Serialize};
+
+const CONTROL_RECORD_KEY_VERSION: i16 = 0;
+const END_TRANSACTION_MARKER_VERSION: i16 = 0;
+
+/// The kind of a control record, stored in its key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ControlRecordType {
+    Abort,
+    Commit,
+}
+
+impl ControlRecordType {
+    fn from_i16(value: i16) -> Option<Self> {
+        match value {
+            0 => Some(Self::Abort),
+            1 => Some(Self::Commit),
+            _ => None,
+        }
+    }
+
+    fn as_i16(self) -> i16 {
+        match self {
+            Self::Abort => 0,
+            Self::Commit => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ControlRecordKey {
+    version: i16,
+    r#type: i16,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct EndTransactionMarker {
+    version: i16,
+    coordinator_epoch: i32,
+}
+
+impl RecordBatch<VarintBytes> {
+    /// Builds the control batch which commits or aborts the transaction of a
+    /// producer in one partition.
+    pub(crate) fn end_transaction_marker(
+        timestamp: i64,
+        producer_id: i64,
+        producer_epoch: i16,
+        control_type: ControlRecordType,
+        coordinator_epoch: i32,
+    ) -> Result<Self, KafkaError> {
+        let key = ControlRecordKey {
+            version: CONTROL_RECORD_KEY_VERSION,
+            r#type: control_type.as_i16(),
+        };
+        let value = EndTransactionMarker {
+            version: END_TRANSACTION_MARKER_VERSION,
+            coordinator_epoch,
+        };
+        let record = Record::new(
+            0,
+            VarintBytes::new(Some(encode(&key)?)),
+            VarintBytes::new(Some(encode(&value)?)),
+        );
+
+        let mut batch =
+            Self::new(timestamp, vec![record])?.into_transactional(producer_id, producer_epoch)?;
+        batch.attributes |= CONTROL_FLAG_MASK;
+        batch.seal()?;
+        Ok(batch)
+    }
+}
+
+impl<V> RecordBatch<V> {
+    /// The type of a control batch, or `None` for batches of data records
+    /// and control records this broker does not know.
+    pub(crate) fn control_type(&self) -> Option<ControlRecordType> {
+        if !self.is_control() {
+            return None;
+        }
+        let record = self.records.as_opt_slice()?.first()?;
+        let key = record.key.as_opt_bytes()?;
+        let key = ControlRecordKey::deserialize(&mut Deserializer::new(key)).ok()?;
+        ControlRecordType::from_i16(key.r#type)
+    }
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, KafkaError> {
+    let mut buf: Vec<u8> = Vec::new();
+    value.serialize(&mut Serializer::new(&mut buf))?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_end_transaction_marker() {
+        let batch =
+            RecordBatch::end_transaction_marker(0, 7, 2, ControlRecordType::Commit, 0).unwrap();
+        assert!(batch.is_valid().unwrap());
+        assert!(batch.is_control());
+        assert!(batch.is_transactional());
+        assert_eq!(batch.control_type(), Some(ControlRecordType::Commit));
+
+        let data = RecordBatch::new(0, batch.records.as_opt_slice().unwrap().to_vec()).unwrap();
+        assert_eq!(data.control_type(), None);
+    }
+}
@@ -1,16 +1,24 @@
-use crate::{KafkaError, de::Deserializer};
+use crate::{KafkaError, de::Deserializer, ser::Serializer, util};
 
 use super::*;
 use std::io::Read;
 
+mod control;
 mod value;
 
+pub(crate) use control::ControlRecordType;
 pub(crate) use value::*;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+const MAGIC: u8 = 2;
+const COMPRESSION_CODEC_MASK: i16 = 0x07;
+const TRANSACTIONAL_FLAG_MASK: i16 = 0x10;
+const CONTROL_FLAG_MASK: i16 = 0x20;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub(crate) struct RecordBatch {
+#[serde(bound(deserialize = "V: DeserializeOwned"))]
+pub(crate) struct RecordBatch<V = RecordValue> {
     pub(crate) base_offset: i64,
     pub(crate) batch_length: i32,
     pub(crate) partition_leader_epoch: i32,
@@ -23,10 +31,10 @@ pub(crate) struct RecordBatch {
     pub(crate) producer_id: i64,
     pub(crate) producer_epoch: i16,
     pub(crate) base_sequence: i32,
-    pub(crate) records: Array<Record>,
+    pub(crate) records: Array<Record<V>>,
 }
 
-impl RecordBatch {
+impl<V: DeserializeOwned> RecordBatch<V> {
     pub(crate) fn from_reader<R: Read>(rdr: R) -> Result<Vec<Self>, KafkaError> {
         let mut deserializer = Deserializer::new(rdr);
         let record_batches: Vec<Self> = Deserialize::deserialize(&mut deserializer)?;
@@ -34,8 +42,176 @@ impl RecordBatch {
     }
 }
 
-impl IntoIterator for RecordBatch {
-    type Item = Record;
+impl<V: Serialize> RecordBatch<V> {
+    /// Builds a batch of non-transactional records without a producer id.
+    /// Offsets are relative to the batch; `base_offset` is assigned by the log
+    /// on append.
+    pub(crate) fn new(timestamp: i64, records: Vec<Record<V>>) -> Result<Self, KafkaError> {
+        let last_offset_delta = records.len() as i32 - 1;
+        let mut batch = Self {
+            base_offset: 0,
+            batch_length: 0,
+            partition_leader_epoch: 0,
+            magic: MAGIC,
+            crc: 0,
+            attributes: 0,
+            last_offset_delta,
+            first_timestamp: timestamp,
+            max_timestamp: timestamp,
+            producer_id: -1,
+            producer_epoch: -1,
+            base_sequence: -1,
+            records: Array::new(Some(records)),
+        };
+        batch.seal()?;
+        Ok(batch)
+    }
+
+    /// Marks the batch as part of a transaction of `producer_id`. Such
+    /// batches carry no sequence numbers when written by the broker itself.
+    pub(crate) fn into_transactional(
+        mut self,
+        producer_id: i64,
+        producer_epoch: i16,
+    ) -> Result<Self, KafkaError> {
+        self.attributes |= TRANSACTIONAL_FLAG_MASK;
+        self.producer_id = producer_id;
+        self.producer_epoch = producer_epoch;
+        self.seal()?;
+        Ok(self)
+    }
+
+    /// Recomputes `batch_length` and `crc` from the current contents. Must be
+    /// called after any field covered by the checksum is changed.
+    pub(crate) fn seal(&mut self) -> Result<(), KafkaError> {
+        let body = self.checksummed_bytes()?;
+        self.crc = util::crc32c(&body);
+        self.batch_length = (self.partition_leader_epoch.byte_size()
+            + self.magic.byte_size()
+            + self.crc.byte_size()
+            + body.len()) as i32;
+        Ok(())
+    }
+
+    /// Whether `crc` matches the current contents, e.g. of a batch received
+    /// from a client.
+    pub(crate) fn is_valid(&self) -> Result<bool, KafkaError> {
+        Ok(self.magic == MAGIC && self.crc == util::crc32c(&self.checksummed_bytes()?))
+    }
+
+    /// The bytes from `attributes` to the end of the batch.
+    fn checksummed_bytes(&self) -> Result<Vec<u8>, KafkaError> {
+        let mut body: Vec<u8> = Vec::new();
+        let mut serializer = Serializer::new(&mut body);
+        (
+            self.attributes,
+            self.last_offset_delta,
+            self.first_timestamp,
+            self.max_timestamp,
+            self.producer_id,
+            self.producer_epoch,
+            self.base_sequence,
+            &self.records,
+        )
+            .serialize(&mut serializer)?;
+        Ok(body)
+    }
+}
+
+impl<V> RecordBatch<V> {
+    pub(crate) fn last_offset(&self) -> i64 {
+        self.base_offset + self.last_offset_delta as i64
+    }
+
+    pub(crate) fn is_transactional(&self) -> bool {
+        self.attributes & TRANSACTIONAL_FLAG_MASK != 0
+    }
+
+    /// Whether the batch holds control records, such as transaction markers,
+    /// instead of data.
+    pub(crate) fn is_control(&self) -> bool {
+        self.attributes & CONTROL_FLAG_MASK != 0
+    }
+
+    /// Whether the batch was written by an idempotent or transactional
+    /// producer.
+    pub(crate) fn has_producer_id(&self) -> bool {
+        self.producer_id >= 0
+    }
+
+    /// The sequence number of the last record, which wraps around to 0
+    /// after `i32::MAX`.
+    pub(crate) fn last_sequence(&self) -> i32 {
+        increment_sequence(self.base_sequence, self.last_offset_delta)
+    }
+}
+
+/// Whether any batch in the encoded `bytes` has compressed records, which
+/// cannot be decoded. Only the batch headers are read; a truncated batch is
+/// left for the decoding to reject.
+pub(crate) fn has_compressed_batch(bytes: &[u8]) -> bool {
+    // base_offset, batch_length, partition_leader_epoch, magic and crc come
+    // before the attributes.
+    const ATTRIBUTES_OFFSET: usize = 21;
+    let mut rest = bytes;
+    while rest.len() >= ATTRIBUTES_OFFSET + 2 {
+        let attributes = i16::from_be_bytes([rest[ATTRIBUTES_OFFSET], rest[ATTRIBUTES_OFFSET + 1]]);
+        if attributes & COMPRESSION_CODEC_MASK != 0 {
+            return true;
+        }
+        let batch_length = i32::from_be_bytes([rest[8], rest[9], rest[10], rest[11]]);
+        let Some(rest_of_batch) = usize::try_from(batch_length)
+            .ok()
+            .and_then(|length| rest.get(12 + length..))
+        else {
+            break;
+        };
+        rest = rest_of_batch;
+    }
+    false
+}
+
+/// The length of the longest prefix of the encoded `bytes` made of whole
+/// batches. Batches with records from `recovery_point` on must also match
+/// their checksum: a crash may have left them half-written.
+pub(crate) fn valid_batches_len(bytes: &[u8], recovery_point: i64) -> usize {
+    // The batch header after batch_length, up to the record count.
+    const HEADER_LENGTH: usize = 49;
+    let mut len = 0;
+    while let Some(header) = bytes.get(len..len + 12) {
+        let base_offset = i64::from_be_bytes(header[..8].try_into().unwrap());
+        let batch_length = i32::from_be_bytes(header[8..].try_into().unwrap());
+        let Some(batch) = usize::try_from(batch_length)
+            .ok()
+            .filter(|&length| length >= HEADER_LENGTH)
+            .and_then(|length| bytes.get(len..len + 12 + length))
+        else {
+            break;
+        };
+
+        let last_offset_delta = i32::from_be_bytes(batch[23..27].try_into().unwrap());
+        if base_offset + i64::from(last_offset_delta) >= recovery_point {
+            let crc = u32::from_be_bytes(batch[17..21].try_into().unwrap());
+            if batch[16] != MAGIC || crc != util::crc32c(&batch[21..]) {
+                break;
+            }
+        }
+        len += batch.len();
+    }
+    len
+}
+
+/// Adds `increment` to a producer sequence number, wrapping around to 0.
+pub(crate) fn increment_sequence(sequence: i32, increment: i32) -> i32 {
+    if sequence > i32::MAX - increment {
+        increment - (i32::MAX - sequence) - 1
+    } else {
+        sequence + increment
+    }
+}
+
+impl<V> IntoIterator for RecordBatch<V> {
+    type Item = Record<V>;
     type IntoIter = std::vec::IntoIter<Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
@@ -44,17 +220,45 @@ impl IntoIterator for RecordBatch {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub(crate) struct Record {
+pub(crate) struct Record<V = RecordValue> {
     pub(crate) length: Varint,
     pub(crate) attributes: u8,
     pub(crate) timestamp_delta: VarLong,
     pub(crate) offset_delta: Varint,
     pub(crate) key: VarintBytes,
-    pub(crate) value: RecordValue,
-    pub(crate) headers: CompactArray<Header>,
+    pub(crate) value: V,
+    pub(crate) headers: VarintArray<Header>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+impl<V: ByteSizeExt> Record<V> {
+    pub(crate) fn new(offset_delta: i32, key: VarintBytes, value: V) -> Self {
+        let mut record = Self {
+            length: Varint::new(0),
+            attributes: 0,
+            timestamp_delta: VarLong::new(0),
+            offset_delta: Varint::new(offset_delta),
+            key,
+            value,
+            headers: VarintArray::new(Some(vec![])),
+        };
+        let length = record.attributes.byte_size()
+            + record.timestamp_delta.byte_size()
+            + record.offset_delta.byte_size()
+            + record.key.byte_size()
+            + record.value.byte_size()
+            + record.headers.byte_size();
+        record.length = Varint::new(length as i32);
+        record
+    }
+}
+
+impl<V: ByteSizeExt> ByteSizeExt for Record<V> {
+    fn byte_size(&self) -> usize {
+        self.length.byte_size() + *self.length.as_ref() as usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ByteSize)]
 pub(crate) struct Header {
     pub(crate) key: VarintString,
     pub(crate) value: VarintBytes,
@@ -106,11 +310,88 @@ mod tests {
                     }),
                     tagged_fields: TaggedFields::new(None),
                 }),
-                headers: CompactArray::new(None),
+                headers: VarintArray::new(Some(vec![])),
             }
         );
     }
 
+    #[test]
+    fn test_record_batch_seal() {
+        let record = Record::new(
+            0,
+            VarintBytes::new(None),
+            RecordValue::new(Value {
+                frame_version: 1,
+                r#type: 12,
+                version: 0,
+                value: RecordVariant::FeatureLevel(FeatureLevel {
+                    name: CompactString::new("metadata.version".into()),
+                    level: 20,
+                }),
+                tagged_fields: TaggedFields::new(None),
+            }),
+        );
+        assert_eq!(record.length, Varint::new(29));
+
+        let mut batch = RecordBatch::new(1726045943832, vec![record]).unwrap();
+        batch.partition_leader_epoch = 1;
+        batch.seal().unwrap();
+        assert_eq!(batch.batch_length, 79);
+        assert_eq!(batch.crc, 0xB069457C);
+    }
+
+    #[test]
+    fn test_record_batch_is_valid() {
+        let record = Record::new(0, VarintBytes::new(None), VarintBytes::new(Some(vec![1])));
+        let mut batch = RecordBatch::new(0, vec![record]).unwrap();
+        assert!(batch.is_valid().unwrap());
+
+        batch.base_sequence = 1;
+        assert!(!batch.is_valid().unwrap());
+    }
+
+    #[test]
+    fn test_has_compressed_batch() {
+        let record = Record::new(0, VarintBytes::new(None), VarintBytes::new(Some(vec![1])));
+        let batch = RecordBatch::new(0, vec![record]).unwrap();
+        let mut bytes = crate::ser::to_bytes(&batch).unwrap();
+        bytes.extend(bytes.clone());
+        assert!(!has_compressed_batch(&bytes));
+
+        // The second batch is compressed with gzip.
+        let second = bytes.len() / 2;
+        bytes[second + 22] |= 0x01;
+        assert!(has_compressed_batch(&bytes));
+        assert!(!has_compressed_batch(&bytes[..second]));
+    }
+
+    #[test]
+    fn test_valid_batches_len() {
+        let record = Record::new(0, VarintBytes::new(None), VarintBytes::new(Some(vec![1])));
+        let mut batch = RecordBatch::new(0, vec![record]).unwrap();
+        let first = crate::ser::to_bytes(&batch).unwrap();
+        batch.base_offset = 1;
+        let mut bytes = [first.clone(), crate::ser::to_bytes(&batch).unwrap()].concat();
+        assert_eq!(valid_batches_len(&bytes, 0), bytes.len());
+
+        // A batch cut short is dropped.
+        assert_eq!(valid_batches_len(&bytes[..bytes.len() - 1], 0), first.len());
+        assert_eq!(valid_batches_len(&bytes[..first.len() + 5], 0), first.len());
+
+        // So is a corrupt batch, unless it is before the recovery point.
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert_eq!(valid_batches_len(&bytes, 0), first.len());
+        assert_eq!(valid_batches_len(&bytes, 2), bytes.len());
+    }
+
+    #[test]
+    fn test_increment_sequence() {
+        assert_eq!(increment_sequence(5, 2), 7);
+        assert_eq!(increment_sequence(i32::MAX, 1), 0);
+        assert_eq!(increment_sequence(i32::MAX - 1, 3), 1);
+    }
+
     #[test]
     #[ignore]
     fn test_record_batch_deserialization() {
@@ -181,7 +462,7 @@ mod tests {
                         }),
                         tagged_fields: TaggedFields::new(None),
                     }),
-                    headers: CompactArray::new(None),
+                    headers: VarintArray::new(Some(vec![])),
                 }]))
             }]
         );
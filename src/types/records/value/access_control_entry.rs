@@ -0,0 +1,21 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+
+/// An ACL, identified by `id` so that it can be removed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ByteSize)]
+pub(crate) struct AccessControlEntryRecord {
+    pub(crate) id: Uuid,
+    pub(crate) resource_type: i8,
+    pub(crate) resource_name: CompactString,
+    pub(crate) pattern_type: i8,
+    pub(crate) principal: CompactString,
+    pub(crate) host: CompactString,
+    pub(crate) operation: i8,
+    pub(crate) permission_type: i8,
+}
+
+/// Removes the ACL with the ID.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ByteSize)]
+pub(crate) struct RemoveAccessControlEntryRecord {
+    pub(crate) id: Uuid,
+}
@@ -0,0 +1,20 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+
+/// Sets or removes one quota of a client entity.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ByteSize)]
+pub(crate) struct ClientQuotaRecord {
+    pub(crate) entity: CompactArray<EntityData>,
+    pub(crate) key: CompactString,
+    pub(crate) value: f64,
+    pub(crate) remove: bool,
+}
+
+/// One component of a quota entity, such as the user. A null name stands
+/// for the default entity of the type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ByteSize)]
+pub(crate) struct EntityData {
+    pub(crate) entity_type: CompactString,
+    pub(crate) entity_name: CompactNullableString,
+    pub(crate) tagged_fields: TaggedFields,
+}
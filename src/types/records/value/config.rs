@@ -0,0 +1,11 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+
+/// Sets a config of a resource, or removes it when `value` is null.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ByteSize)]
+pub(crate) struct ConfigRecord {
+    pub(crate) resource_type: i8,
+    pub(crate) resource_name: CompactString,
+    pub(crate) name: CompactString,
+    pub(crate) value: CompactNullableString,
+}
@@ -1,14 +1,8 @@
 use super::*;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ByteSize)]
 pub(crate) struct FeatureLevel {
     pub(crate) name: CompactString,
     pub(crate) level: i16,
 }
-
-impl ByteSizeExt for FeatureLevel {
-    fn byte_size(&self) -> usize {
-        self.name.byte_size() + self.level.byte_size()
-    }
-}
@@ -2,17 +2,39 @@ use super::*;
 use serde::{Serialize, de};
 use std::fmt;
 
+mod access_control_entry;
+mod client_quota;
+mod config;
 mod feature_level;
 mod partition;
+mod producer_ids;
+mod register_broker;
 mod topic;
+mod user_scram_credential;
 
+pub(crate) use access_control_entry::{AccessControlEntryRecord, RemoveAccessControlEntryRecord};
+pub(crate) use client_quota::{ClientQuotaRecord, EntityData};
+pub(crate) use config::ConfigRecord;
 pub(crate) use feature_level::FeatureLevel;
 pub(crate) use partition::Partition;
+pub(crate) use producer_ids::ProducerIds;
+pub(crate) use register_broker::{BrokerEndpoint, RegisterBrokerRecord};
 pub(crate) use topic::Topic;
+pub(crate) use user_scram_credential::{
+    RemoveUserScramCredentialRecord, UserScramCredentialRecord,
+};
 
+const API_KEY_REGISTER_BROKER: u8 = 0;
 const API_KEY_FEATURE_LEVELS: u8 = 12;
 const API_KEY_PARTITION: u8 = 3;
 const API_KEY_TOPIC: u8 = 2;
+const API_KEY_CONFIG: u8 = 4;
+const API_KEY_USER_SCRAM_CREDENTIAL: u8 = 11;
+const API_KEY_CLIENT_QUOTA: u8 = 14;
+const API_KEY_PRODUCER_IDS: u8 = 15;
+const API_KEY_REMOVE_USER_SCRAM_CREDENTIAL: u8 = 22;
+const API_KEY_ACCESS_CONTROL_ENTRY: u8 = 23;
+const API_KEY_REMOVE_ACCESS_CONTROL_ENTRY: u8 = 24;
 
 pub(crate) type RecordValue = LenPrefixObject<Varint, Value>;
 
@@ -25,6 +47,35 @@ pub(crate) struct Value {
     pub(crate) tagged_fields: TaggedFields,
 }
 
+impl Value {
+    /// Wraps a record in the frame written to the metadata log.
+    pub(crate) fn new(value: RecordVariant) -> Self {
+        let (r#type, version) = match value {
+            RecordVariant::AccessControlEntry(_) => (API_KEY_ACCESS_CONTROL_ENTRY, 0),
+            RecordVariant::ClientQuota(_) => (API_KEY_CLIENT_QUOTA, 0),
+            RecordVariant::Config(_) => (API_KEY_CONFIG, 0),
+            RecordVariant::FeatureLevel(_) => (API_KEY_FEATURE_LEVELS, 0),
+            RecordVariant::Partition(_) => (API_KEY_PARTITION, 0),
+            RecordVariant::ProducerIds(_) => (API_KEY_PRODUCER_IDS, 0),
+            // The layout of `RegisterBrokerRecord` is the one of version 3.
+            RecordVariant::RegisterBroker(_) => (API_KEY_REGISTER_BROKER, 3),
+            RecordVariant::RemoveAccessControlEntry(_) => (API_KEY_REMOVE_ACCESS_CONTROL_ENTRY, 0),
+            RecordVariant::RemoveUserScramCredential(_) => {
+                (API_KEY_REMOVE_USER_SCRAM_CREDENTIAL, 0)
+            }
+            RecordVariant::Topic(_) => (API_KEY_TOPIC, 0),
+            RecordVariant::UserScramCredential(_) => (API_KEY_USER_SCRAM_CREDENTIAL, 0),
+        };
+        Self {
+            frame_version: 1,
+            r#type,
+            version,
+            value,
+            tagged_fields: TaggedFields::new(None),
+        }
+    }
+}
+
 impl ByteSizeExt for Value {
     fn byte_size(&self) -> usize {
         self.frame_version.byte_size()
@@ -66,6 +117,20 @@ impl<'de> de::Deserialize<'de> for Value {
                     .ok_or_else(|| de::Error::custom("expected u8 for version"))?;
 
                 let value: RecordVariant = match r#type {
+                    API_KEY_ACCESS_CONTROL_ENTRY => seq
+                        .next_element::<AccessControlEntryRecord>()?
+                        .map(RecordVariant::AccessControlEntry)
+                        .ok_or_else(|| {
+                            de::Error::custom("expected AccessControlEntryRecord for value")
+                        })?,
+                    API_KEY_CLIENT_QUOTA => seq
+                        .next_element::<ClientQuotaRecord>()?
+                        .map(RecordVariant::ClientQuota)
+                        .ok_or_else(|| de::Error::custom("expected ClientQuotaRecord for value"))?,
+                    API_KEY_CONFIG => seq
+                        .next_element::<ConfigRecord>()?
+                        .map(RecordVariant::Config)
+                        .ok_or_else(|| de::Error::custom("expected ConfigRecord for value"))?,
                     API_KEY_FEATURE_LEVELS => seq
                         .next_element::<FeatureLevel>()?
                         .map(RecordVariant::FeatureLevel)
@@ -74,10 +139,38 @@ impl<'de> de::Deserialize<'de> for Value {
                         .next_element::<Partition>()?
                         .map(RecordVariant::Partition)
                         .ok_or_else(|| de::Error::custom("expected Partition for value"))?,
+                    API_KEY_PRODUCER_IDS => seq
+                        .next_element::<ProducerIds>()?
+                        .map(RecordVariant::ProducerIds)
+                        .ok_or_else(|| de::Error::custom("expected ProducerIds for value"))?,
+                    API_KEY_REGISTER_BROKER => seq
+                        .next_element::<RegisterBrokerRecord>()?
+                        .map(RecordVariant::RegisterBroker)
+                        .ok_or_else(|| {
+                            de::Error::custom("expected RegisterBrokerRecord for value")
+                        })?,
+                    API_KEY_REMOVE_ACCESS_CONTROL_ENTRY => seq
+                        .next_element::<RemoveAccessControlEntryRecord>()?
+                        .map(RecordVariant::RemoveAccessControlEntry)
+                        .ok_or_else(|| {
+                            de::Error::custom("expected RemoveAccessControlEntryRecord for value")
+                        })?,
+                    API_KEY_REMOVE_USER_SCRAM_CREDENTIAL => seq
+                        .next_element::<RemoveUserScramCredentialRecord>()?
+                        .map(RecordVariant::RemoveUserScramCredential)
+                        .ok_or_else(|| {
+                            de::Error::custom("expected RemoveUserScramCredentialRecord for value")
+                        })?,
                     API_KEY_TOPIC => seq
                         .next_element::<Topic>()?
                         .map(RecordVariant::Topic)
                         .ok_or_else(|| de::Error::custom("expected Topic for value"))?,
+                    API_KEY_USER_SCRAM_CREDENTIAL => seq
+                        .next_element::<UserScramCredentialRecord>()?
+                        .map(RecordVariant::UserScramCredential)
+                        .ok_or_else(|| {
+                            de::Error::custom("expected UserScramCredentialRecord for value")
+                        })?,
                     _ => return Err(de::Error::custom(format!("unknown type: {}", r#type))),
                 };
 
@@ -102,17 +195,33 @@ impl<'de> de::Deserialize<'de> for Value {
 #[derive(Debug, Clone, PartialEq, Serialize)]
 #[serde(untagged)]
 pub(crate) enum RecordVariant {
+    AccessControlEntry(AccessControlEntryRecord),
+    ClientQuota(ClientQuotaRecord),
+    Config(ConfigRecord),
     FeatureLevel(FeatureLevel),
     Partition(Partition),
+    ProducerIds(ProducerIds),
+    RegisterBroker(RegisterBrokerRecord),
+    RemoveAccessControlEntry(RemoveAccessControlEntryRecord),
+    RemoveUserScramCredential(RemoveUserScramCredentialRecord),
     Topic(Topic),
+    UserScramCredential(UserScramCredentialRecord),
 }
 
 impl ByteSizeExt for RecordVariant {
     fn byte_size(&self) -> usize {
         match self {
+            Self::AccessControlEntry(entry) => entry.byte_size(),
+            Self::ClientQuota(quota) => quota.byte_size(),
+            Self::Config(config) => config.byte_size(),
             Self::FeatureLevel(feature_level) => feature_level.byte_size(),
             Self::Partition(partition) => partition.byte_size(),
+            Self::ProducerIds(producer_ids) => producer_ids.byte_size(),
+            Self::RegisterBroker(register_broker) => register_broker.byte_size(),
+            Self::RemoveAccessControlEntry(remove) => remove.byte_size(),
+            Self::RemoveUserScramCredential(remove) => remove.byte_size(),
             Self::Topic(topic) => topic.byte_size(),
+            Self::UserScramCredential(credential) => credential.byte_size(),
         }
     }
 }
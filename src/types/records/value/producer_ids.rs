@@ -0,0 +1,10 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+
+/// Reserves the producer IDs below `next_producer_id` for `broker_id`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ByteSize)]
+pub(crate) struct ProducerIds {
+    pub(crate) broker_id: i32,
+    pub(crate) broker_epoch: i64,
+    pub(crate) next_producer_id: i64,
+}
@@ -0,0 +1,35 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+
+/// Registers a broker with the cluster. A broker writes a new one, with a
+/// new incarnation ID, every time it starts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ByteSize)]
+pub(crate) struct RegisterBrokerRecord {
+    pub(crate) broker_id: i32,
+    pub(crate) is_migrating_zk_broker: bool,
+    pub(crate) incarnation_id: Uuid,
+    pub(crate) broker_epoch: i64,
+    pub(crate) end_points: CompactArray<BrokerEndpoint>,
+    pub(crate) features: CompactArray<BrokerFeature>,
+    pub(crate) rack: CompactNullableString,
+    pub(crate) fenced: bool,
+    pub(crate) in_controlled_shutdown: bool,
+    pub(crate) log_dirs: CompactArray<Uuid>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ByteSize)]
+pub(crate) struct BrokerEndpoint {
+    pub(crate) name: CompactString,
+    pub(crate) host: CompactString,
+    pub(crate) port: u16,
+    pub(crate) security_protocol: i16,
+    pub(crate) tagged_fields: TaggedFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ByteSize)]
+pub(crate) struct BrokerFeature {
+    pub(crate) name: CompactString,
+    pub(crate) min_supported_version: i16,
+    pub(crate) max_supported_version: i16,
+    pub(crate) tagged_fields: TaggedFields,
+}
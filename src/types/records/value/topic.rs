@@ -1,14 +1,8 @@
 use super::*;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ByteSize)]
 pub(crate) struct Topic {
     pub(crate) name: CompactString,
     pub(crate) topic_id: Uuid,
 }
-
-impl ByteSizeExt for Topic {
-    fn byte_size(&self) -> usize {
-        self.name.byte_size() + self.topic_id.byte_size()
-    }
-}
@@ -0,0 +1,21 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+
+/// The SCRAM credential of a user for one mechanism. Only the keys derived
+/// from the password are kept, never the password itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ByteSize)]
+pub(crate) struct UserScramCredentialRecord {
+    pub(crate) name: CompactString,
+    pub(crate) mechanism: i8,
+    pub(crate) salt: CompactBytes,
+    pub(crate) stored_key: CompactBytes,
+    pub(crate) server_key: CompactBytes,
+    pub(crate) iterations: i32,
+}
+
+/// Removes the SCRAM credential of a user for one mechanism.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ByteSize)]
+pub(crate) struct RemoveUserScramCredentialRecord {
+    pub(crate) name: CompactString,
+    pub(crate) mechanism: i8,
+}
@@ -1,4 +1,5 @@
 use super::*;
+use crate::de::{ArraySeed, ByteSeed};
 
 use serde::{
     de,
@@ -6,12 +7,26 @@ use serde::{
 };
 use std::fmt;
 
+/// A field of the tagged fields section: its tag, then its data prefixed by
+/// the data size. Unlike compact bytes, the size is not offset by one.
 #[derive(Debug, Clone, PartialEq)]
-pub(crate) struct TaggedField(Uvarint, CompactBytes);
+pub(crate) struct TaggedField {
+    tag: Uvarint,
+    data: Vec<u8>,
+}
+
+impl TaggedField {
+    pub(crate) fn new(tag: u64, data: Vec<u8>) -> Self {
+        Self {
+            tag: Uvarint::new(tag),
+            data,
+        }
+    }
+}
 
 impl ByteSizeExt for TaggedField {
     fn byte_size(&self) -> usize {
-        self.0.byte_size() + self.1.byte_size()
+        self.tag.byte_size() + Uvarint::new(self.data.len() as u64).byte_size() + self.data.len()
     }
 }
 
@@ -20,9 +35,10 @@ impl ser::Serialize for TaggedField {
     where
         S: ser::Serializer,
     {
-        let mut seq = serializer.serialize_seq(Some(2))?;
-        seq.serialize_element(&self.0)?;
-        seq.serialize_element(&self.1)?;
+        let mut seq = serializer.serialize_seq(Some(3))?;
+        seq.serialize_element(&self.tag)?;
+        seq.serialize_element(&Uvarint::new(self.data.len() as u64))?;
+        seq.serialize_element(self.data.as_slice())?;
         seq.end()
     }
 }
@@ -32,13 +48,13 @@ impl<'de> de::Deserialize<'de> for TaggedField {
     where
         D: de::Deserializer<'de>,
     {
-        struct TaggedFieldsVisitor;
+        struct TaggedFieldVisitor;
 
-        impl<'de> de::Visitor<'de> for TaggedFieldsVisitor {
+        impl<'de> de::Visitor<'de> for TaggedFieldVisitor {
             type Value = TaggedField;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("TaggedField as (Uvarint, CompactBytes)")
+                formatter.write_str("TaggedField as (Uvarint, Uvarint, bytes)")
             }
 
             fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
@@ -48,12 +64,144 @@ impl<'de> de::Deserialize<'de> for TaggedField {
                 let tag: Uvarint = seq
                     .next_element()?
                     .ok_or_else(|| de::Error::custom("expected Uvarint for tag"))?;
-                let value: CompactBytes = seq
+                let size: Uvarint = seq
                     .next_element()?
-                    .ok_or_else(|| de::Error::custom("expected CompactBytes for value"))?;
-                Ok(TaggedField(tag, value))
+                    .ok_or_else(|| de::Error::custom("expected Uvarint for size"))?;
+                let data = seq
+                    .next_element_seed(ByteSeed::new(size.deref() as usize))?
+                    .ok_or_else(|| de::Error::custom("expected bytes for data"))?;
+                Ok(TaggedField { tag, data })
+            }
+        }
+        deserializer.deserialize_tuple(3, TaggedFieldVisitor)
+    }
+}
+
+/// The tagged fields section ending every struct of a flexible message: the
+/// number of fields, not offset by one, then the fields in tag order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct TaggedFields(Vec<TaggedField>);
+
+impl TaggedFields {
+    pub(crate) fn new(fields: Option<Vec<TaggedField>>) -> Self {
+        Self(fields.unwrap_or_default())
+    }
+
+    /// The data of the field with the given tag.
+    pub(crate) fn get(&self, tag: u64) -> Option<&[u8]> {
+        self.0
+            .iter()
+            .find(|field| field.tag.deref() == tag)
+            .map(|field| field.data.as_slice())
+    }
+
+    /// Sets the data of the field with the given tag, keeping the fields in
+    /// tag order.
+    pub(crate) fn insert(&mut self, tag: u64, data: Vec<u8>) {
+        match self.0.binary_search_by_key(&tag, |field| field.tag.deref()) {
+            Ok(i) => self.0[i].data = data,
+            Err(i) => self.0.insert(i, TaggedField::new(tag, data)),
+        }
+    }
+
+    /// Takes the data of the field with the given tag.
+    pub(crate) fn remove(&mut self, tag: u64) -> Option<Vec<u8>> {
+        let i = self.0.iter().position(|field| field.tag.deref() == tag)?;
+        Some(self.0.remove(i).data)
+    }
+}
+
+impl ByteSizeExt for TaggedFields {
+    fn byte_size(&self) -> usize {
+        Uvarint::new(self.0.len() as u64).byte_size()
+            + self.0.iter().map(|field| field.byte_size()).sum::<usize>()
+    }
+}
+
+impl ser::Serialize for TaggedFields {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(1 + self.0.len()))?;
+        seq.serialize_element(&Uvarint::new(self.0.len() as u64))?;
+        for field in &self.0 {
+            seq.serialize_element(field)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> de::Deserialize<'de> for TaggedFields {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct TaggedFieldsVisitor;
+
+        impl<'de> de::Visitor<'de> for TaggedFieldsVisitor {
+            type Value = TaggedFields;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("tagged fields with their count")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let count: Uvarint = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::custom("expected Uvarint for count"))?;
+                if count.deref() == 0 {
+                    return Ok(TaggedFields::default());
+                }
+                let fields = seq
+                    .next_element_seed(ArraySeed::<TaggedField>::new(count.deref() as usize))?
+                    .ok_or_else(|| de::Error::custom("expected tagged fields"))?;
+                Ok(TaggedFields(fields))
             }
         }
         deserializer.deserialize_tuple(2, TaggedFieldsVisitor)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{de::Deserializer, ser::Serializer};
+    use serde::{Deserialize, Serialize};
+
+    #[test]
+    fn test_tagged_fields_serialization() {
+        let fields = TaggedFields::new(Some(vec![TaggedField::new(1, vec![0x02, 0x00])]));
+        let mut buf: Vec<u8> = Vec::new();
+        let mut serializer = Serializer::new(&mut buf);
+        fields.serialize(&mut serializer).unwrap();
+        assert_eq!(buf, vec![0x01, 0x01, 0x02, 0x02, 0x00]);
+        assert_eq!(fields.byte_size(), buf.len());
+
+        let mut deserializer = Deserializer::new(&buf[..]);
+        let v: TaggedFields = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(v, fields);
+        assert_eq!(v.get(1), Some(&[0x02, 0x00][..]));
+        assert_eq!(v.get(2), None);
+
+        let mut v = v;
+        v.insert(0, vec![0x05]);
+        v.insert(1, vec![0x03]);
+        assert_eq!(
+            v,
+            TaggedFields::new(Some(vec![
+                TaggedField::new(0, vec![0x05]),
+                TaggedField::new(1, vec![0x03]),
+            ]))
+        );
+        assert_eq!(v.remove(0), Some(vec![0x05]));
+        assert_eq!(v.remove(0), None);
+
+        let mut deserializer = Deserializer::new(&[0x00][..]);
+        let v: TaggedFields = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(v, TaggedFields::new(None));
+    }
+}
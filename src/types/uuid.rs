@@ -1,11 +1,55 @@
 use super::*;
 
 use serde::{de, ser};
+use std::collections::hash_map::RandomState;
 use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
 
-#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub(crate) struct Uuid([u8; 16]);
 
+impl Uuid {
+    /// Generates a random (version 4) UUID. The randomness comes from the
+    /// standard library's hasher keys and is not suitable for secrets.
+    pub(crate) fn random() -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let state = RandomState::new();
+        let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+        let mut bytes = [0u8; 16];
+        for (i, chunk) in bytes.chunks_mut(8).enumerate() {
+            let mut hasher = state.build_hasher();
+            hasher.write_u64(counter);
+            hasher.write_usize(i);
+            chunk.copy_from_slice(&hasher.finish().to_be_bytes());
+        }
+        bytes[6] = (bytes[6] & 0x0F) | 0x40; // version 4
+        bytes[8] = (bytes[8] & 0x3F) | 0x80; // IETF variant
+
+        Self(bytes)
+    }
+}
+
+impl From<[u8; 16]> for Uuid {
+    fn from(bytes: [u8; 16]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl fmt::Display for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if matches!(i, 4 | 6 | 8 | 10) {
+                f.write_str("-")?;
+            }
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
 impl ByteSizeExt for Uuid {
     fn byte_size(&self) -> usize {
         16
@@ -40,11 +84,10 @@ impl<'de> de::Deserialize<'de> for Uuid {
                 A: de::SeqAccess<'de>,
             {
                 let mut arr = [0u8; 16];
-                for i in 0..16 {
-                    let byte: u8 = seq
+                for byte in arr.iter_mut() {
+                    *byte = seq
                         .next_element()?
                         .ok_or_else(|| de::Error::custom("expected byte"))?;
-                    arr[i] = byte;
                 }
                 Ok(Uuid(arr))
             }
@@ -53,3 +96,25 @@ impl<'de> de::Deserialize<'de> for Uuid {
         deserializer.deserialize_tuple(16, UuidVisitor)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let uuid = Uuid([
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xAA, 0xBB, 0xCC, 0xDD,
+            0xEE, 0xFF,
+        ]);
+        assert_eq!(uuid.to_string(), "00112233-4455-6677-8899-aabbccddeeff");
+    }
+
+    #[test]
+    fn test_random() {
+        let a = Uuid::random();
+        let b = Uuid::random();
+        assert_ne!(a, b);
+        assert_eq!(a.0[6] >> 4, 4);
+    }
+}
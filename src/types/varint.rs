@@ -13,11 +13,12 @@ macro_rules! define_varint {
 
         paste! {
             impl $name {
+                #[allow(dead_code)]
                 pub(crate) fn new(value: $inner) -> Self {
                     Self(value)
                 }
 
-                pub(crate) fn into_byte_buf(&self) -> Vec<u8> {
+                pub(crate) fn to_byte_buf(&self) -> Vec<u8> {
                     util::[<encode_varint_ $inner>](self.0)
                 }
 
@@ -26,6 +27,7 @@ macro_rules! define_varint {
                     Ok(Self(v))
                 }
 
+                #[allow(dead_code)]
                 pub(crate) fn deref(&self) -> $inner {
                     self.0
                 }
@@ -44,15 +46,15 @@ macro_rules! define_varint {
             }
         }
 
-        impl Into<$inner> for $name {
-            fn into(self) -> $inner {
-                self.0
+        impl From<$name> for $inner {
+            fn from(value: $name) -> Self {
+                value.0
             }
         }
 
         impl ByteSizeExt for $name {
             fn byte_size(&self) -> usize {
-                self.into_byte_buf().len()
+                self.to_byte_buf().len()
             }
         }
 
@@ -61,7 +63,7 @@ macro_rules! define_varint {
             where
                 S: ser::Serializer,
             {
-                let bytes = self.into_byte_buf();
+                let bytes = self.to_byte_buf();
                 serializer.serialize_bytes(&bytes)
             }
         }
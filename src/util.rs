@@ -113,6 +113,51 @@ pub(crate) fn decode_varint_i64(mut bytes: Vec<u8>) -> Result<i64> {
     Ok(v)
 }
 
+const CRC32C_TABLE: [u32; 256] = crc32c_table();
+
+const fn crc32c_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x82F6_3B78 // Castagnoli polynomial (reversed)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// CRC-32C checksum used by record batches.
+pub(crate) fn crc32c(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc = CRC32C_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+pub(crate) fn now_ms() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Equivalent of Java's `String.hashCode`, used where Kafka maps a key to a
+/// partition of an internal topic.
+pub(crate) fn java_string_hash(s: &str) -> i32 {
+    s.encode_utf16()
+        .fold(0i32, |h, c| h.wrapping_mul(31).wrapping_add(c as i32))
+}
+
 struct LengthBytes<'a, R: io::Read + 'a> {
     reader: &'a mut R,
     finished: bool,
@@ -207,6 +252,19 @@ mod tests {
         assert_eq!(encoded, vec![0b1010_1011, 0b0000_0010]);
     }
 
+    #[test]
+    fn test_crc32c() {
+        assert_eq!(crc32c(b""), 0);
+        assert_eq!(crc32c(b"123456789"), 0xE306_9283);
+    }
+
+    #[test]
+    fn test_java_string_hash() {
+        assert_eq!(java_string_hash(""), 0);
+        assert_eq!(java_string_hash("hello"), 99162322);
+        assert_eq!(java_string_hash("my-consumer-group"), 1_513_705_513);
+    }
+
     #[test]
     fn test_decode_varint_i32() {
         let data = vec![0b0011_1010];